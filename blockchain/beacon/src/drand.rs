@@ -35,10 +35,7 @@ impl DrandPublic {
 }
 
 #[async_trait]
-pub trait Beacon
-where
-    Self: Sized,
-{
+pub trait Beacon {
     /// Verify a new beacon entry against the most recent one before it.
     async fn verify_entry(
         &self,
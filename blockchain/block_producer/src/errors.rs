@@ -0,0 +1,67 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use blocks::Error as BlkErr;
+use chain::Error as StoreErr;
+use chain_sync::Error as SyncErr;
+use encoding::Error as EncErr;
+use message_pool::Error as MpoolErr;
+use state_manager::Error as StErr;
+use std::error::Error as StdError;
+use thiserror::Error;
+
+/// Block producer error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Error originating constructing blockchain structures
+    #[error(transparent)]
+    Blockchain(#[from] BlkErr),
+    /// Error indicating a chain store error
+    #[error(transparent)]
+    Store(#[from] StoreErr),
+    /// Error originating from state
+    #[error(transparent)]
+    State(#[from] StErr),
+    /// Error retrieving messages from the message pool
+    #[error(transparent)]
+    MessagePool(#[from] MpoolErr),
+    /// Error retrieving entries from the randomness beacon
+    #[error("Beacon error: {0}")]
+    Beacon(String),
+    /// Error signing with the worker key
+    #[error("Signing error: {0}")]
+    Signing(String),
+    /// Any other error that does not need to be specifically handled
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<EncErr> for Error {
+    fn from(e: EncErr) -> Error {
+        Error::Other(e.to_string())
+    }
+}
+
+impl From<SyncErr> for Error {
+    fn from(e: SyncErr) -> Error {
+        Error::Other(e.to_string())
+    }
+}
+
+impl From<crypto::Error> for Error {
+    fn from(e: crypto::Error) -> Error {
+        Error::Signing(e.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(e: String) -> Error {
+        Error::Other(e)
+    }
+}
+
+impl From<Box<dyn StdError>> for Error {
+    fn from(e: Box<dyn StdError>) -> Error {
+        Error::Other(e.to_string())
+    }
+}
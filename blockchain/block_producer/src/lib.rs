@@ -0,0 +1,14 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod errors;
+mod mining;
+mod producer;
+mod prover;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use self::errors::*;
+pub use self::mining::*;
+pub use self::producer::*;
+pub use self::prover::*;
@@ -0,0 +1,262 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::Error;
+use actor::miner;
+use address::Address;
+use beacon::{Beacon, BeaconEntry};
+use blocks::{Block, BlockHeader, BlockHeaderBuilder, ElectionProof, Ticket, Tipset, TipsetKeys};
+use chain_sync::compute_msg_meta;
+use cid::Cid;
+use clock::ChainEpoch;
+use crypto::{aggregate_bls, DomainSeparationTag, Signature, SignatureType};
+use encoding::Cbor;
+use fil_types::{
+    verifier::ProofVerifier, PoStProof, Randomness, SectorInfo, SectorSize, StoragePower,
+};
+use ipld_blockstore::BlockStore;
+use message::{SignedMessage, UnsignedMessage};
+use num_traits::Zero;
+use state_manager::StateManager;
+use std::sync::Arc;
+
+/// Information a miner needs to attempt to produce a block in a round.
+#[derive(Clone, Debug)]
+pub struct MiningBaseInfo {
+    pub miner_power: StoragePower,
+    pub network_power: StoragePower,
+    /// Sectors challenged for the winning PoSt of the round.
+    pub sectors: Vec<SectorInfo>,
+    pub worker_key: Address,
+    pub sector_size: SectorSize,
+    /// Latest beacon entry in the chain of the base tipset.
+    pub prev_beacon_entry: BeaconEntry,
+    /// Beacon entries from the previous one up to the round, to be included in the block.
+    pub beacon_entries: Vec<BeaconEntry>,
+    /// Whether the miner meets the minimum power and has not been slashed.
+    pub eligible_for_mining: bool,
+}
+
+impl MiningBaseInfo {
+    /// Returns the beacon entry the randomness of the round is drawn from.
+    pub fn randomness_base(&self) -> &BeaconEntry {
+        self.beacon_entries
+            .last()
+            .unwrap_or(&self.prev_beacon_entry)
+    }
+}
+
+/// Parameters of a block to create on top of a parent tipset. Elections, tickets and proofs are
+/// computed by the miner beforehand.
+#[derive(Clone, Debug)]
+pub struct BlockTemplate {
+    pub miner: Address,
+    pub parents: TipsetKeys,
+    pub ticket: Ticket,
+    pub election_proof: ElectionProof,
+    pub beacon_values: Vec<BeaconEntry>,
+    pub messages: Vec<SignedMessage>,
+    pub epoch: ChainEpoch,
+    pub timestamp: u64,
+    pub winning_post_proof: Vec<PoStProof>,
+}
+
+/// Block assembled from a template, which only lacks the signature of the worker of the miner.
+pub struct UnsignedBlock {
+    header: BlockHeaderBuilder,
+    bls_messages: Vec<UnsignedMessage>,
+    secp_messages: Vec<SignedMessage>,
+}
+
+impl UnsignedBlock {
+    /// Returns the bytes the worker signs.
+    pub fn signing_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.header.build_and_validate()?.to_signing_bytes()?)
+    }
+
+    /// Returns the block with the worker's signature over its signing bytes.
+    pub fn sign(mut self, signature: Signature) -> Result<Block, Error> {
+        let header = self
+            .header
+            .signature(Some(signature))
+            .build_and_validate()?;
+        Ok(Block {
+            header,
+            bls_messages: self.bls_messages,
+            secp_messages: self.secp_messages,
+        })
+    }
+}
+
+/// Returns the information needed by the miner to produce a block on top of the base tipset at
+/// the given round. Returns `None` if the miner does not exist, or has no sectors to prove.
+pub async fn get_mining_base_info<DB, B, V>(
+    sm: &Arc<StateManager<DB>>,
+    beacon: &B,
+    miner: &Address,
+    round: ChainEpoch,
+    base: &Arc<Tipset>,
+) -> Result<Option<MiningBaseInfo>, Error>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    B: Beacon + ?Sized,
+    V: ProofVerifier,
+{
+    let cs = sm.chain_store();
+
+    // Beacon entries from the latest one in the chain up to the round
+    let prev_beacon_entry = cs.latest_beacon_entry(base)?;
+    let mut beacon_entries = Vec::new();
+    for r in prev_beacon_entry.round() + 1..=beacon.max_beacon_round_for_epoch(round) {
+        let entry = beacon
+            .entry(r)
+            .await
+            .map_err(|e| Error::Beacon(e.to_string()))?;
+        beacon_entries.push(entry);
+    }
+    let rbase = beacon_entries.last().unwrap_or(&prev_beacon_entry).clone();
+
+    // Power, worker and sectors are looked up in the state of the lookback tipset
+    let (lbts, lbst) = lookback_state::<_, V>(sm, base, round).await?;
+    if sm.get_actor(miner, &lbst)?.is_none() {
+        return Ok(None);
+    }
+
+    let rand = draw_randomness(
+        rbase.data(),
+        DomainSeparationTag::WinningPoStChallengeSeed,
+        round,
+        &miner.marshal_cbor()?,
+    )?;
+    let sectors = sm.get_sectors_for_winning_post::<V>(&lbst, miner, Randomness(rand))?;
+    if sectors.is_empty() {
+        return Ok(None);
+    }
+
+    let (mpow, tpow) = sm.get_power(&lbst, miner)?;
+    let worker_key = sm.get_miner_work_addr(&lbst, miner)?;
+    let mas: miner::State = sm.load_actor_state(miner, &lbst)?;
+    let sector_size = mas.get_info(sm.blockstore())?.sector_size;
+
+    let eligible_for_mining = sm.miner_has_min_power(miner, &lbts)?
+        && !sm.is_miner_slashed(miner, base.parent_state())?
+        && !mpow.quality_adj_power.is_zero();
+
+    Ok(Some(MiningBaseInfo {
+        miner_power: mpow.quality_adj_power,
+        network_power: tpow.quality_adj_power,
+        sectors,
+        worker_key,
+        sector_size,
+        prev_beacon_entry,
+        beacon_entries,
+        eligible_for_mining,
+    }))
+}
+
+/// Returns the key address of the worker of the miner, which signs the blocks the miner produces
+/// on top of the base tipset at the given round. The worker is looked up in the lookback state
+/// of the round, as it is when blocks are validated.
+pub async fn get_miner_worker<DB, V>(
+    sm: &Arc<StateManager<DB>>,
+    miner: &Address,
+    round: ChainEpoch,
+    base: &Arc<Tipset>,
+) -> Result<Address, Error>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    V: ProofVerifier,
+{
+    let (_, lbst) = lookback_state::<_, V>(sm, base, round).await?;
+    Ok(sm.get_miner_work_addr(&lbst, miner)?)
+}
+
+/// Assembles the block of the template on top of its parent tipset. The parent state and
+/// receipts are computed, and the messages are split by signature type, with the BLS signatures
+/// aggregated.
+pub async fn create_block<DB, V>(
+    sm: &Arc<StateManager<DB>>,
+    template: BlockTemplate,
+) -> Result<UnsignedBlock, Error>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    V: ProofVerifier,
+{
+    let base = sm.chain_store().tipset_from_keys(&template.parents)?;
+    let (state_root, message_receipts) = sm
+        .tipset_state::<V>(&base)
+        .await
+        .map_err(|e| Error::Other(format!("could not compute parent state: {}", e)))?;
+    let parent_base_fee = chain::compute_base_fee(sm.blockstore(), &base)?;
+    let weight = chain::weight(sm.blockstore(), &base)?;
+
+    let mut bls_messages = Vec::new();
+    let mut bls_sigs = Vec::new();
+    let mut secp_messages = Vec::new();
+    for smsg in template.messages {
+        if smsg.signature().signature_type() == SignatureType::BLS {
+            bls_sigs.push(smsg.signature().clone());
+            bls_messages.push(smsg.into_message());
+        } else {
+            secp_messages.push(smsg);
+        }
+    }
+    let messages = compute_msg_meta(sm.blockstore(), &bls_messages, &secp_messages)?;
+    let bls_aggregate = aggregate_bls(&bls_sigs)?;
+
+    let mut header = BlockHeader::builder();
+    header
+        .parents(template.parents)
+        .weight(weight)
+        .epoch(template.epoch)
+        .beacon_entries(template.beacon_values)
+        .winning_post_proof(template.winning_post_proof)
+        .miner_address(template.miner)
+        .messages(messages)
+        .message_receipts(message_receipts)
+        .state_root(state_root)
+        .fork_signal(0)
+        .timestamp(template.timestamp)
+        .ticket(Some(template.ticket))
+        .election_proof(Some(template.election_proof))
+        .bls_aggregate(Some(bls_aggregate))
+        .parent_base_fee(parent_base_fee);
+
+    Ok(UnsignedBlock {
+        header,
+        bls_messages,
+        secp_messages,
+    })
+}
+
+/// Returns the lookback tipset of the round on top of the base tipset, and its state.
+async fn lookback_state<DB, V>(
+    sm: &Arc<StateManager<DB>>,
+    base: &Arc<Tipset>,
+    round: ChainEpoch,
+) -> Result<(Arc<Tipset>, Cid), Error>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    V: ProofVerifier,
+{
+    let lbts = sm
+        .chain_store()
+        .get_lookback_tipset_for_round(base, round)?
+        .map(Arc::new)
+        .unwrap_or_else(|| Arc::clone(base));
+    let (lbst, _) = sm
+        .tipset_state::<V>(&lbts)
+        .await
+        .map_err(|e| Error::Other(format!("could not compute lookback state: {}", e)))?;
+    Ok((lbts, lbst))
+}
+
+pub(crate) fn draw_randomness(
+    rbase: &[u8],
+    pers: DomainSeparationTag,
+    round: ChainEpoch,
+    entropy: &[u8],
+) -> Result<[u8; 32], Error> {
+    chain::draw_randomness(rbase, pers, round, entropy)
+        .map_err(|e| Error::Other(format!("failed to draw randomness: {}", e)))
+}
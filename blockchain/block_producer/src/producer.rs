@@ -0,0 +1,492 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::{
+    create_block, draw_randomness, get_mining_base_info, BlockTemplate, Error, WinningPoStProver,
+};
+use address::Address;
+use async_std::sync::{RwLock, Sender};
+use async_std::task;
+use beacon::Beacon;
+use blocks::{Block, ElectionProof, GossipBlock, Ticket, Tipset, BLOCK_MESSAGE_LIMIT};
+use chain::HeadChange;
+use clock::ChainEpoch;
+use crypto::{DomainSeparationTag, Signature, Signer, VRFProof};
+use encoding::Cbor;
+use fil_types::{
+    verifier::ProofVerifier, Randomness, BLOCK_GAS_LIMIT, TICKET_RANDOMNESS_LOOKBACK,
+    UPGRADE_SMOKE_HEIGHT,
+};
+use forest_libp2p::{NetworkMessage, Topic, PUBSUB_BLOCK_STR};
+use interpreter::price_list_by_epoch;
+use ipld_blockstore::BlockStore;
+use log::{debug, info, warn};
+use message::{Message, SignedMessage};
+use message_pool::{MessagePool, Provider};
+use state_manager::StateManager;
+use state_tree::StateTree;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wallet::{KeyStore, KeyStoreSigner};
+
+/// Produces blocks for a miner actor on top of the heaviest tipset of the node. Blocks are signed
+/// with the key of the worker of the miner, which has to be in the keystore unless an external
+/// signer is used.
+pub struct BlockProducer<DB, KS, TBeacon, V, M> {
+    state_manager: Arc<StateManager<DB>>,
+    mpool: Arc<MessagePool<M>>,
+    beacon: Arc<TBeacon>,
+    keystore: Arc<RwLock<KS>>,
+    signer: Option<Arc<dyn Signer + Send + Sync>>,
+    prover: Arc<dyn WinningPoStProver + Send + Sync>,
+    /// Channel and network name produced blocks are gossiped with, if set.
+    gossip: Option<(Sender<NetworkMessage>, String)>,
+    miner: Address,
+    block_delay: u64,
+    verifier: PhantomData<V>,
+}
+
+impl<DB, KS, TBeacon, V, M> BlockProducer<DB, KS, TBeacon, V, M>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+    TBeacon: Beacon + Send + Sync + 'static,
+    V: ProofVerifier + Send + Sync + 'static,
+    M: Provider + Send + Sync + 'static,
+{
+    pub fn new(
+        state_manager: Arc<StateManager<DB>>,
+        mpool: Arc<MessagePool<M>>,
+        beacon: Arc<TBeacon>,
+        keystore: Arc<RwLock<KS>>,
+        prover: Arc<dyn WinningPoStProver + Send + Sync>,
+        miner: Address,
+        block_delay: u64,
+    ) -> Self {
+        Self {
+            state_manager,
+            mpool,
+            beacon,
+            keystore,
+            signer: None,
+            prover,
+            gossip: None,
+            miner,
+            block_delay,
+            verifier: Default::default(),
+        }
+    }
+
+    /// Signs with the given external signer instead of the worker key in the keystore.
+    pub fn with_signer(mut self, signer: Arc<dyn Signer + Send + Sync>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Publishes produced blocks on the blocks topic of the given network.
+    pub fn with_gossip(
+        mut self,
+        network_send: Sender<NetworkMessage>,
+        network_name: String,
+    ) -> Self {
+        self.gossip = Some((network_send, network_name));
+        self
+    }
+
+    /// Attempts to produce a block every round, for as long as the node runs.
+    pub async fn run(self) {
+        let mut base: Option<Arc<Tipset>> = None;
+        let mut nulls: ChainEpoch = 0;
+        loop {
+            let head = match self.state_manager.chain_store().heaviest_tipset().await {
+                Some(head) => head,
+                None => {
+                    task::sleep(Duration::from_secs(self.block_delay)).await;
+                    continue;
+                }
+            };
+            if base.as_ref().map(|b| b.key()) != Some(head.key()) {
+                base = Some(head.clone());
+                nulls = 0;
+            }
+
+            // Rounds which have already passed are skipped rather than filled with blocks
+            let now = now_secs();
+            let elapsed = now.saturating_sub(head.min_timestamp()) / self.block_delay;
+            nulls = nulls.max(elapsed.saturating_sub(1) as ChainEpoch);
+
+            let round = head.epoch() + nulls + 1;
+            let timestamp = head.min_timestamp() + self.block_delay * (nulls as u64 + 1);
+            if timestamp > now {
+                task::sleep(Duration::from_secs(timestamp - now)).await;
+            }
+
+            // A heavier tipset may have arrived while waiting, which is mined on instead
+            match self.state_manager.chain_store().heaviest_tipset().await {
+                Some(ts) if ts.key() != head.key() => continue,
+                _ => (),
+            }
+
+            match self.mine_one(&head, round, timestamp).await {
+                Ok(Some(block)) => {
+                    let cid = block.cid().to_string();
+                    match self.submit(block).await {
+                        Ok(()) => info!(cid = cid.as_str(), epoch = round; "Produced block"),
+                        Err(e) => {
+                            warn!("Failed to submit block at epoch {}: {}", round, e);
+                            nulls += 1;
+                        }
+                    }
+                }
+                Ok(None) => {
+                    debug!("Miner {} not elected at epoch {}", self.miner, round);
+                    nulls += 1;
+                }
+                Err(e) => {
+                    warn!("Failed to produce block at epoch {}: {}", round, e);
+                    nulls += 1;
+                }
+            }
+        }
+    }
+
+    /// Attempts to produce a block on top of the base tipset at the given round. Returns `None`
+    /// if the miner is not eligible or was not elected for the round.
+    pub async fn mine_one(
+        &self,
+        base: &Arc<Tipset>,
+        round: ChainEpoch,
+        timestamp: u64,
+    ) -> Result<Option<Block>, Error> {
+        let info = match get_mining_base_info::<_, _, V>(
+            &self.state_manager,
+            self.beacon.as_ref(),
+            &self.miner,
+            round,
+            base,
+        )
+        .await?
+        {
+            Some(info) => info,
+            None => {
+                debug!("Miner {} has no sectors to prove", self.miner);
+                return Ok(None);
+            }
+        };
+        if !info.eligible_for_mining {
+            debug!("Miner {} is not eligible for mining", self.miner);
+            return Ok(None);
+        }
+        let rbase = info.randomness_base();
+        let miner_bytes = self.miner.marshal_cbor()?;
+
+        let vrf_base = draw_randomness(
+            rbase.data(),
+            DomainSeparationTag::ElectionProofProduction,
+            round,
+            &miner_bytes,
+        )?;
+        let mut election_proof = ElectionProof {
+            win_count: 0,
+            vrfproof: self.compute_vrf(&info.worker_key, &vrf_base).await?,
+        };
+        election_proof.win_count =
+            election_proof.compute_win_count(&info.miner_power, &info.network_power);
+        if election_proof.win_count < 1 {
+            return Ok(None);
+        }
+
+        let mut entropy = miner_bytes.clone();
+        if round > UPGRADE_SMOKE_HEIGHT {
+            let base_ticket = base
+                .min_ticket()
+                .ok_or_else(|| Error::Other("base tipset has no ticket".to_owned()))?;
+            entropy.extend_from_slice(base_ticket.vrfproof.as_bytes());
+        }
+        let vrf_base = draw_randomness(
+            rbase.data(),
+            DomainSeparationTag::TicketProduction,
+            round - TICKET_RANDOMNESS_LOOKBACK,
+            &entropy,
+        )?;
+        let ticket = Ticket::new(self.compute_vrf(&info.worker_key, &vrf_base).await?);
+
+        let rand = draw_randomness(
+            rbase.data(),
+            DomainSeparationTag::WinningPoStChallengeSeed,
+            round,
+            &miner_bytes,
+        )?;
+        let miner_id = self
+            .miner
+            .id()
+            .map_err(|e| Error::Other(format!("miner address is not an ID address: {}", e)))?;
+        // Proving reads the challenged sectors from disk, so it is kept off the async executor
+        let prover = self.prover.clone();
+        let sectors = info.sectors.clone();
+        let winning_post_proof = task::spawn_blocking(move || {
+            prover
+                .generate_winning_post(miner_id, &sectors, Randomness(rand))
+                .map_err(|e| Error::Other(format!("failed to generate winning PoSt: {}", e)))
+        })
+        .await?;
+
+        let messages = self.select_messages(base).await?;
+        let template = BlockTemplate {
+            miner: self.miner,
+            parents: base.key().clone(),
+            ticket,
+            election_proof,
+            beacon_values: info.beacon_entries.clone(),
+            messages,
+            epoch: round,
+            timestamp,
+            winning_post_proof,
+        };
+        let unsigned = create_block::<_, V>(&self.state_manager, template).await?;
+        let signature = self
+            .sign(&info.worker_key, unsigned.signing_bytes()?)
+            .await?;
+        Ok(Some(unsigned.sign(signature)?))
+    }
+
+    /// Selects pending messages from the message pool which can be included on top of the
+    /// base tipset.
+    async fn select_messages(&self, base: &Arc<Tipset>) -> Result<Vec<SignedMessage>, Error> {
+        let sm = &self.state_manager;
+        let (state_root, _) = sm
+            .tipset_state::<V>(base)
+            .await
+            .map_err(|e| Error::Other(format!("could not compute parent state: {}", e)))?;
+        let base_fee = chain::compute_base_fee(sm.blockstore(), base)?;
+        let (pending, _) = self.mpool.pending().await?;
+
+        let mut by_sender: HashMap<Address, Vec<SignedMessage>> = HashMap::new();
+        for msg in pending {
+            by_sender.entry(*msg.from()).or_default().push(msg);
+        }
+
+        // Only messages which continue from the sequence of the sender in the parent state
+        // can be applied
+        let tree = StateTree::new_from_root(sm.blockstore(), &state_root)?;
+        let mut chains = Vec::new();
+        for (from, mut msgs) in by_sender {
+            let mut sequence = match tree.get_actor(&from)? {
+                Some(actor) => actor.sequence,
+                None => continue,
+            };
+            msgs.sort_by_key(|m| m.sequence());
+            let chain: Vec<SignedMessage> = msgs
+                .into_iter()
+                .skip_while(|m| m.sequence() < sequence)
+                .take_while(|m| {
+                    let next = m.sequence() == sequence;
+                    sequence += 1;
+                    next
+                })
+                .collect();
+            if !chain.is_empty() {
+                chains.push(chain);
+            }
+        }
+        // Senders paying the highest premium are included first
+        chains.sort_by(|a, b| b[0].gas_premium().cmp(a[0].gas_premium()));
+
+        let pl = price_list_by_epoch(base.epoch());
+        let mut gas_limit = 0;
+        let mut count = 0;
+        let mut selected = Vec::new();
+        'chains: for chain in chains {
+            for smsg in chain {
+                let msg = smsg.message();
+                let min_gas = pl.on_chain_message(msg.marshal_cbor()?.len());
+                if msg.gas_fee_cap() < &base_fee
+                    || msg.valid_for_block_inclusion(min_gas.total()).is_err()
+                    || gas_limit + msg.gas_limit() > BLOCK_GAS_LIMIT
+                    || count >= BLOCK_MESSAGE_LIMIT
+                {
+                    // Later messages of the sender can not be applied without this one
+                    continue 'chains;
+                }
+                gas_limit += msg.gas_limit();
+                count += 1;
+
+                selected.push(smsg);
+            }
+        }
+
+        Ok(selected)
+    }
+
+    /// Stores the block and sets it as the head, if it is heavier than the current one.
+    async fn submit(&self, block: Block) -> Result<(), Error> {
+        let cs = self.state_manager.chain_store();
+        chain::persist_objects(cs.blockstore(), &block.bls_messages)?;
+        chain::persist_objects(cs.blockstore(), &block.secp_messages)?;
+
+        let ts = Arc::new(Tipset::new(vec![block.header.clone()])?);
+        cs.set_tipset_tracker(&block.header).await?;
+        cs.put_tipset(&ts).await?;
+
+        if let Some((network_send, network_name)) = &self.gossip {
+            let gossip_block = GossipBlock {
+                header: block.header.clone(),
+                bls_messages: block
+                    .bls_messages
+                    .iter()
+                    .map(|m| m.cid())
+                    .collect::<Result<_, _>>()?,
+                secpk_messages: block
+                    .secp_messages
+                    .iter()
+                    .map(|m| m.cid())
+                    .collect::<Result<_, _>>()?,
+            };
+            network_send
+                .send(NetworkMessage::PubsubMessage {
+                    topic: Topic::new(format!("{}/{}", PUBSUB_BLOCK_STR, network_name)),
+                    message: gossip_block.marshal_cbor()?,
+                })
+                .await;
+        }
+
+        // The message pool and message waiters are only notified of applied tipsets
+        if cs.heaviest_tipset().await.as_ref() == Some(&ts) {
+            cs.publisher()
+                .write()
+                .await
+                .publish(HeadChange::Apply(ts))
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Computes a VRF proof over the randomness, with the key of the worker.
+    async fn compute_vrf(&self, worker: &Address, vrf_base: &[u8]) -> Result<VRFProof, Error> {
+        let sig = self.sign(worker, vrf_base.to_vec()).await?;
+        Ok(VRFProof::new(sig.bytes().to_vec()))
+    }
+
+    async fn sign(&self, addr: &Address, bytes: Vec<u8>) -> Result<Signature, Error> {
+        match &self.signer {
+            Some(signer) => {
+                let signer = signer.clone();
+                let addr = *addr;
+                task::spawn_blocking(move || {
+                    signer
+                        .sign_bytes(&bytes, &addr)
+                        .map_err(|e| Error::Signing(e.to_string()))
+                })
+                .await
+            }
+            None => {
+                let keystore = self.keystore.read().await;
+                KeyStoreSigner::new(&*keystore)
+                    .sign_bytes(&bytes, addr)
+                    .map_err(|e| Error::Signing(e.to_string()))
+            }
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Devnet, DevnetProducer, DEVNET_BLOCK_DELAY};
+    use chain_sync::validate_block;
+    use fil_types::verifier::MockVerifier;
+    use genesis::MINER_START;
+    use message_pool::test_provider::TestApi;
+
+    /// Returns the devnet and a producer for its miner.
+    async fn devnet_producer() -> (Devnet, DevnetProducer<TestApi>) {
+        let devnet = Devnet::new().await;
+        let mpool = MessagePool::new(TestApi::default(), "test".to_owned(), Default::default())
+            .await
+            .unwrap();
+        let producer = devnet.producer(Arc::new(mpool));
+        (devnet, producer)
+    }
+
+    #[test]
+    fn devnet_miner_is_eligible() {
+        task::block_on(async {
+            let (devnet, producer) = devnet_producer().await;
+            let genesis = &devnet.genesis;
+            let info = get_mining_base_info::<_, _, MockVerifier>(
+                &producer.state_manager,
+                producer.beacon.as_ref(),
+                &producer.miner,
+                1,
+                genesis,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+            // The only miner of the devnet has all the power of the network
+            assert!(info.eligible_for_mining);
+            assert_eq!(info.miner_power, info.network_power);
+            assert!(!info.sectors.is_empty());
+            assert_eq!(info.beacon_entries.len(), 1);
+
+            // Miners without power are never elected
+            let election_proof = ElectionProof {
+                win_count: 0,
+                vrfproof: producer
+                    .compute_vrf(&info.worker_key, &[0; 32])
+                    .await
+                    .unwrap(),
+            };
+            assert_eq!(
+                election_proof.compute_win_count(&Default::default(), &info.network_power),
+                0
+            );
+
+            // Miners without an actor don't mine
+            let info = get_mining_base_info::<_, _, MockVerifier>(
+                &producer.state_manager,
+                producer.beacon.as_ref(),
+                &Address::new_id(MINER_START + 1),
+                1,
+                genesis,
+            )
+            .await
+            .unwrap();
+            assert!(info.is_none());
+        });
+    }
+
+    #[test]
+    fn devnet_round_produces_valid_block() {
+        task::block_on(async {
+            let (devnet, producer) = devnet_producer().await;
+            let genesis = &devnet.genesis;
+            let (round, block) = devnet.mine(&producer).await;
+
+            let header = block.header();
+            assert_eq!(header.epoch(), round);
+            assert_eq!(header.parents(), genesis.key());
+            assert_eq!(header.miner_address(), &producer.miner);
+            assert!(header.election_proof().as_ref().unwrap().win_count > 0);
+
+            validate_block::<_, _, MockVerifier>(
+                Arc::clone(&producer.state_manager),
+                Arc::clone(&producer.beacon),
+                Arc::new(block),
+                DEVNET_BLOCK_DELAY,
+            )
+            .await
+            .unwrap();
+        });
+    }
+}
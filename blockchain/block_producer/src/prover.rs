@@ -0,0 +1,123 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use commcid::cid_to_replica_commitment_v1;
+use fil_types::verifier::prover_id_from_u64;
+use fil_types::{PoStProof, Randomness, SectorInfo};
+use filecoin_proofs_api::{post, PrivateReplicaInfo, SectorId};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::error::Error as StdError;
+use std::path::PathBuf;
+
+/// Generates the winning PoSt proofs which elected miners include in their blocks.
+pub trait WinningPoStProver {
+    /// Proves the challenged sectors of the miner are stored, for the given challenge randomness.
+    fn generate_winning_post(
+        &self,
+        miner_id: u64,
+        sectors: &[SectorInfo],
+        randomness: Randomness,
+    ) -> Result<Vec<PoStProof>, Box<dyn StdError>>;
+}
+
+/// Prover which generates empty proofs. These only verify with mock proof verification, so this
+/// prover can only be used on devnets.
+pub struct MockProver;
+
+impl WinningPoStProver for MockProver {
+    fn generate_winning_post(
+        &self,
+        _: u64,
+        sectors: &[SectorInfo],
+        _: Randomness,
+    ) -> Result<Vec<PoStProof>, Box<dyn StdError>> {
+        let sector = sectors.first().ok_or("miner has no sectors to prove")?;
+        Ok(vec![PoStProof {
+            post_proof: sector.proof.registered_winning_post_proof()?,
+            proof_bytes: Vec::new(),
+        }])
+    }
+}
+
+/// Prover which generates proofs from sealed sectors on the local filesystem. Sectors are
+/// expected in the `sealed` and `cache` directories of the storage path, named `s-t0<miner>-<sector>`.
+pub struct LocalProver {
+    storage: PathBuf,
+}
+
+impl LocalProver {
+    pub fn new(storage: impl Into<PathBuf>) -> Self {
+        Self {
+            storage: storage.into(),
+        }
+    }
+}
+
+impl WinningPoStProver for LocalProver {
+    fn generate_winning_post(
+        &self,
+        miner_id: u64,
+        sectors: &[SectorInfo],
+        Randomness(mut randomness): Randomness,
+    ) -> Result<Vec<PoStProof>, Box<dyn StdError>> {
+        let post_proof = match sectors.first() {
+            Some(sector) => sector.proof.registered_winning_post_proof()?,
+            None => return Err("miner has no sectors to prove".into()),
+        };
+
+        // Necessary to be valid bls12 381 element.
+        randomness[31] &= 0x3f;
+
+        let mut replicas = BTreeMap::new();
+        for sector in sectors {
+            let name = format!("s-t0{}-{}", miner_id, sector.sector_number);
+            let comm_r = cid_to_replica_commitment_v1(&sector.sealed_cid)?;
+            let replica = PrivateReplicaInfo::new(
+                post_proof.try_into()?,
+                comm_r,
+                self.storage.join("cache").join(&name),
+                self.storage.join("sealed").join(&name),
+            );
+            replicas.insert(SectorId::from(sector.sector_number), replica);
+        }
+
+        let proofs =
+            post::generate_winning_post(&randomness, &replicas, prover_id_from_u64(miner_id))?;
+        Ok(proofs
+            .into_iter()
+            .map(|(_, proof_bytes)| PoStProof {
+                post_proof,
+                proof_bytes,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cid::Cid;
+    use fil_types::{RegisteredPoStProof, RegisteredSealProof, SectorSize};
+
+    #[test]
+    fn mock_prover_proves_challenged_sectors() {
+        let sectors = vec![SectorInfo {
+            proof: RegisteredSealProof::from(SectorSize::_2KiB),
+            sector_number: 1,
+            sealed_cid: Cid::default(),
+        }];
+        let proofs = MockProver
+            .generate_winning_post(1000, &sectors, Randomness([0; 32]))
+            .unwrap();
+        assert_eq!(proofs.len(), 1);
+        assert_eq!(
+            proofs[0].post_proof,
+            RegisteredPoStProof::StackedDRGWinning2KiBV1
+        );
+
+        assert!(MockProver
+            .generate_winning_post(1000, &[], Randomness([0; 32]))
+            .is_err());
+    }
+}
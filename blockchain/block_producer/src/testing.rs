@@ -0,0 +1,105 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::{BlockProducer, MockProver};
+use address::Address;
+use async_std::sync::RwLock;
+use beacon::MockBeacon;
+use blocks::{Block, Tipset};
+use chain::ChainStore;
+use clock::ChainEpoch;
+use crypto::SignatureType;
+use db::MemoryDB;
+use fil_types::verifier::MockVerifier;
+use fil_types::{DevnetParams, NetworkPolicy};
+use genesis::{build_genesis, devnet_template, MINER_START};
+use message_pool::{MessagePool, Provider};
+use state_manager::StateManager;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wallet::{generate_key, MemKeyStore};
+
+/// Seconds between epochs of the test devnet.
+pub const DEVNET_BLOCK_DELAY: u64 = 1;
+
+/// Rounds mined before giving up on the miner being elected. With all the power of the network,
+/// the miner is expected to win several times per round.
+const DEVNET_ROUNDS: ChainEpoch = 10;
+
+/// Producer of blocks for the test devnet.
+pub type DevnetProducer<M> = BlockProducer<MemoryDB, MemKeyStore, MockBeacon, MockVerifier, M>;
+
+/// Devnet chain with a single miner, for tests which produce and validate blocks. The genesis
+/// is the head of the chain and the worker key of the miner is in the keystore.
+pub struct Devnet {
+    pub state_manager: Arc<StateManager<MemoryDB>>,
+    pub keystore: Arc<RwLock<MemKeyStore>>,
+    pub beacon: Arc<MockBeacon>,
+    pub genesis: Arc<Tipset>,
+}
+
+impl Devnet {
+    /// Builds the devnet genesis. It is dated before the rounds which are mined, so blocks are
+    /// never from the future and rounds don't have to be waited for.
+    pub async fn new() -> Self {
+        let key = generate_key(SignatureType::BLS).unwrap();
+        let mut keystore = MemKeyStore::new();
+        wallet::import(key.key_info, &mut keystore).unwrap();
+
+        let db = Arc::new(MemoryDB::default());
+        let mut template = devnet_template("test".to_owned(), key.address, 2);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        template.set_timestamp(now - DEVNET_BLOCK_DELAY * DEVNET_ROUNDS as u64);
+        let header =
+            build_genesis::<MockVerifier, DevnetParams, _>(db.as_ref(), &template).unwrap();
+        let cs = Arc::new(ChainStore::new(db));
+        cs.set_genesis(&header).unwrap();
+        let genesis = Arc::new(Tipset::new(vec![header]).unwrap());
+        cs.set_heaviest_tipset(Arc::clone(&genesis)).await.unwrap();
+
+        Self {
+            state_manager: Arc::new(StateManager::new(cs).with_network(NetworkPolicy::Devnet)),
+            keystore: Arc::new(RwLock::new(keystore)),
+            beacon: Arc::new(MockBeacon::new(Duration::from_secs(DEVNET_BLOCK_DELAY))),
+            genesis,
+        }
+    }
+
+    /// Returns a producer for the devnet miner, which includes messages of the pool.
+    pub fn producer<M>(&self, mpool: Arc<MessagePool<M>>) -> DevnetProducer<M>
+    where
+        M: Provider + Send + Sync + 'static,
+    {
+        BlockProducer::new(
+            Arc::clone(&self.state_manager),
+            mpool,
+            Arc::clone(&self.beacon),
+            Arc::clone(&self.keystore),
+            Arc::new(MockProver),
+            Address::new_id(MINER_START),
+            DEVNET_BLOCK_DELAY,
+        )
+    }
+
+    /// Mines on top of the genesis until the miner is elected, and returns the round and the
+    /// block produced in it.
+    pub async fn mine<M>(&self, producer: &DevnetProducer<M>) -> (ChainEpoch, Block)
+    where
+        M: Provider + Send + Sync + 'static,
+    {
+        for round in 1..=DEVNET_ROUNDS {
+            let timestamp = self.genesis.min_timestamp() + DEVNET_BLOCK_DELAY * round as u64;
+            if let Some(block) = producer
+                .mine_one(&self.genesis, round, timestamp)
+                .await
+                .unwrap()
+            {
+                return (round, block);
+            }
+        }
+        panic!("miner was not elected in any round");
+    }
+}
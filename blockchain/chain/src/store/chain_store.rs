@@ -181,6 +181,37 @@ where
         Ok(())
     }
 
+    /// Verifies that the state root of the heaviest tipset is stored, otherwise rolls the head
+    /// back to the closest ancestor whose state root is. States are written in a single batch,
+    /// so a stored root means the whole state is. Returns the new head if it was rolled back.
+    pub async fn rollback_to_stored_state(&self) -> Result<Option<Arc<Tipset>>, Error> {
+        let head = match self.heaviest_tipset().await {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+        if self.db.exists(head.parent_state().to_bytes())? {
+            return Ok(None);
+        }
+
+        let mut ts = head.as_ref().clone();
+        while !self.db.exists(ts.parent_state().to_bytes())? {
+            if ts.epoch() == 0 {
+                return Err(Error::Other(
+                    "No tipset with a stored state found".to_owned(),
+                ));
+            }
+            ts = self.tipset_from_keys(ts.parents())?;
+        }
+        warn!(
+            "State of head epoch={} not found, rolling back to epoch={}",
+            head.epoch(),
+            ts.epoch()
+        );
+        let ts = Arc::new(ts);
+        self.set_heaviest_tipset(ts.clone()).await?;
+        Ok(Some(ts))
+    }
+
     /// Returns genesis blockheader from blockstore
     pub fn genesis(&self) -> Result<Option<BlockHeader>, Error> {
         genesis(self.blockstore())
@@ -890,4 +921,41 @@ mod tests {
         cs.mark_block_as_validated(&cid).unwrap();
         assert_eq!(cs.is_block_validated(&cid).unwrap(), true);
     }
+
+    #[test]
+    fn rollback_head_without_state() {
+        let db = Arc::new(db::MemoryDB::default());
+        let cs = ChainStore::new(db.clone());
+
+        let header = |epoch: ChainEpoch, parents: Vec<Cid>, state_root: Cid| {
+            BlockHeader::builder()
+                .epoch(epoch)
+                .parents(TipsetKeys::new(parents))
+                .messages(Cid::new_from_cbor(&[], Identity))
+                .message_receipts(Cid::new_from_cbor(&[], Identity))
+                .state_root(state_root)
+                .miner_address(Address::new_id(0))
+                .build_and_validate()
+                .unwrap()
+        };
+        let stored_root = db.put(&"state", Blake2b256).unwrap();
+        let missing_root = Cid::new_from_cbor(&[1, 2, 3], Blake2b256);
+
+        let gen = header(0, vec![], stored_root);
+        let first = header(1, vec![*gen.cid()], stored_root);
+        let second = header(2, vec![*first.cid()], missing_root);
+        persist_objects(db.as_ref(), &[&gen, &first, &second]).unwrap();
+
+        let first_ts = Arc::new(Tipset::new(vec![first]).unwrap());
+        task::block_on(cs.set_heaviest_tipset(first_ts.clone())).unwrap();
+        assert_eq!(task::block_on(cs.rollback_to_stored_state()).unwrap(), None);
+
+        let second_ts = Arc::new(Tipset::new(vec![second]).unwrap());
+        task::block_on(cs.set_heaviest_tipset(second_ts)).unwrap();
+        assert_eq!(
+            task::block_on(cs.rollback_to_stored_state()).unwrap(),
+            Some(first_ts.clone())
+        );
+        assert_eq!(task::block_on(cs.heaviest_tipset()), Some(first_ts));
+    }
 }
@@ -40,6 +40,9 @@ pub enum Error {
     /// Block validated was from the future, this is intended to be a temporal error.
     #[error("Block received was from the future (now={0}, blk={1}")]
     Temporal(u64, u64),
+    /// Sync was stopped because the node is shutting down.
+    #[error("Sync stopped for shutdown")]
+    Stopped,
     /// Any other error that does not need to be specifically handled
     #[error("{0}")]
     Other(String),
@@ -18,6 +18,7 @@ extern crate serde;
 pub use self::bad_block_cache::BadBlockCache;
 pub use self::errors::Error;
 pub use self::network_context::SyncNetworkContext;
-pub use self::sync::ChainSyncer;
+pub use self::peer_manager::PeerManager;
+pub use self::sync::{ChainSyncer, SyncStopHandle};
 pub use self::sync_state::{SyncStage, SyncState};
-pub use self::sync_worker::compute_msg_meta;
+pub use self::sync_worker::{compute_msg_meta, validate_block};
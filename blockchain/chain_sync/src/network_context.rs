@@ -228,6 +228,14 @@ where
         let res_duration = SystemTime::now()
             .duration_since(req_pre_time)
             .unwrap_or_default();
+        let result = if matches!(res, Ok(Ok(_))) {
+            "success"
+        } else {
+            "failure"
+        };
+        metrics::BLOCKSYNC_REQUEST_SECONDS
+            .with_label_values(&[result])
+            .observe(res_duration.as_secs_f64());
         match res {
             Ok(Ok(bs_res)) => {
                 self.peer_manager.log_success(&peer_id, res_duration).await;
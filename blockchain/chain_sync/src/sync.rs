@@ -6,6 +6,7 @@ mod peer_test;
 
 use super::bad_block_cache::BadBlockCache;
 use super::bucket::{SyncBucket, SyncBucketSet};
+use super::peer_manager::PeerManager;
 use super::sync_state::SyncState;
 use super::sync_worker::SyncWorker;
 use super::{Error, SyncNetworkContext};
@@ -17,7 +18,7 @@ use blocks::{Block, FullTipset, Tipset, TipsetKeys, TxMeta};
 use chain::ChainStore;
 use cid::{Cid, Code::Blake2b256};
 use encoding::{Cbor, Error as EncodingError};
-use fil_types::verifier::ProofVerifier;
+use fil_types::{verifier::ProofVerifier, BLOCK_DELAY_SECS};
 use forest_libp2p::{hello::HelloRequest, NetworkEvent, NetworkMessage};
 use futures::future::try_join_all;
 use futures::select;
@@ -29,6 +30,7 @@ use message::{SignedMessage, UnsignedMessage};
 use message_pool::{MessagePool, Provider};
 use state_manager::StateManager;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 // TODO revisit this type, necessary for two sets of Arc<Mutex<>> because each state is
@@ -83,10 +85,36 @@ pub struct ChainSyncer<DB, TBeacon, V, M> {
     ///  incoming network events to be handled by syncer
     net_handler: Receiver<NetworkEvent>,
 
+    /// Seconds between epochs, which block timestamps are validated against.
+    block_delay: u64,
+
     /// Proof verification implementation.
     verifier: PhantomData<V>,
 
     mpool: Arc<MessagePool<M>>,
+
+    /// Set when the syncer is stopped, shared with the sync workers.
+    stopping: Arc<AtomicBool>,
+    /// Wakes the syncer when it is stopped.
+    stop_tx: Sender<()>,
+    stop_rx: Receiver<()>,
+}
+
+/// Handle to stop a running `ChainSyncer`, for a graceful shutdown of the node.
+#[derive(Clone)]
+pub struct SyncStopHandle {
+    stopping: Arc<AtomicBool>,
+    stop_tx: Sender<()>,
+}
+
+impl SyncStopHandle {
+    /// Stops the syncer. Sync workers stop at the next point where no partially validated
+    /// tipsets are left behind, which `ChainSyncer::start` awaits before returning.
+    pub async fn stop(&self) {
+        if !self.stopping.swap(true, Ordering::SeqCst) {
+            self.stop_tx.send(()).await;
+        }
+    }
 }
 
 impl<DB, TBeacon, V, M> ChainSyncer<DB, TBeacon, V, M>
@@ -109,6 +137,7 @@ where
             Default::default(),
             state_manager.blockstore_cloned(),
         );
+        let (stop_tx, stop_rx) = channel(1);
 
         Ok(Self {
             state: ChainSyncState::Bootstrap,
@@ -122,11 +151,21 @@ where
             sync_queue: SyncBucketSet::default(),
             active_sync_tipsets: SyncBucketSet::default(),
             next_sync_target: None,
+            block_delay: BLOCK_DELAY_SECS,
             verifier: Default::default(),
             mpool,
+            stopping: Default::default(),
+            stop_tx,
+            stop_rx,
         })
     }
 
+    /// Sets the seconds between epochs, for networks which don't use the default block time.
+    pub fn with_block_delay(mut self, block_delay: u64) -> Self {
+        self.block_delay = block_delay;
+        self
+    }
+
     /// Returns a clone of the bad blocks cache to be used outside of chain sync.
     pub fn bad_blocks_cloned(&self) -> Arc<BadBlockCache> {
         self.bad_blocks.clone()
@@ -137,11 +176,26 @@ where
         self.worker_state.clone()
     }
 
-    /// Spawns a network handler and begins the syncing process.
+    /// Returns a cloned `Arc` of the manager of the peers blocks are synced from.
+    pub fn peer_manager_cloned(&self) -> Arc<PeerManager> {
+        self.network.peer_manager_cloned()
+    }
+
+    /// Returns a handle to stop the syncer.
+    pub fn stop_handle(&self) -> SyncStopHandle {
+        SyncStopHandle {
+            stopping: self.stopping.clone(),
+            stop_tx: self.stop_tx.clone(),
+        }
+    }
+
+    /// Spawns a network handler and begins the syncing process. Returns once the network
+    /// handler closes or the syncer is stopped, and the sync workers have finished.
     pub async fn start(mut self, num_workers: usize) {
         let (worker_tx, worker_rx) = channel(5);
+        let mut workers = Vec::with_capacity(num_workers);
         for _ in 0..num_workers {
-            self.spawn_worker(worker_rx.clone()).await;
+            workers.push(self.spawn_worker(worker_rx.clone()).await);
         }
 
         // Channels to handle fetching hello tipsets in separate task and return tipset.
@@ -149,6 +203,7 @@ where
 
         let mut fused_handler = self.net_handler.clone().fuse();
         let mut fused_inform_channel = new_ts_rx.fuse();
+        let mut fused_stop = self.stop_rx.clone().fuse();
 
         loop {
             // TODO would be ideal if this is a future attached to the select
@@ -218,12 +273,13 @@ where
                                         continue;
                                     }
                                 };
-                                info!("Received block over GossipSub: {} from {}", b.header.epoch(), source);
+                                info!("Received block over GossipSub epoch={} peer={}", b.header.epoch(), source);
                                 // Get bls_messages in the store or over Bitswap
                                 let bmsgs: Vec<_> = b.bls_messages.into_iter().map(|m| self.network.bitswap_get::<UnsignedMessage>(m)).collect();
                                 let bmsgs = try_join_all(bmsgs).await;
                                 if let Err(e) = &bmsgs {
                                     warn!("Failed to get UnsignedMessage: {}", e);
+                                    metrics::record_gossip(metrics::GOSSIP_BLOCK, false);
                                     continue;
                                 }
                                 // Get secp_messages in the store or over Bitswap
@@ -231,6 +287,7 @@ where
                                 let smsgs = try_join_all(smsgs).await;
                                 if let Err(e) = &smsgs {
                                     warn!("Failed to get SignedMessage: {}", e);
+                                    metrics::record_gossip(metrics::GOSSIP_BLOCK, false);
                                     continue;
                                 }
                                 // Form block
@@ -240,14 +297,18 @@ where
                                     secp_messages: smsgs.unwrap(),
                                 };
                                 let ts = FullTipset::new(vec![block]).unwrap();
-                                if self.inform_new_head(source.clone(), &ts).await.is_err() {
+                                let accepted = self.inform_new_head(source.clone(), &ts).await.is_ok();
+                                metrics::record_gossip(metrics::GOSSIP_BLOCK, accepted);
+                                if !accepted {
                                     warn!("failed to inform new head from peer {}", source);
                                 }
                             }
                             forest_libp2p::PubsubMessage::Message(m) => {
                                 // add message to message pool
                                 // TODO handle adding message to mempool in seperate task.
-                                if let Err(e) = self.mpool.add(m).await {
+                                let res = self.mpool.add(m).await;
+                                metrics::record_gossip(metrics::GOSSIP_MESSAGE, res.is_ok());
+                                if let Err(e) = res {
                                     trace!("Gossip Message failed to be added to Message pool: {}", e);
                                 }
                             }
@@ -264,9 +325,17 @@ where
                         }
                     }
                     None => break,
-                }
+                },
+                _ = fused_stop.next() => break,
             }
         }
+
+        // Closing the channel stops the workers once they finish or stop their current sync
+        drop(worker_tx);
+        for worker in workers {
+            worker.await;
+        }
+        info!("Chain syncer stopped");
     }
 
     /// Fetches a tipset from store or network, then passes the tipset back through the channel
@@ -301,7 +370,9 @@ where
             network: self.network.clone(),
             genesis: self.genesis.clone(),
             bad_blocks: self.bad_blocks.clone(),
+            block_delay: self.block_delay,
             verifier: PhantomData::<V>::default(),
+            stopping: self.stopping.clone(),
         }
         .spawn(channel)
         .await
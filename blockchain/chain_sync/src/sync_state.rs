@@ -94,6 +94,11 @@ impl SyncState {
         self.stage = stage;
     }
 
+    /// Returns the epoch of the last tipset synced.
+    pub fn epoch(&self) -> ChainEpoch {
+        self.epoch
+    }
+
     /// Sets epoch of the sync.
     pub fn set_epoch(&mut self, epoch: ChainEpoch) {
         self.epoch = epoch;
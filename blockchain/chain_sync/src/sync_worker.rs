@@ -18,6 +18,7 @@ use beacon::{Beacon, BeaconEntry, IGNORE_DRAND_VAR};
 use blocks::{Block, BlockHeader, FullTipset, Tipset, TipsetKeys, TxMeta};
 use chain::{persist_objects, ChainStore};
 use cid::{Cid, Code::Blake2b256};
+use clock::ChainEpoch;
 use crypto::{verify_bls_aggregate, DomainSeparationTag};
 use encoding::{Cbor, Error as EncodingError};
 use fil_types::{
@@ -33,12 +34,32 @@ use message::{Message, SignedMessage, UnsignedMessage};
 use state_manager::StateManager;
 use state_tree::StateTree;
 use std::cmp::min;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryInto;
 use std::error::Error as StdError;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Number of tipsets which can be validated at the same time during message sync.
+const VALIDATION_WINDOW: usize = 2;
+
+/// Block validations of a tipset which have been started but not awaited.
+struct PendingValidation {
+    epoch: ChainEpoch,
+    blocks: Vec<JoinHandle<Result<Arc<Block>, (Cid, Error)>>>,
+    started: Instant,
+}
+
+impl PendingValidation {
+    /// Cancels the block validations of a tipset which will not be finished.
+    async fn cancel(self) {
+        for block in self.blocks {
+            block.cancel().await;
+        }
+    }
+}
 
 /// Worker to handle syncing chain with the blocksync protocol.
 pub(crate) struct SyncWorker<DB, TBeacon, V> {
@@ -61,8 +82,14 @@ pub(crate) struct SyncWorker<DB, TBeacon, V> {
     /// Will mark any invalid blocks and all childen as bad in this bounded cache.
     pub bad_blocks: Arc<BadBlockCache>,
 
+    /// Seconds between epochs, which block timestamps are validated against.
+    pub block_delay: u64,
+
     /// Proof verification implementation.
     pub verifier: PhantomData<V>,
+
+    /// Set when the node is shutting down, for the worker to stop at the next safe point.
+    pub stopping: Arc<AtomicBool>,
 }
 
 impl<DB, TBeacon, V> SyncWorker<DB, TBeacon, V>
@@ -78,10 +105,17 @@ where
     pub async fn spawn(self, mut inbound_channel: Receiver<Arc<Tipset>>) -> JoinHandle<()> {
         task::spawn(async move {
             while let Some(ts) = inbound_channel.next().await {
-                if let Err(e) = self.sync(ts).await {
-                    let err = e.to_string();
-                    warn!("failed to sync tipset: {}", &err);
-                    self.state.write().await.error(err);
+                match self.sync(ts).await {
+                    Ok(()) => {}
+                    Err(Error::Stopped) => {
+                        info!("Sync worker stopped");
+                        break;
+                    }
+                    Err(e) => {
+                        let err = e.to_string();
+                        warn!("failed to sync tipset: {}", &err);
+                        self.state.write().await.error(err);
+                    }
                 }
             }
         })
@@ -93,6 +127,7 @@ where
         // TODO increase bootstrap peer count before syncing
         const MIN_PEERS: usize = 1;
         loop {
+            self.check_stopping()?;
             let peer_count = self.network.peer_manager().len().await;
             if peer_count < MIN_PEERS {
                 debug!("bootstrapping peers, have {}", peer_count);
@@ -144,6 +179,16 @@ where
         Ok(())
     }
 
+    /// Returns `Error::Stopped` if the node is shutting down. This is checked where stopping
+    /// leaves no partially validated tipsets behind, the head being only set once fully synced.
+    fn check_stopping(&self) -> Result<(), Error> {
+        if self.stopping.load(Ordering::SeqCst) {
+            Err(Error::Stopped)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Sets the managed sync status
     pub async fn set_stage(&self, new_stage: SyncStage) {
         debug!("Sync stage set to: {}", new_stage);
@@ -170,6 +215,7 @@ where
 
         // Loop until most recent tipset height is less than to tipset height
         'sync: while let Some(cur_ts) = return_set.last() {
+            self.check_stopping()?;
             // Check if parent cids exist in bad block caches
             self.validate_tipset_against_cache(cur_ts.parents(), &accepted_blocks)
                 .await?;
@@ -301,13 +347,43 @@ where
         ))
     }
 
-    /// Syncs messages by first checking state for message existence otherwise fetches messages from blocksync
+    /// Syncs messages by first checking state for message existence otherwise fetches messages from blocksync.
+    ///
+    /// Validation of a tipset is started once the state of its grandparent is computed, so the
+    /// signature, ticket and PoSt checks of a tipset run while the state of its parent is being
+    /// computed by the validation of the previous tipset.
     async fn sync_messages_check_state(&self, tipsets: Vec<Tipset>) -> Result<(), Error> {
+        let mut pending = VecDeque::new();
+        let res = self.sync_messages(tipsets, &mut pending).await;
+        // Validations are only left pending if syncing failed, they are cancelled rather than
+        // left running detached.
+        for validation in pending {
+            validation.cancel().await;
+        }
+        res
+    }
+
+    /// Fetches the messages of the tipsets and validates them in order, keeping the
+    /// validations which are started but not finished in `pending`.
+    async fn sync_messages(
+        &self,
+        tipsets: Vec<Tipset>,
+        pending: &mut VecDeque<PendingValidation>,
+    ) -> Result<(), Error> {
         let mut ts_iter = tipsets.into_iter().rev();
         // Currently syncing 1 height at a time, no reason for us to sync more
         const REQUEST_WINDOW: usize = 1;
 
+        let mut prev: Option<Tipset> = None;
+
         while let Some(ts) = ts_iter.next() {
+            if self.stopping.load(Ordering::SeqCst) {
+                // Finish the validations already started, so the states they compute are stored
+                while let Some(validation) = pending.pop_front() {
+                    self.finish_validation(validation).await?;
+                }
+                return Err(Error::Stopped);
+            }
             // check storage first to see if we have full tipset
             let fts = match self.chain_store().fill_tipset(ts) {
                 Ok(fts) => fts,
@@ -343,69 +419,158 @@ where
                         // construct full tipsets from fetched messages
                         let fts: FullTipset = (&bundle).try_into().map_err(Error::Other)?;
 
-                        // validate tipset and messages
-                        let curr_epoch = fts.epoch();
-                        self.validate_tipset(fts).await?;
-                        self.state.write().await.set_epoch(curr_epoch);
-
-                        // store messages
+                        // store messages before validation, the state of this tipset is
+                        // computed when validating the next one
                         if let Some(m) = bundle.messages {
                             chain::persist_objects(self.state_manager.blockstore(), &m.bls_msgs)?;
                             chain::persist_objects(self.state_manager.blockstore(), &m.secp_msgs)?;
                         } else {
                             warn!("Blocksync request for messages returned null messages");
                         }
+
+                        self.queue_validation(fts, &mut prev, pending).await?;
                     }
 
                     continue;
                 }
             };
             // full tipset found in storage; validate and continue
-            let curr_epoch = fts.epoch();
-            self.validate_tipset(fts).await?;
-            self.state.write().await.set_epoch(curr_epoch);
-            continue;
+            self.queue_validation(fts, &mut prev, pending).await?;
+        }
+
+        while let Some(validation) = pending.pop_front() {
+            self.finish_validation(validation).await?;
         }
 
         Ok(())
     }
 
-    /// validates tipsets and adds header data to tipset tracker
-    async fn validate_tipset(&self, fts: FullTipset) -> Result<(), Error> {
+    /// Starts the validation of a tipset after the previous tipset `prev`, once the state
+    /// its blocks are checked against can be computed. Validations which are out of the
+    /// window are finished in order.
+    async fn queue_validation(
+        &self,
+        fts: FullTipset,
+        prev: &mut Option<Tipset>,
+        pending: &mut VecDeque<PendingValidation>,
+    ) -> Result<(), Error> {
+        // Validating the tipset computes the state of its parent, which is applied on top of
+        // the state of its grandparent.
+        if let Some(prev) = prev.as_ref().filter(|prev| prev.epoch() > 0) {
+            let grandparent = self.chain_store().tipset_from_keys(prev.parents())?;
+            let state = self
+                .state_manager
+                .tipset_state::<V>(&grandparent)
+                .await
+                .map_err(|e| format!("Could not update state: {}", e));
+            if let Err(e) = state {
+                // Report the invalid block which led to the state failing, if any
+                while let Some(validation) = pending.pop_front() {
+                    self.finish_validation(validation).await?;
+                }
+                return Err(Error::Other(e));
+            }
+        }
+        *prev = Some(fts.to_tipset());
+
+        let epoch = fts.epoch();
+        match self.start_validation(fts) {
+            Some(validation) => self.push_validation(validation, pending).await,
+            None => {
+                self.state.write().await.set_epoch(epoch);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a started validation to the window, finishing the oldest validations in order
+    /// while more than `VALIDATION_WINDOW` are pending.
+    async fn push_validation(
+        &self,
+        validation: PendingValidation,
+        pending: &mut VecDeque<PendingValidation>,
+    ) -> Result<(), Error> {
+        pending.push_back(validation);
+        while pending.len() > VALIDATION_WINDOW {
+            let validation = pending.pop_front().expect("pending validations not empty");
+            self.finish_validation(validation).await?;
+        }
+        Ok(())
+    }
+
+    /// Spawns the validation of each block of a tipset. Returns `None` for the genesis tipset,
+    /// which isn't validated.
+    fn start_validation(&self, fts: FullTipset) -> Option<PendingValidation> {
         if &fts.to_tipset() == self.genesis.as_ref() {
             debug!("Skipping tipset validation for genesis");
-            return Ok(());
+            return None;
         }
 
         let epoch = fts.epoch();
-
-        let mut validations = FuturesUnordered::new();
+        let started = Instant::now();
+        let mut blocks = Vec::new();
         for b in fts.into_blocks() {
             let cs = self.chain_store().clone();
             let sm = self.state_manager.clone();
             let bc = self.beacon.clone();
-            let v = task::spawn(async move { Self::validate_block(cs, sm, bc, Arc::new(b)).await });
-            validations.push(v);
+            let delay = self.block_delay;
+            let v =
+                task::spawn(
+                    async move { Self::validate_block(cs, sm, bc, Arc::new(b), delay).await },
+                );
+            blocks.push(v);
         }
+        Some(PendingValidation {
+            epoch,
+            blocks,
+            started,
+        })
+    }
 
-        while let Some(result) = validations.next().await {
-            match result {
-                Ok(b) => {
-                    self.chain_store().set_tipset_tracker(b.header()).await?;
-                }
-                Err((cid, e)) => {
-                    // If the error is temporally invalidated, don't add to bad blocks cache.
-                    if !matches!(e, Error::Temporal(_, _)) {
-                        self.bad_blocks.put(cid, e.to_string()).await;
-                    }
-                    return Err(Error::Other(format!("Invalid block detected: {}", e)));
+    /// Awaits the block validations of a tipset. The validations of the other blocks are
+    /// cancelled once a block fails.
+    async fn finish_validation(&self, validation: PendingValidation) -> Result<(), Error> {
+        let PendingValidation {
+            epoch,
+            blocks,
+            started,
+        } = validation;
+        let mut blocks = blocks.into_iter();
+        while let Some(block) = blocks.next() {
+            if let Err(e) = self.finish_block(block).await {
+                for block in blocks {
+                    block.cancel().await;
                 }
+                return Err(e);
             }
         }
-        info!("Successfully validated tipset at epoch: {}", epoch);
+        metrics::TIPSET_VALIDATION_SECONDS.observe(started.elapsed().as_secs_f64());
+        info!(epoch = epoch; "Successfully validated tipset");
+        self.state.write().await.set_epoch(epoch);
         Ok(())
     }
 
+    /// Awaits the validation of a block, adding the header to the tipset tracker if it is valid
+    /// and the block to the bad blocks cache otherwise.
+    async fn finish_block(
+        &self,
+        block: JoinHandle<Result<Arc<Block>, (Cid, Error)>>,
+    ) -> Result<(), Error> {
+        match block.await {
+            Ok(b) => {
+                self.chain_store().set_tipset_tracker(b.header()).await?;
+                Ok(())
+            }
+            Err((cid, e)) => {
+                // If the error is temporally invalidated, don't add to bad blocks cache.
+                if !matches!(e, Error::Temporal(_, _)) {
+                    self.bad_blocks.put(cid, e.to_string()).await;
+                }
+                Err(Error::Other(format!("Invalid block detected: {}", e)))
+            }
+        }
+    }
+
     /// Validates block semantically according to https://github.com/filecoin-project/specs/blob/6ab401c0b92efb6420c6e198ec387cf56dc86057/validation.md
     /// Returns the validated block if `Ok`.
     /// Returns the block cid (for marking bad) and `Error` if invalid (`Err`).
@@ -414,6 +579,7 @@ where
         sm: Arc<StateManager<DB>>,
         bc: Arc<TBeacon>,
         block: Arc<Block>,
+        block_delay: u64,
     ) -> Result<Arc<Block>, (Cid, Error)> {
         debug!(
             "Validating block at epoch: {} with weight: {}",
@@ -465,7 +631,7 @@ where
 
         // Timestamp checks
         let nulls = (header.epoch() - (base_ts.epoch() + 1)) as u64;
-        let target_timestamp = base_ts.min_timestamp() + BLOCK_DELAY_SECS * (nulls + 1);
+        let target_timestamp = base_ts.min_timestamp() + block_delay * (nulls + 1);
         if target_timestamp != header.timestamp() {
             return Err((
                 *block_cid,
@@ -969,6 +1135,25 @@ fn block_sanity_checks(header: &BlockHeader) -> Result<(), &'static str> {
     Ok(())
 }
 
+/// Validates a block on top of its parent tipset in the chain store, with the checks synced blocks
+/// go through. Returns the validated block.
+pub async fn validate_block<DB, TBeacon, V>(
+    state_manager: Arc<StateManager<DB>>,
+    beacon: Arc<TBeacon>,
+    block: Arc<Block>,
+    block_delay: u64,
+) -> Result<Arc<Block>, Error>
+where
+    TBeacon: Beacon + Sync + Send + 'static,
+    DB: BlockStore + Sync + Send + 'static,
+    V: ProofVerifier + Sync + Send + 'static,
+{
+    let cs = Arc::clone(state_manager.chain_store());
+    SyncWorker::<DB, TBeacon, V>::validate_block(cs, state_manager, beacon, block, block_delay)
+        .await
+        .map_err(|(_, e)| e)
+}
+
 /// Returns message root CID from bls and secp message contained in the param Block
 pub fn compute_msg_meta<DB: BlockStore>(
     blockstore: &DB,
@@ -1012,7 +1197,9 @@ mod tests {
     use libp2p::PeerId;
     use std::sync::Arc;
     use std::time::Duration;
-    use test_utils::{construct_blocksync_response, construct_dummy_header, construct_tipset};
+    use test_utils::{
+        construct_blocksync_response, construct_dummy_header, construct_headers, construct_tipset,
+    };
 
     fn sync_worker_setup(
         db: Arc<MemoryDB>,
@@ -1038,7 +1225,9 @@ mod tests {
                 network: SyncNetworkContext::new(local_sender, Default::default(), db),
                 genesis: genesis_ts,
                 bad_blocks: Default::default(),
+                block_delay: BLOCK_DELAY_SECS,
                 verifier: Default::default(),
+                stopping: Default::default(),
             },
             test_receiver,
         )
@@ -1083,4 +1272,114 @@ mod tests {
             assert_eq!(return_set.await.unwrap().len(), 4);
         });
     }
+
+    fn test_block(epoch: ChainEpoch, i: usize) -> Arc<Block> {
+        Arc::new(Block {
+            header: construct_headers(epoch, 10).swap_remove(i),
+            bls_messages: Vec::new(),
+            secp_messages: Vec::new(),
+        })
+    }
+
+    fn pending_validation(
+        epoch: ChainEpoch,
+        blocks: Vec<JoinHandle<Result<Arc<Block>, (Cid, Error)>>>,
+    ) -> PendingValidation {
+        PendingValidation {
+            epoch,
+            blocks,
+            started: Instant::now(),
+        }
+    }
+
+    /// Spawns a block validation which completes with the result after the delay, setting
+    /// `completed` if given.
+    fn validated_after(
+        result: Result<Arc<Block>, (Cid, Error)>,
+        delay: Duration,
+        completed: Option<Arc<AtomicBool>>,
+    ) -> JoinHandle<Result<Arc<Block>, (Cid, Error)>> {
+        task::spawn(async move {
+            task::sleep(delay).await;
+            if let Some(completed) = completed {
+                completed.store(true, Ordering::SeqCst);
+            }
+            result
+        })
+    }
+
+    #[test]
+    fn validation_window_finishes_in_order() {
+        let db = Arc::new(MemoryDB::default());
+        let (sw, _network_receiver) = sync_worker_setup(db);
+
+        task::block_on(async move {
+            let mut pending = VecDeque::new();
+            for epoch in 1..=3 {
+                // Later tipsets complete their validation first
+                let delay = Duration::from_millis(30 * (3 - epoch) as u64);
+                let block = validated_after(Ok(test_block(epoch, 0)), delay, None);
+                sw.push_validation(pending_validation(epoch, vec![block]), &mut pending)
+                    .await
+                    .unwrap();
+
+                // Validations are only finished once they fall out of the window
+                let finished = (epoch as usize).saturating_sub(VALIDATION_WINDOW);
+                assert_eq!(sw.state.read().await.epoch(), finished as ChainEpoch);
+                assert_eq!(pending.len(), min(epoch as usize, VALIDATION_WINDOW));
+            }
+            for epoch in 2..=3 {
+                let validation = pending.pop_front().unwrap();
+                assert_eq!(validation.epoch, epoch);
+                sw.finish_validation(validation).await.unwrap();
+                assert_eq!(sw.state.read().await.epoch(), epoch);
+            }
+        });
+    }
+
+    #[test]
+    fn invalid_block_cancels_validations() {
+        let db = Arc::new(MemoryDB::default());
+        let (sw, _network_receiver) = sync_worker_setup(db);
+        let completed = Arc::new(AtomicBool::new(false));
+        let slow = |epoch, i| {
+            validated_after(
+                Ok(test_block(epoch, i)),
+                Duration::from_millis(100),
+                Some(completed.clone()),
+            )
+        };
+
+        task::block_on(async {
+            let mut pending = VecDeque::new();
+            let invalid = *test_block(1, 0).cid();
+            let error = Error::Validation("invalid block".to_owned());
+            let blocks = vec![
+                validated_after(Err((invalid, error)), Duration::from_millis(0), None),
+                slow(1, 1),
+            ];
+            sw.push_validation(pending_validation(1, blocks), &mut pending)
+                .await
+                .unwrap();
+            sw.push_validation(pending_validation(2, vec![slow(2, 0)]), &mut pending)
+                .await
+                .unwrap();
+
+            // The invalid tipset fails once it falls out of the window
+            let res = sw
+                .push_validation(pending_validation(3, vec![slow(3, 0)]), &mut pending)
+                .await;
+            assert!(res.is_err());
+            assert!(sw.bad_blocks.peek(&invalid).await.is_some());
+            assert_eq!(sw.state.read().await.epoch(), 0);
+
+            // The validations left pending are cancelled when syncing fails
+            assert_eq!(pending.len(), 2);
+            for validation in pending {
+                validation.cancel().await;
+            }
+            task::sleep(Duration::from_millis(200)).await;
+            assert!(!completed.load(Ordering::SeqCst));
+        });
+    }
 }
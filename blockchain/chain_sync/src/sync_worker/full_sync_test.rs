@@ -75,7 +75,9 @@ async fn space_race_full_sync() {
         network,
         genesis,
         bad_blocks: Default::default(),
+        block_delay: BLOCK_DELAY_SECS,
         verifier: PhantomData::<FullVerifier>::default(),
+        stopping: Default::default(),
     };
 
     // Setup process to handle requests from syncer
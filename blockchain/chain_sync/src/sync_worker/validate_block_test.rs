@@ -56,6 +56,7 @@ async fn validate_specific_block() {
             state_manager.clone(),
             beacon.clone(),
             Arc::new(block),
+            BLOCK_DELAY_SECS,
         ))
         .unwrap();
     }
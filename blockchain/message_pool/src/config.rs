@@ -13,7 +13,7 @@ const SIZE_LIMIT_LOW: i64 = 20000;
 const SIZE_LIMIT_HIGH: i64 = 30000;
 const PRUNE_COOLDOWN: Duration = Duration::from_secs(60); // 1 minute
 const REPLACE_BY_FEE_RATIO: f64 = 1.25;
-const GAS_LIMIT_OVERESTIMATION: f64 = 1.25;
+pub const GAS_LIMIT_OVERESTIMATION: f64 = 1.25;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct MpoolConfig {
@@ -69,6 +69,10 @@ impl MpoolConfig {
             gas_limit_overestimation,
         })
     }
+    /// Factor by which estimated gas limits are padded.
+    pub fn gas_limit_overestimation(&self) -> f64 {
+        self.gas_limit_overestimation
+    }
     pub fn save_config<DB: Store>(&self, store: &DB) -> Result<(), Box<dyn StdError>> {
         Ok(store.write(MPOOL_CONFIG_KEY, to_vec(&self)?)?)
     }
@@ -33,6 +33,13 @@ const RBF_NUM: u64 = ((REPLACE_BY_FEE_RATIO - 1f32) * 256f32) as u64;
 const RBF_DENOM: u64 = 256;
 const BASE_FEE_LOWER_BOUND_FACTOR_CONSERVATIVE: i64 = 100;
 
+/// Returns the gas premium a replacement message must exceed to replace a pending message with
+/// the given premium, according to the `REPLACE_BY_FEE_RATIO`.
+pub fn min_replace_premium(premium: &BigInt) -> BigInt {
+    let rbf_denom = BigInt::from(RBF_DENOM);
+    premium + ((premium * RBF_NUM).div_floor(&rbf_denom)) + 1u8
+}
+
 /// Simple struct that contains a hashmap of messages where k: a message from address, v: a message
 /// which corresponds to that address
 #[derive(Clone, Default, Debug)]
@@ -60,9 +67,7 @@ impl MsgSet {
         }
         if let Some(exms) = self.msgs.get(&m.sequence()) {
             if m.cid()? != exms.cid()? {
-                let premium = exms.message().gas_premium();
-                let rbf_denom = BigInt::from(RBF_DENOM);
-                let min_price = premium + ((premium * RBF_NUM).div_floor(&rbf_denom)) + 1u8;
+                let min_price = min_replace_premium(exms.message().gas_premium());
                 if m.message().gas_premium() <= &min_price {
                     warn!("message gas price is below min gas price");
                     return Err(Error::GasPriceTooLow);
@@ -1305,4 +1310,12 @@ pub mod tests {
             assert_eq!(cur_ts.as_ref(), &tipset);
         })
     }
+
+    #[test]
+    fn test_min_replace_premium() {
+        // 1.25 ratio with a 256 denominator, plus one
+        assert_eq!(min_replace_premium(&BigInt::from(0)), BigInt::from(1));
+        assert_eq!(min_replace_premium(&BigInt::from(256)), BigInt::from(321));
+        assert_eq!(min_replace_premium(&BigInt::from(1000)), BigInt::from(1251));
+    }
 }
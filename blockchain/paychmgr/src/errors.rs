@@ -0,0 +1,44 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use db::Error as DbErr;
+use encoding::{error::Error as CborError, Error as EncodeError};
+use state_manager::Error as StateManagerError;
+use thiserror::Error;
+
+/// Payment channel manager error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// No channel is tracked for the given address or pair of addresses
+    #[error("payment channel not found: {0}")]
+    ChannelNotFound(String),
+    /// Voucher failed validation against the channel state
+    #[error("invalid voucher: {0}")]
+    InvalidVoucher(String),
+    /// Error originating from the channel store
+    #[error(transparent)]
+    DB(#[from] DbErr),
+    /// Error originating from the state manager
+    #[error(transparent)]
+    State(#[from] StateManagerError),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<EncodeError> for Error {
+    fn from(e: EncodeError) -> Self {
+        Error::Other(e.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(e: String) -> Self {
+        Error::Other(e)
+    }
+}
+
+impl From<CborError> for Error {
+    fn from(e: CborError) -> Self {
+        Error::Other(e.to_string())
+    }
+}
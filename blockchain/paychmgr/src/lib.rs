@@ -0,0 +1,10 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod errors;
+mod manager;
+mod store;
+
+pub use self::errors::*;
+pub use self::manager::*;
+pub use self::store::*;
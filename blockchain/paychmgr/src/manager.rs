@@ -0,0 +1,532 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::{ChannelInfo, Direction, Error, PaychStore, VoucherInfo};
+use actor::{
+    init::{ExecParams, ExecReturn, Method as InitMethod},
+    paych::{ConstructorParams, LaneState, Method, SignedVoucher, State},
+    ActorState, Serialized, TokenAmount, INIT_ACTOR_ADDR, PAYCH_ACTOR_CODE_ID,
+};
+use address::Address;
+use async_std::sync::{Mutex, RwLock};
+use blocks::Tipset;
+use blockstore::BlockStore;
+use cid::Cid;
+use clock::ChainEpoch;
+use fil_types::verifier::FullVerifier;
+use ipld_amt::Amt;
+use message::UnsignedMessage;
+use state_manager::StateManager;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Manages the payment channels of the node: builds the messages creating, funding, settling
+/// and collecting channels, and validates and stores the vouchers sent through them.
+///
+/// Messages are returned unsigned so the caller can sign and push them with the node wallet.
+pub struct PaychManager<DB> {
+    state_manager: Arc<StateManager<DB>>,
+    store: RwLock<PaychStore<DB>>,
+    /// Locks held while a channel between a pair of addresses is created or funded, so
+    /// concurrent requests do not create several channels.
+    pair_locks: Mutex<HashMap<(Address, Address), Arc<Mutex<()>>>>,
+}
+
+impl<DB> PaychManager<DB>
+where
+    DB: BlockStore + Send + Sync + 'static,
+{
+    pub fn new(state_manager: Arc<StateManager<DB>>) -> Self {
+        let store = PaychStore::new(state_manager.blockstore_cloned());
+        Self {
+            state_manager,
+            store: RwLock::new(store),
+            pair_locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the outbound channel from `from` to `to`, creating it or adding `amount` to it
+    /// with the message given to `push`, which returns the CID of the pushed message. No message
+    /// is pushed if the channel exists and `amount` is zero.
+    pub async fn get_paych<F, Fut, E>(
+        &self,
+        from: Address,
+        to: Address,
+        amount: TokenAmount,
+        push: F,
+    ) -> Result<(Option<Address>, Option<Cid>), E>
+    where
+        F: FnOnce(UnsignedMessage) -> Fut,
+        Fut: Future<Output = Result<Cid, E>>,
+        E: From<Error>,
+    {
+        let pair_lock = Arc::clone(
+            self.pair_locks
+                .lock()
+                .await
+                .entry((from, to))
+                .or_insert_with(Default::default),
+        );
+        let _pair_guard = pair_lock.lock().await;
+
+        let (ch, msg) = self.paych_message(from, to, amount.clone()).await?;
+        let msg_cid = match msg {
+            Some(msg) => push(msg).await?,
+            None => return Ok((ch, None)),
+        };
+
+        let store = self.store.write().await;
+        let mut info = match ch {
+            Some(ch) => store
+                .by_address(&ch)?
+                .ok_or_else(|| Error::ChannelNotFound(ch.to_string()))?,
+            None => {
+                let mut info = ChannelInfo::new(from, to, Direction::Outbound);
+                info.create_msg = Some(msg_cid);
+                info
+            }
+        };
+        info.amount += amount;
+        store.put_channel_info(&info)?;
+        Ok((ch, Some(msg_cid)))
+    }
+
+    /// Returns the message creating the channel from `from` to `to` or adding `amount` to it.
+    async fn paych_message(
+        &self,
+        from: Address,
+        to: Address,
+        amount: TokenAmount,
+    ) -> Result<(Option<Address>, Option<UnsignedMessage>), Error> {
+        let store = self.store.write().await;
+        let info = match store.by_from_to(&from, &to)? {
+            Some(info) => self.resolve_create(&store, info).await?,
+            None => None,
+        };
+
+        if let Some(info) = &info {
+            if let Some(cid) = &info.create_msg {
+                return Err(Error::Other(format!(
+                    "channel from {} to {} is being created by message {}",
+                    from, to, cid
+                )));
+            }
+        }
+
+        match info.and_then(|info| info.channel) {
+            Some(ch) if amount == TokenAmount::from(0) => Ok((Some(ch), None)),
+            Some(ch) => {
+                let msg = UnsignedMessage::builder()
+                    .to(ch)
+                    .from(from)
+                    .value(amount)
+                    .build()?;
+                Ok((Some(ch), Some(msg)))
+            }
+            None => {
+                let constructor_params = Serialized::serialize(ConstructorParams { from, to })?;
+                let params = Serialized::serialize(ExecParams {
+                    code_cid: *PAYCH_ACTOR_CODE_ID,
+                    constructor_params,
+                })?;
+                let msg = UnsignedMessage::builder()
+                    .to(*INIT_ACTOR_ADDR)
+                    .from(from)
+                    .value(amount)
+                    .method_num(InitMethod::Exec as u64)
+                    .params(params)
+                    .build()?;
+                Ok((None, Some(msg)))
+            }
+        }
+    }
+
+    /// Returns the info of all tracked channels.
+    pub async fn list_channels(&self) -> Result<Vec<ChannelInfo>, Error> {
+        self.store.read().await.list_channels()
+    }
+
+    /// Returns the info of a tracked channel.
+    pub async fn get_channel_info(&self, ch: &Address) -> Result<ChannelInfo, Error> {
+        self.store
+            .read()
+            .await
+            .by_address(ch)?
+            .ok_or_else(|| Error::ChannelNotFound(ch.to_string()))
+    }
+
+    /// Creates an unsigned voucher for `amount` on the given lane of an outbound channel, or on
+    /// a newly allocated lane if none is given. Returns the address that must sign the voucher.
+    pub async fn create_voucher(
+        &self,
+        ch: Address,
+        amount: TokenAmount,
+        lane: Option<u64>,
+    ) -> Result<(Address, SignedVoucher), Error> {
+        let store = self.store.write().await;
+        let info = store
+            .by_address(&ch)?
+            .ok_or_else(|| Error::ChannelNotFound(ch.to_string()))?;
+        if info.direction != Direction::Outbound {
+            return Err(Error::Other(format!(
+                "cannot create vouchers on inbound channel {}",
+                ch
+            )));
+        }
+        let lane = match lane {
+            Some(lane) => {
+                // Keep lanes picked by the caller from being allocated again
+                if lane >= info.next_lane {
+                    let mut info = info.clone();
+                    info.next_lane = lane + 1;
+                    store.put_channel_info(&info)?;
+                }
+                lane
+            }
+            None => store.allocate_lane(&ch)?,
+        };
+
+        let (_, st) = self.load_paych_state(&ch).await?;
+        let lanes = self.lane_states(&st, &info)?;
+        let nonce = lanes.get(&lane).map(|ls| ls.nonce + 1).unwrap_or(1);
+
+        let voucher = SignedVoucher {
+            channel_addr: ch,
+            time_lock_min: 0,
+            time_lock_max: 0,
+            secret_pre_image: Vec::new(),
+            extra: None,
+            lane,
+            nonce,
+            amount,
+            min_settle_height: 0,
+            merges: Vec::new(),
+            signature: None,
+        };
+        Ok((info.control, voucher))
+    }
+
+    /// Checks that a voucher is signed by the paying side of the channel, does not reuse a
+    /// nonce or decrease the amount of its lane, and is covered by the channel balance.
+    pub async fn check_voucher_valid(&self, ch: &Address, sv: &SignedVoucher) -> Result<(), Error> {
+        let info = self.store.read().await.by_address(ch)?;
+        self.validate_voucher(ch, sv, info.as_ref())
+            .await
+            .map(|_| ())
+    }
+
+    /// Validates and stores a voucher, returning the amount it adds to its lane. Vouchers
+    /// received on channels not yet tracked start tracking the channel as inbound.
+    pub async fn add_voucher(
+        &self,
+        ch: Address,
+        sv: SignedVoucher,
+        proof: Vec<u8>,
+        min_delta: TokenAmount,
+    ) -> Result<TokenAmount, Error> {
+        let store = self.store.write().await;
+        let mut info = match store.by_address(&ch)? {
+            Some(info) => info,
+            None => {
+                let (_, st) = self.load_paych_state(&ch).await?;
+                let mut info = ChannelInfo::new(st.to, st.from, Direction::Inbound);
+                info.channel = Some(ch);
+                info
+            }
+        };
+
+        // Adding the same voucher again is a no-op
+        if info.vouchers.iter().any(|vi| vi.voucher == sv) {
+            return Ok(TokenAmount::from(0));
+        }
+
+        let redeemed = self.validate_voucher(&ch, &sv, Some(&info)).await?;
+        let delta = &sv.amount - redeemed;
+        if delta < min_delta {
+            return Err(Error::InvalidVoucher(format!(
+                "voucher adds {} to its lane, less than the minimum of {}",
+                delta, min_delta
+            )));
+        }
+
+        info.vouchers.push(VoucherInfo { voucher: sv, proof });
+        store.put_channel_info(&info)?;
+        Ok(delta)
+    }
+
+    /// Returns the stored vouchers of a channel.
+    pub async fn list_vouchers(&self, ch: &Address) -> Result<Vec<VoucherInfo>, Error> {
+        Ok(self.get_channel_info(ch).await?.vouchers)
+    }
+
+    /// Returns the message starting the settlement of a channel.
+    pub async fn settle(&self, ch: Address) -> Result<UnsignedMessage, Error> {
+        let store = self.store.write().await;
+        let mut info = store
+            .by_address(&ch)?
+            .ok_or_else(|| Error::ChannelNotFound(ch.to_string()))?;
+        let msg = UnsignedMessage::builder()
+            .to(ch)
+            .from(info.control)
+            .method_num(Method::Settle as u64)
+            .build()?;
+        info.settling = true;
+        store.put_channel_info(&info)?;
+        Ok(msg)
+    }
+
+    /// Returns the message paying out a settled channel.
+    pub async fn collect(&self, ch: Address) -> Result<UnsignedMessage, Error> {
+        let info = self.get_channel_info(&ch).await?;
+        Ok(UnsignedMessage::builder()
+            .to(ch)
+            .from(info.control)
+            .method_num(Method::Collect as u64)
+            .build()?)
+    }
+
+    /// Validates a voucher against the channel state, returning the amount already redeemed
+    /// on its lane.
+    async fn validate_voucher(
+        &self,
+        ch: &Address,
+        sv: &SignedVoucher,
+        info: Option<&ChannelInfo>,
+    ) -> Result<TokenAmount, Error> {
+        if &sv.channel_addr != ch {
+            return Err(Error::InvalidVoucher(format!(
+                "voucher is for channel {}, not {}",
+                sv.channel_addr, ch
+            )));
+        }
+        if !sv.merges.is_empty() {
+            return Err(Error::InvalidVoucher(
+                "lane merges are not supported".to_string(),
+            ));
+        }
+
+        let (actor, st) = self.load_paych_state(ch).await?;
+
+        // The voucher must be signed by the paying side of the channel
+        let head = self.heaviest_tipset().await?;
+        let from = self
+            .state_manager
+            .resolve_to_key_addr::<FullVerifier>(&st.from, &head)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?;
+        let sig = sv
+            .signature
+            .as_ref()
+            .ok_or_else(|| Error::InvalidVoucher("voucher is not signed".to_string()))?;
+        sig.verify(&sv.signing_bytes()?, &from)
+            .map_err(Error::InvalidVoucher)?;
+        check_voucher_epochs(sv, &st, head.epoch())?;
+
+        let empty = ChannelInfo::new(st.to, st.from, Direction::Inbound);
+        let lanes = self.lane_states(&st, info.unwrap_or(&empty))?;
+        let redeemed = match lanes.get(&sv.lane) {
+            Some(ls) if sv.nonce <= ls.nonce => {
+                return Err(Error::InvalidVoucher(format!(
+                    "nonce {} is not above the lane nonce {}",
+                    sv.nonce, ls.nonce
+                )))
+            }
+            Some(ls) if sv.amount <= ls.redeemed => {
+                return Err(Error::InvalidVoucher(format!(
+                    "amount {} is not above the lane amount {}",
+                    sv.amount, ls.redeemed
+                )))
+            }
+            Some(ls) => ls.redeemed.clone(),
+            None => TokenAmount::from(0),
+        };
+
+        let total: TokenAmount = lanes
+            .iter()
+            .filter(|(lane, _)| **lane != sv.lane)
+            .map(|(_, ls)| &ls.redeemed)
+            .sum::<TokenAmount>()
+            + &sv.amount;
+        if total > actor.balance {
+            return Err(Error::InvalidVoucher(format!(
+                "channel balance {} does not cover the total voucher amount {}",
+                actor.balance, total
+            )));
+        }
+        Ok(redeemed)
+    }
+
+    /// Returns the state of each lane, combining the lane states on chain with the stored
+    /// vouchers not yet submitted.
+    fn lane_states(
+        &self,
+        st: &State,
+        info: &ChannelInfo,
+    ) -> Result<HashMap<u64, LaneState>, Error> {
+        let mut lanes = HashMap::new();
+        let amt: Amt<LaneState, _> = Amt::load(&st.lane_states, self.state_manager.blockstore())
+            .map_err(|e| Error::Other(e.to_string()))?;
+        amt.for_each(|lane, ls| {
+            lanes.insert(lane, ls.clone());
+            Ok(())
+        })
+        .map_err(|e| Error::Other(e.to_string()))?;
+
+        for vi in &info.vouchers {
+            let sv = &vi.voucher;
+            let ls = lanes.entry(sv.lane).or_insert_with(LaneState::default);
+            if sv.nonce > ls.nonce {
+                ls.nonce = sv.nonce;
+                ls.redeemed = sv.amount.clone();
+            }
+        }
+        Ok(lanes)
+    }
+
+    /// Resolves the channel address once the message creating the channel has executed,
+    /// moving the channel info from the create message key to the channel address key. A channel
+    /// which failed to be created is forgotten, so it can be created again.
+    async fn resolve_create(
+        &self,
+        store: &PaychStore<DB>,
+        info: ChannelInfo,
+    ) -> Result<Option<ChannelInfo>, Error> {
+        let cid = match (&info.channel, &info.create_msg) {
+            (None, Some(cid)) => *cid,
+            _ => return Ok(Some(info)),
+        };
+        let head = self.heaviest_tipset().await?;
+        let receipt = match self.state_manager.get_receipt(&head, &cid) {
+            Ok(receipt) => receipt,
+            // Not executed yet
+            Err(_) => return Ok(Some(info)),
+        };
+
+        store.remove_channel_info(&info)?;
+        if !receipt.exit_code.is_success() {
+            return Ok(None);
+        }
+        let ret: ExecReturn = receipt.return_data.deserialize()?;
+        let mut resolved = info;
+        resolved.channel = Some(ret.robust_address);
+        resolved.create_msg = None;
+        store.put_channel_info(&resolved)?;
+        Ok(Some(resolved))
+    }
+
+    async fn load_paych_state(&self, ch: &Address) -> Result<(ActorState, State), Error> {
+        let head = self.heaviest_tipset().await?;
+        let actor = self
+            .state_manager
+            .get_actor(ch, head.parent_state())?
+            .ok_or_else(|| Error::ChannelNotFound(ch.to_string()))?;
+        let st = self
+            .state_manager
+            .load_actor_state(ch, head.parent_state())?;
+        Ok((actor, st))
+    }
+
+    async fn heaviest_tipset(&self) -> Result<Arc<Tipset>, Error> {
+        self.state_manager
+            .chain_store()
+            .heaviest_tipset()
+            .await
+            .ok_or_else(|| Error::Other("no heaviest tipset".to_string()))
+    }
+}
+
+/// Checks that the voucher can still be submitted to the channel after the epoch: its time lock
+/// has not expired, and it unlocks before the channel settles.
+fn check_voucher_epochs(sv: &SignedVoucher, st: &State, epoch: ChainEpoch) -> Result<(), Error> {
+    if sv.time_lock_min < 0 || sv.time_lock_max < 0 || sv.min_settle_height < 0 {
+        return Err(Error::InvalidVoucher(
+            "voucher epochs must not be negative".to_string(),
+        ));
+    }
+    if sv.time_lock_max != 0 {
+        if sv.time_lock_min > sv.time_lock_max {
+            return Err(Error::InvalidVoucher(format!(
+                "time lock min {} is after time lock max {}",
+                sv.time_lock_min, sv.time_lock_max
+            )));
+        }
+        if epoch > sv.time_lock_max {
+            return Err(Error::InvalidVoucher(format!(
+                "voucher expired at epoch {}",
+                sv.time_lock_max
+            )));
+        }
+    }
+    if st.settling_at != 0 {
+        if epoch >= st.settling_at {
+            return Err(Error::InvalidVoucher(format!(
+                "channel settled at epoch {}",
+                st.settling_at
+            )));
+        }
+        // Submitting the voucher moves the settlement to its min settle height
+        let settle_height = std::cmp::max(st.settling_at, sv.min_settle_height);
+        if sv.time_lock_min >= settle_height {
+            return Err(Error::InvalidVoucher(format!(
+                "voucher unlocks at epoch {}, after the channel settles at {}",
+                sv.time_lock_min, settle_height
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voucher(
+        time_lock_min: ChainEpoch,
+        time_lock_max: ChainEpoch,
+        min_settle: ChainEpoch,
+    ) -> SignedVoucher {
+        SignedVoucher {
+            channel_addr: Address::new_id(1000),
+            time_lock_min,
+            time_lock_max,
+            secret_pre_image: Vec::new(),
+            extra: None,
+            lane: 0,
+            nonce: 1,
+            amount: TokenAmount::from(10),
+            min_settle_height: min_settle,
+            merges: Vec::new(),
+            signature: None,
+        }
+    }
+
+    fn state(settling_at: ChainEpoch) -> State {
+        let mut st = State::new(Address::new_id(100), Address::new_id(101), Cid::default());
+        st.settling_at = settling_at;
+        st
+    }
+
+    #[test]
+    fn voucher_time_locks() {
+        let st = state(0);
+        assert!(check_voucher_epochs(&voucher(0, 0, 0), &st, 50).is_ok());
+        // Vouchers locked until a later epoch can be stored before they unlock
+        assert!(check_voucher_epochs(&voucher(100, 0, 0), &st, 50).is_ok());
+        assert!(check_voucher_epochs(&voucher(0, 100, 0), &st, 100).is_ok());
+        assert!(check_voucher_epochs(&voucher(0, 100, 0), &st, 101).is_err());
+        assert!(check_voucher_epochs(&voucher(20, 10, 0), &st, 5).is_err());
+        assert!(check_voucher_epochs(&voucher(-1, 0, 0), &st, 5).is_err());
+    }
+
+    #[test]
+    fn voucher_settle_heights() {
+        let st = state(100);
+        assert!(check_voucher_epochs(&voucher(0, 0, 0), &st, 50).is_ok());
+        assert!(check_voucher_epochs(&voucher(0, 0, 0), &st, 100).is_err());
+        assert!(check_voucher_epochs(&voucher(100, 0, 0), &st, 50).is_err());
+        // The voucher's min settle height delays the settlement past its time lock
+        assert!(check_voucher_epochs(&voucher(100, 0, 150), &st, 50).is_ok());
+        assert!(check_voucher_epochs(&voucher(0, 0, -5), &st, 50).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::Error;
+use actor::{paych::SignedVoucher, TokenAmount};
+use address::Address;
+use cid::Cid;
+use db::Store;
+use encoding::{from_slice, repr::*, serde_bytes, to_vec, tuple::*, Cbor};
+use num_bigint::bigint_ser;
+use std::sync::Arc;
+
+/// Key of the list of tracked channel keys.
+const CHANNEL_INDEX_KEY: &str = "/paych/index";
+/// Prefix of the keys channel infos are stored under, followed by the channel address.
+const CHANNEL_KEY_PREFIX: &str = "/paych/channel/";
+/// Prefix of the keys infos of channels being created are stored under, followed by the CID
+/// of the create message.
+const PENDING_KEY_PREFIX: &str = "/paych/pending/";
+
+/// Direction of the payments in a channel, relative to the node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum Direction {
+    /// The node receives payments through the channel.
+    Inbound = 0,
+    /// The node sends payments through the channel.
+    Outbound = 1,
+}
+
+/// A voucher stored along with the proof needed to redeem it.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct VoucherInfo {
+    pub voucher: SignedVoucher,
+    #[serde(with = "serde_bytes")]
+    pub proof: Vec<u8>,
+}
+
+/// Information tracked about a payment channel. `control` is the node's side of the channel
+/// and `target` the counterparty, regardless of the direction of the payments.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct ChannelInfo {
+    /// Address of the channel actor, unset until the create message is executed.
+    pub channel: Option<Address>,
+    pub control: Address,
+    pub target: Address,
+    pub direction: Direction,
+    pub vouchers: Vec<VoucherInfo>,
+    /// Next lane to allocate for outbound vouchers.
+    pub next_lane: u64,
+    /// Total funds sent to the channel by the node.
+    #[serde(with = "bigint_ser")]
+    pub amount: TokenAmount,
+    /// Message creating the channel, while it is waiting to be executed.
+    pub create_msg: Option<Cid>,
+    pub settling: bool,
+}
+
+impl Cbor for ChannelInfo {}
+
+impl ChannelInfo {
+    /// Creates the info of a channel which is not yet tracked.
+    pub fn new(control: Address, target: Address, direction: Direction) -> Self {
+        Self {
+            channel: None,
+            control,
+            target,
+            direction,
+            vouchers: Vec::new(),
+            next_lane: 0,
+            amount: TokenAmount::from(0),
+            create_msg: None,
+            settling: false,
+        }
+    }
+
+    /// Address paying through the channel.
+    pub fn from(&self) -> &Address {
+        match self.direction {
+            Direction::Outbound => &self.control,
+            Direction::Inbound => &self.target,
+        }
+    }
+
+    /// Address receiving payments through the channel.
+    pub fn to(&self) -> &Address {
+        match self.direction {
+            Direction::Outbound => &self.target,
+            Direction::Inbound => &self.control,
+        }
+    }
+
+    /// Channels are stored by their address, or by their create message until it executes.
+    fn key(&self) -> Result<String, Error> {
+        match (&self.channel, &self.create_msg) {
+            (Some(ch), _) => Ok(channel_key(ch)),
+            (None, Some(cid)) => Ok(format!("{}{}", PENDING_KEY_PREFIX, cid)),
+            (None, None) => Err(Error::Other(format!(
+                "channel from {} to {} has no address or create message",
+                self.from(),
+                self.to()
+            ))),
+        }
+    }
+}
+
+fn channel_key(ch: &Address) -> String {
+    format!("{}{}", CHANNEL_KEY_PREFIX, ch)
+}
+
+/// Persistent store of the payment channels and vouchers tracked by the node.
+pub struct PaychStore<DS> {
+    ds: Arc<DS>,
+}
+
+impl<DS> PaychStore<DS>
+where
+    DS: Store,
+{
+    pub fn new(ds: Arc<DS>) -> Self {
+        Self { ds }
+    }
+
+    /// Inserts or updates the info of a channel.
+    pub fn put_channel_info(&self, info: &ChannelInfo) -> Result<(), Error> {
+        let key = info.key()?;
+        let mut index = self.index()?;
+        if !index.contains(&key) {
+            index.push(key.clone());
+            self.ds.write(CHANNEL_INDEX_KEY, to_vec(&index)?)?;
+        }
+        self.ds.write(key, info.marshal_cbor()?)?;
+        Ok(())
+    }
+
+    /// Stops tracking a channel.
+    pub fn remove_channel_info(&self, info: &ChannelInfo) -> Result<(), Error> {
+        let key = info.key()?;
+        let mut index = self.index()?;
+        if let Some(pos) = index.iter().position(|k| k == &key) {
+            index.remove(pos);
+            self.ds.write(CHANNEL_INDEX_KEY, to_vec(&index)?)?;
+        }
+        self.ds.delete(key)?;
+        Ok(())
+    }
+
+    /// Returns the info of all tracked channels.
+    pub fn list_channels(&self) -> Result<Vec<ChannelInfo>, Error> {
+        self.index()?
+            .iter()
+            .filter_map(|key| self.get(key).transpose())
+            .collect()
+    }
+
+    /// Returns the info of the outbound channel paying from `from` to `to` which is not
+    /// settling, including a channel still being created.
+    pub fn by_from_to(&self, from: &Address, to: &Address) -> Result<Option<ChannelInfo>, Error> {
+        Ok(self.list_channels()?.into_iter().find(|info| {
+            info.direction == Direction::Outbound
+                && !info.settling
+                && info.from() == from
+                && info.to() == to
+        }))
+    }
+
+    /// Returns the info of the channel with the given actor address.
+    pub fn by_address(&self, ch: &Address) -> Result<Option<ChannelInfo>, Error> {
+        self.get(&channel_key(ch))
+    }
+
+    /// Allocates a new lane of an outbound channel.
+    pub fn allocate_lane(&self, ch: &Address) -> Result<u64, Error> {
+        let mut info = self
+            .by_address(ch)?
+            .ok_or_else(|| Error::ChannelNotFound(ch.to_string()))?;
+        let lane = info.next_lane;
+        info.next_lane += 1;
+        self.put_channel_info(&info)?;
+        Ok(lane)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<ChannelInfo>, Error> {
+        match self.ds.read(key)? {
+            Some(bz) => Ok(Some(ChannelInfo::unmarshal_cbor(&bz)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn index(&self) -> Result<Vec<String>, Error> {
+        match self.ds.read(CHANNEL_INDEX_KEY)? {
+            Some(bz) => Ok(from_slice(&bz)?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::MemoryDB;
+
+    fn channel(from: u64, to: u64, ch: u64) -> ChannelInfo {
+        let mut info = ChannelInfo::new(
+            Address::new_id(from),
+            Address::new_id(to),
+            Direction::Outbound,
+        );
+        info.channel = Some(Address::new_id(ch));
+        info
+    }
+
+    #[test]
+    fn put_and_get_channels() {
+        let store = PaychStore::new(Arc::new(MemoryDB::default()));
+        let a = channel(100, 101, 1000);
+        let b = channel(100, 102, 1001);
+        store.put_channel_info(&a).unwrap();
+        store.put_channel_info(&b).unwrap();
+        // Updating a channel does not duplicate it
+        store.put_channel_info(&a).unwrap();
+
+        assert_eq!(store.list_channels().unwrap(), vec![a.clone(), b.clone()]);
+        assert_eq!(
+            store
+                .by_from_to(&Address::new_id(100), &Address::new_id(102))
+                .unwrap(),
+            Some(b)
+        );
+        assert_eq!(store.by_address(&Address::new_id(1000)).unwrap(), Some(a));
+        assert_eq!(store.by_address(&Address::new_id(1002)).unwrap(), None);
+    }
+
+    #[test]
+    fn channels_keyed_by_address() {
+        let store = PaychStore::new(Arc::new(MemoryDB::default()));
+        let from = Address::new_id(100);
+        let to = Address::new_id(101);
+
+        // An inbound channel between the same addresses does not replace the outbound one
+        let outbound = channel(100, 101, 1000);
+        let mut inbound = ChannelInfo::new(from, to, Direction::Inbound);
+        inbound.channel = Some(Address::new_id(1001));
+        store.put_channel_info(&outbound).unwrap();
+        store.put_channel_info(&inbound).unwrap();
+        assert_eq!(store.list_channels().unwrap().len(), 2);
+        assert_eq!(
+            store.by_from_to(&from, &to).unwrap(),
+            Some(outbound.clone())
+        );
+
+        // Once settling, a new channel can be opened for the pair
+        let mut settling = outbound;
+        settling.settling = true;
+        store.put_channel_info(&settling).unwrap();
+        assert_eq!(store.by_from_to(&from, &to).unwrap(), None);
+        let mut pending = ChannelInfo::new(from, to, Direction::Outbound);
+        pending.create_msg = Some(Cid::default());
+        store.put_channel_info(&pending).unwrap();
+        assert_eq!(store.by_from_to(&from, &to).unwrap(), Some(pending.clone()));
+
+        store.remove_channel_info(&pending).unwrap();
+        assert_eq!(store.by_from_to(&from, &to).unwrap(), None);
+        assert_eq!(store.list_channels().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn allocate_lanes() {
+        let store = PaychStore::new(Arc::new(MemoryDB::default()));
+        store.put_channel_info(&channel(100, 101, 1000)).unwrap();
+
+        let ch = Address::new_id(1000);
+        assert_eq!(store.allocate_lane(&ch).unwrap(), 0);
+        assert_eq!(store.allocate_lane(&ch).unwrap(), 1);
+        assert!(store.allocate_lane(&Address::new_id(1001)).is_err());
+    }
+}
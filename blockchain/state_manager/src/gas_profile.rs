@@ -0,0 +1,224 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use actor::builtin_actor_name;
+use address::Address;
+use cid::Cid;
+use clock::ChainEpoch;
+use interpreter::{ExecutionTrace, GasTrace};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use vm::MethodNum;
+
+/// Maximum number of epochs a gas profile can span, as every tipset in the range is re-executed.
+pub const MAX_GAS_PROFILE_EPOCHS: ChainEpoch = 120;
+
+/// Gas consumed by a group of gas charges.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GasStats {
+    pub count: u64,
+    pub total_gas: i64,
+    pub compute_gas: i64,
+    pub storage_gas: i64,
+}
+
+impl GasStats {
+    fn add(&mut self, charge: &GasTrace) {
+        self.count += 1;
+        self.total_gas += charge.total_gas;
+        self.compute_gas += charge.compute_gas;
+        self.storage_gas += charge.storage_gas;
+    }
+}
+
+/// Gas charged under a single charge name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ChargeProfile {
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: GasStats,
+}
+
+/// Gas charged by calls to a method of an actor type, excluding the gas charged by the calls
+/// it makes to other actors.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MethodProfile {
+    pub actor: String,
+    pub method: MethodNum,
+    pub calls: u64,
+    #[serde(flatten)]
+    pub stats: GasStats,
+}
+
+/// Breakdown of the gas charged while applying the messages of a range of tipsets.
+/// Entries are sorted by decreasing total gas.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GasProfile {
+    pub start_epoch: ChainEpoch,
+    pub end_epoch: ChainEpoch,
+    pub messages: u64,
+    pub total_gas: i64,
+    pub by_charge: Vec<ChargeProfile>,
+    pub by_method: Vec<MethodProfile>,
+}
+
+/// Aggregates execution traces into a `GasProfile`.
+#[derive(Default)]
+pub(crate) struct GasProfiler {
+    messages: u64,
+    total_gas: i64,
+    by_charge: HashMap<String, GasStats>,
+    by_method: HashMap<(String, MethodNum), (u64, GasStats)>,
+}
+
+impl GasProfiler {
+    /// Adds the trace of a message applied on chain. `code_of` returns the code of the actor
+    /// at an address, if it exists after the messages are applied.
+    pub fn add_trace<F>(&mut self, trace: &ExecutionTrace, code_of: &F)
+    where
+        F: Fn(&Address) -> Option<Cid>,
+    {
+        self.messages += 1;
+        self.add_call(trace, code_of);
+    }
+
+    fn add_call<F>(&mut self, trace: &ExecutionTrace, code_of: &F)
+    where
+        F: Fn(&Address) -> Option<Cid>,
+    {
+        let actor = match code_of(&trace.msg.to) {
+            Some(code) => builtin_actor_name(&code)
+                .map(str::to_owned)
+                .unwrap_or_else(|| code.to_string()),
+            None => "unknown".to_owned(),
+        };
+        let (calls, method_stats) = self
+            .by_method
+            .entry((actor, trace.msg.method_num))
+            .or_default();
+        *calls += 1;
+
+        for charge in &trace.gas_charges {
+            method_stats.add(charge);
+            self.by_charge
+                .entry(charge.name.clone())
+                .or_default()
+                .add(charge);
+            self.total_gas += charge.total_gas;
+        }
+        for subcall in &trace.subcalls {
+            self.add_call(subcall, code_of);
+        }
+    }
+
+    pub fn finish(self, start_epoch: ChainEpoch, end_epoch: ChainEpoch) -> GasProfile {
+        let mut by_charge: Vec<_> = self
+            .by_charge
+            .into_iter()
+            .map(|(name, stats)| ChargeProfile { name, stats })
+            .collect();
+        by_charge.sort_by(|a, b| b.stats.total_gas.cmp(&a.stats.total_gas));
+
+        let mut by_method: Vec<_> = self
+            .by_method
+            .into_iter()
+            .map(|((actor, method), (calls, stats))| MethodProfile {
+                actor,
+                method,
+                calls,
+                stats,
+            })
+            .collect();
+        by_method.sort_by(|a, b| b.stats.total_gas.cmp(&a.stats.total_gas));
+
+        GasProfile {
+            start_epoch,
+            end_epoch,
+            messages: self.messages,
+            total_gas: self.total_gas,
+            by_charge,
+            by_method,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actor::{ACCOUNT_ACTOR_CODE_ID, MULTISIG_ACTOR_CODE_ID};
+    use message::{MessageReceipt, UnsignedMessage};
+    use vm::{ExitCode, Serialized};
+
+    fn charge(name: &str, compute_gas: i64) -> GasTrace {
+        GasTrace {
+            name: name.to_owned(),
+            total_gas: compute_gas,
+            compute_gas,
+            storage_gas: 0,
+        }
+    }
+
+    fn trace(to: u64, method_num: MethodNum, gas_charges: Vec<GasTrace>) -> ExecutionTrace {
+        ExecutionTrace {
+            msg: UnsignedMessage::builder()
+                .to(Address::new_id(to))
+                .from(Address::new_id(100))
+                .method_num(method_num)
+                .build()
+                .unwrap(),
+            msg_rct: MessageReceipt {
+                exit_code: ExitCode::Ok,
+                return_data: Serialized::default(),
+                gas_used: 0,
+            },
+            error: None,
+            gas_charges,
+            subcalls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn aggregates_charges_and_methods() {
+        let mut top = trace(
+            1000,
+            2,
+            vec![charge("OnChainMessage", 50), charge("OnIpldGet", 10)],
+        );
+        top.subcalls
+            .push(trace(1001, 0, vec![charge("OnMethodInvocation", 5)]));
+        top.subcalls
+            .push(trace(1002, 1, vec![charge("OnIpldGet", 10)]));
+
+        let code_of = |addr: &Address| match addr.id().unwrap() {
+            1000 => Some(*MULTISIG_ACTOR_CODE_ID),
+            1001 => Some(*ACCOUNT_ACTOR_CODE_ID),
+            _ => None,
+        };
+        let mut profiler = GasProfiler::default();
+        profiler.add_trace(&top, &code_of);
+        let profile = profiler.finish(10, 10);
+
+        assert_eq!(profile.messages, 1);
+        assert_eq!(profile.total_gas, 75);
+        assert_eq!(profile.by_charge[0].name, "OnChainMessage");
+        let ipld_get = profile
+            .by_charge
+            .iter()
+            .find(|c| c.name == "OnIpldGet")
+            .unwrap();
+        assert_eq!(ipld_get.stats.count, 2);
+        assert_eq!(ipld_get.stats.total_gas, 20);
+
+        assert_eq!(profile.by_method[0].actor, "multisig");
+        assert_eq!(profile.by_method[0].method, 2);
+        assert_eq!(profile.by_method[0].stats.total_gas, 60);
+        assert!(profile
+            .by_method
+            .iter()
+            .any(|m| m.actor == "unknown" && m.method == 1 && m.calls == 1));
+    }
+}
@@ -6,10 +6,12 @@ extern crate lazy_static;
 
 mod chain_rand;
 mod errors;
+mod gas_profile;
 pub mod utils;
 mod vm_circ_supply;
 
 pub use self::errors::*;
+pub use self::gas_profile::*;
 use actor::*;
 use address::{Address, BLSPublicKey, Payload, Protocol, BLS_PUB_LEN};
 use async_log::span;
@@ -22,18 +24,23 @@ use cid::Cid;
 use clock::ChainEpoch;
 use encoding::de::DeserializeOwned;
 use encoding::Cbor;
-use fil_types::{get_network_version_default, verifier::ProofVerifier};
+use fil_types::{
+    get_network_version_default, verifier::ProofVerifier, NetworkPolicy, PaddedPieceSize,
+    StoragePower,
+};
 use flo_stream::Subscriber;
 use forest_blocks::{BlockHeader, Tipset, TipsetKeys};
 use futures::channel::oneshot;
 use futures::stream::{FuturesUnordered, StreamExt};
-use interpreter::{resolve_to_key_addr, ApplyRet, BlockMessages, Rand, VM};
+use interpreter::{
+    resolve_to_key_addr, ApplyRet, BlockMessages, CircSupplyCalc, ExecutionTrace, Rand, VM,
+};
 use ipld_amt::Amt;
 use lazycell::AtomicLazyCell;
 use log::{debug, info, trace, warn};
 use message::{message_receipt, unsigned_message};
 use message::{ChainMessage, Message, MessageReceipt, UnsignedMessage};
-use num_bigint::{bigint_ser, BigInt};
+use num_bigint::{bigint_ser, bigint_ser::BigIntDe, BigInt};
 use serde::{Deserialize, Serialize};
 use state_tree::StateTree;
 use std::collections::HashMap;
@@ -41,6 +48,27 @@ use std::error::Error as StdError;
 use std::sync::Arc;
 use vm_circ_supply::GenesisInfoPair;
 
+/// Evaluates the body with `$params` aliased to the `NetworkParams` of the given network, which
+/// selects the actor policy the VM applies messages with.
+macro_rules! with_network_params {
+    ($network:expr, $params:ident => $body:expr) => {
+        match $network {
+            NetworkPolicy::Mainnet => {
+                type $params = fil_types::MainnetParams;
+                $body
+            }
+            NetworkPolicy::Testnet => {
+                type $params = fil_types::TestnetParams;
+                $body
+            }
+            NetworkPolicy::Devnet => {
+                type $params = fil_types::DevnetParams;
+                $body
+            }
+        }
+    };
+}
+
 /// Intermediary for retrieving state objects and updating actor states
 pub type CidPair = (Cid, Cid);
 
@@ -53,6 +81,7 @@ pub struct InvocResult {
     #[serde(with = "message_receipt::json::opt")]
     pub msg_rct: Option<MessageReceipt>,
     pub error: Option<String>,
+    pub execution_trace: Option<ExecutionTrace>,
 }
 
 // An alias Result that represents an InvocResult and an Error
@@ -67,6 +96,14 @@ pub struct MarketBalance {
     locked: BigInt,
 }
 
+/// A storage market deal proposal along with its on chain state, which is only set once the
+/// deal has been activated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarketDeal {
+    pub proposal: market::DealProposal,
+    pub state: Option<market::DealState>,
+}
+
 pub struct StateManager<DB> {
     cs: Arc<ChainStore<DB>>,
 
@@ -76,6 +113,7 @@ pub struct StateManager<DB> {
     cache: RwLock<HashMap<TipsetKeys, Arc<RwLock<Option<CidPair>>>>>,
     subscriber: Option<Subscriber<HeadChange>>,
     genesis_info: GenesisInfoPair,
+    network: NetworkPolicy,
 }
 
 impl<DB> StateManager<DB>
@@ -88,6 +126,7 @@ where
             cache: RwLock::new(HashMap::new()),
             subscriber: None,
             genesis_info: GenesisInfoPair::default(),
+            network: NetworkPolicy::default(),
         }
     }
 
@@ -101,8 +140,21 @@ where
             cache: RwLock::new(HashMap::new()),
             subscriber: Some(chain_subs),
             genesis_info: GenesisInfoPair::default(),
+            network: NetworkPolicy::default(),
         }
     }
+
+    /// Sets the network whose parameters are used when applying messages, mainnet by default.
+    pub fn with_network(mut self, network: NetworkPolicy) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Returns the network whose parameters are used when applying messages.
+    pub fn network(&self) -> NetworkPolicy {
+        self.network
+    }
+
     /// Loads actor state from IPLD Store
     pub fn load_actor_state<D>(&self, addr: &Address, state_cid: &Cid) -> Result<D, Error>
     where
@@ -199,6 +251,7 @@ where
 
     /// Performs the state transition for the tipset and applies all unique messages in all blocks.
     /// This function returns the state root and receipt root of the transition.
+    /// If `trace` is set, the results passed to the callback include execution traces.
     #[allow(clippy::too_many_arguments)]
     pub fn apply_blocks<R, V, CB>(
         &self,
@@ -209,6 +262,7 @@ where
         rand: &R,
         base_fee: BigInt,
         callback: Option<CB>,
+        trace: bool,
     ) -> Result<CidPair, Box<dyn StdError>>
     where
         R: Rand,
@@ -216,27 +270,31 @@ where
         CB: FnMut(&Cid, &ChainMessage, &ApplyRet) -> Result<(), String>,
     {
         let mut buf_store = BufferedBlockStore::new(self.blockstore());
-        // TODO change from statically using devnet params when needed
-        let mut vm = VM::<_, _, _, _, V>::new(
-            p_state,
-            &buf_store,
-            epoch,
-            rand,
-            base_fee,
-            get_network_version_default,
-            &self.genesis_info,
-        )?;
+        let (state_root, rect_root) = with_network_params!(self.network, P => {
+            let mut vm = VM::<_, _, _, _, V, P>::new(
+                p_state,
+                &buf_store,
+                epoch,
+                rand,
+                base_fee,
+                get_network_version_default,
+                &self.genesis_info,
+            )?;
+            if trace {
+                vm.enable_tracing();
+            }
 
-        // Apply tipset messages
-        let receipts = vm.apply_block_messages(messages, parent_epoch, epoch, callback)?;
+            // Apply tipset messages
+            let receipts = vm.apply_block_messages(messages, parent_epoch, epoch, callback)?;
 
-        // Construct receipt root from receipts
-        let rect_root = Amt::new_from_slice(self.blockstore(), &receipts)?;
+            // Construct receipt root from receipts
+            let rect_root = Amt::new_from_slice(&buf_store, &receipts)?;
 
-        // Flush changes to blockstore
-        let state_root = vm.flush()?;
-        // Persist changes connected to root
-        buf_store.flush(&state_root)?;
+            // Flush changes to blockstore
+            (vm.flush()?, rect_root)
+        });
+        // Persist changes connected to the roots in a single write
+        buf_store.flush_roots(&[state_root, rect_root])?;
 
         Ok((state_root, rect_root))
     }
@@ -294,7 +352,8 @@ where
             } else {
                 // generic constants are not implemented yet this is a lowcost method for now
                 let no_func = None::<fn(&Cid, &ChainMessage, &ApplyRet) -> Result<(), String>>;
-                self.compute_tipset_state::<V, _>(&tipset, no_func).await?
+                self.compute_tipset_state::<V, _>(&tipset, no_func, false)
+                    .await?
             };
 
             // Fill entry with calculated cid pair
@@ -316,16 +375,6 @@ where
         span!("state_call_raw", {
             let block_store = self.blockstore();
             let buf_store = BufferedBlockStore::new(block_store);
-            let mut vm = VM::<_, _, _, _, V>::new(
-                bstate,
-                &buf_store,
-                *bheight,
-                rand,
-                0.into(),
-                get_network_version_default,
-                &self.genesis_info,
-            )?;
-
             if msg.gas_limit() == 0 {
                 msg.set_gas_limit(10000000000)
             }
@@ -334,7 +383,18 @@ where
                 .get_actor(msg.from(), bstate)?
                 .ok_or_else(|| Error::Other("Could not get actor".to_string()))?;
             msg.set_sequence(actor.sequence);
-            let apply_ret = vm.apply_implicit_message(msg);
+            let apply_ret = with_network_params!(self.network, P => {
+                let mut vm = VM::<_, _, _, _, V, P>::new(
+                    bstate,
+                    &buf_store,
+                    *bheight,
+                    rand,
+                    0.into(),
+                    get_network_version_default,
+                    &self.genesis_info,
+                )?;
+                vm.apply_implicit_message(msg)
+            });
             trace!(
                 "gas limit {:},gas premium{:?},value {:?}",
                 msg.gas_limit(),
@@ -349,6 +409,7 @@ where
                 msg: msg.clone(),
                 msg_rct: Some(apply_ret.msg_receipt.clone()),
                 error: apply_ret.act_error.map(|e| e.to_string()),
+                execution_trace: apply_ret.exec_trace,
             })
         })
     }
@@ -398,32 +459,35 @@ where
             .map_err(|_| Error::Other("Could not load tipset state".to_string()))?;
         let chain_rand = ChainRand::new(ts.key().to_owned(), self.cs.clone());
 
-        let mut vm = VM::<_, _, _, _, V>::new(
-            &st,
-            self.blockstore(),
-            ts.epoch() + 1,
-            &chain_rand,
-            ts.blocks()[0].parent_base_fee().clone(),
-            get_network_version_default,
-            &self.genesis_info,
-        )?;
-
-        for msg in prior_messages {
-            vm.apply_message(&msg)?;
-        }
-        let from_actor = vm
-            .state()
-            .get_actor(message.from())
-            .map_err(|e| Error::Other(format!("Could not get actor from state: {}", e)))?
-            .ok_or_else(|| Error::Other("cant find actor in state tree".to_string()))?;
-        message.set_sequence(from_actor.sequence);
+        let ret = with_network_params!(self.network, P => {
+            let mut vm = VM::<_, _, _, _, V, P>::new(
+                &st,
+                self.blockstore(),
+                ts.epoch() + 1,
+                &chain_rand,
+                ts.blocks()[0].parent_base_fee().clone(),
+                get_network_version_default,
+                &self.genesis_info,
+            )?;
 
-        let ret = vm.apply_message(&message)?;
+            for msg in prior_messages {
+                vm.apply_message(&msg)?;
+            }
+            let from_actor = vm
+                .state()
+                .get_actor(message.from())
+                .map_err(|e| Error::Other(format!("Could not get actor from state: {}", e)))?
+                .ok_or_else(|| Error::Other("cant find actor in state tree".to_string()))?;
+            message.set_sequence(from_actor.sequence);
+
+            vm.apply_message(&message)?
+        });
 
         Ok(InvocResult {
             msg: message.message().clone(),
             msg_rct: Some(ret.msg_receipt.clone()),
             error: ret.act_error.map(|e| e.to_string()),
+            execution_trace: ret.exec_trace,
         })
     }
 
@@ -451,7 +515,9 @@ where
 
             Ok(())
         };
-        let result = self.compute_tipset_state::<V, _>(&ts, Some(callback)).await;
+        let result = self
+            .compute_tipset_state::<V, _>(&ts, Some(callback), true)
+            .await;
 
         if let Err(error_message) = result {
             if error_message.to_string() != "halt" {
@@ -471,10 +537,13 @@ where
         Ok((out_mes, out_ret))
     }
 
+    /// Computes the state of the tipset. If `trace` is set, the results passed to the callback
+    /// include execution traces.
     pub async fn compute_tipset_state<V, CB: 'static>(
         self: &Arc<Self>,
         tipset: &Tipset,
         callback: Option<CB>,
+        trace: bool,
     ) -> Result<CidPair, Error>
     where
         V: ProofVerifier,
@@ -498,23 +567,7 @@ where
                 return Err(Error::Other(format!("duplicate miner in a tipset ({})", a)));
             }
 
-            let parent_epoch = if first_block.epoch() > 0 {
-                let parent_cid = first_block
-                    .parents()
-                    .cids()
-                    .get(0)
-                    .ok_or_else(|| Error::Other("block must have parents".to_string()))?;
-                let parent: BlockHeader = self
-                    .blockstore()
-                    .get(parent_cid)
-                    .map_err(|e| Error::Other(e.to_string()))?
-                    .ok_or_else(|| {
-                        format!("Could not find parent block with cid {}", parent_cid)
-                    })?;
-                parent.epoch()
-            } else {
-                Default::default()
-            };
+            let parent_epoch = self.parent_epoch(first_block)?;
 
             let tipset_keys =
                 TipsetKeys::new(block_headers.iter().map(|s| s.cid()).cloned().collect());
@@ -538,6 +591,7 @@ where
                     &chain_rand,
                     base_fee,
                     callback,
+                    trace,
                 )
                 .map_err(|e| Error::Other(e.to_string()))
             })
@@ -545,6 +599,161 @@ where
         })
     }
 
+    /// Re-executes the messages of the tipsets from `start` up to and including `end`, and
+    /// returns the gas charged broken down by charge name and by actor method. The range can
+    /// span at most `MAX_GAS_PROFILE_EPOCHS`.
+    pub async fn gas_profile<V>(
+        self: &Arc<Self>,
+        start: ChainEpoch,
+        end: &Tipset,
+    ) -> Result<GasProfile, Error>
+    where
+        V: ProofVerifier,
+    {
+        if start > end.epoch() {
+            return Err(Error::Other(format!(
+                "start epoch {} is after the end tipset epoch {}",
+                start,
+                end.epoch()
+            )));
+        }
+        if end.epoch() - start >= MAX_GAS_PROFILE_EPOCHS {
+            return Err(Error::Other(format!(
+                "cannot profile more than {} epochs at once",
+                MAX_GAS_PROFILE_EPOCHS
+            )));
+        }
+
+        let mut profiler = GasProfiler::default();
+        let mut ts = end.clone();
+        while ts.epoch() >= start && ts.epoch() > 0 {
+            let traces = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let traces_cb = traces.clone();
+            let callback = move |_: &Cid, _: &ChainMessage, ret: &ApplyRet| {
+                if let Some(trace) = &ret.exec_trace {
+                    traces_cb
+                        .lock()
+                        .map_err(|e| e.to_string())?
+                        .push(trace.clone());
+                }
+                Ok(())
+            };
+            let (state_root, _) = self
+                .compute_tipset_state::<V, _>(&ts, Some(callback), true)
+                .await?;
+
+            // Actors are resolved after execution, so actors created by the messages are found,
+            // and before it for actors deleted by the messages
+            let post_state = StateTree::new_from_root(self.blockstore(), &state_root)
+                .map_err(|e| Error::State(e.to_string()))?;
+            let pre_state = StateTree::new_from_root(self.blockstore(), ts.parent_state())
+                .map_err(|e| Error::State(e.to_string()))?;
+            let code_of = |addr: &Address| {
+                post_state
+                    .get_actor(addr)
+                    .ok()
+                    .flatten()
+                    .or_else(|| pre_state.get_actor(addr).ok().flatten())
+                    .map(|a| a.code)
+            };
+            let traces = traces.lock().map_err(|e| Error::Other(e.to_string()))?;
+            for trace in traces.iter() {
+                profiler.add_trace(trace, &code_of);
+            }
+
+            ts = self
+                .chain_store()
+                .tipset_from_keys(ts.parents())
+                .map_err(|e| Error::Other(e.to_string()))?;
+        }
+        Ok(profiler.finish(start, end.epoch()))
+    }
+
+    /// Returns the epoch of the parent of a block, or 0 for the genesis block.
+    fn parent_epoch(&self, block: &BlockHeader) -> Result<ChainEpoch, Error> {
+        if block.epoch() == 0 {
+            return Ok(Default::default());
+        }
+        let parent_cid = block
+            .parents()
+            .cids()
+            .get(0)
+            .ok_or_else(|| Error::Other("block must have parents".to_string()))?;
+        let parent: BlockHeader = self
+            .blockstore()
+            .get(parent_cid)
+            .map_err(|e| Error::Other(e.to_string()))?
+            .ok_or_else(|| format!("Could not find parent block with cid {}", parent_cid))?;
+        Ok(parent.epoch())
+    }
+
+    /// Computes the state of the tipset at the given height with the given messages appended to
+    /// the messages of its first block. Returns the state root along with the result and
+    /// execution trace of every message applied, including implicit ones.
+    pub async fn compute_state<V>(
+        self: &Arc<Self>,
+        height: ChainEpoch,
+        msgs: Vec<UnsignedMessage>,
+        tipset: &Tipset,
+    ) -> Result<(Cid, Vec<InvocResult>), Error>
+    where
+        V: ProofVerifier,
+    {
+        if height < tipset.epoch() {
+            return Err(Error::Other(format!(
+                "cannot compute state at height {} below the tipset epoch {}",
+                height,
+                tipset.epoch()
+            )));
+        }
+        let first_block = tipset
+            .blocks()
+            .first()
+            .ok_or_else(|| Error::Other("Empty tipset in compute_state".to_string()))?;
+        let parent_epoch = self.parent_epoch(first_block)?;
+        let chain_rand = ChainRand::new(tipset.key().clone(), self.cs.clone());
+        let base_fee = first_block.parent_base_fee().clone();
+
+        let mut blocks = self
+            .chain_store()
+            .block_msgs_for_tipset(tipset)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        if let Some(block) = blocks.first_mut() {
+            block
+                .messages
+                .extend(msgs.into_iter().map(ChainMessage::Unsigned));
+        }
+
+        let sm = self.clone();
+        let sr = *first_block.state_root();
+        task::spawn_blocking(move || {
+            let mut trace = Vec::new();
+            let callback = |_: &Cid, msg: &ChainMessage, ret: &ApplyRet| {
+                trace.push(InvocResult {
+                    msg: msg.message().clone(),
+                    msg_rct: Some(ret.msg_receipt.clone()),
+                    error: ret.act_error.as_ref().map(|e| e.to_string()),
+                    execution_trace: ret.exec_trace.clone(),
+                });
+                Ok(())
+            };
+            let (root, _) = sm
+                .apply_blocks::<_, V, _>(
+                    parent_epoch,
+                    &sr,
+                    &blocks,
+                    height,
+                    &chain_rand,
+                    base_fee,
+                    Some(callback),
+                    true,
+                )
+                .map_err(|e| Error::Other(e.to_string()))?;
+            Ok((root, trace))
+        })
+        .await
+    }
+
     fn tipset_executed_message(
         &self,
         tipset: &Tipset,
@@ -866,6 +1075,170 @@ where
         Ok(out)
     }
 
+    /// Returns all deals in the storage market at the given tipset, indexed by deal ID.
+    /// Deals which have not been activated yet have no state.
+    pub fn market_deals(&self, ts: &Tipset) -> Result<HashMap<DealID, MarketDeal>, Error> {
+        let market_state: market::State =
+            self.load_actor_state(&*STORAGE_MARKET_ACTOR_ADDR, ts.parent_state())?;
+        let proposals =
+            Amt::<market::DealProposal, _>::load(&market_state.proposals, self.blockstore())
+                .map_err(|e| Error::State(e.to_string()))?;
+        let states = Amt::<market::DealState, _>::load(&market_state.states, self.blockstore())
+            .map_err(|e| Error::State(e.to_string()))?;
+
+        let mut out = HashMap::new();
+        proposals
+            .for_each(|deal_id, proposal| {
+                out.insert(
+                    deal_id,
+                    MarketDeal {
+                        proposal: proposal.clone(),
+                        state: states.get(deal_id)?.copied(),
+                    },
+                );
+                Ok(())
+            })
+            .map_err(|e| Error::State(e.to_string()))?;
+        Ok(out)
+    }
+
+    /// Returns the proposal and state of a single storage market deal.
+    pub fn market_storage_deal(&self, deal_id: DealID, ts: &Tipset) -> Result<MarketDeal, Error> {
+        let market_state: market::State =
+            self.load_actor_state(&*STORAGE_MARKET_ACTOR_ADDR, ts.parent_state())?;
+        let proposals =
+            Amt::<market::DealProposal, _>::load(&market_state.proposals, self.blockstore())
+                .map_err(|e| Error::State(e.to_string()))?;
+        let proposal = proposals
+            .get(deal_id)
+            .map_err(|e| Error::State(e.to_string()))?
+            .ok_or_else(|| Error::State(format!("deal {} not found", deal_id)))?
+            .clone();
+
+        let states = Amt::<market::DealState, _>::load(&market_state.states, self.blockstore())
+            .map_err(|e| Error::State(e.to_string()))?;
+        let state = states
+            .get(deal_id)
+            .map_err(|e| Error::State(e.to_string()))?
+            .copied();
+
+        Ok(MarketDeal { proposal, state })
+    }
+
+    /// Returns the escrow and locked balances of every address with funds in the storage market.
+    pub fn market_participants(
+        &self,
+        ts: &Tipset,
+    ) -> Result<HashMap<Address, MarketBalance>, Error> {
+        let market_state: market::State =
+            self.load_actor_state(&*STORAGE_MARKET_ACTOR_ADDR, ts.parent_state())?;
+        let escrow_table =
+            make_map_with_root::<_, BigIntDe>(&market_state.escrow_table, self.blockstore())
+                .map_err(|e| Error::State(e.to_string()))?;
+        let locked_table = BalanceTable::from_root(self.blockstore(), &market_state.locked_table)
+            .map_err(|e| Error::State(e.to_string()))?;
+
+        let mut out = HashMap::new();
+        escrow_table
+            .for_each(|key, escrow| {
+                let addr = Address::from_bytes(&key.0)?;
+                let locked = locked_table.get(&addr)?;
+                out.insert(
+                    addr,
+                    MarketBalance {
+                        escrow: escrow.0.clone(),
+                        locked,
+                    },
+                );
+                Ok(())
+            })
+            .map_err(|e| Error::State(e.to_string()))?;
+        Ok(out)
+    }
+
+    /// Returns the data cap of a verified client, or `None` if the address is not a verified
+    /// client.
+    pub fn verified_client_status(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> Result<Option<StoragePower>, Error> {
+        let vrs: verifreg::State =
+            self.load_actor_state(&*VERIFIED_REGISTRY_ACTOR_ADDR, ts.parent_state())?;
+        self.verifreg_lookup(&vrs.verified_clients, addr, ts)
+    }
+
+    /// Returns the data cap a verifier can still allocate, or `None` if the address is not a
+    /// verifier.
+    pub fn verifier_status(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> Result<Option<StoragePower>, Error> {
+        let vrs: verifreg::State =
+            self.load_actor_state(&*VERIFIED_REGISTRY_ACTOR_ADDR, ts.parent_state())?;
+        self.verifreg_lookup(&vrs.verifiers, addr, ts)
+    }
+
+    /// Looks up the data cap of an address in one of the verified registry maps, which are keyed
+    /// by ID address.
+    fn verifreg_lookup(
+        &self,
+        root: &Cid,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> Result<Option<StoragePower>, Error> {
+        let id_addr = match self.lookup_id(addr, ts)? {
+            Some(id_addr) => id_addr,
+            None => return Ok(None),
+        };
+        let map = make_map_with_root::<_, BigIntDe>(root, self.blockstore())
+            .map_err(|e| Error::State(e.to_string()))?;
+        Ok(map
+            .get(&id_addr.to_bytes())
+            .map_err(|e| Error::State(e.to_string()))?
+            .map(|dcap| dcap.0.clone()))
+    }
+
+    /// Returns the minimum and maximum collateral a storage provider must put up for a deal of
+    /// the given size, given the network power and circulating supply at the tipset.
+    pub fn deal_provider_collateral_bounds(
+        &self,
+        size: PaddedPieceSize,
+        verified: bool,
+        ts: &Tipset,
+    ) -> Result<(TokenAmount, TokenAmount), Error> {
+        let power_state: power::State =
+            self.load_actor_state(&*STORAGE_POWER_ACTOR_ADDR, ts.parent_state())?;
+        let reward_state: reward::State =
+            self.load_actor_state(&*REWARD_ACTOR_ADDR, ts.parent_state())?;
+        let circ_supply = self.get_circulating_supply(ts.epoch(), ts.parent_state())?;
+
+        let (min, max) = market::deal_provider_collateral_bounds(
+            size,
+            verified,
+            &power_state.total_raw_byte_power,
+            &power_state.total_quality_adj_power,
+            &reward_state.this_epoch_baseline_power,
+            &circ_supply,
+            get_network_version_default(ts.epoch()),
+        );
+        Ok((min, max.clone()))
+    }
+
+    /// Returns the circulating supply of FIL at the given height and state root.
+    pub fn get_circulating_supply(
+        &self,
+        height: ChainEpoch,
+        state_root: &Cid,
+    ) -> Result<TokenAmount, Error> {
+        let state_tree = StateTree::new_from_root(self.blockstore(), state_root)
+            .map_err(|e| Error::State(e.to_string()))?;
+        self.genesis_info
+            .get_supply(height, &state_tree)
+            .map_err(|e| Error::State(e.to_string()))
+    }
+
     /// Similar to `resolve_to_key_addr` in the vm crate but does not allow `Actor` type of addresses.
     /// Uses `ts` to generate the VM state.
     pub async fn resolve_to_key_addr<V>(
@@ -901,8 +1274,10 @@ where
         let ps: power::State = self
             .load_actor_state(&*STORAGE_POWER_ACTOR_ADDR, ts.parent_state())
             .map_err(|e| format!("loading power actor state: {}", e))?;
-        ps.miner_nominal_power_meets_consensus_minimum(self.blockstore(), addr)
-            .map_err(|e| e.to_string())
+        with_network_params!(self.network, P => {
+            ps.miner_nominal_power_meets_consensus_minimum::<P, _>(self.blockstore(), addr)
+        })
+        .map_err(|e| e.to_string())
     }
 
     pub async fn validate_chain<V: ProofVerifier>(
@@ -948,3 +1323,159 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cid::Code::Blake2b256;
+    use db::MemoryDB;
+    use ipld_hamt::BytesKey;
+
+    fn proposal(client: u64, provider: u64, verified_deal: bool) -> market::DealProposal {
+        market::DealProposal {
+            piece_cid: Cid::default(),
+            piece_size: PaddedPieceSize(2048),
+            verified_deal,
+            client: Address::new_id(client),
+            provider: Address::new_id(provider),
+            label: "deal".to_owned(),
+            start_epoch: 10,
+            end_epoch: 100,
+            storage_price_per_epoch: TokenAmount::from(1),
+            provider_collateral: TokenAmount::from(5),
+            client_collateral: TokenAmount::from(0),
+        }
+    }
+
+    fn data_cap_map(db: &MemoryDB, caps: &[(u64, u64)]) -> Cid {
+        let mut map = make_map::<_, BigIntDe>(db);
+        for (id, cap) in caps {
+            map.set(
+                BytesKey(Address::new_id(*id).to_bytes()),
+                BigIntDe(StoragePower::from(*cap)),
+            )
+            .unwrap();
+        }
+        map.flush().unwrap()
+    }
+
+    fn set_actor<S: Cbor>(state_tree: &mut StateTree<MemoryDB>, addr: &Address, code: Cid, st: &S) {
+        let head = state_tree.store().put(st, Blake2b256).unwrap();
+        state_tree
+            .set_actor(addr, ActorState::new(code, head, TokenAmount::from(0), 0))
+            .unwrap();
+    }
+
+    /// Builds a state with two market deals, of which only the first is active, escrow for
+    /// the client and provider, and a verifier with a verified client.
+    fn setup() -> (StateManager<MemoryDB>, Tipset) {
+        let db = Arc::new(MemoryDB::default());
+        let empty_map = make_map::<_, ()>(db.as_ref()).flush().unwrap();
+        let empty_arr = Amt::<(), _>::new(db.as_ref()).flush().unwrap();
+
+        let mut proposals = Amt::new(db.as_ref());
+        proposals.set(0, proposal(100, 1000, false)).unwrap();
+        proposals.set(1, proposal(100, 1000, true)).unwrap();
+        let mut states = Amt::new(db.as_ref());
+        states
+            .set(
+                0,
+                market::DealState {
+                    sector_start_epoch: 5,
+                    last_updated_epoch: -1,
+                    slash_epoch: -1,
+                },
+            )
+            .unwrap();
+        let mut escrow = BalanceTable::new(db.as_ref());
+        escrow
+            .set(&Address::new_id(100), TokenAmount::from(500))
+            .unwrap();
+        escrow
+            .set(&Address::new_id(1000), TokenAmount::from(50))
+            .unwrap();
+        let mut locked = BalanceTable::new(db.as_ref());
+        locked
+            .set(&Address::new_id(100), TokenAmount::from(90))
+            .unwrap();
+
+        let mut market_state = market::State::new(empty_arr, empty_map, empty_map);
+        market_state.proposals = proposals.flush().unwrap();
+        market_state.states = states.flush().unwrap();
+        market_state.escrow_table = escrow.root().unwrap();
+        market_state.locked_table = locked.root().unwrap();
+
+        let mut verifreg_state = verifreg::State::new(empty_map, Address::new_id(80));
+        verifreg_state.verifiers = data_cap_map(&db, &[(200, 1 << 20)]);
+        verifreg_state.verified_clients = data_cap_map(&db, &[(100, 1 << 10)]);
+
+        let mut state_tree = StateTree::new(db.as_ref());
+        set_actor(
+            &mut state_tree,
+            &*STORAGE_MARKET_ACTOR_ADDR,
+            *MARKET_ACTOR_CODE_ID,
+            &market_state,
+        );
+        set_actor(
+            &mut state_tree,
+            &*VERIFIED_REGISTRY_ACTOR_ADDR,
+            *VERIFREG_ACTOR_CODE_ID,
+            &verifreg_state,
+        );
+        let state_root = state_tree.flush().unwrap();
+
+        let header = BlockHeader::builder()
+            .miner_address(Address::new_id(1000))
+            .state_root(state_root)
+            .build_and_validate()
+            .unwrap();
+        let ts = Tipset::new(vec![header]).unwrap();
+        let state_manager = StateManager::new(Arc::new(ChainStore::new(db)));
+        (state_manager, ts)
+    }
+
+    #[test]
+    fn market_deals() {
+        let (sm, ts) = setup();
+        let deals = sm.market_deals(&ts).unwrap();
+        assert_eq!(deals.len(), 2);
+        assert_eq!(deals[&0].proposal, proposal(100, 1000, false));
+        assert_eq!(deals[&0].state.unwrap().sector_start_epoch, 5);
+        assert!(deals[&1].proposal.verified_deal);
+        assert_eq!(deals[&1].state, None);
+
+        assert_eq!(sm.market_storage_deal(0, &ts).unwrap(), deals[&0]);
+        assert_eq!(sm.market_storage_deal(1, &ts).unwrap(), deals[&1]);
+        assert!(sm.market_storage_deal(2, &ts).is_err());
+    }
+
+    #[test]
+    fn market_participants() {
+        let (sm, ts) = setup();
+        let participants = sm.market_participants(&ts).unwrap();
+        assert_eq!(participants.len(), 2);
+        let client = &participants[&Address::new_id(100)];
+        assert_eq!(client.escrow, BigInt::from(500));
+        assert_eq!(client.locked, BigInt::from(90));
+        let provider = &participants[&Address::new_id(1000)];
+        assert_eq!(provider.escrow, BigInt::from(50));
+        assert_eq!(provider.locked, BigInt::from(0));
+    }
+
+    #[test]
+    fn verified_registry_status() {
+        let (sm, ts) = setup();
+        let client = Address::new_id(100);
+        let verifier = Address::new_id(200);
+        assert_eq!(
+            sm.verified_client_status(&client, &ts).unwrap(),
+            Some(StoragePower::from(1 << 10))
+        );
+        assert_eq!(sm.verified_client_status(&verifier, &ts).unwrap(), None);
+        assert_eq!(
+            sm.verifier_status(&verifier, &ts).unwrap(),
+            Some(StoragePower::from(1 << 20))
+        );
+        assert_eq!(sm.verifier_status(&client, &ts).unwrap(), None);
+    }
+}
@@ -195,6 +195,21 @@ pub fn verify_bls_aggregate(data: &[&[u8]], pub_keys: &[&[u8]], aggregate_sig: &
     verify(&sig, &hashed_data[..], &pks[..])
 }
 
+/// Aggregates bls signatures into one, which can be verified with `verify_bls_aggregate`.
+/// The aggregate of no signatures is empty.
+pub fn aggregate_bls(signatures: &[Signature]) -> Result<Signature, Error> {
+    if signatures.is_empty() {
+        return Ok(Signature::new_bls(Vec::new()));
+    }
+    let sigs = signatures
+        .iter()
+        .map(|s| BlsSignature::from_bytes(s.bytes()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::SigningError(e.to_string()))?;
+    let agg = bls_signatures::aggregate(&sigs).map_err(|e| Error::SigningError(e.to_string()))?;
+    Ok(Signature::new_bls(agg.as_bytes()))
+}
+
 /// Return Address for a message given it's signing bytes hash and signature.
 pub fn ecrecover(hash: &[u8; 32], signature: &[u8; SECP_SIG_LEN]) -> Result<Address, Error> {
     // generate types to recover key from
@@ -254,6 +269,12 @@ mod tests {
             verify_bls_aggregate(&data, &public_keys_slice, &calculated_bls_agg),
             true
         );
+
+        let sigs: Vec<_> = signatures
+            .iter()
+            .map(|s| Signature::new_bls(s.as_bytes()))
+            .collect();
+        assert_eq!(aggregate_bls(&sigs).unwrap(), calculated_bls_agg);
     }
 
     #[test]
@@ -1,36 +1,81 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use actor::EPOCH_DURATION_SECONDS;
 use beacon::DrandPublic;
+use fil_types::NetworkPolicy;
 use forest_libp2p::Libp2pConfig;
+use logger::LoggerConfig;
 use serde::Deserialize;
 use utils::get_home_dir;
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct Config {
+    /// Network whose parameters are used to validate the chain (`mainnet`, `testnet` or `devnet`).
+    pub chain: NetworkPolicy,
     pub network: Libp2pConfig,
     pub data_dir: String,
     pub genesis_file: Option<String>,
     pub drand_public: DrandPublic,
     pub enable_rpc: bool,
     pub rpc_port: String,
+    /// If this is true, metrics are served in the Prometheus format at `/metrics`.
+    pub enable_metrics: bool,
+    /// Address the metrics endpoint listens on.
+    pub metrics_address: String,
+    /// If this is true, liveness and readiness probes are served at `/healthz/livez` and
+    /// `/readyz`.
+    pub enable_health: bool,
+    /// Address the health endpoints listen on.
+    pub health_address: String,
     /// If this is true, then we do not validate the imported snapshot.
     /// Otherwise, we validate and compute the states.
     pub snapshot: bool,
     pub snapshot_path: Option<String>,
+    /// If this is true, keys are stored in an encrypted keystore, unlocked with the passphrase
+    /// from the `FOREST_KEYSTORE_PHRASE` environment variable or a prompt at startup.
+    pub encrypt_keystore: bool,
+    /// Endpoint of an external signer (`unix:<path>` or `tcp:<host>:<port>`). If set, the node
+    /// signs through it rather than with the keys in its keystore. A TCP signer is authenticated
+    /// with the token from the `FOREST_SIGNER_TOKEN` environment variable.
+    pub signer_endpoint: Option<String>,
+    /// If this is true, the node runs a local devnet: it boots from a generated genesis, uses a
+    /// mock beacon and mock proofs, does not connect to outside peers and produces its own blocks.
+    pub devnet: bool,
+    /// Seconds between devnet blocks.
+    pub block_time: u64,
+    /// Miner actor to produce blocks for. Blocks are signed with the key of its worker.
+    pub miner_address: Option<String>,
+    /// Directory with the `sealed` and `cache` directories of the miner's sectors, which winning
+    /// PoSts are generated from. Not needed on devnets, where proofs are mocked.
+    pub sector_storage: Option<String>,
+    /// Format of the logs, and the file they are also written to.
+    pub logging: LoggerConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            chain: NetworkPolicy::default(),
             network: Libp2pConfig::default(),
             data_dir: get_home_dir() + "/.forest",
             genesis_file: None,
             drand_public: DrandPublic{coefficient: hex::decode("8cad0c72c606ab27d36ee06de1d5b2db1faf92e447025ca37575ab3a8aac2eaae83192f846fc9e158bc738423753d000").unwrap()},
             enable_rpc : true,
             rpc_port: "1234".to_string(),
+            enable_metrics: false,
+            metrics_address: "127.0.0.1:6116".to_string(),
+            enable_health: false,
+            health_address: "127.0.0.1:6117".to_string(),
             snapshot_path: None,
             snapshot: false,
+            encrypt_keystore: false,
+            signer_endpoint: None,
+            devnet: false,
+            block_time: EPOCH_DURATION_SECONDS as u64,
+            miner_address: None,
+            sector_storage: None,
+            logging: LoggerConfig::default(),
         }
     }
 }
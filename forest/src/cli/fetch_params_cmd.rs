@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use fil_types::SectorSize;
-use paramfetch::{get_params_default, SectorSizeOpt};
+use paramfetch::{get_params_from, ParamSource, SectorSizeOpt, DEFAULT_PARAMETERS};
 use structopt::StructOpt;
 
 #[allow(missing_docs)]
@@ -16,6 +16,12 @@ pub struct FetchCommands {
     params_size: Option<String>,
     #[structopt(short, long, help = "Show verbose logging")]
     verbose: bool,
+    #[structopt(
+        short,
+        long,
+        help = "Gateway URL, or file:// URL or directory of a local mirror to fetch from"
+    )]
+    source: Option<String>,
 }
 
 impl FetchCommands {
@@ -31,7 +37,14 @@ impl FetchCommands {
             panic!("Sector size option must be chosen. Choose between --all, --keys, or <size>");
         };
 
-        get_params_default(sizes, self.verbose).await.unwrap();
+        let source = match &self.source {
+            Some(source) => ParamSource::parse(source),
+            None => ParamSource::from_env(),
+        };
+
+        get_params_from(DEFAULT_PARAMETERS, sizes, self.verbose, source)
+            .await
+            .unwrap();
     }
 }
 
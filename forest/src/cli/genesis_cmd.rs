@@ -2,8 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use address::Address;
+use db::MemoryDB;
 use fil_types::genesis::{Actor, ActorType, Miner, Template as GenesisTemplate};
-use fil_types::FILECOIN_PRECISION;
+use fil_types::verifier::FullVerifier;
+use fil_types::{DevnetParams, MainnetParams, NetworkPolicy, TestnetParams, FILECOIN_PRECISION};
+use genesis::{build_genesis, MINER_START};
 use log::{info, warn};
 use num_bigint::BigInt;
 use std::collections::HashMap;
@@ -13,7 +16,6 @@ use std::str::FromStr;
 use structopt::StructOpt;
 use uuid::Uuid;
 
-const ACCOUNT_START: u64 = 1000;
 #[derive(Debug, StructOpt)]
 pub enum GenesisCommands {
     /// Creates new genesis template
@@ -38,6 +40,22 @@ pub enum GenesisCommands {
         #[structopt(short, help = "Preseal filepath")]
         preseal_path: String,
     },
+    /// Builds the genesis block and state from a template.
+    #[structopt(
+        about = "Build genesis CAR file. Ex.: 'genesis build [genesis.json] [genesis.car]'"
+    )]
+    Build {
+        #[structopt(help = "Genesis template filepath")]
+        template: String,
+        #[structopt(help = "Output CAR filepath")]
+        out: String,
+        #[structopt(
+            long,
+            default_value = "devnet",
+            help = "Network parameters to build with: mainnet, testnet or devnet"
+        )]
+        chain: NetworkPolicy,
+    },
 }
 
 impl GenesisCommands {
@@ -71,6 +89,15 @@ impl GenesisCommands {
                     warn!("Cannot add miner(s), error: {}", err)
                 };
             }
+            Self::Build {
+                template,
+                out,
+                chain,
+            } => {
+                if let Err(err) = build(template, out, *chain) {
+                    warn!("Cannot build genesis, error: {}", err)
+                };
+            }
         }
     }
 }
@@ -87,7 +114,7 @@ fn add_miner(genesis_path: String, preseal_path: String) -> Result<(), Box<dyn s
     for (miner_address_str, miner) in miners.into_iter() {
         info!("Adding miner {} to genesis template", miner_address_str);
 
-        let id = ACCOUNT_START + template.miners.len() as u64;
+        let id = MINER_START + template.miners.len() as u64;
 
         let maddress = match Address::from_str(&miner_address_str) {
             Ok(addr) => addr,
@@ -112,6 +139,7 @@ fn add_miner(genesis_path: String, preseal_path: String) -> Result<(), Box<dyn s
             actor_type: ActorType::Account,
             balance: BigInt::from(50_000_000) * FILECOIN_PRECISION,
             owner: miner_owner,
+            multisig: None,
         })
     }
 
@@ -122,3 +150,26 @@ fn add_miner(genesis_path: String, preseal_path: String) -> Result<(), Box<dyn s
 
     Ok(())
 }
+
+fn build(
+    template_path: &str,
+    out: &str,
+    chain: NetworkPolicy,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let template: GenesisTemplate = serde_json::from_reader(File::open(template_path)?)?;
+
+    let store = MemoryDB::default();
+    let header = match chain {
+        NetworkPolicy::Mainnet => {
+            build_genesis::<FullVerifier, MainnetParams, _>(&store, &template)?
+        }
+        NetworkPolicy::Testnet => {
+            build_genesis::<FullVerifier, TestnetParams, _>(&store, &template)?
+        }
+        NetworkPolicy::Devnet => build_genesis::<FullVerifier, DevnetParams, _>(&store, &template)?,
+    };
+
+    forest_car::write_car(&store, vec![*header.cid()], File::create(out)?)?;
+    info!("Wrote genesis {} to {}", header.cid(), out);
+    Ok(())
+}
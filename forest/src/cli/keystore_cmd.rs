@@ -0,0 +1,35 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::{cli_error_and_die, read_keystore_passphrase};
+use structopt::StructOpt;
+use utils::get_home_dir;
+use wallet::PersistentKeyStore;
+
+#[derive(Debug, StructOpt)]
+pub enum KeystoreCommands {
+    /// Encrypts the plaintext keystore.json with a passphrase and removes the plaintext file
+    #[structopt(about = "Migrate the plaintext keystore to an encrypted keystore")]
+    Encrypt {
+        #[structopt(long, help = "Data directory of the node (default = ~/.forest)")]
+        data_dir: Option<String>,
+    },
+}
+
+impl KeystoreCommands {
+    pub async fn run(&self) {
+        match self {
+            Self::Encrypt { data_dir } => {
+                let data_dir = data_dir
+                    .clone()
+                    .unwrap_or_else(|| get_home_dir() + "/.forest");
+                let passphrase = read_keystore_passphrase();
+                let ks = PersistentKeyStore::migrate_to_encrypted(data_dir, &passphrase)
+                    .unwrap_or_else(|e| {
+                        cli_error_and_die(&format!("Failed to encrypt keystore: {}", e))
+                    });
+                println!("Encrypted keystore with {} keys", ks.key_info.len());
+            }
+        }
+    }
+}
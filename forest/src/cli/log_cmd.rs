@@ -0,0 +1,65 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::stringify_rpc_err;
+use rpc_client::{log_list, log_set_level, new_client, new_client_with_token};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub enum LogCommands {
+    /// Lists the level logged for each module of the node
+    #[structopt(about = "List the log level of each module")]
+    List,
+
+    /// Sets the level logged for a module and its submodules
+    #[structopt(about = "<Level> Set the log level of a module, or of all modules")]
+    SetLevel {
+        #[structopt(help = "Level to log: off, error, warn, info, debug or trace")]
+        level: String,
+        #[structopt(
+            long,
+            default_value = "*",
+            help = "Module to set the level of, e.g. chain_sync (all modules if not set)"
+        )]
+        module: String,
+        #[structopt(
+            long,
+            env = "FOREST_API_TOKEN",
+            hide_env_values = true,
+            help = "API token with write permission, created with `forest auth create-token -p write`"
+        )]
+        token: Option<String>,
+    },
+}
+
+impl LogCommands {
+    pub async fn run(&self) {
+        match self {
+            Self::List => {
+                let mut client = new_client();
+                let levels = log_list(&mut client)
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+                for entry in levels.as_array().into_iter().flatten() {
+                    println!(
+                        "{:<32} {}",
+                        entry["Module"].as_str().unwrap_or_default(),
+                        entry["Level"].as_str().unwrap_or_default()
+                    );
+                }
+            }
+            Self::SetLevel {
+                level,
+                module,
+                token,
+            } => {
+                let mut client = new_client_with_token(token.clone());
+                log_set_level(&mut client, module.to_owned(), level.to_owned())
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+            }
+        }
+    }
+}
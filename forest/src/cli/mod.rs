@@ -6,14 +6,28 @@ mod chain_cmd;
 mod config;
 mod fetch_params_cmd;
 mod genesis_cmd;
+mod keystore_cmd;
+mod log_cmd;
+mod mpool_cmd;
+mod msig_cmd;
+mod send_cmd;
+mod state_cmd;
 
 pub(super) use self::auth_cmd::AuthCommands;
 pub(super) use self::chain_cmd::ChainCommands;
 pub use self::config::Config;
 pub(super) use self::fetch_params_cmd::FetchCommands;
 pub(super) use self::genesis_cmd::GenesisCommands;
+pub(super) use self::keystore_cmd::KeystoreCommands;
+pub(super) use self::log_cmd::LogCommands;
+pub(super) use self::mpool_cmd::MpoolCommands;
+pub(super) use self::msig_cmd::MsigCommand;
+pub(super) use self::send_cmd::SendCommand;
+pub(super) use self::state_cmd::StateCommands;
 
+use fil_types::{NetworkPolicy, FILECOIN_PRECISION};
 use jsonrpc_v2::Error as JsonRpcError;
+use num_bigint::BigInt;
 use std::cell::RefCell;
 use std::io;
 use std::process;
@@ -55,6 +69,24 @@ pub enum Subcommand {
 
     #[structopt(name = "genesis", about = "Work with blockchain genesis")]
     Genesis(GenesisCommands),
+
+    #[structopt(name = "send", about = "Send funds between accounts")]
+    Send(SendCommand),
+
+    #[structopt(name = "mpool", about = "Interact with the message pool")]
+    Mpool(MpoolCommands),
+
+    #[structopt(name = "msig", about = "Manage multisig wallets")]
+    Msig(MsigCommand),
+
+    #[structopt(name = "keystore", about = "Manage the node keystore")]
+    Keystore(KeystoreCommands),
+
+    #[structopt(name = "state", about = "Inspect and profile chain state")]
+    State(StateCommands),
+
+    #[structopt(name = "log", about = "Manage the logging of the node")]
+    Log(LogCommands),
 }
 
 /// Daemon process command line options.
@@ -64,10 +96,25 @@ pub struct DaemonOpts {
     pub config: Option<String>,
     #[structopt(short, long, help = "The genesis CAR file")]
     pub genesis: Option<String>,
+    #[structopt(
+        long,
+        help = "Network parameters to validate with: mainnet, testnet or devnet (default = mainnet)"
+    )]
+    pub chain: Option<NetworkPolicy>,
     #[structopt(short, long, help = "Allow rpc to be active or not (default = true)")]
     pub rpc: Option<bool>,
     #[structopt(short, long, help = "The port used for communication")]
     pub port: Option<String>,
+    #[structopt(
+        long,
+        help = "Serve Prometheus metrics at /metrics on this address (e.g. 0.0.0.0:6116)"
+    )]
+    pub metrics_address: Option<String>,
+    #[structopt(
+        long,
+        help = "Serve health probes at /healthz/livez and /readyz on this address (e.g. 0.0.0.0:6117)"
+    )]
+    pub health_address: Option<String>,
     #[structopt(short, long, help = "Allow Kademlia (default = true)")]
     pub kademlia: Option<bool>,
     #[structopt(short, long, help = "Allow MDNS (default = true)")]
@@ -76,6 +123,28 @@ pub struct DaemonOpts {
     pub import_snapshot: Option<String>,
     #[structopt(long, help = "Import a chain from CAR file")]
     pub import_chain: Option<String>,
+    #[structopt(long, help = "Store keys in an encrypted keystore (default = false)")]
+    pub encrypt_keystore: Option<bool>,
+    #[structopt(
+        long,
+        help = "Sign through an external signer (unix:<path> or tcp:<host>:<port>)"
+    )]
+    pub signer: Option<String>,
+    #[structopt(
+        long,
+        help = "Run a local devnet with mock randomness and proofs, producing its own blocks"
+    )]
+    pub devnet: bool,
+    #[structopt(long, help = "Seconds between devnet blocks (default = 30)")]
+    pub block_time: Option<u64>,
+    #[structopt(long, help = "Miner actor to produce blocks for")]
+    pub miner: Option<String>,
+    #[structopt(long, help = "Directory of the sealed sectors of the miner")]
+    pub sector_storage: Option<String>,
+    #[structopt(long, help = "Write logs as JSON objects, one per line")]
+    pub log_json: bool,
+    #[structopt(long, help = "File to also write logs to, rotated as it grows")]
+    pub log_file: Option<String>,
 }
 
 impl DaemonOpts {
@@ -89,6 +158,9 @@ impl DaemonOpts {
             }
             None => Config::default(),
         };
+        if let Some(chain) = self.chain {
+            cfg.chain = chain;
+        }
         if let Some(genesis_file) = &self.genesis {
             cfg.genesis_file = Some(genesis_file.to_owned());
         }
@@ -111,15 +183,50 @@ impl DaemonOpts {
             }
         }
 
+        if let Some(address) = &self.metrics_address {
+            cfg.enable_metrics = true;
+            cfg.metrics_address = address.to_owned();
+        }
+        if let Some(address) = &self.health_address {
+            cfg.enable_health = true;
+            cfg.health_address = address.to_owned();
+        }
+
+        cfg.encrypt_keystore = self.encrypt_keystore.unwrap_or(cfg.encrypt_keystore);
+        if let Some(signer) = &self.signer {
+            cfg.signer_endpoint = Some(signer.to_owned());
+        }
         cfg.network.kademlia = self.kademlia.unwrap_or(cfg.network.kademlia);
         cfg.network.mdns = self.mdns.unwrap_or(cfg.network.mdns);
         // (where to find these flags, should be easy to do with structops)
 
+        cfg.devnet = self.devnet || cfg.devnet;
+        cfg.block_time = self.block_time.unwrap_or(cfg.block_time);
+        if let Some(miner) = &self.miner {
+            cfg.miner_address = Some(miner.to_owned());
+        }
+        if let Some(storage) = &self.sector_storage {
+            cfg.sector_storage = Some(storage.to_owned());
+        }
+        cfg.logging.json = self.log_json || cfg.logging.json;
+        if let Some(file) = &self.log_file {
+            cfg.logging.file = Some(file.to_owned());
+        }
+        if cfg.devnet {
+            // A devnet is self-contained, so it neither validates, discovers nor dials other
+            // networks
+            cfg.chain = NetworkPolicy::Devnet;
+            cfg.network.kademlia = false;
+            cfg.network.mdns = false;
+            cfg.network.bootstrap_peers.clear();
+        }
+
         Ok(cfg)
     }
 }
 
-/// Blocks current thread until ctrl-c is received
+/// Blocks current thread until ctrl-c is received. Further interrupts do not interrupt the
+/// shutdown, unless ctrl-c is hit twice more to force the process to exit.
 pub(super) async fn block_until_sigint() {
     let (ctrlc_send, ctrlc_oneshot) = futures::channel::oneshot::channel();
     let ctrlc_send_c = RefCell::new(Some(ctrlc_send));
@@ -127,14 +234,19 @@ pub(super) async fn block_until_sigint() {
     let running = Arc::new(AtomicUsize::new(0));
     ctrlc::set_handler(move || {
         let prev = running.fetch_add(1, Ordering::SeqCst);
-        if prev == 0 {
-            println!("Got interrupt, shutting down...");
-            // Send sig int in channel to blocking task
-            if let Some(ctrlc_send) = ctrlc_send_c.try_borrow_mut().unwrap().take() {
-                ctrlc_send.send(()).expect("Error sending ctrl-c message");
+        match prev {
+            0 => {
+                println!("Got interrupt, shutting down...");
+                // Send sig int in channel to blocking task
+                if let Some(ctrlc_send) = ctrlc_send_c.try_borrow_mut().unwrap().take() {
+                    ctrlc_send.send(()).expect("Error sending ctrl-c message");
+                }
+            }
+            1 => println!("Shutdown in progress, hit ctrl-c again to force exit"),
+            _ => {
+                println!("Forcing exit, the node data may not have been flushed");
+                process::exit(1);
             }
-        } else {
-            process::exit(0);
         }
     })
     .expect("Error setting Ctrl-C handler");
@@ -142,6 +254,29 @@ pub(super) async fn block_until_sigint() {
     ctrlc_oneshot.await.unwrap();
 }
 
+/// Environment variable the keystore passphrase is read from, before falling back to a prompt
+const KEYSTORE_PHRASE_ENV: &str = "FOREST_KEYSTORE_PHRASE";
+
+/// Returns the passphrase for the encrypted keystore, read from the environment or prompted for
+/// without echoing it. Exits if the passphrase can't be read or is empty.
+pub(super) fn read_keystore_passphrase() -> String {
+    let phrase = match std::env::var(KEYSTORE_PHRASE_ENV) {
+        Ok(phrase) => phrase,
+        Err(_) => rpassword::read_password_from_tty(Some("Enter the keystore passphrase: "))
+            .unwrap_or_else(|e| cli_error_and_die(&format!("Failed to read passphrase: {}", e))),
+    };
+    if phrase.is_empty() {
+        cli_error_and_die("Keystore passphrase must not be empty");
+    }
+    phrase
+}
+
+/// Prints the error and exits the process with an error code
+pub(super) fn cli_error_and_die(msg: &str) -> ! {
+    eprintln!("Error: {}", msg);
+    process::exit(1);
+}
+
 /// Returns a stringified JSON-RPC error
 pub(super) fn stringify_rpc_err(e: JsonRpcError) -> String {
     match e {
@@ -157,3 +292,59 @@ pub(super) fn stringify_rpc_err(e: JsonRpcError) -> String {
         }
     }
 }
+
+/// Parses a decimal FIL amount, e.g. `1.5`, into attoFIL
+pub(super) fn parse_fil(amount: &str) -> Result<BigInt, String> {
+    let amount = amount.trim();
+    let amount = amount
+        .strip_suffix("FIL")
+        .or_else(|| amount.strip_suffix("fil"))
+        .unwrap_or(amount)
+        .trim();
+    let decimals = FILECOIN_PRECISION.to_string().len() - 1;
+
+    let (int_part, frac_part) = match amount.find('.') {
+        Some(idx) => (&amount[..idx], &amount[idx + 1..]),
+        None => (amount, ""),
+    };
+    if int_part.is_empty() && frac_part.is_empty() {
+        return Err(format!("Invalid FIL amount: {}", amount));
+    }
+    if frac_part.len() > decimals {
+        return Err(format!(
+            "FIL amount {} has more than {} decimal places",
+            amount, decimals
+        ));
+    }
+    if !int_part
+        .chars()
+        .chain(frac_part.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(format!("Invalid FIL amount: {}", amount));
+    }
+
+    let atto = format!("{}{:0<width$}", int_part, frac_part, width = decimals);
+    atto.parse()
+        .map_err(|e| format!("Invalid FIL amount {}: {}", amount, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fil_amount_parsing() {
+        assert_eq!(parse_fil("1").unwrap(), BigInt::from(FILECOIN_PRECISION));
+        assert_eq!(
+            parse_fil("1.5 FIL").unwrap(),
+            BigInt::from(FILECOIN_PRECISION) * 3 / 2
+        );
+        assert_eq!(parse_fil(".000000000000000001").unwrap(), BigInt::from(1));
+        assert_eq!(parse_fil("0").unwrap(), BigInt::from(0));
+        assert!(parse_fil("1.0000000000000000001").is_err());
+        assert!(parse_fil("-1").is_err());
+        assert!(parse_fil("abc").is_err());
+        assert!(parse_fil(".").is_err());
+    }
+}
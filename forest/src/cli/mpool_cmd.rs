@@ -0,0 +1,204 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::send_cmd::estimate_gas;
+use super::stringify_rpc_err;
+use address::Address;
+use encoding::Cbor;
+use message::{signed_message::json::SignedMessageJsonRef, Message, UnsignedMessage};
+use message_pool::min_replace_premium;
+use num_bigint::BigInt;
+use rpc_client::{head, mpool_get_nonce, mpool_pending, mpool_push_message, new_client};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub enum MpoolCommands {
+    /// Prints the pending messages in the message pool
+    #[structopt(about = "Print pending messages")]
+    Pending {
+        #[structopt(long, help = "Only print messages sent from this address")]
+        from: Option<String>,
+        #[structopt(long, help = "Only print the CIDs of the messages")]
+        cids: bool,
+    },
+
+    /// Prints the next sequence to use for the given address
+    #[structopt(about = "<Address> Print the next sequence of an address")]
+    Nonce {
+        #[structopt(help = "Address to get the sequence for")]
+        address: String,
+    },
+
+    /// Replaces a pending message with a new one paying a higher gas premium
+    #[structopt(about = "<Address> <Nonce> Replace a pending message with a higher fee")]
+    Replace {
+        #[structopt(help = "Sender of the pending message")]
+        from: String,
+        #[structopt(help = "Sequence of the pending message")]
+        nonce: u64,
+        #[structopt(
+            long,
+            help = "Gas premium in attoFIL (defaults to the minimum accepted replacement premium)"
+        )]
+        gas_premium: Option<String>,
+        #[structopt(long, help = "Gas fee cap in attoFIL (re-estimated if not set)")]
+        gas_feecap: Option<String>,
+        #[structopt(long, help = "Gas limit (defaults to the pending message gas limit)")]
+        gas_limit: Option<i64>,
+    },
+}
+
+impl MpoolCommands {
+    pub async fn run(&self) {
+        match self {
+            Self::Pending { from, cids } => {
+                let from: Option<Address> = from.as_ref().map(|a| a.parse().unwrap());
+                let mut client = new_client();
+
+                let canonical = head(&mut client).await.map_err(stringify_rpc_err).unwrap();
+                let pending = mpool_pending(&mut client, canonical.0.cids().to_vec())
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+
+                for msg in pending
+                    .iter()
+                    .filter(|m| from.as_ref().map(|a| m.from() == a).unwrap_or(true))
+                {
+                    if *cids {
+                        println!("{}", msg.cid().unwrap());
+                    } else {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&SignedMessageJsonRef(msg)).unwrap()
+                        );
+                    }
+                }
+            }
+            Self::Nonce { address } => {
+                let address: Address = address.parse().unwrap();
+                let mut client = new_client();
+
+                let nonce = mpool_get_nonce(&mut client, address.to_string())
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+                println!("{}", nonce);
+            }
+            Self::Replace {
+                from,
+                nonce,
+                gas_premium,
+                gas_feecap,
+                gas_limit,
+            } => {
+                let from: Address = from.parse().unwrap();
+                let mut client = new_client();
+
+                let canonical = head(&mut client).await.map_err(stringify_rpc_err).unwrap();
+                let pending = mpool_pending(&mut client, canonical.0.cids().to_vec())
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+                let found = pending
+                    .into_iter()
+                    .find(|m| m.from() == &from && m.sequence() == *nonce)
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "No pending message found from {} with nonce {}",
+                            from, nonce
+                        )
+                    });
+                let mut msg = found.into_message();
+
+                set_replacement_fees(
+                    &mut msg,
+                    gas_premium.as_ref().map(|p| p.parse().unwrap()),
+                    gas_feecap.as_ref().map(|f| f.parse().unwrap()),
+                )
+                .unwrap_or_else(|e| panic!("{}", e));
+                let gas_limit = Some(gas_limit.unwrap_or_else(|| msg.gas_limit()));
+
+                estimate_gas(&mut client, &mut msg, gas_limit, None, None).await;
+
+                let smsg = mpool_push_message(&mut client, msg)
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+                println!("{}", smsg.cid().unwrap());
+            }
+        }
+    }
+}
+
+/// Sets the gas premium of a replacement message, which defaults to the minimum premium
+/// accepted to replace the pending message. The previous fee cap is kept if it still covers
+/// the new premium, otherwise it is cleared to be estimated again.
+fn set_replacement_fees(
+    msg: &mut UnsignedMessage,
+    gas_premium: Option<BigInt>,
+    gas_feecap: Option<BigInt>,
+) -> Result<(), String> {
+    let min_premium = min_replace_premium(msg.gas_premium());
+    let gas_premium = match gas_premium {
+        Some(premium) if premium <= min_premium => {
+            return Err(format!(
+                "Gas premium {} is too low to replace the message, must be greater than {}",
+                premium, min_premium
+            ))
+        }
+        Some(premium) => premium,
+        None => min_premium + 1u8,
+    };
+    let gas_feecap = match gas_feecap {
+        Some(feecap) => feecap,
+        None if msg.gas_fee_cap() >= &gas_premium => msg.gas_fee_cap().clone(),
+        None => BigInt::from(0),
+    };
+    msg.set_gas_premium(gas_premium);
+    msg.set_gas_fee_cap(gas_feecap);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(fee_cap: u64, premium: u64) -> UnsignedMessage {
+        UnsignedMessage::builder()
+            .to(Address::new_id(1))
+            .from(Address::new_id(2))
+            .gas_limit(1000)
+            .gas_fee_cap(fee_cap.into())
+            .gas_premium(premium.into())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn replacement_fee_cap() {
+        // The previous fee cap still covers the minimum replacement premium
+        let mut msg = pending(1000, 256);
+        set_replacement_fees(&mut msg, None, None).unwrap();
+        assert_eq!(msg.gas_premium(), &BigInt::from(322));
+        assert_eq!(msg.gas_fee_cap(), &BigInt::from(1000));
+
+        // A fee cap below the new premium is cleared so it is estimated again
+        let mut msg = pending(300, 256);
+        set_replacement_fees(&mut msg, None, None).unwrap();
+        assert_eq!(msg.gas_premium(), &BigInt::from(322));
+        assert_eq!(msg.gas_fee_cap(), &BigInt::from(0));
+
+        let mut msg = pending(300, 256);
+        set_replacement_fees(&mut msg, Some(400.into()), Some(500.into())).unwrap();
+        assert_eq!(msg.gas_premium(), &BigInt::from(400));
+        assert_eq!(msg.gas_fee_cap(), &BigInt::from(500));
+    }
+
+    #[test]
+    fn replacement_premium_too_low() {
+        let mut msg = pending(1000, 256);
+        assert!(set_replacement_fees(&mut msg, Some(321.into()), None).is_err());
+        assert_eq!(msg.gas_premium(), &BigInt::from(256));
+    }
+}
@@ -0,0 +1,521 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::send_cmd::encode_json_params;
+use super::{parse_fil, stringify_rpc_err};
+use address::Address;
+use blocks::TipsetKeys;
+use jsonrpsee::raw::RawClient;
+use rpc_client::{
+    head, msig_add_signer, msig_approve, msig_cancel, msig_change_threshold, msig_create,
+    msig_get_available_balance, msig_get_pending, msig_get_vested, msig_lock_balance, msig_propose,
+    msig_remove_signer, msig_swap_signer, new_client_with_token, tipset_by_height,
+    wallet_default_address, HttpTransport,
+};
+use structopt::StructOpt;
+
+/// Multisig commands, which send messages from the node wallet and so need a token with write
+/// permission
+#[derive(Debug, StructOpt)]
+pub struct MsigCommand {
+    #[structopt(
+        long,
+        env = "FOREST_API_TOKEN",
+        hide_env_values = true,
+        help = "API token with write permission, created with `forest auth create-token -p write`"
+    )]
+    token: Option<String>,
+    #[structopt(subcommand)]
+    cmd: MsigCommands,
+}
+
+impl MsigCommand {
+    pub async fn run(&self) {
+        let mut client = new_client_with_token(self.token.clone());
+        self.cmd.run(&mut client).await;
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum MsigCommands {
+    /// Creates a new multisig wallet
+    #[structopt(about = "<Signers>... Create a new multisig wallet")]
+    Create {
+        #[structopt(required = true, help = "Addresses of the signers")]
+        signers: Vec<String>,
+        #[structopt(
+            long,
+            help = "Number of approvals required (defaults to the number of signers)"
+        )]
+        required: Option<u64>,
+        #[structopt(long, default_value = "0", help = "Initial funds to lock in FIL")]
+        value: String,
+        #[structopt(
+            long,
+            default_value = "0",
+            help = "Number of epochs over which the initial funds vest"
+        )]
+        duration: i64,
+        #[structopt(long, help = "Account to send the create message from")]
+        from: Option<String>,
+    },
+
+    /// Proposes a transaction on a multisig wallet
+    #[structopt(about = "<Multisig> <Target> <Amount> Propose a multisig transaction")]
+    Propose {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "Recipient of the transaction")]
+        target: String,
+        #[structopt(help = "Amount of FIL to send")]
+        amount: String,
+        #[structopt(
+            long,
+            default_value = "0",
+            help = "Method number to invoke on the recipient"
+        )]
+        method: u64,
+        #[structopt(
+            long,
+            help = "Method parameters as JSON, encoded to CBOR before sending"
+        )]
+        params_json: Option<String>,
+        #[structopt(long, help = "Signer to propose from")]
+        from: Option<String>,
+    },
+
+    /// Approves a pending multisig transaction
+    #[structopt(about = "<Multisig> <TxnID> Approve a pending multisig transaction")]
+    Approve {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "ID of the pending transaction")]
+        txn_id: i64,
+        #[structopt(long, help = "Signer to approve from")]
+        from: Option<String>,
+    },
+
+    /// Cancels a pending multisig transaction
+    #[structopt(about = "<Multisig> <TxnID> Cancel a pending multisig transaction")]
+    Cancel {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "ID of the pending transaction")]
+        txn_id: i64,
+        #[structopt(long, help = "Proposer of the transaction")]
+        from: Option<String>,
+    },
+
+    /// Prints the balances and pending transactions of a multisig wallet
+    #[structopt(about = "<Multisig> Inspect a multisig wallet")]
+    Inspect {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+    },
+
+    /// Prints the amount vested between two epochs
+    #[structopt(about = "<Multisig> Print the amount vested between two epochs")]
+    Vested {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(long, default_value = "0", help = "Start epoch")]
+        start_epoch: i64,
+        #[structopt(long, help = "End epoch (defaults to the chain head)")]
+        end_epoch: Option<i64>,
+    },
+
+    /// Proposes adding a signer to a multisig wallet
+    #[structopt(about = "<Multisig> <Signer> Propose adding a signer")]
+    AddSigner {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "Signer to add")]
+        signer: String,
+        #[structopt(long, help = "Increase the approval threshold by one")]
+        increase_threshold: bool,
+        #[structopt(long, help = "Signer to propose from")]
+        from: Option<String>,
+    },
+
+    /// Proposes removing a signer from a multisig wallet
+    #[structopt(about = "<Multisig> <Signer> Propose removing a signer")]
+    RemoveSigner {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "Signer to remove")]
+        signer: String,
+        #[structopt(long, help = "Decrease the approval threshold by one")]
+        decrease_threshold: bool,
+        #[structopt(long, help = "Signer to propose from")]
+        from: Option<String>,
+    },
+
+    /// Proposes replacing a signer of a multisig wallet
+    #[structopt(about = "<Multisig> <Old> <New> Propose swapping a signer")]
+    SwapSigner {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "Signer to replace")]
+        old_signer: String,
+        #[structopt(help = "Replacement signer")]
+        new_signer: String,
+        #[structopt(long, help = "Signer to propose from")]
+        from: Option<String>,
+    },
+
+    /// Proposes changing the number of approvals required
+    #[structopt(about = "<Multisig> <Threshold> Propose changing the approval threshold")]
+    SetThreshold {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "New number of approvals required")]
+        threshold: u64,
+        #[structopt(long, help = "Signer to propose from")]
+        from: Option<String>,
+    },
+
+    /// Proposes locking part of the balance with a linear vesting schedule
+    #[structopt(about = "<Multisig> <StartEpoch> <Duration> <Amount> Propose locking funds")]
+    LockBalance {
+        #[structopt(help = "Address of the multisig")]
+        msig: String,
+        #[structopt(help = "Epoch the vesting starts at")]
+        start_epoch: i64,
+        #[structopt(help = "Number of epochs over which the funds vest")]
+        duration: i64,
+        #[structopt(help = "Amount of FIL to lock")]
+        amount: String,
+        #[structopt(long, help = "Signer to propose from")]
+        from: Option<String>,
+    },
+}
+
+impl MsigCommands {
+    async fn run(&self, client: &mut RawClient<HttpTransport>) {
+        let cid = match self {
+            Self::Create {
+                signers,
+                required,
+                value,
+                duration,
+                from,
+            } => {
+                let from = sender(client, from).await;
+                let signers: Vec<String> = signers
+                    .iter()
+                    .map(|s| s.parse::<Address>().unwrap().to_string())
+                    .collect();
+                let required = required.unwrap_or(signers.len() as u64);
+                let value = parse_fil(value).unwrap();
+                msig_create(
+                    client,
+                    required,
+                    signers,
+                    *duration,
+                    value.to_string(),
+                    from,
+                )
+                .await
+            }
+            Self::Propose {
+                msig,
+                target,
+                amount,
+                method,
+                params_json,
+                from,
+            } => {
+                let from = sender(client, from).await;
+                let value = parse_fil(amount).unwrap();
+                let params = match params_json {
+                    Some(json) => base64::encode(encode_json_params(json).unwrap().bytes()),
+                    None => String::new(),
+                };
+                msig_propose(
+                    client,
+                    parse_addr(msig),
+                    parse_addr(target),
+                    value.to_string(),
+                    from,
+                    *method,
+                    params,
+                )
+                .await
+            }
+            Self::Approve { msig, txn_id, from } => {
+                let from = sender(client, from).await;
+                msig_approve(client, parse_addr(msig), *txn_id, from).await
+            }
+            Self::Cancel { msig, txn_id, from } => {
+                let from = sender(client, from).await;
+                msig_cancel(client, parse_addr(msig), *txn_id, from).await
+            }
+            Self::Inspect { msig } => {
+                inspect(client, parse_addr(msig)).await;
+                return;
+            }
+            Self::Vested {
+                msig,
+                start_epoch,
+                end_epoch,
+            } => {
+                let canonical = head(client).await.map_err(stringify_rpc_err).unwrap();
+                let head_key = canonical.0.key().clone();
+                let start = tipset_by_height(client, *start_epoch, head_key.clone())
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+                let end = match end_epoch {
+                    Some(epoch) => tipset_by_height(client, *epoch, head_key)
+                        .await
+                        .map_err(stringify_rpc_err)
+                        .unwrap(),
+                    None => canonical,
+                };
+                let vested = msig_get_vested(
+                    client,
+                    parse_addr(msig),
+                    start.0.key().clone(),
+                    end.0.key().clone(),
+                )
+                .await
+                .map_err(stringify_rpc_err)
+                .unwrap();
+                println!("Vested: {} attoFIL", vested);
+                return;
+            }
+            Self::AddSigner {
+                msig,
+                signer,
+                increase_threshold,
+                from,
+            } => {
+                let from = sender(client, from).await;
+                msig_add_signer(
+                    client,
+                    parse_addr(msig),
+                    from,
+                    parse_addr(signer),
+                    *increase_threshold,
+                )
+                .await
+            }
+            Self::RemoveSigner {
+                msig,
+                signer,
+                decrease_threshold,
+                from,
+            } => {
+                let from = sender(client, from).await;
+                msig_remove_signer(
+                    client,
+                    parse_addr(msig),
+                    from,
+                    parse_addr(signer),
+                    *decrease_threshold,
+                )
+                .await
+            }
+            Self::SwapSigner {
+                msig,
+                old_signer,
+                new_signer,
+                from,
+            } => {
+                let from = sender(client, from).await;
+                msig_swap_signer(
+                    client,
+                    parse_addr(msig),
+                    from,
+                    parse_addr(old_signer),
+                    parse_addr(new_signer),
+                )
+                .await
+            }
+            Self::SetThreshold {
+                msig,
+                threshold,
+                from,
+            } => {
+                let from = sender(client, from).await;
+                msig_change_threshold(client, parse_addr(msig), from, *threshold).await
+            }
+            Self::LockBalance {
+                msig,
+                start_epoch,
+                duration,
+                amount,
+                from,
+            } => {
+                let from = sender(client, from).await;
+                let amount = parse_fil(amount).unwrap();
+                msig_lock_balance(
+                    client,
+                    parse_addr(msig),
+                    from,
+                    *start_epoch,
+                    *duration,
+                    amount.to_string(),
+                )
+                .await
+            }
+        };
+        println!("{}", cid.map_err(stringify_rpc_err).unwrap());
+    }
+}
+
+/// Prints the spendable balance and the pending transactions of a multisig at the chain head.
+async fn inspect(client: &mut RawClient<HttpTransport>, msig: String) {
+    let canonical = head(client).await.map_err(stringify_rpc_err).unwrap();
+    let tsk: TipsetKeys = canonical.0.key().clone();
+
+    let available = msig_get_available_balance(client, msig.clone(), tsk.clone())
+        .await
+        .map_err(stringify_rpc_err)
+        .unwrap();
+    println!("Available balance: {} attoFIL", available);
+
+    let pending = msig_get_pending(client, msig, tsk)
+        .await
+        .map_err(stringify_rpc_err)
+        .unwrap();
+    println!(
+        "Pending transactions:\n{}",
+        serde_json::to_string_pretty(&pending).unwrap()
+    );
+}
+
+/// Validates the address and returns it in its string form.
+fn parse_addr(addr: &str) -> String {
+    addr.parse::<Address>().unwrap().to_string()
+}
+
+/// Returns the given sender, or the wallet default address if none is set.
+async fn sender(client: &mut RawClient<HttpTransport>, from: &Option<String>) -> String {
+    match from {
+        Some(from) => parse_addr(from),
+        None => wallet_default_address(client)
+            .await
+            .map_err(stringify_rpc_err)
+            .unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::io::BufReader;
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::prelude::*;
+    use async_std::task;
+    use auth::{create_token, has_perms, Error as AuthError, READ, WRITE};
+    use rpc_client::new_client_at;
+    use serde_json::{json, Value};
+
+    const JWT_KEY: &[u8] = b"msig-cmd-test-key";
+    const MSG_CID: &str = "bafy2bzacea3wsdh6y3a36tb3skempjoxqpuyompjbmfeyf34fi3uy6uue42v4";
+
+    /// Answers JSON-RPC calls over HTTP with a message CID, rejecting calls without a token
+    /// with write permission as the node does.
+    async fn serve_connection(stream: TcpStream) {
+        let mut reader = BufReader::new(stream.clone());
+        let mut writer = stream;
+        loop {
+            let mut authorization = None;
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                    return;
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(i) = line.find(':') {
+                    let value = line[i + 1..].trim().to_owned();
+                    match line[..i].to_ascii_lowercase().as_str() {
+                        "authorization" => authorization = Some(value),
+                        "content-length" => content_length = value.parse().unwrap(),
+                        _ => {}
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).await.unwrap();
+            let request: Value = serde_json::from_slice(&body).unwrap();
+
+            let response = match authorization {
+                Some(header) if has_perms(header, "write", JWT_KEY).is_ok() => {
+                    json!({ "jsonrpc": "2.0", "result": { "/": MSG_CID }, "id": request["id"] })
+                }
+                Some(_) => json!({
+                    "jsonrpc": "2.0",
+                    "error": { "code": 1, "message": AuthError::InvalidPermissions.to_string() },
+                    "id": request["id"],
+                }),
+                None => json!({
+                    "jsonrpc": "2.0",
+                    "error": { "code": 200, "message": AuthError::NoAuthHeader.to_string() },
+                    "id": request["id"],
+                }),
+            };
+            let response = response.to_string();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+                response.len()
+            );
+            writer.write_all(head.as_bytes()).await.unwrap();
+            writer.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    async fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/rpc/v0", listener.local_addr().unwrap());
+        task::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                task::spawn(serve_connection(stream));
+            }
+        });
+        url
+    }
+
+    fn token(perms: &[&str]) -> String {
+        create_token(perms.iter().map(|p| p.to_string()).collect(), JWT_KEY).unwrap()
+    }
+
+    #[async_std::test]
+    async fn approve_sends_token() {
+        let url = start_server().await;
+        let token = token(&WRITE);
+        let cmd = MsigCommand::from_iter_safe(&[
+            "msig", "--token", &token, "approve", "t01001", "0", "--from", "t01002",
+        ])
+        .unwrap();
+        assert_eq!(cmd.token.as_deref(), Some(token.as_str()));
+
+        // Panics if the server rejects the call
+        let mut client = new_client_at(&url, cmd.token.clone());
+        cmd.cmd.run(&mut client).await;
+
+        let cid = msig_approve(&mut client, "t01001".to_owned(), 0, "t01002".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(cid.to_string(), MSG_CID);
+    }
+
+    #[async_std::test]
+    async fn approve_without_write_token_is_rejected() {
+        let url = start_server().await;
+
+        for token in vec![None, Some(token(&READ))] {
+            let mut client = new_client_at(&url, token);
+            assert!(
+                msig_approve(&mut client, "t01001".to_owned(), 0, "t01002".to_owned())
+                    .await
+                    .is_err()
+            );
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::{parse_fil, stringify_rpc_err};
+use address::Address;
+use encoding::Cbor;
+use jsonrpsee::raw::RawClient;
+use message::{Message, UnsignedMessage};
+use num_bigint::BigInt;
+use rpc_client::{
+    gas_estimate_message_gas, mpool_get_nonce, mpool_push_message, new_client,
+    wallet_default_address, HttpTransport,
+};
+use structopt::StructOpt;
+use vm::Serialized;
+
+#[derive(Debug, StructOpt)]
+pub struct SendCommand {
+    #[structopt(help = "Address of the recipient")]
+    target: String,
+    #[structopt(help = "Amount of FIL to send, e.g. 1.5")]
+    amount: String,
+    #[structopt(
+        long,
+        help = "Address to send from (defaults to the wallet default address)"
+    )]
+    from: Option<String>,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Method number to invoke on the recipient"
+    )]
+    method: u64,
+    #[structopt(
+        long,
+        help = "Method parameters as a JSON array of the parameter fields in order, encoded to CBOR before sending"
+    )]
+    params_json: Option<String>,
+    #[structopt(
+        long,
+        conflicts_with = "params-json",
+        help = "Method parameters as hex encoded CBOR, for parameters JSON can't express such as addresses, big integers and bytes"
+    )]
+    params_hex: Option<String>,
+    #[structopt(long, help = "Gas premium in attoFIL (estimated if not set)")]
+    gas_premium: Option<String>,
+    #[structopt(long, help = "Gas fee cap in attoFIL (estimated if not set)")]
+    gas_feecap: Option<String>,
+    #[structopt(long, help = "Gas limit (estimated if not set)")]
+    gas_limit: Option<i64>,
+    #[structopt(
+        long,
+        help = "Sequence to use (defaults to the next sequence in the mpool)"
+    )]
+    nonce: Option<u64>,
+}
+
+impl SendCommand {
+    pub async fn run(&self) {
+        let mut client = new_client();
+
+        let to: Address = self.target.parse().unwrap();
+        let from: Address = match &self.from {
+            Some(from) => from.parse().unwrap(),
+            None => wallet_default_address(&mut client)
+                .await
+                .map_err(stringify_rpc_err)
+                .unwrap()
+                .parse()
+                .unwrap(),
+        };
+        let value = parse_fil(&self.amount).unwrap();
+        let params = self.params().unwrap();
+        let sequence = match self.nonce {
+            Some(nonce) => nonce,
+            None => mpool_get_nonce(&mut client, from.to_string())
+                .await
+                .map_err(stringify_rpc_err)
+                .unwrap(),
+        };
+
+        let mut msg = UnsignedMessage::builder()
+            .to(to)
+            .from(from)
+            .sequence(sequence)
+            .value(value)
+            .method_num(self.method)
+            .params(params)
+            .build()
+            .unwrap();
+
+        let gas_premium = self.gas_premium.as_ref().map(|p| p.parse().unwrap());
+        let gas_feecap = self.gas_feecap.as_ref().map(|f| f.parse().unwrap());
+        estimate_gas(
+            &mut client,
+            &mut msg,
+            self.gas_limit,
+            gas_premium,
+            gas_feecap,
+        )
+        .await;
+
+        let smsg = mpool_push_message(&mut client, msg)
+            .await
+            .map_err(stringify_rpc_err)
+            .unwrap();
+        println!("{}", smsg.cid().unwrap());
+    }
+
+    /// Returns the encoded method parameters, given either as JSON or as hex encoded CBOR.
+    fn params(&self) -> Result<Serialized, String> {
+        match (&self.params_json, &self.params_hex) {
+            (Some(json), _) => encode_json_params(json),
+            (None, Some(hex)) => hex::decode(hex)
+                .map(Serialized::new)
+                .map_err(|e| format!("Invalid hex params: {}", e)),
+            (None, None) => Ok(Serialized::default()),
+        }
+    }
+}
+
+/// Fills in the gas limit, premium and fee cap of the message through the node, which estimates
+/// on top of the sender's pending messages. Values given by the user are kept as they are.
+pub(super) async fn estimate_gas(
+    client: &mut RawClient<HttpTransport>,
+    msg: &mut UnsignedMessage,
+    gas_limit: Option<i64>,
+    gas_premium: Option<BigInt>,
+    gas_feecap: Option<BigInt>,
+) {
+    if let Some(limit) = gas_limit {
+        msg.set_gas_limit(limit);
+    }
+    if let Some(premium) = gas_premium {
+        msg.set_gas_premium(premium);
+    }
+    if let Some(feecap) = gas_feecap {
+        msg.set_gas_fee_cap(feecap);
+    }
+    *msg = gas_estimate_message_gas(client, msg.clone())
+        .await
+        .map_err(stringify_rpc_err)
+        .unwrap();
+}
+
+/// Encodes JSON method parameters into the CBOR bytes expected by actors. Actor parameters are
+/// tuple encoded, so they are given as an array of their fields in order, nested structures
+/// being arrays as well. JSON objects would be encoded as maps, so they are rejected.
+///
+/// JSON strings are encoded as CBOR text, so addresses, big integers and bytes, which actors
+/// decode from CBOR bytes, have to be given as hex encoded CBOR instead.
+pub(super) fn encode_json_params(json: &str) -> Result<Serialized, String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    if !value.is_array() {
+        return Err("Params must be a JSON array of the parameter fields in order".to_owned());
+    }
+    if contains_object(&value) {
+        return Err(
+            "Params can't contain JSON objects, structures are given as arrays of their fields"
+                .to_owned(),
+        );
+    }
+    Serialized::serialize(value).map_err(|e| e.to_string())
+}
+
+fn contains_object(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(_) => true,
+        serde_json::Value::Array(values) => values.iter().any(contains_object),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actor::multisig::{self, AddSignerParams};
+
+    #[test]
+    fn params_are_tuple_encoded() {
+        let params = encode_json_params(r#"[1, "a", [true]]"#).unwrap();
+        let expected = Serialized::serialize((1, "a", (true,))).unwrap();
+        assert_eq!(params, expected);
+
+        assert!(encode_json_params(r#"{"a": 1}"#).is_err());
+        assert!(encode_json_params(r#"[1, {"a": 1}]"#).is_err());
+        assert!(encode_json_params("1").is_err());
+    }
+
+    #[test]
+    fn send_address_params_as_hex() {
+        let params = AddSignerParams {
+            signer: Address::new_id(1002),
+            increase: true,
+        };
+        let hex = hex::encode(Serialized::serialize(&params).unwrap().bytes());
+        let cmd = SendCommand::from_iter_safe(&[
+            "send",
+            "t01001",
+            "0",
+            "--method",
+            &(multisig::Method::AddSigner as u64).to_string(),
+            "--params-hex",
+            &hex,
+        ])
+        .unwrap();
+
+        let decoded: AddSignerParams = cmd.params().unwrap().deserialize().unwrap();
+        assert_eq!(decoded.signer, params.signer);
+        assert!(decoded.increase);
+
+        assert!(
+            SendCommand::from_iter_safe(&["send", "t01001", "0", "--params-hex", "zz"])
+                .unwrap()
+                .params()
+                .is_err()
+        );
+        assert!(SendCommand::from_iter_safe(&[
+            "send",
+            "t01001",
+            "0",
+            "--params-json",
+            "[]",
+            "--params-hex",
+            "80",
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::stringify_rpc_err;
+use chain::ChainStore;
+use cid::Cid;
+use db::RocksDb;
+use rpc_client::{head, new_client_with_token, state_gas_profile, tipset_by_height};
+use state_manager::GasProfile;
+use statediff::{diff_state_roots, ActorStateDiff, KeyChanges, StateDiff};
+use std::sync::Arc;
+use structopt::StructOpt;
+use utils::get_home_dir;
+
+#[derive(Debug, StructOpt)]
+pub enum StateCommands {
+    /// Re-executes a range of tipsets and reports where the gas was spent
+    #[structopt(about = "Report the gas charged by charge name and by actor method")]
+    GasProfile {
+        #[structopt(long, help = "First epoch to profile (defaults to the end epoch)")]
+        start: Option<i64>,
+        #[structopt(long, help = "Last epoch to profile (defaults to the chain head)")]
+        end: Option<i64>,
+        #[structopt(
+            long,
+            default_value = "20",
+            help = "Number of rows to print in each table"
+        )]
+        top: usize,
+        #[structopt(long, help = "Print the full profile as JSON")]
+        json: bool,
+        #[structopt(
+            long,
+            env = "FOREST_API_TOKEN",
+            hide_env_values = true,
+            help = "API token with write permission, created with `forest auth create-token -p write`"
+        )]
+        token: Option<String>,
+    },
+    /// Compares the state trees of two tipsets read from the local database, which must not be
+    /// in use by a running daemon
+    #[structopt(about = "Diff the actors of two states in the local database")]
+    Diff {
+        #[structopt(help = "Epoch whose parent state is diffed, or a state root CID")]
+        before: String,
+        #[structopt(help = "Epoch whose parent state is diffed, or a state root CID")]
+        after: String,
+        #[structopt(long, help = "Data directory of the node (default = ~/.forest)")]
+        data_dir: Option<String>,
+        #[structopt(long, help = "Print the diff as JSON")]
+        json: bool,
+    },
+}
+
+impl StateCommands {
+    pub async fn run(&self) {
+        match self {
+            Self::GasProfile {
+                start,
+                end,
+                top,
+                json,
+                token,
+            } => {
+                let mut client = new_client_with_token(token.clone());
+                let canonical = head(&mut client).await.map_err(stringify_rpc_err).unwrap();
+                let end_ts = match end {
+                    Some(epoch) => tipset_by_height(&mut client, *epoch, canonical.0.key().clone())
+                        .await
+                        .map_err(stringify_rpc_err)
+                        .unwrap(),
+                    None => canonical,
+                };
+                let start = start.unwrap_or_else(|| end_ts.0.epoch());
+                let profile = state_gas_profile(&mut client, start, end_ts.0.key().clone())
+                    .await
+                    .map_err(stringify_rpc_err)
+                    .unwrap();
+
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&profile).unwrap());
+                } else {
+                    let profile: GasProfile = serde_json::from_value(profile).unwrap();
+                    print_gas_profile(&profile, *top);
+                }
+            }
+            Self::Diff {
+                before,
+                after,
+                data_dir,
+                json,
+            } => {
+                let data_dir = data_dir
+                    .clone()
+                    .unwrap_or_else(|| get_home_dir() + "/.forest");
+                let mut db = RocksDb::new(data_dir + "/db");
+                db.open().unwrap();
+                let chain_store = ChainStore::new(Arc::new(db));
+
+                let before = state_root(&chain_store, before).await;
+                let after = state_root(&chain_store, after).await;
+                let diff = diff_state_roots(chain_store.blockstore(), &before, &after).unwrap();
+
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+                } else {
+                    print_state_diff(&diff);
+                }
+            }
+        }
+    }
+}
+
+/// Resolves a state root CID, or the parent state of the tipset at an epoch of the heaviest chain.
+async fn state_root(chain_store: &ChainStore<RocksDb>, arg: &str) -> Cid {
+    if let Ok(cid) = arg.parse() {
+        return cid;
+    }
+    let epoch: i64 = arg.parse().expect("Expected an epoch or a state root CID");
+    chain_store.load_heaviest_tipset().await.unwrap();
+    let heaviest = chain_store
+        .heaviest_tipset()
+        .await
+        .expect("No chain head in the database");
+    match chain_store
+        .tipset_by_height(epoch, &heaviest, true)
+        .unwrap()
+    {
+        Some(ts) => *ts.parent_state(),
+        None => *heaviest.parent_state(),
+    }
+}
+
+fn print_key_changes(name: &str, changes: &KeyChanges) {
+    if changes.is_empty() {
+        return;
+    }
+    println!(
+        "    {}: +{:?} -{:?} ~{:?}",
+        name, changes.added, changes.removed, changes.modified
+    );
+}
+
+fn print_state_diff(diff: &StateDiff) {
+    if diff.is_empty() {
+        println!("States are identical");
+        return;
+    }
+    for a in &diff.added {
+        println!(
+            "+ {} code {} balance {} nonce {}",
+            a.address, a.code, a.balance, a.sequence
+        );
+    }
+    for r in &diff.removed {
+        println!(
+            "- {} code {} balance {} nonce {}",
+            r.address, r.code, r.balance, r.sequence
+        );
+    }
+    for c in &diff.changed {
+        println!(
+            "~ {} balance {:+} nonce {:+}",
+            c.address, c.balance_delta, c.sequence_delta
+        );
+        if let Some(code) = &c.new_code {
+            println!("    code {} -> {}", c.code, code.0);
+        }
+        match &c.state {
+            Some(ActorStateDiff::Miner(m)) => {
+                if m.info_changed {
+                    println!("    info changed");
+                }
+                if let Some((b, a)) = m.proving_period_start {
+                    println!("    proving period start {} -> {}", b, a);
+                }
+                if let Some((b, a)) = m.current_deadline {
+                    println!("    current deadline {} -> {}", b, a);
+                }
+                println!(
+                    "    pre-commit deposits {:+}, locked funds {:+}, initial pledge {:+}",
+                    m.pre_commit_deposits_delta, m.locked_funds_delta, m.initial_pledge_delta
+                );
+                print_key_changes("pre-committed sectors", &m.pre_committed_sectors);
+                print_key_changes("sectors", &m.sectors);
+                if !m.deadlines.is_empty() {
+                    println!("    deadlines changed: {:?}", m.deadlines);
+                }
+            }
+            Some(ActorStateDiff::Fields { changes }) => {
+                for f in changes {
+                    println!(
+                        "    field {}: {} -> {}",
+                        f.index,
+                        f.before
+                            .as_ref()
+                            .map(|v| serde_json::to_string(v).unwrap())
+                            .unwrap_or_default(),
+                        f.after
+                            .as_ref()
+                            .map(|v| serde_json::to_string(v).unwrap())
+                            .unwrap_or_default()
+                    );
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn print_gas_profile(profile: &GasProfile, top: usize) {
+    println!(
+        "Epochs {} to {}: {} messages, {} gas",
+        profile.start_epoch, profile.end_epoch, profile.messages, profile.total_gas
+    );
+    let share = |gas: i64| {
+        if profile.total_gas == 0 {
+            0.0
+        } else {
+            gas as f64 * 100.0 / profile.total_gas as f64
+        }
+    };
+
+    println!(
+        "\n{:<28} {:>10} {:>16} {:>16} {:>16} {:>7}",
+        "CHARGE", "COUNT", "TOTAL", "COMPUTE", "STORAGE", "SHARE"
+    );
+    for c in profile.by_charge.iter().take(top) {
+        println!(
+            "{:<28} {:>10} {:>16} {:>16} {:>16} {:>6.2}%",
+            c.name,
+            c.stats.count,
+            c.stats.total_gas,
+            c.stats.compute_gas,
+            c.stats.storage_gas,
+            share(c.stats.total_gas)
+        );
+    }
+
+    println!(
+        "\n{:<20} {:>7} {:>10} {:>16} {:>16} {:>16} {:>7}",
+        "ACTOR", "METHOD", "CALLS", "TOTAL", "COMPUTE", "STORAGE", "SHARE"
+    );
+    for m in profile.by_method.iter().take(top) {
+        println!(
+            "{:<20} {:>7} {:>10} {:>16} {:>16} {:>16} {:>6.2}%",
+            m.actor,
+            m.method,
+            m.calls,
+            m.stats.total_gas,
+            m.stats.compute_gas,
+            m.stats.storage_gas,
+            share(m.stats.total_gas)
+        );
+    }
+}
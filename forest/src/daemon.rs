@@ -1,38 +1,59 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use super::cli::{block_until_sigint, Config};
+use super::cli::{block_until_sigint, cli_error_and_die, read_keystore_passphrase, Config};
+use super::devnet;
+use super::health::{start_health_server, HealthState};
 use actor::EPOCH_DURATION_SECONDS;
-use async_std::sync::RwLock;
-use async_std::task;
+use address::Address;
+use async_std::future;
+use async_std::sync::{RwLock, Sender};
+use async_std::task::{self, JoinHandle};
 use auth::{generate_priv_key, JWT_IDENTIFIER};
-use beacon::{DrandBeacon, DEFAULT_DRAND_URL};
+use beacon::{Beacon, DrandBeacon, MockBeacon, DEFAULT_DRAND_URL};
+use block_producer::{BlockProducer, LocalProver, MockProver};
 use chain::ChainStore;
-use chain_sync::ChainSyncer;
+use chain_sync::{BadBlockCache, ChainSyncer, PeerManager, SyncState, SyncStopHandle};
+use crypto::Signer;
 use db::RocksDb;
-use fil_types::verifier::FullVerifier;
+use fil_types::verifier::{FullVerifier, MockVerifier, ProofVerifier};
 use flo_stream::{MessagePublisher, Publisher};
-use forest_libp2p::{get_keypair, Libp2pService};
-use genesis::{import_chain, initialize_genesis};
+use forest_libp2p::{get_keypair, Libp2pService, NetworkMessage};
+use genesis::{import_chain, initialize_genesis, MINER_START};
 use libp2p::identity::{ed25519, Keypair};
-use log::{debug, info, trace};
+use log::{debug, error, info, trace, warn};
 use message_pool::{MessagePool, MpoolConfig, MpoolRpcProvider};
+use metrics::start_metrics_server;
 use paramfetch::{get_params_default, SectorSizeOpt};
+use paychmgr::PaychManager;
 use rpc::{start_rpc, RpcState};
 use state_manager::StateManager;
 use std::fs::File;
+use std::future::Future;
 use std::io::BufReader;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 use utils::write_to_file;
-use wallet::{KeyStore, PersistentKeyStore};
+use wallet::{KeyStore, PersistentKeyStore, RemoteSigner, SignerEndpoint, SIGNER_TOKEN_ENV};
 
 /// Number of tasks spawned for sync workers.
 // TODO benchmark and/or add this as a config option. (1 is temporary value to avoid overlap)
 const WORKER_TASKS: usize = 1;
 
+/// Time each step of the shutdown is given to complete before it is abandoned.
+const SHUTDOWN_STEP_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Starts daemon process
 pub(super) async fn start(config: Config) {
-    info!("Starting Forest daemon");
+    info!("Starting Forest daemon on {}", config.chain);
+    let producer_config = match ProducerConfig::from_config(&config) {
+        Ok(producer_config) => producer_config,
+        Err(e) => {
+            error!("Invalid block producer config: {}", e);
+            return;
+        }
+    };
     let net_keypair = get_keypair(&format!("{}{}", &config.data_dir, "/libp2p/keypair"))
         .unwrap_or_else(|| {
             // Keypair not found, generate and save generated keypair
@@ -51,26 +72,53 @@ pub(super) async fn start(config: Config) {
         });
 
     // Initialize keystore
-    let mut ks = PersistentKeyStore::new(config.data_dir.to_string()).unwrap();
+    let mut ks = if config.encrypt_keystore {
+        let passphrase = read_keystore_passphrase();
+        PersistentKeyStore::new_encrypted(config.data_dir.to_string(), &passphrase).unwrap_or_else(
+            |e| cli_error_and_die(&format!("Failed to unlock the encrypted keystore: {}", e)),
+        )
+    } else {
+        PersistentKeyStore::new(config.data_dir.to_string()).unwrap()
+    };
     if ks.get(JWT_IDENTIFIER).is_err() {
         ks.put(JWT_IDENTIFIER.to_owned(), generate_priv_key())
             .unwrap();
     }
-    let keystore = Arc::new(RwLock::new(ks));
+
+    // Route signing through an external signer, if configured
+    let signer = config.signer_endpoint.as_ref().map(|endpoint| {
+        info!("Using external signer at {}", endpoint);
+        let endpoint: SignerEndpoint = endpoint.parse().unwrap();
+        let mut signer = RemoteSigner::new(endpoint);
+        if let Ok(token) = std::env::var(SIGNER_TOKEN_ENV) {
+            signer = signer.with_token(token);
+        }
+        Arc::new(signer) as Arc<dyn Signer + Send + Sync>
+    });
 
     // Initialize database
-    let mut db = RocksDb::new(config.data_dir + "/db");
+    let mut db = RocksDb::new(format!("{}/db", config.data_dir));
     db.open().unwrap();
     let db = Arc::new(db);
 
     // Initialize StateManager
     let chain_store = Arc::new(ChainStore::new(Arc::clone(&db)));
-    let state_manager = Arc::new(StateManager::new(Arc::clone(&chain_store)));
+
+    // Generate the devnet genesis and its miner worker key, unless a genesis is given
+    let genesis_file = match &config.genesis_file {
+        None if config.devnet => {
+            Some(devnet::devnet_genesis(&config.data_dir, &mut ks, &chain_store).unwrap())
+        }
+        genesis_file => genesis_file.clone(),
+    };
+    let keystore = Arc::new(RwLock::new(ks));
+    let state_manager =
+        Arc::new(StateManager::new(Arc::clone(&chain_store)).with_network(config.chain));
 
     // Read Genesis file
     // * When snapshot command implemented, this genesis does not need to be initialized
     let (genesis, network_name) =
-        initialize_genesis(config.genesis_file.as_ref(), &state_manager).unwrap();
+        initialize_genesis(genesis_file.as_ref(), &state_manager).unwrap();
 
     // Sync from snapshot
     if let Some(path) = &config.snapshot_path {
@@ -81,10 +129,21 @@ pub(super) async fn start(config: Config) {
             .unwrap();
     }
 
-    // Fetch and ensure verification keys are downloaded
-    get_params_default(SectorSizeOpt::Keys, false)
+    // Roll back a head left without its state, by a node stopped while computing it
+    if let Some(head) = chain_store
+        .rollback_to_stored_state()
         .await
-        .unwrap();
+        .expect("Failed to verify the state of the stored head")
+    {
+        info!(epoch = head.epoch(); "Rolled back head");
+    }
+
+    // Fetch and ensure verification keys are downloaded, proofs are mocked on devnets
+    if !config.devnet {
+        get_params_default(SectorSizeOpt::Keys, false)
+            .await
+            .unwrap();
+    }
 
     // Initialize mpool
     let publisher = chain_store.publisher();
@@ -100,63 +159,178 @@ pub(super) async fn start(config: Config) {
         .unwrap(),
     );
 
+    // Initialize payment channel manager
+    let paych_manager = Arc::new(PaychManager::new(Arc::clone(&state_manager)));
+
     // Libp2p service setup
     let p2p_service = Libp2pService::new(config.network, chain_store, net_keypair, &network_name);
     let network_rx = p2p_service.network_receiver();
     let network_send = p2p_service.network_sender();
 
-    // Get Drand Coefficients
-    let coeff = config.drand_public;
-
-    let beacon = DrandBeacon::new(
-        DEFAULT_DRAND_URL,
-        coeff,
-        genesis.blocks()[0].timestamp(),
-        EPOCH_DURATION_SECONDS as u64,
-    )
-    .await
-    .unwrap();
-
     // Initialize ChainSyncer
     // TODO allow for configuring validation strategy (defaulting to full validation)
-    let chain_syncer = ChainSyncer::<_, _, FullVerifier, _>::new(
-        Arc::clone(&state_manager),
-        Arc::new(beacon),
-        Arc::clone(&mpool),
-        network_send.clone(),
-        network_rx,
-        Arc::new(genesis),
-    )
-    .unwrap();
-    let bad_blocks = chain_syncer.bad_blocks_cloned();
-    let sync_state = chain_syncer.sync_state_cloned();
-    let sync_task = task::spawn(async {
-        chain_syncer.start(WORKER_TASKS).await;
-    });
+    let genesis = Arc::new(genesis);
+    let block_delay = if config.devnet {
+        config.block_time
+    } else {
+        EPOCH_DURATION_SECONDS as u64
+    };
+    let (syncer, producer_task, beacon) = if config.devnet {
+        let beacon = Arc::new(MockBeacon::new(Duration::from_secs(config.block_time)));
+        let chain_syncer = ChainSyncer::<_, _, MockVerifier, _>::new(
+            Arc::clone(&state_manager),
+            Arc::clone(&beacon),
+            Arc::clone(&mpool),
+            network_send.clone(),
+            network_rx,
+            genesis,
+        )
+        .unwrap()
+        .with_block_delay(config.block_time);
+        let producer_task = producer_config.map(|producer_config| {
+            let producer = BlockProducer::<_, _, _, MockVerifier, _>::new(
+                Arc::clone(&state_manager),
+                Arc::clone(&mpool),
+                Arc::clone(&beacon),
+                Arc::clone(&keystore),
+                Arc::new(MockProver),
+                producer_config.miner,
+                config.block_time,
+            );
+            start_producer(
+                producer,
+                signer.clone(),
+                network_send.clone(),
+                network_name.clone(),
+            )
+        });
+        (
+            start_syncer(chain_syncer),
+            producer_task,
+            beacon as Arc<dyn Beacon + Send + Sync>,
+        )
+    } else {
+        // Get Drand Coefficients
+        let coeff = config.drand_public;
+
+        let beacon = DrandBeacon::new(
+            DEFAULT_DRAND_URL,
+            coeff,
+            genesis.blocks()[0].timestamp(),
+            EPOCH_DURATION_SECONDS as u64,
+        )
+        .await
+        .unwrap();
+        let beacon = Arc::new(beacon);
+
+        let chain_syncer = ChainSyncer::<_, _, FullVerifier, _>::new(
+            Arc::clone(&state_manager),
+            Arc::clone(&beacon),
+            Arc::clone(&mpool),
+            network_send.clone(),
+            network_rx,
+            genesis,
+        )
+        .unwrap();
+        let producer_task = producer_config.map(|producer_config| {
+            // Sector storage is checked to be set off devnets when the config is read.
+            let storage = producer_config.sector_storage.unwrap_or_default();
+            let producer = BlockProducer::<_, _, _, FullVerifier, _>::new(
+                Arc::clone(&state_manager),
+                Arc::clone(&mpool),
+                Arc::clone(&beacon),
+                Arc::clone(&keystore),
+                Arc::new(LocalProver::new(storage)),
+                producer_config.miner,
+                EPOCH_DURATION_SECONDS as u64,
+            );
+            start_producer(
+                producer,
+                signer.clone(),
+                network_send.clone(),
+                network_name.clone(),
+            )
+        });
+        (start_syncer(chain_syncer), producer_task, beacon as _)
+    };
 
     // Start services
     let p2p_task = task::spawn(async {
         p2p_service.run().await;
     });
+    let metrics_task = if config.enable_metrics {
+        let chain_store = Arc::clone(state_manager.chain_store());
+        let mpool = Arc::clone(&mpool);
+        let sync_state = Arc::clone(&syncer.sync_state);
+        let db = Arc::clone(&db);
+        let metrics_address = config.metrics_address.clone();
+        Some(task::spawn(async move {
+            let refresh = move || {
+                refresh_metrics(
+                    Arc::clone(&chain_store),
+                    Arc::clone(&mpool),
+                    Arc::clone(&sync_state),
+                    Arc::clone(&db),
+                )
+            };
+            if let Err(e) = start_metrics_server(&metrics_address, refresh).await {
+                error!("Failed to start metrics endpoint: {}", e);
+            }
+        }))
+    } else {
+        debug!("Metrics disabled");
+        None
+    };
+    // Set by the RPC server while it accepts connections, which the node status reports
+    let rpc_listening = Arc::new(AtomicBool::new(false));
+    let health_task = if config.enable_health {
+        let state = HealthState {
+            chain_store: Arc::clone(state_manager.chain_store()),
+            sync_state: Arc::clone(&syncer.sync_state),
+            peer_manager: Arc::clone(&syncer.peer_manager),
+            block_delay,
+            rpc_listening: Arc::clone(&rpc_listening),
+        };
+        let health_address = config.health_address.clone();
+        Some(task::spawn(async move {
+            start_health_server(&health_address, state).await;
+        }))
+    } else {
+        debug!("Health endpoints disabled");
+        None
+    };
     let rpc_task = if config.enable_rpc {
         let keystore_rpc = Arc::clone(&keystore);
+        let mpool_rpc = Arc::clone(&mpool);
+        let bad_blocks = Arc::clone(&syncer.bad_blocks);
+        let sync_state = Arc::clone(&syncer.sync_state);
+        let peer_manager = Arc::clone(&syncer.peer_manager);
         let rpc_listen = format!("127.0.0.1:{}", &config.rpc_port);
+        let devnet = config.devnet;
         Some(task::spawn(async move {
             info!("JSON RPC Endpoint at {}", &rpc_listen);
-            start_rpc(
-                RpcState {
-                    state_manager,
-                    keystore: keystore_rpc,
-                    mpool,
-                    bad_blocks,
-                    sync_state,
-                    network_send,
-                    network_name,
-                    events_pubsub: Arc::new(RwLock::new(Publisher::new(1000))),
-                },
-                &rpc_listen,
-            )
-            .await;
+            let state = RpcState {
+                state_manager,
+                keystore: keystore_rpc,
+                mpool: mpool_rpc,
+                paych_manager,
+                bad_blocks,
+                sync_state,
+                network_send,
+                network_name,
+                events_pubsub: Arc::new(RwLock::new(Publisher::new(1000))),
+                signer,
+                beacon,
+                peer_manager,
+                block_delay,
+                listening: rpc_listening,
+            };
+            // Blocks created through the API are checked with the verifier blocks are synced with
+            if devnet {
+                start_rpc::<_, _, MockVerifier>(state, &rpc_listen).await;
+            } else {
+                start_rpc::<_, _, FullVerifier>(state, &rpc_listen).await;
+            }
         }))
     } else {
         debug!("RPC disabled");
@@ -165,22 +339,177 @@ pub(super) async fn start(config: Config) {
 
     // Block until ctrl-c is hit
     block_until_sigint().await;
+    info!("Shutting down Forest");
 
-    let keystore_write = task::spawn(async move {
-        keystore.read().await.flush().unwrap();
-    });
-
-    // Cancel all async services
-    p2p_task.cancel().await;
-    sync_task.cancel().await;
+    // Stop taking requests, then stop producing and syncing blocks
     if let Some(task) = rpc_task {
-        task.cancel().await;
+        shutdown_step("stop RPC server", task.cancel()).await;
+    }
+    if let Some(task) = metrics_task {
+        shutdown_step("stop metrics endpoint", task.cancel()).await;
+    }
+    if let Some(task) = health_task {
+        shutdown_step("stop health endpoints", task.cancel()).await;
+    }
+    if let Some(task) = producer_task {
+        shutdown_step("stop block producer", task.cancel()).await;
+    }
+    // Sync workers stop once no partially validated tipsets are left behind
+    shutdown_step("stop chain sync", async {
+        syncer.stop.stop().await;
+        syncer.task.await;
+    })
+    .await;
+    shutdown_step("stop network", p2p_task.cancel()).await;
+
+    // Flush pending writes
+    shutdown_step("flush keystore", async {
+        if let Err(e) = keystore.read().await.flush() {
+            error!("Failed to flush keystore: {}", e);
+        }
+    })
+    .await;
+    if let Err(e) = mpool.get_config().save_config(db.as_ref()) {
+        error!("Failed to save message pool config: {}", e);
+    }
+    if let Err(e) = db.flush() {
+        error!("Failed to flush database: {}", e);
     }
-    keystore_write.await;
 
     info!("Forest finish shutdown");
 }
 
+/// Awaits a step of the shutdown, abandoning it if it doesn't complete within the timeout.
+async fn shutdown_step<F>(name: &str, step: F)
+where
+    F: Future,
+{
+    debug!("Shutdown: {}", name);
+    if future::timeout(SHUTDOWN_STEP_TIMEOUT, step).await.is_err() {
+        warn!("Shutdown step timed out, continuing: {}", name);
+    }
+}
+
+/// Updates the metrics sampled from the node state, before they are scraped.
+async fn refresh_metrics(
+    chain_store: Arc<ChainStore<RocksDb>>,
+    mpool: Arc<MessagePool<MpoolRpcProvider<RocksDb>>>,
+    sync_state: Arc<RwLock<Vec<Arc<RwLock<SyncState>>>>>,
+    db: Arc<RocksDb>,
+) {
+    let head_epoch = chain_store
+        .heaviest_tipset()
+        .await
+        .map(|ts| ts.epoch())
+        .unwrap_or_default();
+    metrics::CHAIN_HEAD_EPOCH.set(head_epoch);
+
+    metrics::SYNC_WORKERS.reset();
+    let mut target_epoch = head_epoch;
+    for state in sync_state.read().await.iter() {
+        let state = state.read().await;
+        metrics::SYNC_WORKERS
+            .with_label_values(&[&state.stage().to_string()])
+            .inc();
+        if let Some(target) = state.target() {
+            target_epoch = target_epoch.max(target.epoch());
+        }
+    }
+    metrics::SYNC_LAG_EPOCHS.set(target_epoch - head_epoch);
+
+    if let Ok((pending, _)) = mpool.pending().await {
+        metrics::MPOOL_MESSAGES.set(pending.len() as i64);
+    }
+
+    match db.stats() {
+        Ok(stats) => {
+            metrics::DB_SIZE_BYTES.set(stats.size as i64);
+            metrics::set_counter(&metrics::DB_CACHE_HITS, stats.cache_hits);
+            metrics::set_counter(&metrics::DB_CACHE_MISSES, stats.cache_misses);
+        }
+        Err(e) => warn!("Failed to read database statistics: {}", e),
+    }
+}
+
+/// Miner the block producer mines for, and the storage of the sectors it proves.
+#[derive(Debug, PartialEq)]
+struct ProducerConfig {
+    miner: Address,
+    sector_storage: Option<String>,
+}
+
+impl ProducerConfig {
+    /// Reads the block producer settings from the daemon config. Devnets always produce blocks,
+    /// by default for the genesis miner, other networks only when a miner address is set, and
+    /// then need the sector storage to generate winning PoSts from.
+    fn from_config(config: &Config) -> Result<Option<Self>, String> {
+        let miner = match &config.miner_address {
+            Some(miner) => miner
+                .parse()
+                .map_err(|e| format!("Invalid miner address {}: {}", miner, e))?,
+            None if config.devnet => Address::new_id(MINER_START),
+            None => return Ok(None),
+        };
+        if !config.devnet && config.sector_storage.is_none() {
+            return Err(format!(
+                "Sector storage must be configured to produce blocks for {}",
+                miner
+            ));
+        }
+        Ok(Some(Self {
+            miner,
+            sector_storage: config.sector_storage.clone(),
+        }))
+    }
+}
+
+/// Running chain syncer, with the state it shares with the RPC server and health endpoints.
+struct Syncer {
+    bad_blocks: Arc<BadBlockCache>,
+    sync_state: Arc<RwLock<Vec<Arc<RwLock<SyncState>>>>>,
+    peer_manager: Arc<PeerManager>,
+    stop: SyncStopHandle,
+    task: JoinHandle<()>,
+}
+
+/// Spawns the chain syncer.
+fn start_syncer<TBeacon, V>(
+    chain_syncer: ChainSyncer<RocksDb, TBeacon, V, MpoolRpcProvider<RocksDb>>,
+) -> Syncer
+where
+    TBeacon: Beacon + Send + Sync + 'static,
+    V: ProofVerifier + Send + Sync + 'static,
+{
+    Syncer {
+        bad_blocks: chain_syncer.bad_blocks_cloned(),
+        sync_state: chain_syncer.sync_state_cloned(),
+        peer_manager: chain_syncer.peer_manager_cloned(),
+        stop: chain_syncer.stop_handle(),
+        task: task::spawn(async {
+            chain_syncer.start(WORKER_TASKS).await;
+        }),
+    }
+}
+
+/// Spawns the block producer, which gossips produced blocks and signs through the external
+/// signer if one is configured.
+fn start_producer<TBeacon, V>(
+    producer: BlockProducer<RocksDb, PersistentKeyStore, TBeacon, V, MpoolRpcProvider<RocksDb>>,
+    signer: Option<Arc<dyn Signer + Send + Sync>>,
+    network_send: Sender<NetworkMessage>,
+    network_name: String,
+) -> JoinHandle<()>
+where
+    TBeacon: Beacon + Send + Sync + 'static,
+    V: ProofVerifier + Send + Sync + 'static,
+{
+    let mut producer = producer.with_gossip(network_send, network_name);
+    if let Some(signer) = signer {
+        producer = producer.with_signer(signer);
+    }
+    task::spawn(producer.run())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -210,4 +539,35 @@ mod test {
             .await
             .expect("Failed to import chain");
     }
+
+    #[test]
+    fn producer_config() {
+        // Nodes without a miner address don't produce blocks, except on devnets.
+        let mut config = Config::default();
+        assert_eq!(ProducerConfig::from_config(&config), Ok(None));
+        config.devnet = true;
+        assert_eq!(
+            ProducerConfig::from_config(&config),
+            Ok(Some(ProducerConfig {
+                miner: Address::new_id(MINER_START),
+                sector_storage: None,
+            }))
+        );
+
+        config.miner_address = Some("not an address".to_owned());
+        assert!(ProducerConfig::from_config(&config).is_err());
+
+        // Off devnets, proofs are generated from the sector storage.
+        config.devnet = false;
+        config.miner_address = Some("t01001".to_owned());
+        assert!(ProducerConfig::from_config(&config).is_err());
+        config.sector_storage = Some("/sectors".to_owned());
+        assert_eq!(
+            ProducerConfig::from_config(&config),
+            Ok(Some(ProducerConfig {
+                miner: Address::new_id(1001),
+                sector_storage: Some("/sectors".to_owned()),
+            }))
+        );
+    }
 }
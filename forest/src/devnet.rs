@@ -0,0 +1,69 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use chain::ChainStore;
+use crypto::SignatureType;
+use db::MemoryDB;
+use fil_types::verifier::MockVerifier;
+use fil_types::DevnetParams;
+use genesis::{build_genesis, devnet_template};
+use ipld_blockstore::BlockStore;
+use log::info;
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fs::{self, File};
+use std::path::Path;
+use wallet::{generate_key, Key, KeyStore, PersistentKeyStore};
+
+/// Network name of the generated devnet genesis.
+const DEVNET_NAME: &str = "devnet";
+
+/// Number of sectors the devnet miner is pre-sealed with.
+const DEVNET_SECTORS: u64 = 2;
+
+/// Name of the devnet miner worker key in the keystore.
+const DEVNET_WORKER_KEY: &str = "devnet-worker";
+
+/// Returns the path of the devnet genesis in the data directory. The genesis is kept while the
+/// chain store holds the chain built on it, so a restarted devnet resumes its chain. Otherwise a
+/// genesis timestamped now is generated, so the devnet doesn't start from a genesis far behind
+/// wall clock time. The genesis is generated with the BLS worker key of the devnet, which is
+/// created and stored in the keystore as the default key the first time.
+pub(super) fn devnet_genesis<DB: BlockStore>(
+    data_dir: &str,
+    keystore: &mut PersistentKeyStore,
+    chain_store: &ChainStore<DB>,
+) -> Result<String, Box<dyn StdError>> {
+    let dir = format!("{}/devnet", data_dir);
+    let path = format!("{}/genesis.car", dir);
+    if Path::new(&path).exists() && chain_store.genesis()?.is_some() {
+        return Ok(path);
+    }
+
+    let key = match keystore.get(DEVNET_WORKER_KEY) {
+        Ok(key_info) => Key::try_from(key_info)?,
+        Err(_) => {
+            let key = generate_key(SignatureType::BLS)?;
+            keystore.put(DEVNET_WORKER_KEY.to_owned(), key.key_info.clone())?;
+            keystore.put(format!("wallet-{}", key.address), key.key_info.clone())?;
+            if keystore.get("default").is_err() {
+                keystore.put("default".to_owned(), key.key_info.clone())?;
+            }
+            keystore.flush()?;
+            key
+        }
+    };
+
+    let template = devnet_template(DEVNET_NAME.to_owned(), key.address, DEVNET_SECTORS);
+    let store = MemoryDB::default();
+    let header = build_genesis::<MockVerifier, DevnetParams, _>(&store, &template)?;
+
+    fs::create_dir_all(&dir)?;
+    forest_car::write_car(&store, vec![*header.cid()], File::create(&path)?)?;
+    info!(
+        "Generated devnet genesis {} with worker {}",
+        header.cid(),
+        key.address
+    );
+    Ok(path)
+}
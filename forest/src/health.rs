@@ -0,0 +1,151 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use async_std::sync::RwLock;
+use chain::ChainStore;
+use chain_sync::{PeerManager, SyncState};
+use ipld_blockstore::BlockStore;
+use log::{error, info};
+use metrics::{serve_http, HttpResponse};
+use rpc::compute_node_status;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const LIVEZ_PATH: &str = "/healthz/livez";
+const READYZ_PATH: &str = "/readyz";
+
+/// Components of the node its status is read from.
+pub(super) struct HealthState<DB> {
+    pub chain_store: Arc<ChainStore<DB>>,
+    pub sync_state: Arc<RwLock<Vec<Arc<RwLock<SyncState>>>>>,
+    pub peer_manager: Arc<PeerManager>,
+    pub block_delay: u64,
+    /// Set by the RPC server while it is bound to its endpoint.
+    pub rpc_listening: Arc<AtomicBool>,
+}
+
+/// Serves the liveness probe at `/healthz/livez`, which succeeds while the node responds, and
+/// the readiness probe at `/readyz`, which succeeds once the node is synced and serves RPC
+/// requests. The readiness probe responds with the node status as JSON.
+pub(super) async fn start_health_server<DB>(addr: &str, state: HealthState<DB>)
+where
+    DB: BlockStore + Send + Sync + 'static,
+{
+    info!("Health endpoints at {}", addr);
+    let state = Arc::new(state);
+    let res = serve_http(addr, move |path| {
+        let state = Arc::clone(&state);
+        async move { respond(&path, &state).await }
+    })
+    .await;
+    if let Err(e) = res {
+        error!("Failed to start health endpoints: {}", e);
+    }
+}
+
+async fn respond<DB: BlockStore>(path: &str, state: &HealthState<DB>) -> HttpResponse {
+    match path {
+        LIVEZ_PATH => HttpResponse::ok("text/plain", "ok\n"),
+        READYZ_PATH => {
+            let status = match compute_node_status(
+                &state.chain_store,
+                &state.sync_state,
+                &state.peer_manager,
+                state.block_delay,
+                state.rpc_listening.load(Ordering::Relaxed),
+            )
+            .await
+            {
+                Ok(status) => status,
+                Err(e) => return unavailable("text/plain", format!("{}\n", e).into_bytes()),
+            };
+            let body = serde_json::to_vec(&status).unwrap_or_default();
+            if status.is_ready() {
+                HttpResponse::ok("application/json", body)
+            } else {
+                unavailable("application/json", body)
+            }
+        }
+        _ => HttpResponse::not_found(),
+    }
+}
+
+fn unavailable(content_type: &str, body: Vec<u8>) -> HttpResponse {
+    HttpResponse {
+        status: "503 Service Unavailable",
+        content_type: content_type.to_owned(),
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use address::Address;
+    use blocks::{BlockHeader, Tipset};
+    use db::MemoryDB;
+    use rpc::NodeStatus;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const BLOCK_DELAY: u64 = 30;
+
+    /// Returns the health state of a node whose head is a genesis created `age` seconds ago.
+    async fn health_state(age: u64) -> HealthState<MemoryDB> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let genesis = BlockHeader::builder()
+            .miner_address(Address::new_id(0))
+            .timestamp(now - age)
+            .build_and_validate()
+            .unwrap();
+        let chain_store = Arc::new(ChainStore::new(Arc::new(MemoryDB::default())));
+        chain_store.set_genesis(&genesis).unwrap();
+        chain_store
+            .set_heaviest_tipset(Arc::new(Tipset::new(vec![genesis]).unwrap()))
+            .await
+            .unwrap();
+        HealthState {
+            chain_store,
+            sync_state: Default::default(),
+            peer_manager: Default::default(),
+            block_delay: BLOCK_DELAY,
+            rpc_listening: Default::default(),
+        }
+    }
+
+    #[async_std::test]
+    async fn livez_responds() {
+        let state = health_state(0).await;
+        let res = respond(LIVEZ_PATH, &state).await;
+        assert_eq!(res.status, "200 OK");
+        assert_eq!(res.body, b"ok\n");
+        assert_eq!(respond("/healthz", &state).await, HttpResponse::not_found());
+    }
+
+    #[async_std::test]
+    async fn readyz_once_synced_and_rpc_listening() {
+        let state = health_state(0).await;
+        let res = respond(READYZ_PATH, &state).await;
+        assert_eq!(res.status, "503 Service Unavailable");
+        let status: NodeStatus = serde_json::from_slice(&res.body).unwrap();
+        assert!(status.synced);
+        assert!(!status.rpc_ready);
+
+        state.rpc_listening.store(true, Ordering::Relaxed);
+        let res = respond(READYZ_PATH, &state).await;
+        assert_eq!(res.status, "200 OK");
+        assert_eq!(res.content_type, "application/json");
+        let status: NodeStatus = serde_json::from_slice(&res.body).unwrap();
+        assert!(status.is_ready());
+
+        // A head further behind the expected epoch than the tolerance is not synced
+        let state = health_state(BLOCK_DELAY * 10).await;
+        state.rpc_listening.store(true, Ordering::Relaxed);
+        let res = respond(READYZ_PATH, &state).await;
+        assert_eq!(res.status, "503 Service Unavailable");
+        let status: NodeStatus = serde_json::from_slice(&res.body).unwrap();
+        assert!(!status.synced);
+    }
+}
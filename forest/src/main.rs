@@ -3,24 +3,31 @@
 
 mod cli;
 mod daemon;
-mod logger;
+mod devnet;
+mod health;
 mod subcommand;
 
 use cli::CLI;
+use logger::{setup_logger, LoggerConfig};
 use structopt::StructOpt;
 
 #[async_std::main]
 async fn main() {
-    logger::setup_logger();
-
     // Capture CLI inputs
     match CLI::from_args() {
         CLI {
             daemon_opts,
             cmd: None,
-        } => daemon::start(daemon_opts.to_config().unwrap()).await,
+        } => {
+            let config = daemon_opts.to_config().unwrap();
+            setup_logger(&config.logging).unwrap();
+            daemon::start(config).await
+        }
         CLI {
             cmd: Some(command), ..
-        } => subcommand::process(command).await,
+        } => {
+            setup_logger(&LoggerConfig::default()).unwrap();
+            subcommand::process(command).await
+        }
     }
 }
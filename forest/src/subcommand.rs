@@ -19,5 +19,23 @@ pub(super) async fn process(command: Subcommand) {
         Subcommand::Genesis(cmd) => {
             cmd.run().await;
         }
+        Subcommand::Send(cmd) => {
+            cmd.run().await;
+        }
+        Subcommand::Mpool(cmd) => {
+            cmd.run().await;
+        }
+        Subcommand::Msig(cmd) => {
+            cmd.run().await;
+        }
+        Subcommand::Keystore(cmd) => {
+            cmd.run().await;
+        }
+        Subcommand::State(cmd) => {
+            cmd.run().await;
+        }
+        Subcommand::Log(cmd) => {
+            cmd.run().await;
+        }
     }
 }
@@ -5,11 +5,12 @@ use crate::{
     node::Link, nodes_for_height, BitMap, Error, Node, Root, MAX_HEIGHT, MAX_INDEX, WIDTH,
 };
 use cid::{Cid, Code::Blake2b256};
-use encoding::{de::DeserializeOwned, ser::Serialize};
+use encoding::{de::DeserializeOwned, from_slice, ser::Serialize, to_vec, Error as EncodingError};
 use ipld_blockstore::BlockStore;
 use std::error::Error as StdError;
+use std::ops::{Bound, RangeBounds};
 
-use super::ValueMut;
+use super::{AmtDiff, Iter, ValueMut};
 
 /// Array Mapped Trie allows for the insertion and persistence of data, serializable to a CID.
 ///
@@ -312,4 +313,128 @@ where
             .for_each_while_mut(self.block_store, self.height(), 0, &mut f)
             .map(|_| ())
     }
+
+    /// Returns an iterator over the set indexes and values of the Amt, in index order.
+    ///
+    /// ```
+    /// use ipld_amt::Amt;
+    ///
+    /// let db = db::MemoryDB::default();
+    /// let mut map: Amt<String, _> = Amt::new(&db);
+    /// map.set(1, "One".to_owned()).unwrap();
+    /// map.set(4, "Four".to_owned()).unwrap();
+    ///
+    /// let values: Vec<_> = map.iter().map(|r| r.unwrap()).collect();
+    /// assert_eq!(values, vec![(1, "One".to_owned()), (4, "Four".to_owned())]);
+    ///
+    /// let last = map.iter().rev().next().unwrap().unwrap();
+    /// assert_eq!(last, (4, "Four".to_owned()));
+    /// ```
+    pub fn iter(&self) -> Iter<'_, V, BS>
+    where
+        V: Clone,
+    {
+        self.iter_range(..)
+    }
+
+    /// Returns an iterator over the set indexes and values of the Amt within a range of indexes,
+    /// in index order. Only the nodes which overlap the range are loaded.
+    ///
+    /// ```
+    /// use ipld_amt::Amt;
+    ///
+    /// let db = db::MemoryDB::default();
+    /// let mut map: Amt<u64, _> = Amt::new(&db);
+    /// for i in 0..100 {
+    ///     map.set(i * 2, i).unwrap();
+    /// }
+    ///
+    /// let indexes: Vec<u64> = map.iter_range(10..17).map(|r| r.unwrap().0).collect();
+    /// assert_eq!(indexes, vec![10, 12, 14, 16]);
+    ///
+    /// let indexes: Vec<u64> = map.iter_range(190..).rev().map(|r| r.unwrap().0).collect();
+    /// assert_eq!(indexes, vec![198, 196, 194, 192, 190]);
+    /// ```
+    pub fn iter_range<R>(&self, range: R) -> Iter<'_, V, BS>
+    where
+        V: Clone,
+        R: RangeBounds<u64>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => i.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => i.saturating_add(1),
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => u64::MAX,
+        };
+        Iter::new(self.block_store, &self.root.node, self.height(), start..end)
+    }
+
+    /// Returns the lowest set index of the Amt, or `None` if it is empty.
+    pub fn first(&self) -> Result<Option<u64>, Error>
+    where
+        V: Clone,
+    {
+        Ok(self.iter().next().transpose()?.map(|(i, _)| i))
+    }
+
+    /// Returns the highest set index of the Amt, or `None` if it is empty.
+    pub fn last(&self) -> Result<Option<u64>, Error>
+    where
+        V: Clone,
+    {
+        Ok(self.iter().next_back().transpose()?.map(|(i, _)| i))
+    }
+
+    /// Returns an iterator over the changes from this Amt to another, in index order. Subtrees
+    /// with the same Cid in both Amts are skipped, and nodes are only loaded as the iterator
+    /// advances.
+    ///
+    /// Both Amts must be flushed, as changes which have not been flushed can't be compared by Cid.
+    ///
+    /// ```
+    /// use ipld_amt::{Amt, Change};
+    ///
+    /// let db = db::MemoryDB::default();
+    /// let mut before = Amt::new(&db);
+    /// before.set(1, "One".to_owned()).unwrap();
+    /// before.set(4, "Four".to_owned()).unwrap();
+    /// before.flush().unwrap();
+    ///
+    /// let mut after = Amt::new(&db);
+    /// after.set(4, "Vier".to_owned()).unwrap();
+    /// after.set(100, "Hundred".to_owned()).unwrap();
+    /// after.flush().unwrap();
+    ///
+    /// let changes: Vec<_> = before.diff(&after).unwrap().map(|c| c.unwrap()).collect();
+    /// assert_eq!(changes, vec![
+    ///     Change::Removed(1, "One".to_owned()),
+    ///     Change::Modified(4, "Four".to_owned(), "Vier".to_owned()),
+    ///     Change::Added(100, "Hundred".to_owned()),
+    /// ]);
+    /// ```
+    pub fn diff(&self, other: &Self) -> Result<AmtDiff<'db, V, BS>, Error>
+    where
+        V: PartialEq,
+    {
+        // Roots are copied through their encoding, which also checks they are flushed
+        let before = to_vec(&self.root).map_err(EncodingError::from)?;
+        let after = to_vec(&other.root).map_err(EncodingError::from)?;
+        let (before, after): (Root<V>, Root<V>) = if before == after {
+            (Root::default(), Root::default())
+        } else {
+            (
+                from_slice(&before).map_err(EncodingError::from)?,
+                from_slice(&after).map_err(EncodingError::from)?,
+            )
+        };
+        Ok(AmtDiff::new(
+            self.block_store,
+            (before.height, before.node),
+            (after.height, after.node),
+        ))
+    }
 }
@@ -0,0 +1,179 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::{node::Link, nodes_for_height, BitMap, Error, Node, WIDTH};
+use encoding::de::DeserializeOwned;
+use ipld_blockstore::BlockStore;
+use std::collections::VecDeque;
+
+/// Change of a value between two Amts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<V> {
+    /// Index only set in the second Amt.
+    Added(u64, V),
+    /// Index only set in the first Amt.
+    Removed(u64, V),
+    /// Index set in both Amts with a different value, before and after.
+    Modified(u64, V, V),
+}
+
+impl<V> Change<V> {
+    /// Returns the index of the changed value.
+    pub fn index(&self) -> u64 {
+        match self {
+            Change::Added(i, _) | Change::Removed(i, _) | Change::Modified(i, _, _) => *i,
+        }
+    }
+}
+
+/// Nodes at the same position of both trees which are left to compare.
+struct Item<V> {
+    height: u64,
+    offset: u64,
+    before: Option<Link<V>>,
+    after: Option<Link<V>>,
+}
+
+/// Iterator over the changes between two Amts, created with
+/// [diff](struct.Amt.html#method.diff). Changes are yielded in index order.
+///
+/// Both trees are walked together from the root, and links to the same node are skipped
+/// without loading it. Nodes are only loaded from the store as the iterator advances.
+pub struct AmtDiff<'db, V, BS> {
+    block_store: &'db BS,
+    stack: Vec<Item<V>>,
+    /// Changes found which have not been yielded yet.
+    pending: VecDeque<Change<V>>,
+}
+
+impl<'db, V, BS> AmtDiff<'db, V, BS>
+where
+    V: DeserializeOwned + PartialEq,
+    BS: BlockStore,
+{
+    pub(crate) fn new(
+        block_store: &'db BS,
+        (b_height, before): (u64, Node<V>),
+        (a_height, after): (u64, Node<V>),
+    ) -> Self {
+        let height = std::cmp::max(b_height, a_height);
+        Self {
+            block_store,
+            stack: vec![Item {
+                height,
+                offset: 0,
+                before: Some(Link::Dirty(Box::new(lift(before, b_height, height)))),
+                after: Some(Link::Dirty(Box::new(lift(after, a_height, height)))),
+            }],
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn resolve(&self, link: Option<Link<V>>) -> Result<Option<Node<V>>, Error> {
+        match link {
+            None => Ok(None),
+            Some(Link::Dirty(node)) => Ok(Some(*node)),
+            Some(Link::Cid { cid, .. }) => Ok(Some(
+                self.block_store
+                    .get(&cid)?
+                    .ok_or_else(|| Error::CidNotFound(cid.to_string()))?,
+            )),
+        }
+    }
+
+    fn step(&mut self, item: Item<V>) -> Result<(), Error> {
+        if let (Some(Link::Cid { cid: b, .. }), Some(Link::Cid { cid: a, .. })) =
+            (&item.before, &item.after)
+        {
+            if b == a {
+                return Ok(());
+            }
+        }
+        let before = self.resolve(item.before)?;
+        let after = self.resolve(item.after)?;
+
+        if item.height == 0 {
+            let before = values(before)?;
+            let after = values(after)?;
+            for (i, pair) in (0..).zip(before.into_iter().zip(after)) {
+                let index = item.offset + i;
+                match pair {
+                    (Some(b), Some(a)) if b != a => {
+                        self.pending.push_back(Change::Modified(index, b, a))
+                    }
+                    (Some(b), None) => self.pending.push_back(Change::Removed(index, b)),
+                    (None, Some(a)) => self.pending.push_back(Change::Added(index, a)),
+                    _ => (),
+                }
+            }
+        } else {
+            let span = nodes_for_height(item.height);
+            let pairs = links(before).into_iter().zip(links(after));
+            // Pushed in reverse, so changes are found in index order
+            for (i, (before, after)) in (0..WIDTH as u64).zip(pairs).rev() {
+                if before.is_some() || after.is_some() {
+                    self.stack.push(Item {
+                        height: item.height - 1,
+                        offset: item.offset + i * span,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a root node as the first child of empty nodes, up to the given height.
+fn lift<V>(mut node: Node<V>, mut height: u64, target: u64) -> Node<V> {
+    while height < target {
+        let mut links: [Option<Link<V>>; WIDTH] = Default::default();
+        links[0] = Some(Link::Dirty(Box::new(node)));
+        node = Node::Link {
+            bmap: BitMap::new(1),
+            links,
+        };
+        height += 1;
+    }
+    node
+}
+
+/// Takes the child links of a node. An empty node is decoded as a leaf at any height.
+fn links<V>(node: Option<Node<V>>) -> Vec<Option<Link<V>>> {
+    match node {
+        Some(Node::Link { mut links, .. }) => links.iter_mut().map(Option::take).collect(),
+        _ => (0..WIDTH).map(|_| None).collect(),
+    }
+}
+
+/// Takes the values of a leaf node.
+fn values<V>(node: Option<Node<V>>) -> Result<Vec<Option<V>>, Error> {
+    match node {
+        Some(Node::Leaf { mut vals, .. }) => Ok(vals.iter_mut().map(Option::take).collect()),
+        Some(Node::Link { .. }) => Err(Error::Other("expected leaf node at height 0".to_owned())),
+        None => Ok((0..WIDTH).map(|_| None).collect()),
+    }
+}
+
+impl<'db, V, BS> Iterator for AmtDiff<'db, V, BS>
+where
+    V: DeserializeOwned + PartialEq,
+    BS: BlockStore,
+{
+    type Item = Result<Change<V>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Some(Ok(change));
+            }
+            let item = self.stack.pop()?;
+            if let Err(e) = self.step(item) {
+                // Stop iterating after an error, the remaining changes can't be trusted
+                self.stack.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
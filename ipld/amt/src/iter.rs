@@ -0,0 +1,230 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::{node::Link, nodes_for_height, Error, Node, WIDTH};
+use cid::Cid;
+use encoding::de::DeserializeOwned;
+use ipld_blockstore::BlockStore;
+use std::ops::Range;
+
+/// Node being walked, either from the Amt or loaded from the store for the walk only.
+enum NodeRef<'a, V> {
+    Borrowed(&'a Node<V>),
+    Owned(Box<Node<V>>),
+}
+
+impl<'a, V> NodeRef<'a, V> {
+    fn node(&self) -> &Node<V> {
+        match self {
+            NodeRef::Borrowed(node) => *node,
+            NodeRef::Owned(node) => node,
+        }
+    }
+}
+
+/// Node being walked, with the slots of it which are left to visit.
+struct Frame<'a, V> {
+    node: NodeRef<'a, V>,
+    height: u64,
+    offset: u64,
+    slots: Range<u64>,
+}
+
+/// Iterator over the set indexes and values of an Amt, in index order. Created with
+/// [iter](struct.Amt.html#method.iter) or [iter_range](struct.Amt.html#method.iter_range).
+///
+/// Nodes are loaded as the iterator advances, and subtrees outside of the range or without
+/// values are skipped using the node bitmaps. Iterating from the back with `rev` only loads
+/// the nodes at the end of the range. Loaded nodes are owned by the iterator and dropped once
+/// walked, rather than cached in the Amt, so values are yielded by value, cloned if they are
+/// held by the Amt itself.
+pub struct Iter<'a, V, BS> {
+    block_store: &'a BS,
+    front: Vec<Frame<'a, V>>,
+    back: Vec<Frame<'a, V>>,
+    /// Indexes which are left to yield.
+    range: Range<u64>,
+}
+
+impl<'a, V, BS> Iter<'a, V, BS>
+where
+    V: Clone + DeserializeOwned,
+    BS: BlockStore,
+{
+    pub(crate) fn new(
+        block_store: &'a BS,
+        root: &'a Node<V>,
+        height: u64,
+        range: Range<u64>,
+    ) -> Self {
+        let mut iter = Self {
+            block_store,
+            front: Vec::new(),
+            back: Vec::new(),
+            range,
+        };
+        if let Some(frame) = iter.frame(NodeRef::Borrowed(root), height, 0) {
+            iter.back.push(Frame {
+                node: NodeRef::Borrowed(root),
+                slots: frame.slots.clone(),
+                ..frame
+            });
+            iter.front.push(frame);
+        }
+        iter
+    }
+
+    /// Creates a frame for a node, limited to the slots which overlap the range.
+    fn frame(&self, node: NodeRef<'a, V>, height: u64, offset: u64) -> Option<Frame<'a, V>> {
+        let span = nodes_for_height(height);
+        if self.range.end <= offset || self.range.start >= self.range.end {
+            return None;
+        }
+        let start = std::cmp::min(self.range.start.saturating_sub(offset) / span, WIDTH as u64);
+        let end = std::cmp::min((self.range.end - 1 - offset) / span + 1, WIDTH as u64);
+        Some(Frame {
+            node,
+            height,
+            offset,
+            slots: start..end,
+        })
+    }
+
+    /// Loads a node from the store without caching it in the Amt.
+    fn load(block_store: &BS, cid: &Cid) -> Result<NodeRef<'a, V>, Error> {
+        let node = block_store
+            .get(cid)?
+            .ok_or_else(|| Error::CidNotFound(cid.to_string()))?;
+        Ok(NodeRef::Owned(node))
+    }
+
+    /// Advances the walk from the front or the back, until the next value is found. Values
+    /// and links are taken out of nodes loaded for the walk, as each slot is only visited once.
+    fn step(&mut self, back: bool) -> Result<Option<(u64, V)>, Error> {
+        let block_store = self.block_store;
+        loop {
+            let stack = if back {
+                &mut self.back
+            } else {
+                &mut self.front
+            };
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(None),
+            };
+            let slot = if back {
+                frame.slots.next_back()
+            } else {
+                frame.slots.next()
+            };
+            let (height, offset) = (frame.height, frame.offset);
+            let slot = match slot {
+                Some(slot) => slot,
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            if !frame.node.node().bitmap().get_bit(slot) {
+                continue;
+            }
+
+            let child = match &mut frame.node {
+                NodeRef::Borrowed(node) => match *node {
+                    Node::Leaf { vals, .. } => {
+                        let value = vals[slot as usize]
+                            .clone()
+                            .expect("set bit should contain value");
+                        return Ok(Some((offset + slot, value)));
+                    }
+                    Node::Link { links, .. } => {
+                        match links[slot as usize].as_ref().expect("bit set at index") {
+                            Link::Dirty(node) => NodeRef::Borrowed(node),
+                            Link::Cid { cid, cache } => match cache.borrow() {
+                                Some(node) => NodeRef::Borrowed(node),
+                                None => Self::load(block_store, cid)?,
+                            },
+                        }
+                    }
+                },
+                NodeRef::Owned(node) => match node.as_mut() {
+                    Node::Leaf { vals, .. } => {
+                        let value = vals[slot as usize]
+                            .take()
+                            .expect("set bit should contain value");
+                        return Ok(Some((offset + slot, value)));
+                    }
+                    Node::Link { links, .. } => {
+                        match links[slot as usize].take().expect("bit set at index") {
+                            Link::Dirty(node) => NodeRef::Owned(node),
+                            Link::Cid { cid, .. } => Self::load(block_store, &cid)?,
+                        }
+                    }
+                },
+            };
+            let child_offset = offset + slot * nodes_for_height(height);
+            if let Some(frame) = self.frame(child, height - 1, child_offset) {
+                if back {
+                    self.back.push(frame);
+                } else {
+                    self.front.push(frame);
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.front.clear();
+        self.back.clear();
+        self.range.end = self.range.start;
+    }
+}
+
+impl<'a, V, BS> Iterator for Iter<'a, V, BS>
+where
+    V: Clone + DeserializeOwned,
+    BS: BlockStore,
+{
+    type Item = Result<(u64, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step(false) {
+            // Values already yielded from the back end the iteration
+            Ok(Some((i, v))) if i < self.range.end => {
+                self.range.start = i + 1;
+                Some(Ok((i, v)))
+            }
+            Ok(_) => {
+                self.finish();
+                None
+            }
+            Err(e) => {
+                self.finish();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a, V, BS> DoubleEndedIterator for Iter<'a, V, BS>
+where
+    V: Clone + DeserializeOwned,
+    BS: BlockStore,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self.step(true) {
+            Ok(Some((i, v))) if i >= self.range.start => {
+                self.range.end = i;
+                Some(Ok((i, v)))
+            }
+            Ok(_) => {
+                self.finish();
+                None
+            }
+            Err(e) => {
+                self.finish();
+                Some(Err(e))
+            }
+        }
+    }
+}
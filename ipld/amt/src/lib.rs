@@ -8,14 +8,18 @@
 
 mod amt;
 mod bitmap;
+mod diff;
 mod error;
+mod iter;
 mod node;
 mod root;
 mod value_mut;
 
 pub use self::amt::Amt;
 pub use self::bitmap::BitMap;
+pub use self::diff::{AmtDiff, Change};
 pub use self::error::Error;
+pub use self::iter::Iter;
 pub(crate) use self::node::Node;
 pub(crate) use self::root::Root;
 pub use self::value_mut::ValueMut;
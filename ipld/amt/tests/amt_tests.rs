@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use encoding::{de::DeserializeOwned, ser::Serialize};
-use ipld_amt::{Amt, Error, MAX_INDEX};
+use ipld_amt::{Amt, Change, Error, MAX_INDEX};
 use ipld_blockstore::{BSStats, BlockStore, TrackingBlockStore};
 use std::fmt::Debug;
 
@@ -421,3 +421,114 @@ fn delete_bug_test() {
     #[rustfmt::skip]
     assert_eq!(*db.stats.borrow(), BSStats {r: 1, w: 5, br: 8, bw: 124});
 }
+
+#[test]
+fn diff() {
+    let mem = db::MemoryDB::default();
+    let db = TrackingBlockStore::new(&mem);
+
+    let mut before = Amt::new(&db);
+    for i in 0..600 {
+        before.set(i, i).unwrap();
+    }
+    let c = before.flush().unwrap();
+
+    let mut after: Amt<u64, _> = Amt::load(&c, &db).unwrap();
+    after.delete(5).unwrap();
+    after.set(300, 3).unwrap();
+    // Grows the tree by one level
+    after.set(5000, 5000).unwrap();
+    after.flush().unwrap();
+    assert!(after.height() > before.height());
+
+    let reads = db.stats.borrow().r;
+    let changes: Vec<_> = before.diff(&after).unwrap().map(|c| c.unwrap()).collect();
+    assert_eq!(
+        changes,
+        vec![
+            Change::Removed(5, 5),
+            Change::Modified(300, 300, 3),
+            Change::Added(5000, 5000),
+        ]
+    );
+    // Only the nodes along the changed paths are loaded
+    assert!(db.stats.borrow().r - reads < 20);
+
+    let changes: Vec<_> = after.diff(&before).unwrap().map(|c| c.unwrap()).collect();
+    assert_eq!(changes[0], Change::Added(5, 5));
+    assert_eq!(changes[2], Change::Removed(5000, 5000));
+    assert_eq!(before.diff(&before).unwrap().count(), 0);
+}
+
+#[test]
+fn iter_range() {
+    let mem = db::MemoryDB::default();
+    let db = TrackingBlockStore::new(&mem);
+
+    let mut a = Amt::new(&db);
+    assert_eq!(a.first().unwrap(), None);
+    assert_eq!(a.last().unwrap(), None);
+    assert_eq!(a.iter().count(), 0);
+
+    for i in 0..1000 {
+        a.set(i * 3, i).unwrap();
+    }
+    let c = a.flush().unwrap();
+
+    let a: Amt<u64, _> = Amt::load(&c, &db).unwrap();
+    let all: Vec<_> = a.iter().map(|r| r.unwrap()).collect();
+    assert_eq!(all.len(), 1000);
+    assert!(all
+        .iter()
+        .enumerate()
+        .all(|(i, (k, v))| *k == i as u64 * 3 && *v == i as u64));
+
+    let a: Amt<u64, _> = Amt::load(&c, &db).unwrap();
+    let reads = db.stats.borrow().r;
+    let window: Vec<_> = a.iter_range(1500..1510).map(|r| r.unwrap().0).collect();
+    assert_eq!(window, vec![1500, 1503, 1506, 1509]);
+    // Only the nodes on the path to the window are loaded
+    assert!(db.stats.borrow().r - reads <= 4);
+
+    let rev: Vec<_> = a.iter_range(..=9).rev().map(|r| r.unwrap().0).collect();
+    assert_eq!(rev, vec![9, 6, 3, 0]);
+    assert_eq!(a.iter_range(2998..).count(), 0);
+    assert_eq!(a.first().unwrap(), Some(0));
+    assert_eq!(a.last().unwrap(), Some(2997));
+
+    // Both ends meet without yielding values twice
+    let mut iter = a.iter_range(0..12);
+    assert_eq!(iter.next().unwrap().unwrap().0, 0);
+    assert_eq!(iter.next_back().unwrap().unwrap().0, 9);
+    assert_eq!(iter.next_back().unwrap().unwrap().0, 6);
+    assert_eq!(iter.next().unwrap().unwrap().0, 3);
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+}
+
+#[test]
+fn iter_does_not_cache_nodes() {
+    let mem = db::MemoryDB::default();
+    let db = TrackingBlockStore::new(&mem);
+
+    let mut a = Amt::new(&db);
+    for i in 0..1000 {
+        a.set(i * 3, i).unwrap();
+    }
+    let c = a.flush().unwrap();
+
+    // Every walk reads the nodes from the store again, as none are kept in the Amt's caches
+    let a: Amt<u64, _> = Amt::load(&c, &db).unwrap();
+    let reads = db.stats.borrow().r;
+    assert_eq!(a.iter().count(), 1000);
+    let walk_reads = db.stats.borrow().r - reads;
+    assert!(walk_reads > 0);
+    assert_eq!(a.iter().rev().count(), 1000);
+    assert_eq!(db.stats.borrow().r - reads, 2 * walk_reads);
+
+    // Nodes cached by a lookup are walked in place
+    a.get(0).unwrap();
+    let reads = db.stats.borrow().r;
+    assert_eq!(a.iter().count(), 1000);
+    assert!(db.stats.borrow().r - reads < walk_reads);
+}
@@ -9,7 +9,7 @@ use db::{Error, Store};
 use encoding::from_slice;
 use forest_ipld::Ipld;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error as StdError;
 
 /// Wrapper around `BlockStore` to limit and have control over when values are written.
@@ -34,18 +34,36 @@ where
     /// This will recursively traverse the cache and write all data connected by links to this
     /// root Cid.
     pub fn flush(&mut self, root: &Cid) -> Result<(), Box<dyn StdError>> {
-        write_recursive(self.base, &self.write.borrow(), root)?;
+        self.flush_roots(&[*root])
+    }
+
+    /// Flushes the buffered blocks connected by links to any of the roots, in a single batch
+    /// write to the base store. Links to blocks which are in neither this store nor the base
+    /// store are an error.
+    pub fn flush_roots(&mut self, roots: &[Cid]) -> Result<(), Box<dyn StdError>> {
+        {
+            let cache = self.write.borrow();
+            let mut visited = HashSet::new();
+            let mut batch = Vec::new();
+            for root in roots {
+                collect_recursive(self.base, &cache, root, &mut visited, &mut batch)?;
+            }
+            self.base.bulk_write(&batch)?;
+        }
 
         self.write = Default::default();
         Ok(())
     }
 }
 
-/// Recursively traverses cache through Cid links.
-fn write_recursive<BS>(
+/// Recursively traverses cache through Cid links, collecting the blocks which are not in the
+/// base store.
+fn collect_recursive<'c, BS>(
     base: &BS,
-    cache: &HashMap<Cid, Vec<u8>>,
+    cache: &'c HashMap<Cid, Vec<u8>>,
     cid: &Cid,
+    visited: &mut HashSet<Cid>,
+    batch: &mut Vec<(Vec<u8>, &'c Vec<u8>)>,
 ) -> Result<(), Box<dyn StdError>>
 where
     BS: BlockStore,
@@ -55,6 +73,10 @@ where
         return Ok(());
     }
 
+    if !visited.insert(*cid) {
+        return Ok(());
+    }
+
     let raw_cid_bz = cid.to_bytes();
 
     // If root exists in base store already, can skip
@@ -71,29 +93,30 @@ where
     // but slightly slower to copy and potentially allocate non Cid data.
     let block: Ipld = from_slice(raw_bz)?;
 
-    // Traverse and write linked data recursively
-    for_each_link(&block, &|c| write_recursive(base, cache, c))?;
+    // Traverse and collect linked data recursively
+    for_each_link(&block, &mut |c| {
+        collect_recursive(base, cache, c, visited, batch)
+    })?;
 
-    // Write the root node to base storage
-    base.write(&raw_cid_bz, raw_bz)?;
+    batch.push((raw_cid_bz, raw_bz));
     Ok(())
 }
 
 /// Recursively explores Ipld for links and calls a function with a reference to the Cid.
-fn for_each_link<F>(ipld: &Ipld, cb: &F) -> Result<(), Box<dyn StdError>>
+fn for_each_link<F>(ipld: &Ipld, cb: &mut F) -> Result<(), Box<dyn StdError>>
 where
-    F: Fn(&Cid) -> Result<(), Box<dyn StdError>>,
+    F: FnMut(&Cid) -> Result<(), Box<dyn StdError>>,
 {
     match ipld {
         Ipld::Link(c) => cb(&c)?,
         Ipld::List(arr) => {
             for item in arr {
-                for_each_link(item, cb)?
+                for_each_link(item, &mut *cb)?
             }
         }
         Ipld::Map(map) => {
             for v in map.values() {
-                for_each_link(v, cb)?
+                for_each_link(v, &mut *cb)?
             }
         }
         _ => (),
@@ -252,4 +275,27 @@ mod tests {
         assert_eq!(mem.get::<u8>(&unconnected).unwrap(), None);
         assert_eq!(buf_store.get::<u8>(&unconnected).unwrap(), None);
     }
+
+    #[test]
+    fn flush_links_outside_buffer() {
+        let mem = db::MemoryDB::default();
+        let stored = mem.put(&1u8, Code::Blake2b256).unwrap();
+        let missing = db::MemoryDB::default().put(&2u8, Code::Blake2b256).unwrap();
+
+        // Links to blocks already in the base store are not written again
+        let mut buf_store = BufferedBlockStore::new(&mem);
+        let root = buf_store
+            .put(&ipld!([Link(stored)]), Code::Blake2b256)
+            .unwrap();
+        buf_store.flush(&root).unwrap();
+        assert_eq!(mem.get::<Ipld>(&root).unwrap(), Some(ipld!([Link(stored)])));
+
+        // Links to blocks in neither store are invalid
+        let mut buf_store = BufferedBlockStore::new(&mem);
+        let root = buf_store
+            .put(&ipld!([Link(missing)]), Code::Blake2b256)
+            .unwrap();
+        assert!(buf_store.flush(&root).is_err());
+        assert_eq!(mem.get::<Ipld>(&root).unwrap(), None);
+    }
 }
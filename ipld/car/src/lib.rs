@@ -5,12 +5,14 @@ mod error;
 mod util;
 
 use blockstore::BlockStore;
-use cid::Cid;
+use cid::{Cid, Codec};
 use error::*;
-use forest_encoding::from_slice;
+use forest_encoding::{from_slice, to_vec};
+use forest_ipld::Ipld;
 use serde::{Deserialize, Serialize};
-use std::io::Read;
-use util::{ld_read, read_node};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use util::{ld_read, ld_write, read_node, write_node};
 
 /// CAR file header
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -83,3 +85,45 @@ pub fn load_car<R: Read, B: BlockStore>(s: &B, reader: R) -> Result<Vec<Cid>, Er
         .map_err(|e| Error::Other(e.to_string()))?;
     Ok(car_reader.header.roots)
 }
+
+/// Writes the DAGs under the roots from a BlockStore to a CAR file. Each block is written once,
+/// and links to identity and Filecoin commitment Cids are not followed.
+pub fn write_car<W: Write, B: BlockStore>(
+    s: &B,
+    roots: Vec<Cid>,
+    mut writer: W,
+) -> Result<(), Error> {
+    let header = CarHeader::new(roots, 1);
+    let bz = to_vec(&header).map_err(|e| Error::Other(e.to_string()))?;
+    ld_write(&mut writer, &bz)?;
+
+    let mut visited = HashSet::new();
+    let mut stack: Vec<Cid> = header.roots.iter().rev().copied().collect();
+    while let Some(cid) = stack.pop() {
+        if cid.codec != Codec::DagCBOR || !visited.insert(cid) {
+            continue;
+        }
+        let data = s
+            .get_bytes(&cid)
+            .map_err(|e| Error::Other(e.to_string()))?
+            .ok_or_else(|| Error::Other(format!("block {} not found in store", cid)))?;
+        write_node(&mut writer, &cid, &data)?;
+
+        let ipld: Ipld = from_slice(&data).map_err(|e| Error::ParsingError(e.to_string()))?;
+        let mut links = Vec::new();
+        push_links(&ipld, &mut links);
+        // Pushed in reverse, so blocks are written in depth first order
+        stack.extend(links.into_iter().rev());
+    }
+    Ok(())
+}
+
+/// Collects the links of an Ipld value, in order.
+fn push_links(ipld: &Ipld, links: &mut Vec<Cid>) {
+    match ipld {
+        Ipld::Link(c) => links.push(*c),
+        Ipld::List(arr) => arr.iter().for_each(|item| push_links(item, links)),
+        Ipld::Map(map) => map.values().for_each(|v| push_links(v, links)),
+        _ => (),
+    }
+}
@@ -3,7 +3,7 @@
 
 use super::error::Error;
 use cid::Cid;
-use std::io::Read;
+use std::io::{Read, Write};
 use unsigned_varint::io::ReadError;
 
 pub(crate) fn ld_read<R: Read>(mut reader: &mut R) -> Result<Option<Vec<u8>>, Error> {
@@ -26,6 +26,20 @@ pub(crate) fn ld_read<R: Read>(mut reader: &mut R) -> Result<Option<Vec<u8>>, Er
     Ok(Some(buf))
 }
 
+pub(crate) fn ld_write<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Error> {
+    let mut buf = unsigned_varint::encode::u64_buffer();
+    writer
+        .write_all(unsigned_varint::encode::u64(bytes.len() as u64, &mut buf))
+        .and_then(|_| writer.write_all(bytes))
+        .map_err(|e| Error::Other(e.to_string()))
+}
+
+pub(crate) fn write_node<W: Write>(writer: &mut W, cid: &Cid, data: &[u8]) -> Result<(), Error> {
+    let mut buf = cid.to_bytes();
+    buf.extend_from_slice(data);
+    ld_write(writer, &buf)
+}
+
 pub(crate) fn read_node<R: Read>(buf_reader: &mut R) -> Result<Option<(Cid, Vec<u8>)>, Error> {
     match ld_read(buf_reader)? {
         Some(buf) => {
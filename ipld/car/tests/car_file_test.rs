@@ -1,6 +1,7 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use blockstore::BlockStore;
 use db::MemoryDB;
 use forest_car::*;
 use std::fs::File;
@@ -14,3 +15,19 @@ fn load_into_blockstore() {
 
     let _ = load_car(&mut bs, buf_reader).unwrap();
 }
+
+#[test]
+fn write_and_load() {
+    let file = File::open("tests/test.car").unwrap();
+    let bs = MemoryDB::default();
+    let roots = load_car(&bs, BufReader::new(file)).unwrap();
+
+    let mut buf = Vec::new();
+    write_car(&bs, roots.clone(), &mut buf).unwrap();
+
+    let copy = MemoryDB::default();
+    assert_eq!(load_car(&copy, buf.as_slice()).unwrap(), roots);
+    for root in &roots {
+        assert_eq!(copy.get_bytes(root).unwrap(), bs.get_bytes(root).unwrap());
+    }
+}
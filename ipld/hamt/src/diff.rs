@@ -0,0 +1,239 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::hash_bits::HashBits;
+use super::node::Node;
+use super::pointer::Pointer;
+use super::{Error, Hash, HashAlgorithm, HashedKey, KeyValuePair};
+use ipld_blockstore::BlockStore;
+use serde::de::DeserializeOwned;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Change of an entry between two Hamts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<K, V> {
+    /// Entry only set in the second Hamt.
+    Added(K, V),
+    /// Entry only set in the first Hamt.
+    Removed(K, V),
+    /// Entry set in both Hamts with a different value, before and after.
+    Modified(K, V, V),
+}
+
+impl<K, V> Change<K, V> {
+    /// Returns the key of the changed entry.
+    pub fn key(&self) -> &K {
+        match self {
+            Change::Added(k, _) | Change::Removed(k, _) | Change::Modified(k, _, _) => k,
+        }
+    }
+}
+
+/// Pointers at the same index of nodes of both trees which are left to compare.
+struct Item<K, V, H> {
+    /// Depth of the nodes holding the pointers, the root being at depth 0.
+    depth: u32,
+    before: Option<Pointer<K, V, H>>,
+    after: Option<Pointer<K, V, H>>,
+}
+
+/// Iterator over the changes between two Hamts, created with
+/// [diff](struct.Hamt.html#method.diff). Changes are yielded in the order of their hashed keys.
+///
+/// Both trees are walked together from the root, and pointers to the same child node are
+/// skipped without loading it. Nodes are only loaded from the store as the iterator advances.
+/// When a bucket is compared with a subtree, the bucket is split over the indexes of the next
+/// depth, so the subtree is walked lazily like any other.
+pub struct HamtDiff<'a, BS, K, V, H> {
+    store: &'a BS,
+    bit_width: u32,
+    stack: Vec<Item<K, V, H>>,
+    /// Changes found which have not been yielded yet.
+    pending: VecDeque<Change<K, V>>,
+}
+
+impl<'a, BS, K, V, H> HamtDiff<'a, BS, K, V, H>
+where
+    BS: BlockStore,
+    K: Hash + PartialEq + DeserializeOwned,
+    V: PartialEq + DeserializeOwned,
+    H: HashAlgorithm,
+{
+    pub(crate) fn new(
+        store: &'a BS,
+        bit_width: u32,
+        before: Node<K, V, H>,
+        after: Node<K, V, H>,
+    ) -> Self {
+        let mut diff = Self {
+            store,
+            bit_width,
+            stack: Vec::new(),
+            pending: VecDeque::new(),
+        };
+        diff.push_nodes(0, before, after);
+        diff
+    }
+
+    fn load(&self, cid: &cid::Cid) -> Result<Node<K, V, H>, Error> {
+        self.store
+            .get(cid)?
+            .ok_or_else(|| Error::CidNotFound(cid.to_string()))
+    }
+
+    /// Queues the pointers of two nodes at the same depth, matched by index.
+    fn push_nodes(&mut self, depth: u32, before: Node<K, V, H>, after: Node<K, V, H>) {
+        let before = slots(before, self.bit_width);
+        let after = slots(after, self.bit_width);
+        // Pushed in reverse, so pointers are compared in index order
+        for (before, after) in before.into_iter().zip(after).rev() {
+            if before.is_some() || after.is_some() {
+                self.stack.push(Item {
+                    depth,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+
+    /// Returns the node a pointer of a node at the given depth stands for. A bucket is split
+    /// into a node of the depth below, and a missing pointer is an empty node.
+    fn resolve(
+        &self,
+        pointer: Option<Pointer<K, V, H>>,
+        depth: u32,
+    ) -> Result<Node<K, V, H>, Error> {
+        match pointer {
+            None => Ok(Node::default()),
+            Some(Pointer::Link { cid, .. }) => self.load(&cid),
+            Some(Pointer::Dirty(node)) => Ok(*node),
+            Some(Pointer::Values(kvs)) => {
+                let mut buckets: BTreeMap<u32, Vec<KeyValuePair<K, V>>> = BTreeMap::new();
+                for kv in kvs {
+                    let hash = H::hash(kv.key());
+                    let idx = HashBits::new_at_index(&hash, (depth + 1) * self.bit_width)
+                        .next(self.bit_width)?;
+                    buckets.entry(idx).or_default().push(kv);
+                }
+                let mut node = Node::default();
+                for (idx, kvs) in buckets {
+                    node.bitfield.set_bit(idx);
+                    node.pointers.push(Pointer::Values(kvs));
+                }
+                Ok(node)
+            }
+        }
+    }
+
+    /// Compares the entries of two buckets, merged in the order of their hashed keys.
+    fn diff_buckets(&mut self, before: Vec<KeyValuePair<K, V>>, after: Vec<KeyValuePair<K, V>>) {
+        let mut before = sorted_by_hash::<K, V, H>(before).into_iter().peekable();
+        let mut after = sorted_by_hash::<K, V, H>(after).into_iter().peekable();
+        loop {
+            let order = match (before.peek(), after.peek()) {
+                (Some(b), Some(a)) => b.0.cmp(&a.0),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return,
+            };
+            let change = match order {
+                Ordering::Less => {
+                    let (_, k, v) = before.next().expect("peeked above");
+                    Change::Removed(k, v)
+                }
+                Ordering::Greater => {
+                    let (_, k, v) = after.next().expect("peeked above");
+                    Change::Added(k, v)
+                }
+                Ordering::Equal => {
+                    let (_, k, old) = before.next().expect("peeked above");
+                    let (_, _, new) = after.next().expect("peeked above");
+                    if old == new {
+                        continue;
+                    }
+                    Change::Modified(k, old, new)
+                }
+            };
+            self.pending.push_back(change);
+        }
+    }
+
+    fn step(&mut self, item: Item<K, V, H>) -> Result<(), Error> {
+        match (item.before, item.after) {
+            (Some(Pointer::Link { cid: b, .. }), Some(Pointer::Link { cid: a, .. })) if b == a => {}
+            (b, a) if is_bucket(&b) && is_bucket(&a) => {
+                let into_kvs = |p: Option<Pointer<K, V, H>>| match p {
+                    Some(Pointer::Values(kvs)) => kvs,
+                    _ => Vec::new(),
+                };
+                self.diff_buckets(into_kvs(b), into_kvs(a));
+            }
+            (b, a) => {
+                let before = self.resolve(b, item.depth)?;
+                let after = self.resolve(a, item.depth)?;
+                self.push_nodes(item.depth + 1, before, after);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if the pointer is a bucket of values, or missing.
+fn is_bucket<K, V, H>(pointer: &Option<Pointer<K, V, H>>) -> bool {
+    matches!(pointer, None | Some(Pointer::Values(_)))
+}
+
+/// Sorts the entries of a bucket by their hashed keys, which are equal only for equal keys.
+fn sorted_by_hash<K, V, H>(kvs: Vec<KeyValuePair<K, V>>) -> Vec<(HashedKey, K, V)>
+where
+    K: Hash,
+    H: HashAlgorithm,
+{
+    let mut entries: Vec<_> = kvs
+        .into_iter()
+        .map(|KeyValuePair(k, v)| (H::hash(&k), k, v))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+/// Spreads the pointers of a node over the indexes set in its bitfield.
+fn slots<K, V, H>(node: Node<K, V, H>, bit_width: u32) -> Vec<Option<Pointer<K, V, H>>> {
+    let bitfield = node.bitfield;
+    let mut pointers = node.pointers.into_iter();
+    (0..1u32 << bit_width)
+        .map(|i| {
+            if bitfield.test_bit(i) {
+                pointers.next()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+impl<'a, BS, K, V, H> Iterator for HamtDiff<'a, BS, K, V, H>
+where
+    BS: BlockStore,
+    K: Hash + PartialEq + DeserializeOwned,
+    V: PartialEq + DeserializeOwned,
+    H: HashAlgorithm,
+{
+    type Item = Result<Change<K, V>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Some(Ok(change));
+            }
+            let item = self.stack.pop()?;
+            if let Err(e) = self.step(item) {
+                // Stop iterating after an error, the remaining changes can't be trusted
+                self.stack.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
@@ -2,9 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use super::BytesKey;
+use crate::diff::HamtDiff;
+use crate::iter::{Cursor, Iter};
 use crate::node::Node;
 use crate::{Error, Hash, HashAlgorithm, Sha256, DEFAULT_BIT_WIDTH};
 use cid::{Cid, Code::Blake2b256};
+use forest_encoding::{from_slice, to_vec, Error as EncodingError};
 use ipld_blockstore::BlockStore;
 use serde::{de::DeserializeOwned, Serialize, Serializer};
 use std::borrow::Borrow;
@@ -263,4 +266,129 @@ where
     {
         self.root.for_each(self.store, &mut f)
     }
+
+    /// Returns an iterator over the entries of the Hamt, in the order of their hashed keys.
+    /// Nodes are only loaded as the iterator advances.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipld_hamt::Hamt;
+    ///
+    /// let store = db::MemoryDB::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(&store);
+    /// map.set(1, 1).unwrap();
+    /// map.set(4, 2).unwrap();
+    ///
+    /// let total: u64 = map.iter().map(|r| r.unwrap().1).sum();
+    /// assert_eq!(total, 3);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, BS, K, V, H>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Iter::new(self.store, self.bit_width, &self.root, None)
+    }
+
+    /// Returns an iterator over the entries of the Hamt which come after a cursor, taken from
+    /// an earlier iteration with [cursor](struct.Iter.html#method.cursor).
+    pub fn iter_from(&self, cursor: &Cursor) -> Iter<'_, BS, K, V, H>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Iter::new(self.store, self.bit_width, &self.root, Some(*cursor))
+    }
+
+    /// Returns up to `n` entries of the Hamt which come after a cursor, or from the start if no
+    /// cursor is given. Also returns the cursor to resume from if there are entries left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipld_hamt::Hamt;
+    ///
+    /// let store = db::MemoryDB::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(&store);
+    /// for i in 0..5 {
+    ///     map.set(i, i).unwrap();
+    /// }
+    ///
+    /// let (page, cursor) = map.take(None, 3).unwrap();
+    /// assert_eq!(page.len(), 3);
+    /// let (page, cursor) = map.take(cursor.as_ref(), 3).unwrap();
+    /// assert_eq!(page.len(), 2);
+    /// assert!(cursor.is_none());
+    /// ```
+    pub fn take(
+        &self,
+        cursor: Option<&Cursor>,
+        n: usize,
+    ) -> Result<(Vec<(K, V)>, Option<Cursor>), Error>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut iter = Iter::new(self.store, self.bit_width, &self.root, cursor.copied());
+        let entries = iter.by_ref().take(n).collect::<Result<Vec<_>, _>>()?;
+        let cursor = iter.cursor();
+        let more = iter.next().transpose()?.is_some();
+        Ok((entries, if more { cursor } else { None }))
+    }
+
+    /// Returns an iterator over the changes from this Hamt to another. Subtrees with the same
+    /// Cid in both Hamts are skipped, and nodes are only loaded as the iterator advances.
+    ///
+    /// Both Hamts must have the same bit width and be flushed, as changes which have not been
+    /// flushed can't be compared by Cid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipld_hamt::{Change, Hamt};
+    ///
+    /// let store = db::MemoryDB::default();
+    ///
+    /// let mut before: Hamt<_, _, usize> = Hamt::new(&store);
+    /// before.set(1, "a".to_string()).unwrap();
+    /// before.set(2, "b".to_string()).unwrap();
+    /// before.flush().unwrap();
+    ///
+    /// let mut after: Hamt<_, _, usize> = Hamt::new(&store);
+    /// after.set(2, "c".to_string()).unwrap();
+    /// after.flush().unwrap();
+    ///
+    /// let mut changes: Vec<_> = before.diff(&after).unwrap().map(|c| c.unwrap()).collect();
+    /// changes.sort_by_key(|c| *c.key());
+    /// assert_eq!(changes, vec![
+    ///     Change::Removed(1, "a".to_string()),
+    ///     Change::Modified(2, "b".to_string(), "c".to_string()),
+    /// ]);
+    /// ```
+    pub fn diff(&self, other: &Self) -> Result<HamtDiff<'a, BS, K, V, H>, Error>
+    where
+        V: PartialEq,
+    {
+        if self.bit_width != other.bit_width {
+            return Err(Error::Other(format!(
+                "can't diff Hamts of bit widths {} and {}",
+                self.bit_width, other.bit_width
+            )));
+        }
+        // Roots are copied through their encoding, which also checks they are flushed
+        let before = to_vec(&self.root).map_err(EncodingError::from)?;
+        let after = to_vec(&other.root).map_err(EncodingError::from)?;
+        let (before, after) = if before == after {
+            (Node::default(), Node::default())
+        } else {
+            (
+                from_slice(&before).map_err(EncodingError::from)?,
+                from_slice(&after).map_err(EncodingError::from)?,
+            )
+        };
+        Ok(HamtDiff::new(self.store, self.bit_width, before, after))
+    }
 }
@@ -0,0 +1,269 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::hash_bits::HashBits;
+use super::node::Node;
+use super::pointer::Pointer;
+use super::{Error, Hash, HashAlgorithm, HashedKey, KeyValuePair};
+use cid::Cid;
+use ipld_blockstore::BlockStore;
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::{slice, vec};
+
+/// Position of an entry in the iteration order of a Hamt, which is the order of the hashed
+/// keys. An iteration can be resumed after the entry with
+/// [iter_from](struct.Hamt.html#method.iter_from), even if the Hamt was modified since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor(HashedKey);
+
+impl Serialize for Cursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_bytes::Bytes::new(&self.0).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = serde_bytes::ByteBuf::deserialize(deserializer)?.into_vec();
+        let hash = HashedKey::try_from(bytes.as_slice())
+            .map_err(|_| de::Error::custom("Hamt cursor must be 32 bytes"))?;
+        Ok(Self(hash))
+    }
+}
+
+/// Pointer of a node being walked.
+enum Entry<'a, K, V, H> {
+    /// Pointer of a node of the Hamt.
+    Borrowed(&'a Pointer<K, V, H>),
+    /// Pointer taken from a node loaded for the walk.
+    Owned(Pointer<K, V, H>),
+}
+
+/// Node being walked, either from the Hamt or loaded from the store for the walk only.
+enum NodeRef<'a, K, V, H> {
+    Borrowed(&'a Node<K, V, H>),
+    Owned(Node<K, V, H>),
+}
+
+impl<'a, K, V, H> NodeRef<'a, K, V, H> {
+    fn node(&self) -> &Node<K, V, H> {
+        match self {
+            NodeRef::Borrowed(node) => *node,
+            NodeRef::Owned(node) => node,
+        }
+    }
+
+    fn into_frame(self) -> Frame<'a, K, V, H> {
+        match self {
+            NodeRef::Borrowed(node) => Frame::Borrowed(node.pointers.iter()),
+            NodeRef::Owned(node) => Frame::Owned(node.pointers.into_iter()),
+        }
+    }
+}
+
+/// Pointers of a node being walked which are left to visit.
+enum Frame<'a, K, V, H> {
+    Borrowed(slice::Iter<'a, Pointer<K, V, H>>),
+    Owned(vec::IntoIter<Pointer<K, V, H>>),
+}
+
+impl<'a, K, V, H> Iterator for Frame<'a, K, V, H> {
+    type Item = Entry<'a, K, V, H>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Frame::Borrowed(pointers) => pointers.next().map(Entry::Borrowed),
+            Frame::Owned(pointers) => pointers.next().map(Entry::Owned),
+        }
+    }
+}
+
+/// Iterator over the entries of a Hamt in the order of their hashed keys. Created with
+/// [iter](struct.Hamt.html#method.iter) or [iter_from](struct.Hamt.html#method.iter_from).
+///
+/// Nodes are loaded as the iterator advances, so the iteration can be stopped early without
+/// loading the rest of the tree. Loaded nodes are owned by the iterator and dropped once
+/// walked, rather than cached in the Hamt, so a full walk doesn't keep the whole tree in
+/// memory. Entries are yielded by value, cloned if they are held by the Hamt itself.
+pub struct Iter<'a, BS, K, V, H> {
+    store: &'a BS,
+    bit_width: u32,
+    root: &'a Node<K, V, H>,
+    /// Position to resume from, before the first entry is yielded.
+    resume: Option<Cursor>,
+    /// Nodes being walked, with the pointers of each which are left to visit.
+    stack: Vec<Frame<'a, K, V, H>>,
+    /// Entries of the current bucket which are left to yield, in reverse hash order.
+    bucket: Vec<(HashedKey, K, V)>,
+    last: Option<Cursor>,
+}
+
+impl<'a, BS, K, V, H> Iter<'a, BS, K, V, H>
+where
+    BS: BlockStore,
+    K: Hash + Clone + DeserializeOwned,
+    V: Clone + DeserializeOwned,
+    H: HashAlgorithm,
+{
+    pub(crate) fn new(
+        store: &'a BS,
+        bit_width: u32,
+        root: &'a Node<K, V, H>,
+        resume: Option<Cursor>,
+    ) -> Self {
+        let stack = if resume.is_none() {
+            vec![Frame::Borrowed(root.pointers.iter())]
+        } else {
+            Vec::new()
+        };
+        Self {
+            store,
+            bit_width,
+            root,
+            resume,
+            stack,
+            bucket: Vec::new(),
+            last: resume,
+        }
+    }
+
+    /// Returns the position of the last entry yielded, from which the iteration can be resumed.
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.last
+    }
+
+    /// Loads a node from the store without caching it. Returns `None` if the node is missing
+    /// and dead links are ignored.
+    fn load(&self, cid: &Cid) -> Result<Option<NodeRef<'a, K, V, H>>, Error> {
+        match self.store.get(cid)? {
+            Some(node) => Ok(Some(NodeRef::Owned(node))),
+            None => {
+                #[cfg(not(feature = "ignore-dead-links"))]
+                return Err(Error::CidNotFound(cid.to_string()));
+
+                #[cfg(feature = "ignore-dead-links")]
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Queues the entries of a bucket which come after a position, or returns the node linked
+    /// to. Nodes already cached by the Hamt are walked in place.
+    fn visit(
+        &mut self,
+        entry: Entry<'a, K, V, H>,
+        after: Option<&HashedKey>,
+    ) -> Result<Option<NodeRef<'a, K, V, H>>, Error> {
+        match entry {
+            Entry::Borrowed(Pointer::Values(kvs)) => {
+                let kvs = kvs.iter().map(|kv| (kv.key().clone(), kv.value().clone()));
+                self.fill_bucket(kvs, after);
+                Ok(None)
+            }
+            Entry::Owned(Pointer::Values(kvs)) => {
+                let kvs = kvs.into_iter().map(|KeyValuePair(k, v)| (k, v));
+                self.fill_bucket(kvs, after);
+                Ok(None)
+            }
+            Entry::Borrowed(Pointer::Link { cid, cache }) => match cache.borrow() {
+                Some(node) => Ok(Some(NodeRef::Borrowed(node))),
+                None => self.load(cid),
+            },
+            Entry::Owned(Pointer::Link { cid, .. }) => self.load(&cid),
+            Entry::Borrowed(Pointer::Dirty(node)) => Ok(Some(NodeRef::Borrowed(node))),
+            Entry::Owned(Pointer::Dirty(node)) => Ok(Some(NodeRef::Owned(*node))),
+        }
+    }
+
+    /// Queues the entries of a bucket which come after a position.
+    fn fill_bucket(&mut self, kvs: impl Iterator<Item = (K, V)>, after: Option<&HashedKey>) {
+        self.bucket = kvs
+            .map(|(k, v)| (H::hash(&k), k, v))
+            .filter(|(hash, _, _)| after.map_or(true, |after| hash > after))
+            .collect();
+        self.bucket.sort_by(|a, b| b.0.cmp(&a.0));
+    }
+
+    /// Descends along the path of a position, leaving the walk at the first entry after it.
+    fn seek(&mut self, cursor: &Cursor) -> Result<(), Error> {
+        let mut hash_bits = HashBits::new(&cursor.0);
+        let mut node = NodeRef::Borrowed(self.root);
+        loop {
+            let idx = hash_bits.next(self.bit_width)?;
+            let (set, i) = (
+                node.node().bitfield.test_bit(idx),
+                node.node().index_for_bit_pos(idx),
+            );
+            let mut frame = node.into_frame();
+            if !set {
+                if i > 0 {
+                    frame.nth(i - 1);
+                }
+                self.stack.push(frame);
+                return Ok(());
+            }
+            let entry = frame.nth(i).expect("bit set at index");
+            self.stack.push(frame);
+            node = match self.visit(entry, Some(&cursor.0))? {
+                Some(node) => node,
+                None => return Ok(()),
+            };
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<(K, V)>, Error> {
+        if let Some(cursor) = self.resume.take() {
+            self.seek(&cursor)?;
+        }
+        loop {
+            if let Some((hash, k, v)) = self.bucket.pop() {
+                self.last = Some(Cursor(hash));
+                return Ok(Some((k, v)));
+            }
+            let entry = match self.stack.last_mut() {
+                Some(frame) => frame.next(),
+                None => return Ok(None),
+            };
+            match entry {
+                None => {
+                    self.stack.pop();
+                }
+                Some(entry) => {
+                    if let Some(node) = self.visit(entry, None)? {
+                        self.stack.push(node.into_frame());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, BS, K, V, H> Iterator for Iter<'a, BS, K, V, H>
+where
+    BS: BlockStore,
+    K: Hash + Clone + DeserializeOwned,
+    V: Clone + DeserializeOwned,
+    H: HashAlgorithm,
+{
+    type Item = Result<(K, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step() {
+            Ok(entry) => entry.map(Ok),
+            Err(e) => {
+                // Stop iterating after an error, the walk can't be resumed from the same node
+                self.stack.clear();
+                self.bucket.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
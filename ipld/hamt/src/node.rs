@@ -436,7 +436,7 @@ where
             .insert(i as usize, Pointer::from_key_value(key, value))
     }
 
-    fn index_for_bit_pos(&self, bp: u32) -> usize {
+    pub(crate) fn index_for_bit_pos(&self, bp: u32) -> usize {
         let mask = Bitfield::zero().set_bits_le(bp);
         assert_eq!(mask.count_ones(), bp as usize);
         mask.and(&self.bitfield).count_ones()
@@ -1,7 +1,7 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use ipld_hamt::Hamt;
+use ipld_hamt::{Change, Cursor, Hamt};
 
 use cid::Code::Blake2b256;
 use ipld_blockstore::{BSStats, BlockStore, TrackingBlockStore};
@@ -442,3 +442,148 @@ fn clean_child_ordering() {
     #[cfg(feature = "go-interop")]
     assert_eq!(*store.stats.borrow(), BSStats {r: 9, w: 17, br: 2327, bw: 2845});
 }
+
+#[test]
+fn diff() {
+    let mem = db::MemoryDB::default();
+    let store = TrackingBlockStore::new(&mem);
+
+    let mut before: Hamt<_, _, u64> = Hamt::new_with_bit_width(&store, 5);
+    for i in 0..500 {
+        before.set(i, i).unwrap();
+    }
+    let root = before.flush().unwrap();
+
+    let mut after: Hamt<_, _, u64> = Hamt::load_with_bit_width(&root, &store, 5).unwrap();
+    after.delete(&3).unwrap();
+    after.set(7, 70).unwrap();
+    after.set(600, 600).unwrap();
+    after.flush().unwrap();
+
+    let reads = store.stats.borrow().r;
+    let mut changes: Vec<_> = before.diff(&after).unwrap().map(|c| c.unwrap()).collect();
+    changes.sort_by_key(|c| *c.key());
+    assert_eq!(
+        changes,
+        vec![
+            Change::Removed(3, 3),
+            Change::Modified(7, 7, 70),
+            Change::Added(600, 600),
+        ]
+    );
+    // Only the nodes along the changed paths are loaded
+    assert!(store.stats.borrow().r - reads < 20);
+
+    assert_eq!(before.diff(&before).unwrap().count(), 0);
+    assert_eq!(after.diff(&before).unwrap().count(), 3);
+}
+
+#[test]
+fn diff_bucket_against_subtree() {
+    let mem = db::MemoryDB::default();
+    let store = TrackingBlockStore::new(&mem);
+
+    let mut before: Hamt<_, _, u64> = Hamt::new_with_bit_width(&store, 5);
+    for i in 0..500 {
+        before.set(i, i).unwrap();
+    }
+    before.flush().unwrap();
+
+    // Few enough entries to only hold buckets at the root
+    let mut after: Hamt<_, _, u64> = Hamt::new_with_bit_width(&store, 5);
+    after.set(0, 0).unwrap();
+    after.set(1, 1).unwrap();
+    after.set(2, 20).unwrap();
+    after.flush().unwrap();
+
+    let changes: Vec<_> = before.diff(&after).unwrap().map(|c| c.unwrap()).collect();
+    assert_eq!(changes.len(), 498);
+    assert!(changes.contains(&Change::Modified(2, 2, 20)));
+    assert!(changes
+        .iter()
+        .all(|c| matches!(c, Change::Removed(k, _) if *k >= 3) || c.key() == &2));
+
+    let changes: Vec<_> = after.diff(&before).unwrap().map(|c| c.unwrap()).collect();
+    assert_eq!(changes.len(), 498);
+    assert!(changes.contains(&Change::Modified(2, 20, 2)));
+
+    // The subtrees are walked lazily rather than collected up front
+    let reads = store.stats.borrow().r;
+    assert!(before.diff(&after).unwrap().next().is_some());
+    assert!(store.stats.borrow().r - reads < 5);
+}
+
+#[test]
+fn iter_with_cursor() {
+    let mem = db::MemoryDB::default();
+    let store = TrackingBlockStore::new(&mem);
+
+    let mut hamt: Hamt<_, _, u64> = Hamt::new_with_bit_width(&store, 5);
+    assert!(hamt.iter().next().is_none());
+    for i in 0..300 {
+        hamt.set(i, i * 2).unwrap();
+    }
+    let root = hamt.flush().unwrap();
+
+    let hamt: Hamt<_, u64, u64> = Hamt::load_with_bit_width(&root, &store, 5).unwrap();
+    let mut all: Vec<_> = hamt.iter().map(|r| r.unwrap()).collect();
+    assert_eq!(all.len(), 300);
+    assert!(all.iter().all(|(k, v)| *v == *k * 2));
+
+    // Pages cover every entry once, in the same order as a full iteration
+    let mut paged = Vec::new();
+    let mut cursor = None;
+    loop {
+        let (page, next) = hamt.take(cursor.as_ref(), 64).unwrap();
+        paged.extend(page);
+        cursor = next;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(paged, all);
+
+    // A cursor survives a round trip through its encoding
+    let mut iter = hamt.iter();
+    iter.by_ref().take(100).for_each(|r| {
+        r.unwrap();
+    });
+    let cursor = iter.cursor().unwrap();
+    let bz = forest_encoding::to_vec(&cursor).unwrap();
+    let cursor: Cursor = forest_encoding::from_slice(&bz).unwrap();
+    let rest: Vec<_> = hamt.iter_from(&cursor).map(|r| r.unwrap()).collect();
+    assert_eq!(rest, all.split_off(100));
+
+    // Stopping early only loads part of the tree
+    let hamt: Hamt<_, u64, u64> = Hamt::load_with_bit_width(&root, &store, 5).unwrap();
+    let reads = store.stats.borrow().r;
+    assert_eq!(hamt.iter().take(3).count(), 3);
+    assert!(store.stats.borrow().r - reads < 5);
+}
+
+#[test]
+fn iter_does_not_cache_nodes() {
+    let mem = db::MemoryDB::default();
+    let store = TrackingBlockStore::new(&mem);
+
+    let mut hamt: Hamt<_, _, u64> = Hamt::new_with_bit_width(&store, 5);
+    for i in 0..300 {
+        hamt.set(i, i * 2).unwrap();
+    }
+    let root = hamt.flush().unwrap();
+
+    // Every walk reads the nodes from the store again, as none are kept in the Hamt's caches
+    let hamt: Hamt<_, u64, u64> = Hamt::load_with_bit_width(&root, &store, 5).unwrap();
+    let reads = store.stats.borrow().r;
+    assert_eq!(hamt.iter().count(), 300);
+    let walk_reads = store.stats.borrow().r - reads;
+    assert!(walk_reads > 0);
+    assert_eq!(hamt.iter().count(), 300);
+    assert_eq!(store.stats.borrow().r - reads, 2 * walk_reads);
+
+    // Nodes cached by a lookup are walked in place
+    hamt.get(&0).unwrap();
+    let reads = store.stats.borrow().r;
+    assert_eq!(hamt.iter().count(), 300);
+    assert!(store.stats.borrow().r - reads < walk_reads);
+}
@@ -0,0 +1,54 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Stand-in for an external signing service. Serves signing requests for the keys of a Forest
+//! keystore directory on a local socket, e.g.
+//! `local-signer ~/.forest unix:/tmp/forest-signer.sock`.
+//! The keystore is opened encrypted if `FOREST_KEYSTORE_PHRASE` is set. A TCP endpoint requires
+//! clients to send the token set in `FOREST_SIGNER_TOKEN`.
+
+use key_management::{serve_tcp, PersistentKeyStore, SignerEndpoint, SIGNER_TOKEN_ENV};
+use std::env;
+use std::net::TcpListener;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!(
+            "usage: {} <keystore-dir> <unix:path | tcp:host:port>",
+            args[0]
+        );
+        process::exit(1);
+    }
+
+    let keystore = match env::var("FOREST_KEYSTORE_PHRASE") {
+        Ok(phrase) => PersistentKeyStore::new_encrypted(args[1].clone(), &phrase),
+        Err(_) => PersistentKeyStore::new(args[1].clone()),
+    }
+    .expect("failed to open keystore");
+    let endpoint: SignerEndpoint = args[2].parse().expect("invalid signer endpoint");
+
+    let res = match endpoint {
+        SignerEndpoint::Tcp(addr) => {
+            let token = env::var(SIGNER_TOKEN_ENV).unwrap_or_else(|_| {
+                eprintln!("{} must be set to serve over tcp", SIGNER_TOKEN_ENV);
+                process::exit(1);
+            });
+            serve_tcp(
+                TcpListener::bind(addr).expect("failed to bind signer endpoint"),
+                &keystore,
+                &token,
+            )
+        }
+        #[cfg(unix)]
+        SignerEndpoint::Unix(path) => key_management::serve_unix(
+            std::os::unix::net::UnixListener::bind(path).expect("failed to bind signer endpoint"),
+            &keystore,
+        ),
+    };
+    if let Err(e) = res {
+        eprintln!("signer stopped: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::errors::Error;
+use argon2::{Config, ThreadMode, Variant, Version};
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::{rngs::OsRng, RngCore};
+use std::fmt;
+
+/// Magic bytes prefixing every encrypted keystore file.
+const MAGIC: &[u8] = b"FKS";
+/// Version of the encrypted keystore file layout.
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: u32 = 32;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+/// Symmetric key derived from the keystore passphrase with Argon2id. The salt is kept alongside
+/// the key so the keystore can be re-encrypted on every write without re-running the (slow) key
+/// derivation.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct EncryptionKey {
+    salt: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("salt", &self.salt)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl EncryptionKey {
+    /// Derives a new key from the passphrase, using a freshly generated salt.
+    pub fn generate(passphrase: &str) -> Result<Self, Error> {
+        let mut salt = vec![0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, salt)
+    }
+
+    fn derive(passphrase: &str, salt: Vec<u8>) -> Result<Self, Error> {
+        let config = Config {
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            mem_cost: 65536,
+            time_cost: 3,
+            lanes: 4,
+            thread_mode: ThreadMode::Parallel,
+            secret: &[],
+            ad: &[],
+            hash_length: KEY_LEN,
+        };
+        let key = argon2::hash_raw(passphrase.as_bytes(), &salt, &config)
+            .map_err(|e| Error::Encryption(format!("key derivation failed: {}", e)))?;
+        Ok(Self { salt, key })
+    }
+
+    /// Encrypts the plaintext, returning the bytes of the encrypted keystore file.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| Error::Encryption("failed to encrypt keystore".to_string()))?;
+
+        let mut out = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.salt);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    /// Decrypts the bytes of an encrypted keystore file, returning the key derived from the
+    /// passphrase along with the plaintext.
+    pub fn decrypt(passphrase: &str, data: &[u8]) -> Result<(Self, Vec<u8>), Error> {
+        if !is_encrypted(data) {
+            return Err(Error::Encryption(
+                "file is not an encrypted keystore".to_string(),
+            ));
+        }
+        if data.len() < HEADER_LEN {
+            return Err(Error::Encryption(
+                "encrypted keystore is truncated".to_string(),
+            ));
+        }
+        let version = data[MAGIC.len()];
+        if version != VERSION {
+            return Err(Error::Encryption(format!(
+                "unsupported encrypted keystore version: {}",
+                version
+            )));
+        }
+        let salt_start = MAGIC.len() + 1;
+        let nonce_start = salt_start + SALT_LEN;
+        let salt = data[salt_start..nonce_start].to_vec();
+        let nonce = &data[nonce_start..HEADER_LEN];
+
+        let key = Self::derive(passphrase, salt)?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key.key));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), &data[HEADER_LEN..])
+            .map_err(|_| {
+                Error::Encryption(
+                    "failed to decrypt keystore, passphrase is invalid or file is corrupted"
+                        .to_string(),
+                )
+            })?;
+        Ok((key, plaintext))
+    }
+}
+
+/// Returns true if the bytes start with the encrypted keystore header.
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let key = EncryptionKey::generate("passphrase").unwrap();
+        let data = key.encrypt(b"secret keys").unwrap();
+        assert!(is_encrypted(&data));
+
+        let (decoded_key, plaintext) = EncryptionKey::decrypt("passphrase", &data).unwrap();
+        assert_eq!(decoded_key, key);
+        assert_eq!(plaintext, b"secret keys");
+    }
+
+    #[test]
+    fn decrypt_invalid_passphrase() {
+        let key = EncryptionKey::generate("passphrase").unwrap();
+        let data = key.encrypt(b"secret keys").unwrap();
+        assert!(EncryptionKey::decrypt("wrong", &data).is_err());
+    }
+
+    #[test]
+    fn decrypt_tampered_data() {
+        let key = EncryptionKey::generate("passphrase").unwrap();
+        let mut data = key.encrypt(b"secret keys").unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(EncryptionKey::decrypt("passphrase", &data).is_err());
+    }
+}
@@ -22,6 +22,8 @@ pub enum Error {
     Other(String),
     #[error("Could not convert from KeyInfo to Key")]
     KeyInfoConversion,
+    #[error("Keystore encryption error: {0}")]
+    Encryption(String),
 }
 
 impl From<io::Error> for Error {
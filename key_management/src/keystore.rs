@@ -3,16 +3,20 @@
 
 extern crate serde_json;
 
+use super::encryption::{is_encrypted, EncryptionKey};
 use super::errors::Error;
 use crypto::SignatureType;
 use log::{error, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
-use std::io::{BufReader, BufWriter, ErrorKind};
-use std::path::Path;
+use std::io::{BufReader, ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
 const KEYSTORE_NAME: &str = "/keystore.json";
+const ENCRYPTED_KEYSTORE_NAME: &str = "/keystore";
 
 /// KeyInfo struct, this contains the type of key (stored as a string) and the private key.
 /// note how the private key is stored as a byte vector
@@ -143,15 +147,24 @@ impl KeyStore for MemKeyStore {
     }
 }
 
-/// KeyStore that persists data in KEYSTORE_LOCATION
+/// KeyStore that persists data in KEYSTORE_LOCATION. Keys are stored either as plain JSON in
+/// `keystore.json`, or encrypted with a passphrase derived key in `keystore`.
 #[derive(Default, Clone, PartialEq, Debug, Eq)]
 pub struct PersistentKeyStore {
     pub key_info: HashMap<String, KeyInfo>,
     location: String,
+    encryption: Option<EncryptionKey>,
 }
 
 impl PersistentKeyStore {
+    /// Loads the plaintext keystore in the given directory, initializing a new one if the file
+    /// does not exist.
     pub fn new(location: String) -> Result<Self, Error> {
+        if Self::is_encrypted(&location) {
+            return Err(Error::Other(
+                "keystore is encrypted, a passphrase is required to open it".to_string(),
+            ));
+        }
         let loc = format!("{}{}", location, KEYSTORE_NAME);
         let file_op = File::open(&loc);
         match file_op {
@@ -166,6 +179,7 @@ impl PersistentKeyStore {
                 Ok(Self {
                     key_info: data,
                     location: loc,
+                    encryption: None,
                 })
             }
             Err(e) => {
@@ -174,6 +188,7 @@ impl PersistentKeyStore {
                     Ok(Self {
                         key_info: HashMap::new(),
                         location: loc,
+                        encryption: None,
                     })
                 } else {
                     Err(Error::Other(e.to_string()))
@@ -182,20 +197,114 @@ impl PersistentKeyStore {
         }
     }
 
+    /// Loads the encrypted keystore in the given directory, initializing a new one if the file
+    /// does not exist. Fails if only a plaintext keystore exists, which has to be migrated with
+    /// [`PersistentKeyStore::migrate_to_encrypted`] first.
+    pub fn new_encrypted(location: String, passphrase: &str) -> Result<Self, Error> {
+        let loc = format!("{}{}", location, ENCRYPTED_KEYSTORE_NAME);
+        match fs::read(&loc) {
+            Ok(data) => {
+                let (encryption, plaintext) = EncryptionKey::decrypt(passphrase, &data)?;
+                let key_info: HashMap<String, KeyInfo> = serde_json::from_slice(&plaintext)
+                    .map_err(|e| Error::Other(format!("failed to deserialize keystore: {}", e)))?;
+                Ok(Self {
+                    key_info,
+                    location: loc,
+                    encryption: Some(encryption),
+                })
+            }
+            Err(e) => {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(Error::Other(e.to_string()));
+                }
+                if Path::new(&format!("{}{}", location, KEYSTORE_NAME)).exists() {
+                    return Err(Error::Other(
+                        "found plaintext keystore.json, migrate it to an encrypted keystore first"
+                            .to_string(),
+                    ));
+                }
+                warn!("encrypted keystore does not exist, initializing new keystore");
+                Ok(Self {
+                    key_info: HashMap::new(),
+                    location: loc,
+                    encryption: Some(EncryptionKey::generate(passphrase)?),
+                })
+            }
+        }
+    }
+
+    /// Encrypts the plaintext keystore in the given directory with the passphrase. The plaintext
+    /// file is removed once the encrypted keystore has been written.
+    pub fn migrate_to_encrypted(location: String, passphrase: &str) -> Result<Self, Error> {
+        if Self::is_encrypted(&location) {
+            return Err(Error::Other("keystore is already encrypted".to_string()));
+        }
+        let plain_loc = format!("{}{}", location, KEYSTORE_NAME);
+        let data = fs::read(&plain_loc)?;
+        let key_info: HashMap<String, KeyInfo> = serde_json::from_slice(&data)
+            .map_err(|e| Error::Other(format!("failed to deserialize keystore.json: {}", e)))?;
+
+        let ks = Self {
+            key_info,
+            location: format!("{}{}", location, ENCRYPTED_KEYSTORE_NAME),
+            encryption: Some(EncryptionKey::generate(passphrase)?),
+        };
+        ks.flush()?;
+        fs::remove_file(&plain_loc)?;
+        Ok(ks)
+    }
+
+    /// Returns true if an encrypted keystore exists in the given directory.
+    pub fn is_encrypted(location: &str) -> bool {
+        fs::read(format!("{}{}", location, ENCRYPTED_KEYSTORE_NAME))
+            .map(|data| is_encrypted(&data))
+            .unwrap_or(false)
+    }
+
+    /// Writes the keystore to disk. The file is written to a temporary file readable only by the
+    /// owner and then renamed over the keystore, so a failed write never leaves a corrupted file.
     pub fn flush(&self) -> Result<(), Error> {
-        let dir = Path::new(&self.location)
+        let path = Path::new(&self.location);
+        let dir = path
             .parent()
             .ok_or_else(|| Error::Other("Invalid Path".to_string()))?;
         fs::create_dir_all(dir)?;
 
-        let file = File::create(&self.location)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer(writer, &self.key_info)
-            .map_err(|e| Error::Other(format!("failed to serialize and write key info: {}", e)))?;
+        let data = serde_json::to_vec(&self.key_info)
+            .map_err(|e| Error::Other(format!("failed to serialize key info: {}", e)))?;
+        let data = match &self.encryption {
+            Some(encryption) => encryption.encrypt(&data)?,
+            None => data,
+        };
+
+        let tmp_path = tmp_path(path);
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&tmp_path)?;
+        // Mode is only applied on creation, make sure a stale temporary file is not readable
+        #[cfg(unix)]
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        // Sync the directory so the rename itself survives a crash
+        #[cfg(unix)]
+        File::open(dir)?.sync_all()?;
         Ok(())
     }
 }
 
+/// Returns the temporary path a keystore file is written to before being renamed over it. Each
+/// keystore file has its own, so writes of the plain and encrypted keystores don't clobber
+/// each other.
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
 impl KeyStore for PersistentKeyStore {
     fn list(&self) -> Vec<String> {
         self.key_info.iter().map(|(key, _)| key.clone()).collect()
@@ -210,24 +319,102 @@ impl KeyStore for PersistentKeyStore {
             return Err(Error::KeyExists);
         }
         self.key_info.insert(key, key_info);
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&self.location)
-            .map_err(|err| Error::Other(err.to_string()))?;
-        serde_json::to_writer(&file, &self.key_info)
-            .map_err(|err| Error::Other(err.to_string()))?;
-        Ok(())
+        self.flush()
     }
 
     fn remove(&mut self, key: String) -> Result<KeyInfo, Error> {
         let key_out = self.key_info.remove(&key).ok_or(Error::KeyInfo)?;
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&self.location)
-            .map_err(|err| Error::Other(err.to_string()))?;
-        serde_json::to_writer(file, &self.key_info).map_err(|err| Error::Other(err.to_string()))?;
+        self.flush()?;
         Ok(key_out)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{distributions::Alphanumeric, Rng};
+
+    fn temp_dir() -> String {
+        let suffix: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .collect();
+        let dir = std::env::temp_dir().join(format!("forest_keystore_{}", suffix));
+        dir.to_str().unwrap().to_owned()
+    }
+
+    fn key_info() -> KeyInfo {
+        KeyInfo::new(SignatureType::Secp256k1, vec![1, 2, 3])
+    }
+
+    #[test]
+    fn persistent_put_remove_reload() {
+        let dir = temp_dir();
+        let mut ks = PersistentKeyStore::new(dir.clone()).unwrap();
+        ks.put("a-long-key-name".to_owned(), key_info()).unwrap();
+        ks.put("b".to_owned(), key_info()).unwrap();
+        // Removing shrinks the file, which must not leave trailing bytes behind
+        ks.remove("a-long-key-name".to_owned()).unwrap();
+
+        let reloaded = PersistentKeyStore::new(dir.clone()).unwrap();
+        assert_eq!(reloaded.key_info, ks.key_info);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tmp_path_per_keystore_file() {
+        let dir = Path::new("/data/keystore");
+        let plain = tmp_path(&dir.join(KEYSTORE_NAME.trim_start_matches('/')));
+        let encrypted = tmp_path(&dir.join(ENCRYPTED_KEYSTORE_NAME.trim_start_matches('/')));
+        assert_ne!(plain, encrypted);
+        assert_eq!(plain.parent(), Some(dir));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn persistent_file_permissions() {
+        let dir = temp_dir();
+        let mut ks = PersistentKeyStore::new(dir.clone()).unwrap();
+        ks.put("a".to_owned(), key_info()).unwrap();
+
+        let mode = fs::metadata(format!("{}{}", dir, KEYSTORE_NAME))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn encrypted_put_reload() {
+        let dir = temp_dir();
+        let mut ks = PersistentKeyStore::new_encrypted(dir.clone(), "passphrase").unwrap();
+        ks.put("a".to_owned(), key_info()).unwrap();
+        assert!(PersistentKeyStore::is_encrypted(&dir));
+
+        let data = fs::read(format!("{}{}", dir, ENCRYPTED_KEYSTORE_NAME)).unwrap();
+        assert!(serde_json::from_slice::<HashMap<String, KeyInfo>>(&data).is_err());
+
+        let reloaded = PersistentKeyStore::new_encrypted(dir.clone(), "passphrase").unwrap();
+        assert_eq!(reloaded.get("a").unwrap(), key_info());
+        assert!(PersistentKeyStore::new_encrypted(dir.clone(), "wrong").is_err());
+        assert!(PersistentKeyStore::new(dir.clone()).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migrate_plaintext_keystore() {
+        let dir = temp_dir();
+        let mut ks = PersistentKeyStore::new(dir.clone()).unwrap();
+        ks.put("a".to_owned(), key_info()).unwrap();
+        assert!(PersistentKeyStore::new_encrypted(dir.clone(), "passphrase").is_err());
+
+        let migrated = PersistentKeyStore::migrate_to_encrypted(dir.clone(), "passphrase").unwrap();
+        assert_eq!(migrated.key_info, ks.key_info);
+        assert!(!Path::new(&format!("{}{}", dir, KEYSTORE_NAME)).exists());
+
+        let reloaded = PersistentKeyStore::new_encrypted(dir.clone(), "passphrase").unwrap();
+        assert_eq!(reloaded.key_info, ks.key_info);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,19 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+mod encryption;
 mod errors;
 mod keystore;
+#[cfg(feature = "json")]
+mod remote;
+mod signer;
 mod wallet;
 mod wallet_helpers;
 
 pub use errors::*;
 pub use keystore::*;
+#[cfg(feature = "json")]
+pub use remote::*;
+pub use signer::*;
 pub use wallet::*;
 pub use wallet_helpers::*;
@@ -0,0 +1,395 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::errors::Error;
+use super::{list_addrs, KeyStore, KeyStoreSigner};
+use address::Address;
+use crypto::signature::json::{SignatureJson, SignatureJsonRef};
+use crypto::{Signature, Signer};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::error::Error as StdError;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// JSON-RPC method used to request a signature from the signer.
+pub const SIGN_METHOD: &str = "Signer.Sign";
+/// JSON-RPC method used to list the addresses the signer holds keys for.
+pub const LIST_METHOD: &str = "Signer.List";
+/// Environment variable holding the token shared between the node and a TCP signer.
+pub const SIGNER_TOKEN_ENV: &str = "FOREST_SIGNER_TOKEN";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Location of an external signer process.
+///
+/// Parsed from `unix:<path>` for a local socket, or `tcp:<host>:<port>` (or just `<host>:<port>`)
+/// for a TCP endpoint. Requests to a TCP endpoint must carry the token the signer was started
+/// with, while a unix socket is protected by the permissions of its path.
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub enum SignerEndpoint {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for SignerEndpoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            #[cfg(unix)]
+            return Ok(SignerEndpoint::Unix(PathBuf::from(path)));
+            #[cfg(not(unix))]
+            return Err(Error::Other(format!(
+                "unix socket signer endpoints are not supported: {}",
+                path
+            )));
+        }
+        let addr = s.strip_prefix("tcp:").unwrap_or(s);
+        if addr.is_empty() {
+            return Err(Error::Other("empty signer endpoint".to_string()));
+        }
+        Ok(SignerEndpoint::Tcp(addr.to_owned()))
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    jsonrpc: &'a str,
+    id: u64,
+    method: &'a str,
+    params: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// Signer that forwards signing requests to an external process over line delimited JSON-RPC,
+/// so that private keys never have to be loaded by the node.
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub struct RemoteSigner {
+    endpoint: SignerEndpoint,
+    token: Option<String>,
+}
+
+impl RemoteSigner {
+    /// Return a new RemoteSigner connecting to the given endpoint
+    pub fn new(endpoint: SignerEndpoint) -> Self {
+        Self {
+            endpoint,
+            token: None,
+        }
+    }
+
+    /// Set the token sent to authenticate with the signer, which a TCP endpoint requires
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Return the addresses the remote signer holds keys for
+    pub fn list_addrs(&self) -> Result<Vec<Address>, Error> {
+        let addrs: Vec<String> = serde_json::from_value(self.call(LIST_METHOD, json!([]))?)
+            .map_err(|e| Error::Other(format!("invalid signer response: {}", e)))?;
+        addrs
+            .iter()
+            .map(|a| Address::from_str(a).map_err(|e| Error::Other(e.to_string())))
+            .collect()
+    }
+
+    /// Request a signature over the data from the key of the given address
+    pub fn sign(&self, address: &Address, data: &[u8]) -> Result<Signature, Error> {
+        let params = json!([address.to_string(), base64::encode(data)]);
+        let SignatureJson(sig) = serde_json::from_value(self.call(SIGN_METHOD, params)?)
+            .map_err(|e| Error::Other(format!("invalid signer response: {}", e)))?;
+        Ok(sig)
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, Error> {
+        let request = Request {
+            jsonrpc: "2.0",
+            id: 1,
+            method,
+            params,
+            token: self.token.as_deref(),
+        };
+        let response = match &self.endpoint {
+            SignerEndpoint::Tcp(addr) => {
+                if self.token.is_none() {
+                    return Err(Error::Other(
+                        "tcp signer endpoints require an auth token".to_string(),
+                    ));
+                }
+                let stream = TcpStream::connect(addr)?;
+                stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+                stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+                round_trip(stream, &request)?
+            }
+            #[cfg(unix)]
+            SignerEndpoint::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+                stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+                round_trip(stream, &request)?
+            }
+        };
+        match response {
+            Response {
+                error: Some(err), ..
+            } => Err(Error::Other(format!(
+                "remote signer error {}: {}",
+                err.code, err.message
+            ))),
+            Response {
+                result: Some(result),
+                ..
+            } => Ok(result),
+            _ => Err(Error::Other("empty remote signer response".to_string())),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn sign_bytes(&self, data: &[u8], address: &Address) -> Result<Signature, Box<dyn StdError>> {
+        Ok(self.sign(address, data)?)
+    }
+}
+
+fn round_trip<S: Read + Write>(mut stream: S, request: &Request) -> Result<Response, Error> {
+    let mut bytes = serde_json::to_vec(request).map_err(|e| Error::Other(e.to_string()))?;
+    bytes.push(b'\n');
+    stream.write_all(&bytes)?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    serde_json::from_str(&line).map_err(|e| Error::Other(format!("invalid signer response: {}", e)))
+}
+
+/// Serves signing requests for the keys in the KeyStore over TCP, rejecting requests that do not
+/// carry the token. This is the local stand-in for an external signer and blocks until the
+/// listener fails.
+pub fn serve_tcp<T: KeyStore>(listener: TcpListener, keystore: &T, token: &str) -> io::Result<()> {
+    if token.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "tcp signer requires a non empty auth token",
+        ));
+    }
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = stream.try_clone()?;
+        if let Err(e) = handle_connection(reader, stream, keystore, Some(token)) {
+            warn!("failed to handle signer connection: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Serves signing requests for the keys in the KeyStore over a unix socket. This is the local
+/// stand-in for an external signer and blocks until the listener fails.
+#[cfg(unix)]
+pub fn serve_unix<T: KeyStore>(listener: UnixListener, keystore: &T) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = stream.try_clone()?;
+        if let Err(e) = handle_connection(reader, stream, keystore, None) {
+            warn!("failed to handle signer connection: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection<R, W, T>(
+    reader: R,
+    mut writer: W,
+    keystore: &T,
+    token: Option<&str>,
+) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+    T: KeyStore,
+{
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => {
+                let id = request.get("id").cloned().unwrap_or(Value::Null);
+                let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+                let params = request.get("params").cloned().unwrap_or(Value::Null);
+                debug!("signer request: {}", method);
+                let authorized = match token {
+                    Some(token) => request
+                        .get("token")
+                        .and_then(Value::as_str)
+                        .map_or(false, |t| tokens_match(t, token)),
+                    None => true,
+                };
+                let result = if authorized {
+                    handle_request(method, params, keystore)
+                } else {
+                    Err(Error::Other("unauthorized".to_string()))
+                };
+                match result {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err(e) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": RpcError { code: 1, message: e.to_string() },
+                    }),
+                }
+            }
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": Value::Null,
+                "error": RpcError { code: -32700, message: e.to_string() },
+            }),
+        };
+        let mut bytes = serde_json::to_vec(&response)?;
+        bytes.push(b'\n');
+        writer.write_all(&bytes)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Compares the tokens in time independent of where they differ.
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+fn handle_request<T: KeyStore>(method: &str, params: Value, keystore: &T) -> Result<Value, Error> {
+    match method {
+        SIGN_METHOD => {
+            let (addr, data): (String, String) = serde_json::from_value(params)
+                .map_err(|e| Error::Other(format!("invalid params: {}", e)))?;
+            let addr = Address::from_str(&addr).map_err(|e| Error::Other(e.to_string()))?;
+            let data = base64::decode(data).map_err(|e| Error::Other(e.to_string()))?;
+            let sig = KeyStoreSigner::new(keystore)
+                .sign_bytes(&data, &addr)
+                .map_err(|e| Error::Other(e.to_string()))?;
+            serde_json::to_value(SignatureJsonRef(&sig)).map_err(|e| Error::Other(e.to_string()))
+        }
+        LIST_METHOD => {
+            let addrs: Vec<String> = list_addrs(keystore)?
+                .iter()
+                .map(|a| a.to_string())
+                .collect();
+            Ok(json!(addrs))
+        }
+        _ => Err(Error::Other(format!("unknown method: {}", method))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_key, MemKeyStore};
+    use crypto::SignatureType;
+    use std::thread;
+
+    const TOKEN: &str = "secret";
+
+    fn start_signer(keystore: MemKeyStore) -> RemoteSigner {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve_tcp(listener, &keystore, TOKEN));
+        RemoteSigner::new(format!("tcp:{}", addr).parse().unwrap()).with_token(TOKEN)
+    }
+
+    #[test]
+    fn parse_endpoints() {
+        assert_eq!(
+            "127.0.0.1:1235".parse::<SignerEndpoint>().unwrap(),
+            SignerEndpoint::Tcp("127.0.0.1:1235".to_owned())
+        );
+        assert_eq!(
+            "tcp:127.0.0.1:1235".parse::<SignerEndpoint>().unwrap(),
+            SignerEndpoint::Tcp("127.0.0.1:1235".to_owned())
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            "unix:/tmp/signer.sock".parse::<SignerEndpoint>().unwrap(),
+            SignerEndpoint::Unix(PathBuf::from("/tmp/signer.sock"))
+        );
+        assert!("".parse::<SignerEndpoint>().is_err());
+    }
+
+    #[test]
+    fn remote_sign_and_list() {
+        let mut keystore = MemKeyStore::new();
+        let mut addrs = Vec::new();
+        for typ in &[SignatureType::Secp256k1, SignatureType::BLS] {
+            let key = generate_key(*typ).unwrap();
+            keystore
+                .put(format!("wallet-{}", key.address), key.key_info)
+                .unwrap();
+            addrs.push(key.address);
+        }
+        addrs.sort_by_key(|a| a.to_string());
+
+        let signer = start_signer(keystore);
+        assert_eq!(signer.list_addrs().unwrap(), addrs);
+
+        let msg = b"remote signing";
+        for addr in &addrs {
+            let sig = signer.sign_bytes(msg, addr).unwrap();
+            assert!(sig.verify(msg, addr).is_ok());
+        }
+    }
+
+    #[test]
+    fn remote_sign_unknown_key() {
+        let signer = start_signer(MemKeyStore::new());
+        let key = generate_key(SignatureType::Secp256k1).unwrap();
+        assert!(signer.sign(&key.address, b"data").is_err());
+    }
+
+    #[test]
+    fn remote_sign_requires_token() {
+        let mut keystore = MemKeyStore::new();
+        let key = generate_key(SignatureType::Secp256k1).unwrap();
+        keystore
+            .put(format!("wallet-{}", key.address), key.key_info)
+            .unwrap();
+        let signer = start_signer(keystore);
+
+        let mut wrong_token = signer.clone().with_token("wrong");
+        assert!(wrong_token.sign(&key.address, b"data").is_err());
+        wrong_token.token = None;
+        assert!(wrong_token.list_addrs().is_err());
+        assert!(signer.sign(&key.address, b"data").is_ok());
+    }
+
+    #[test]
+    fn tcp_signer_requires_non_empty_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(serve_tcp(listener, &MemKeyStore::new(), "").is_err());
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::{find_key, wallet_helpers, KeyStore};
+use address::Address;
+use crypto::{Signature, Signer};
+use std::error::Error as StdError;
+
+/// Signer which signs locally with the private keys held in a KeyStore.
+pub struct KeyStoreSigner<'a, T> {
+    keystore: &'a T,
+}
+
+impl<'a, T> KeyStoreSigner<'a, T>
+where
+    T: KeyStore,
+{
+    /// Return a new signer backed by the given KeyStore
+    pub fn new(keystore: &'a T) -> Self {
+        Self { keystore }
+    }
+}
+
+impl<T> Signer for KeyStoreSigner<'_, T>
+where
+    T: KeyStore,
+{
+    fn sign_bytes(&self, data: &[u8], address: &Address) -> Result<Signature, Box<dyn StdError>> {
+        let key = find_key(address, self.keystore)?;
+        Ok(wallet_helpers::sign(
+            *key.key_info.key_type(),
+            key.key_info.private_key(),
+            data,
+        )?)
+    }
+}
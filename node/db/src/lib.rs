@@ -9,7 +9,7 @@ pub use errors::Error;
 pub use memory::MemoryDB;
 
 #[cfg(feature = "rocksdb")]
-pub use rocks::{RocksDb, WriteBatch};
+pub use rocks::{DbStats, RocksDb, WriteBatch};
 
 /// Store interface used as a KV store implementation
 pub trait Store {
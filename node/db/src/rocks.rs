@@ -51,6 +51,7 @@ impl RocksDb {
             DbStatus::Unopened(path) => {
                 let mut db_opts = Options::default();
                 db_opts.create_if_missing(true);
+                db_opts.enable_statistics();
                 self.status = DbStatus::Open(DB::open(&db_opts, path)?);
                 Ok(())
             }
@@ -65,6 +66,53 @@ impl RocksDb {
             DbStatus::Open(db) => Ok(db),
         }
     }
+
+    /// Flushes the memtables to the database files, so no writes are left to be recovered from
+    /// the write-ahead log when the database is next opened.
+    pub fn flush(&self) -> Result<(), Error> {
+        Ok(self.db()?.flush()?)
+    }
+
+    /// Returns the size of the database files and the block cache statistics.
+    pub fn stats(&self) -> Result<DbStats, Error> {
+        let db = self.db()?;
+        let stats = db
+            .property_value("rocksdb.options-statistics")?
+            .unwrap_or_default();
+        Ok(DbStats {
+            size: db
+                .property_int_value("rocksdb.total-sst-files-size")?
+                .unwrap_or_default(),
+            cache_hits: ticker(&stats, "rocksdb.block.cache.hit"),
+            cache_misses: ticker(&stats, "rocksdb.block.cache.miss"),
+        })
+    }
+}
+
+/// Size and block cache statistics of a `RocksDb`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DbStats {
+    /// Size of the database files in bytes.
+    pub size: u64,
+    /// Reads served from the block cache.
+    pub cache_hits: u64,
+    /// Reads which missed the block cache.
+    pub cache_misses: u64,
+}
+
+/// Returns the count of a ticker from the statistics dump, with lines such as
+/// `rocksdb.block.cache.hit COUNT : 10`.
+fn ticker(stats: &str, name: &str) -> u64 {
+    stats
+        .lines()
+        .find_map(|line| {
+            let mut parts = line.split_whitespace();
+            if parts.next()? != name {
+                return None;
+            }
+            parts.last()?.parse().ok()
+        })
+        .unwrap_or_default()
 }
 
 impl Store for RocksDb {
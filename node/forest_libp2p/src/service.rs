@@ -175,12 +175,14 @@ where
                     Some(event) => match event {
                         ForestBehaviourEvent::PeerDialed(peer_id) => {
                             debug!("Peer dialed, {:?}", peer_id);
+                            metrics::PEERS_CONNECTED.set(swarm_stream.get_ref().peers().len() as i64);
                             emit_event(&self.network_sender_out, NetworkEvent::PeerDialed {
                                 peer_id
                             }).await;
                         }
                         ForestBehaviourEvent::PeerDisconnected(peer_id) => {
                             debug!("Peer disconnected, {:?}", peer_id);
+                            metrics::PEERS_CONNECTED.set(swarm_stream.get_ref().peers().len() as i64);
                         }
                         ForestBehaviourEvent::GossipMessage {
                             source,
@@ -204,7 +206,10 @@ where
                                             message: PubsubMessage::Block(b),
                                         }).await;
                                     }
-                                    Err(e) => warn!("Gossip Block from peer {:?} could not be deserialized: {}", source, e)
+                                    Err(e) => {
+                                        warn!("Gossip Block from peer {:?} could not be deserialized: {}", source, e);
+                                        metrics::record_gossip(metrics::GOSSIP_BLOCK, false);
+                                    }
                                 }
                             } else if topic == pubsub_msg_str {
                                 match from_slice::<SignedMessage>(&message) {
@@ -214,7 +219,10 @@ where
                                             message: PubsubMessage::Message(m),
                                         }).await;
                                     }
-                                    Err(e) => warn!("Gossip Message from peer {:?} could not be deserialized: {}", source, e)
+                                    Err(e) => {
+                                        warn!("Gossip Message from peer {:?} could not be deserialized: {}", source, e);
+                                        metrics::record_gossip(metrics::GOSSIP_MESSAGE, false);
+                                    }
                                 }
                             } else {
                                 warn!("Getting gossip messages from unknown topic: {}", topic);
@@ -325,7 +333,9 @@ where
                     None => { break; }
                 },
                 interval_event = interval.next() => if interval_event.is_some() {
-                    info!("Peers connected: {}", swarm_stream.get_ref().peers().len());
+                    let peers = swarm_stream.get_ref().peers().len();
+                    metrics::PEERS_CONNECTED.set(peers as i64);
+                    info!("Peers connected: {}", peers);
                 }
             };
         }
@@ -2,10 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
 use auth::*;
 use jsonrpc_v2::Error as JsonRpcError;
 use jsonrpsee::raw::RawClient;
-use jsonrpsee::transport::http::HttpTransportClient as HTC;
 
 /// Creates a new JWT Token
 pub async fn auth_new(client: &mut RawClient<HTC>, perm: String) -> Result<String, JsonRpcError> {
@@ -2,11 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use super::client::Filecoin;
-use blocks::{header::json::BlockHeaderJson, tipset_json::TipsetJson};
+use super::transport::HttpTransport as HTC;
+use blocks::{header::json::BlockHeaderJson, tipset_json::TipsetJson, TipsetKeys};
 use cid::{json::CidJson, Cid};
 use jsonrpc_v2::Error as JsonRpcError;
 use jsonrpsee::raw::RawClient;
-use jsonrpsee::transport::http::HttpTransportClient as HTC;
 use message::unsigned_message::json::UnsignedMessageJson;
 
 /// Returns a block with specified CID fom chain via RPC
@@ -24,6 +24,16 @@ pub async fn head(client: &mut RawClient<HTC>) -> Result<TipsetJson, JsonRpcErro
     Ok(Filecoin::chain_get_head(client).await?)
 }
 
+/// Returns the tipset at the given height, looking back from the tipset with the given keys,
+/// via RPC
+pub async fn tipset_by_height(
+    client: &mut RawClient<HTC>,
+    height: i64,
+    tsk: TipsetKeys,
+) -> Result<TipsetJson, JsonRpcError> {
+    Ok(Filecoin::chain_get_tipset_by_height(client, height, tsk).await?)
+}
+
 /// Returns messages with specified CID from chain via RPC
 pub async fn messages(
     client: &mut RawClient<HTC>,
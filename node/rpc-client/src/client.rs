@@ -4,11 +4,14 @@
 #![allow(clippy::all)]
 #![allow(unused_variables, dead_code)]
 
-use blocks::{header::json::BlockHeaderJson, tipset_json::TipsetJson};
-use cid::json::CidJson;
+use super::transport::HttpTransport;
+use blocks::{header::json::BlockHeaderJson, tipset_json::TipsetJson, TipsetKeys};
+use cid::json::{vec::CidJsonVec, CidJson};
 use jsonrpsee::raw::RawClient;
-use jsonrpsee::transport::http::HttpTransportClient;
-use message::unsigned_message::json::UnsignedMessageJson;
+use message::{
+    signed_message::json::{vec::SignedMessageJsonVec, SignedMessageJson},
+    unsigned_message::json::UnsignedMessageJson,
+};
 
 jsonrpsee::rpc_api! {
     pub Filecoin {
@@ -28,8 +31,83 @@ jsonrpsee::rpc_api! {
         #[rpc(method = "Filecoin.ChainGetMessage", positional_params)]
         fn chain_get_messages(cid: CidJson) -> UnsignedMessageJson;
 
+        #[rpc(method = "Filecoin.ChainGetTipsetByHeight", positional_params)]
+        fn chain_get_tipset_by_height(height: i64, tsk: TipsetKeys) -> TipsetJson;
+
         #[rpc(method = "Filecoin.ChainGetObj", positional_params)]
         fn chain_read_obj(cid: CidJson) -> Vec<u8>;
+        /// Gas
+        #[rpc(method = "Filecoin.GasEstimateGasLimit", positional_params)]
+        fn gas_estimate_gas_limit(msg: UnsignedMessageJson, tsk: TipsetKeys) -> i64;
+
+        #[rpc(method = "Filecoin.GasEstimateGasPremium", positional_params)]
+        fn gas_estimate_gas_premium(nblocksincl: u64, sender: String, gas_limit: i64, tsk: TipsetKeys) -> String;
+
+        #[rpc(method = "Filecoin.GasEstimateFeeCap", positional_params)]
+        fn gas_estimate_fee_cap(msg: UnsignedMessageJson, max_queue_blks: i64, tsk: TipsetKeys) -> String;
+
+        #[rpc(method = "Filecoin.GasEstimateMessageGas", positional_params)]
+        fn gas_estimate_message_gas(msg: UnsignedMessageJson, spec: Option<serde_json::Value>, tsk: TipsetKeys) -> UnsignedMessageJson;
+        /// Log
+        #[rpc(method = "Filecoin.LogList")]
+        fn log_list() -> serde_json::Value;
+
+        #[rpc(method = "Filecoin.LogSetLevel", positional_params)]
+        fn log_set_level(module: String, level: String) -> ();
+        /// Message Pool
+        #[rpc(method = "Filecoin.MpoolGetNonce", positional_params)]
+        fn mpool_get_nonce(addr: String) -> u64;
+
+        #[rpc(method = "Filecoin.MpoolPending", positional_params)]
+        fn mpool_pending(cids: CidJsonVec) -> SignedMessageJsonVec;
+
+        #[rpc(method = "Filecoin.MpoolPushMessage", positional_params)]
+        fn mpool_push_message(msg: UnsignedMessageJson) -> SignedMessageJson;
+        /// Multisig
+        #[rpc(method = "Filecoin.MsigCreate", positional_params)]
+        fn msig_create(threshold: u64, signers: Vec<String>, unlock_duration: i64, value: String, src: String) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigPropose", positional_params)]
+        fn msig_propose(msig: String, to: String, value: String, src: String, method: u64, params: String) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigApprove", positional_params)]
+        fn msig_approve(msig: String, txn_id: i64, src: String) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigCancel", positional_params)]
+        fn msig_cancel(msig: String, txn_id: i64, src: String) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigAddSigner", positional_params)]
+        fn msig_add_signer(msig: String, src: String, signer: String, increase: bool) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigRemoveSigner", positional_params)]
+        fn msig_remove_signer(msig: String, src: String, signer: String, decrease: bool) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigSwapSigner", positional_params)]
+        fn msig_swap_signer(msig: String, src: String, from: String, to: String) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigChangeThreshold", positional_params)]
+        fn msig_change_threshold(msig: String, src: String, threshold: u64) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigLockBalance", positional_params)]
+        fn msig_lock_balance(msig: String, src: String, start_epoch: i64, unlock_duration: i64, amount: String) -> CidJson;
+
+        #[rpc(method = "Filecoin.MsigGetPending", positional_params)]
+        fn msig_get_pending(msig: String, tsk: TipsetKeys) -> serde_json::Value;
+
+        #[rpc(method = "Filecoin.MsigGetVested", positional_params)]
+        fn msig_get_vested(msig: String, start: TipsetKeys, end: TipsetKeys) -> String;
+
+        #[rpc(method = "Filecoin.MsigGetAvailableBalance", positional_params)]
+        fn msig_get_available_balance(msig: String, tsk: TipsetKeys) -> String;
+        /// Node
+        #[rpc(method = "Filecoin.NodeStatus")]
+        fn node_status() -> serde_json::Value;
+        /// State
+        #[rpc(method = "Filecoin.StateGasProfile", positional_params)]
+        fn state_gas_profile(start: i64, tsk: TipsetKeys) -> serde_json::Value;
+        /// Wallet
+        #[rpc(method = "Filecoin.WalletDefaultAddress")]
+        fn wallet_default_address() -> String;
     }
 }
 
@@ -37,6 +115,17 @@ jsonrpsee::rpc_api! {
 const URL: &str = "http://127.0.0.1:1234/rpc/v0";
 
 // TODO pass config for URL
-pub fn new_client() -> RawClient<HttpTransportClient> {
-    RawClient::new(HttpTransportClient::new(URL))
+pub fn new_client() -> RawClient<HttpTransport> {
+    new_client_with_token(None)
+}
+
+/// Returns a client which sends the token with each request, as needed by methods that require
+/// write access
+pub fn new_client_with_token(token: Option<String>) -> RawClient<HttpTransport> {
+    new_client_at(URL, token)
+}
+
+/// Returns a client for the node serving RPC at the given URL
+pub fn new_client_at(url: &str, token: Option<String>) -> RawClient<HttpTransport> {
+    RawClient::new(HttpTransport::new(url, token))
 }
@@ -0,0 +1,68 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
+use blocks::TipsetKeys;
+use jsonrpc_v2::Error as JsonRpcError;
+use jsonrpsee::raw::RawClient;
+use message::{unsigned_message::json::UnsignedMessageJson, UnsignedMessage};
+
+/// Estimates the gas limit of a message against the current head via RPC
+pub async fn gas_estimate_gas_limit(
+    client: &mut RawClient<HTC>,
+    msg: UnsignedMessage,
+) -> Result<i64, JsonRpcError> {
+    Ok(
+        Filecoin::gas_estimate_gas_limit(client, UnsignedMessageJson(msg), TipsetKeys::default())
+            .await?,
+    )
+}
+
+/// Estimates the gas premium needed for inclusion within `nblocksincl` blocks via RPC
+pub async fn gas_estimate_gas_premium(
+    client: &mut RawClient<HTC>,
+    nblocksincl: u64,
+    sender: String,
+    gas_limit: i64,
+) -> Result<String, JsonRpcError> {
+    Ok(Filecoin::gas_estimate_gas_premium(
+        client,
+        nblocksincl,
+        sender,
+        gas_limit,
+        TipsetKeys::default(),
+    )
+    .await?)
+}
+
+/// Estimates the fee cap of a message via RPC
+pub async fn gas_estimate_fee_cap(
+    client: &mut RawClient<HTC>,
+    msg: UnsignedMessage,
+    max_queue_blks: i64,
+) -> Result<String, JsonRpcError> {
+    Ok(Filecoin::gas_estimate_fee_cap(
+        client,
+        UnsignedMessageJson(msg),
+        max_queue_blks,
+        TipsetKeys::default(),
+    )
+    .await?)
+}
+
+/// Fills in the gas limit, premium and fee cap of a message which are not set via RPC, estimating
+/// on top of the sender's pending messages
+pub async fn gas_estimate_message_gas(
+    client: &mut RawClient<HTC>,
+    msg: UnsignedMessage,
+) -> Result<UnsignedMessage, JsonRpcError> {
+    let UnsignedMessageJson(msg) = Filecoin::gas_estimate_message_gas(
+        client,
+        UnsignedMessageJson(msg),
+        None,
+        TipsetKeys::default(),
+    )
+    .await?;
+    Ok(msg)
+}
@@ -4,7 +4,23 @@
 mod auth_ops;
 mod chain_ops;
 mod client;
+mod gas_ops;
+mod log_ops;
+mod mpool_ops;
+mod msig_ops;
+mod node_ops;
+mod state_ops;
+mod transport;
+mod wallet_ops;
 
 pub use self::auth_ops::*;
 pub use self::chain_ops::*;
 pub use self::client::*;
+pub use self::gas_ops::*;
+pub use self::log_ops::*;
+pub use self::mpool_ops::*;
+pub use self::msig_ops::*;
+pub use self::node_ops::*;
+pub use self::state_ops::*;
+pub use self::transport::*;
+pub use self::wallet_ops::*;
@@ -0,0 +1,21 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
+use jsonrpc_v2::Error as JsonRpcError;
+use jsonrpsee::raw::RawClient;
+
+/// Returns the level logged for each module of the node via RPC
+pub async fn log_list(client: &mut RawClient<HTC>) -> Result<serde_json::Value, JsonRpcError> {
+    Ok(Filecoin::log_list(client).await?)
+}
+
+/// Sets the level logged for a module of the node via RPC
+pub async fn log_set_level(
+    client: &mut RawClient<HTC>,
+    module: String,
+    level: String,
+) -> Result<(), JsonRpcError> {
+    Ok(Filecoin::log_set_level(client, module, level).await?)
+}
@@ -0,0 +1,40 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
+use cid::{json::vec::CidJsonVec, Cid};
+use jsonrpc_v2::Error as JsonRpcError;
+use jsonrpsee::raw::RawClient;
+use message::{
+    signed_message::json::{vec::SignedMessageJsonVec, SignedMessageJson},
+    unsigned_message::json::UnsignedMessageJson,
+    SignedMessage, UnsignedMessage,
+};
+
+/// Returns the next sequence to use for the given address via RPC
+pub async fn mpool_get_nonce(
+    client: &mut RawClient<HTC>,
+    addr: String,
+) -> Result<u64, JsonRpcError> {
+    Ok(Filecoin::mpool_get_nonce(client, addr).await?)
+}
+
+/// Returns the pending messages in the message pool, relative to the given tipset keys, via RPC
+pub async fn mpool_pending(
+    client: &mut RawClient<HTC>,
+    cids: Vec<Cid>,
+) -> Result<Vec<SignedMessage>, JsonRpcError> {
+    let SignedMessageJsonVec(msgs) = Filecoin::mpool_pending(client, CidJsonVec(cids)).await?;
+    Ok(msgs)
+}
+
+/// Signs the message with the node wallet and pushes it to the message pool via RPC
+pub async fn mpool_push_message(
+    client: &mut RawClient<HTC>,
+    msg: UnsignedMessage,
+) -> Result<SignedMessage, JsonRpcError> {
+    let SignedMessageJson(smsg) =
+        Filecoin::mpool_push_message(client, UnsignedMessageJson(msg)).await?;
+    Ok(smsg)
+}
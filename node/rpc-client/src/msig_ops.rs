@@ -0,0 +1,151 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
+use blocks::TipsetKeys;
+use cid::{json::CidJson, Cid};
+use jsonrpc_v2::Error as JsonRpcError;
+use jsonrpsee::raw::RawClient;
+
+/// Creates a multisig wallet via RPC, returns the CID of the create message
+pub async fn msig_create(
+    client: &mut RawClient<HTC>,
+    threshold: u64,
+    signers: Vec<String>,
+    unlock_duration: i64,
+    value: String,
+    src: String,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) =
+        Filecoin::msig_create(client, threshold, signers, unlock_duration, value, src).await?;
+    Ok(cid)
+}
+
+/// Proposes a multisig transaction via RPC, returns the CID of the propose message.
+/// The params are base64 encoded CBOR bytes.
+pub async fn msig_propose(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    to: String,
+    value: String,
+    src: String,
+    method: u64,
+    params: String,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) = Filecoin::msig_propose(client, msig, to, value, src, method, params).await?;
+    Ok(cid)
+}
+
+/// Approves a pending multisig transaction via RPC, returns the CID of the approve message
+pub async fn msig_approve(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    txn_id: i64,
+    src: String,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) = Filecoin::msig_approve(client, msig, txn_id, src).await?;
+    Ok(cid)
+}
+
+/// Cancels a pending multisig transaction via RPC, returns the CID of the cancel message
+pub async fn msig_cancel(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    txn_id: i64,
+    src: String,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) = Filecoin::msig_cancel(client, msig, txn_id, src).await?;
+    Ok(cid)
+}
+
+/// Proposes adding a signer to a multisig via RPC, returns the CID of the propose message
+pub async fn msig_add_signer(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    src: String,
+    signer: String,
+    increase: bool,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) = Filecoin::msig_add_signer(client, msig, src, signer, increase).await?;
+    Ok(cid)
+}
+
+/// Proposes removing a signer from a multisig via RPC, returns the CID of the propose message
+pub async fn msig_remove_signer(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    src: String,
+    signer: String,
+    decrease: bool,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) = Filecoin::msig_remove_signer(client, msig, src, signer, decrease).await?;
+    Ok(cid)
+}
+
+/// Proposes swapping a multisig signer via RPC, returns the CID of the propose message
+pub async fn msig_swap_signer(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    src: String,
+    from: String,
+    to: String,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) = Filecoin::msig_swap_signer(client, msig, src, from, to).await?;
+    Ok(cid)
+}
+
+/// Proposes changing the multisig approval threshold via RPC, returns the CID of the propose
+/// message
+pub async fn msig_change_threshold(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    src: String,
+    threshold: u64,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) = Filecoin::msig_change_threshold(client, msig, src, threshold).await?;
+    Ok(cid)
+}
+
+/// Proposes locking part of the multisig balance via RPC, returns the CID of the propose message
+pub async fn msig_lock_balance(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    src: String,
+    start_epoch: i64,
+    unlock_duration: i64,
+    amount: String,
+) -> Result<Cid, JsonRpcError> {
+    let CidJson(cid) =
+        Filecoin::msig_lock_balance(client, msig, src, start_epoch, unlock_duration, amount)
+            .await?;
+    Ok(cid)
+}
+
+/// Returns the pending transactions of a multisig via RPC
+pub async fn msig_get_pending(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    tsk: TipsetKeys,
+) -> Result<serde_json::Value, JsonRpcError> {
+    Ok(Filecoin::msig_get_pending(client, msig, tsk).await?)
+}
+
+/// Returns the amount vested in a multisig between two tipsets via RPC
+pub async fn msig_get_vested(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    start: TipsetKeys,
+    end: TipsetKeys,
+) -> Result<String, JsonRpcError> {
+    Ok(Filecoin::msig_get_vested(client, msig, start, end).await?)
+}
+
+/// Returns the spendable balance of a multisig via RPC
+pub async fn msig_get_available_balance(
+    client: &mut RawClient<HTC>,
+    msig: String,
+    tsk: TipsetKeys,
+) -> Result<String, JsonRpcError> {
+    Ok(Filecoin::msig_get_available_balance(client, msig, tsk).await?)
+}
@@ -0,0 +1,12 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
+use jsonrpc_v2::Error as JsonRpcError;
+use jsonrpsee::raw::RawClient;
+
+/// Returns the sync status, head epoch, peers and readiness of the node via RPC
+pub async fn node_status(client: &mut RawClient<HTC>) -> Result<serde_json::Value, JsonRpcError> {
+    Ok(Filecoin::node_status(client).await?)
+}
@@ -0,0 +1,17 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
+use blocks::TipsetKeys;
+use jsonrpc_v2::Error as JsonRpcError;
+use jsonrpsee::raw::RawClient;
+
+/// Returns the gas charged from the `start` epoch up to the given tipset via RPC
+pub async fn state_gas_profile(
+    client: &mut RawClient<HTC>,
+    start: i64,
+    tsk: TipsetKeys,
+) -> Result<serde_json::Value, JsonRpcError> {
+    Ok(Filecoin::state_gas_profile(client, start, tsk).await?)
+}
@@ -0,0 +1,70 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use jsonrpsee::common::{Request, Response};
+use jsonrpsee::transport::TransportClient;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use thiserror::Error;
+
+/// Errors raised while sending a request to the node or reading its response
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error("HTTP request failed: {0}")]
+    Http(String),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("No response to read")]
+    NoResponse,
+}
+
+/// JSON-RPC transport over HTTP, which sends the API token with every request so that methods
+/// requiring write access are authorized.
+pub struct HttpTransport {
+    url: String,
+    token: Option<String>,
+    responses: VecDeque<Response>,
+}
+
+impl HttpTransport {
+    /// Returns a transport posting requests to the given URL, authenticated with the token if set
+    pub fn new(url: &str, token: Option<String>) -> Self {
+        Self {
+            url: url.to_owned(),
+            token,
+            responses: VecDeque::new(),
+        }
+    }
+}
+
+impl TransportClient for HttpTransport {
+    type Error = TransportError;
+
+    fn send_request<'s>(
+        &'s mut self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 's>> {
+        Box::pin(async move {
+            let body = serde_json::to_vec(&request)?;
+            let mut req = surf::post(&self.url)
+                .content_type(surf::http::mime::JSON)
+                .body(body);
+            if let Some(token) = &self.token {
+                req = req.header("Authorization", format!("Bearer: {}", token));
+            }
+            let res = req
+                .recv_bytes()
+                .await
+                .map_err(|e| TransportError::Http(e.to_string()))?;
+            self.responses.push_back(serde_json::from_slice(&res)?);
+            Ok(())
+        })
+    }
+
+    fn next_response<'s>(
+        &'s mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Response, Self::Error>> + Send + 's>> {
+        Box::pin(async move { self.responses.pop_front().ok_or(TransportError::NoResponse) })
+    }
+}
@@ -0,0 +1,12 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::client::Filecoin;
+use super::transport::HttpTransport as HTC;
+use jsonrpc_v2::Error as JsonRpcError;
+use jsonrpsee::raw::RawClient;
+
+/// Returns the default address of the node wallet via RPC
+pub async fn wallet_default_address(client: &mut RawClient<HTC>) -> Result<String, JsonRpcError> {
+    Ok(Filecoin::wallet_default_address(client).await?)
+}
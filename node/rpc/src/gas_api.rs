@@ -2,20 +2,34 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use crate::RpcState;
-use address::Address;
+use address::json::AddressJson;
 use blocks::TipsetKeys;
 use blockstore::BlockStore;
 use chain::{BASE_FEE_MAX_CHANGE_DENOM, BLOCK_GAS_TARGET, MINIMUM_BASE_FEE};
 use fil_types::{verifier::FullVerifier, BLOCK_GAS_LIMIT};
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use message::unsigned_message::json::UnsignedMessageJson;
-use message::{ChainMessage, Message};
-use num_bigint::BigInt;
+use message::{ChainMessage, Message, UnsignedMessage};
+use num_bigint::{bigint_ser::json as bigint_json, BigInt};
 use num_traits::{FromPrimitive, Zero};
 use rand_distr::{Distribution, Normal};
+use serde::Deserialize;
 use wallet::KeyStore;
 const MIN_GAS_PREMIUM: f64 = 100000.0;
 const MAX_SPEND_ON_FEE_DENOM: i64 = 100;
+/// Number of blocks a message should be included within when estimating its gas premium.
+const DEFAULT_INCLUSION_BLOCKS: u64 = 10;
+/// Number of blocks a message may wait in the queue when estimating its fee cap.
+const DEFAULT_MAX_QUEUE_BLOCKS: i64 = 20;
+
+/// Options for building a message.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct MessageSendSpec {
+    /// Maximum fee the sender is willing to pay for the message, unbounded if zero.
+    #[serde(with = "bigint_json")]
+    pub max_fee: BigInt,
+}
 
 /// Estimate the fee cap
 pub(crate) async fn gas_estimate_fee_cap<DB, KS>(
@@ -27,7 +41,21 @@ where
     KS: KeyStore + Send + Sync + 'static,
 {
     let (UnsignedMessageJson(msg), max_queue_blks, _tsk) = params;
+    estimate_fee_cap(&data, &msg, max_queue_blks)
+        .await
+        .map(|cap| cap.to_string())
+}
 
+/// Estimate the fee cap of a message expected to wait up to `max_queue_blks` blocks
+pub(crate) async fn estimate_fee_cap<DB, KS>(
+    data: &RpcState<DB, KS>,
+    msg: &UnsignedMessage,
+    max_queue_blks: i64,
+) -> Result<BigInt, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
     let ts = data
         .state_manager
         .chain_store()
@@ -58,20 +86,33 @@ where
     } else {
         fee_in_future
     };
-    Ok(out.to_string())
+    Ok(out)
 }
 
-/// Estimate the fee cap
+/// Estimate the gas premium
 pub(crate) async fn gas_estimate_gas_premium<DB, KS>(
     data: Data<RpcState<DB, KS>>,
-    Params(params): Params<(u64, Address, i64, TipsetKeys)>,
+    Params(params): Params<(u64, AddressJson, i64, TipsetKeys)>,
 ) -> Result<String, JsonRpcError>
 where
     DB: BlockStore + Send + Sync + 'static,
     KS: KeyStore + Send + Sync + 'static,
 {
-    let (mut nblocksincl, _sender, _gas_limit, _) = params;
+    let (nblocksincl, _sender, _gas_limit, _) = params;
+    estimate_premium(&data, nblocksincl)
+        .await
+        .map(|premium| premium.to_string())
+}
 
+/// Estimate the gas premium needed for a message to be included within `nblocksincl` blocks
+pub(crate) async fn estimate_premium<DB, KS>(
+    data: &RpcState<DB, KS>,
+    mut nblocksincl: u64,
+) -> Result<BigInt, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
     if nblocksincl == 0 {
         nblocksincl = 1;
     }
@@ -130,7 +171,7 @@ where
         }
         if prev == 0.into() {
             let ret: BigInt = price.price + 1;
-            return Ok(ret.to_string());
+            return Ok(ret);
         }
         premium = (&price.price + &prev) / 2 + 1
     }
@@ -154,7 +195,7 @@ where
         .ok_or("failed to converrt gas premium f64 to bigint")?;
     premium /= 1 << precision;
 
-    Ok(premium.to_string())
+    Ok(premium)
 }
 
 /// Estimate the gas limit
@@ -166,7 +207,20 @@ where
     DB: BlockStore + Send + Sync + 'static,
     KS: KeyStore + Send + Sync + 'static,
 {
-    let (UnsignedMessageJson(mut msg), _) = params;
+    let (UnsignedMessageJson(msg), _) = params;
+    estimate_gas_limit(&data, msg).await
+}
+
+/// Estimate the gas limit of a message by executing it on top of the sender's pending messages.
+/// Returns -1 if the message fails to execute.
+pub(crate) async fn estimate_gas_limit<DB, KS>(
+    data: &RpcState<DB, KS>,
+    mut msg: UnsignedMessage,
+) -> Result<i64, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
     msg.set_gas_limit(BLOCK_GAS_LIMIT);
     msg.set_gas_fee_cap(MINIMUM_BASE_FEE.clone() + 1);
     msg.set_gas_premium(1.into());
@@ -182,10 +236,11 @@ where
         .resolve_to_key_addr::<FullVerifier>(msg.from(), &curr_ts)
         .await?;
 
-    let pending = data.mpool.pending_for(&from_a).await;
-    let prior_messages: Vec<ChainMessage> = pending
-        .map(|s| s.into_iter().map(ChainMessage::Signed).collect::<Vec<_>>())
-        .unwrap_or_default();
+    let pending = data.mpool.pending_for(&from_a).await.unwrap_or_default();
+    let prior_messages: Vec<ChainMessage> = prior_messages(pending, msg.sequence())
+        .into_iter()
+        .map(ChainMessage::Signed)
+        .collect();
     let res = data
         .state_manager
         .call_with_gas::<FullVerifier>(
@@ -204,3 +259,132 @@ where
         None => Ok(-1),
     }
 }
+
+/// Returns the pending messages of the sender to apply before a message with the sequence, in
+/// sequence order. A message with the sequence of a pending message replaces it, so only the
+/// messages before it are applied. Otherwise the sequence is unset or follows the pending
+/// messages, and all of them are applied.
+fn prior_messages<M: Message>(mut pending: Vec<M>, sequence: u64) -> Vec<M> {
+    if pending.iter().any(|m| m.sequence() == sequence) {
+        pending.retain(|m| m.sequence() < sequence);
+    }
+    pending.sort_by_key(|m| m.sequence());
+    pending
+}
+
+/// Estimate the gas limit, premium and fee cap of a message, keeping the values already set
+pub(crate) async fn gas_estimate_message_gas<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(UnsignedMessageJson, Option<MessageSendSpec>, TipsetKeys)>,
+) -> Result<UnsignedMessageJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (UnsignedMessageJson(msg), spec, _) = params;
+    estimate_message_gas(&data, msg, &spec.unwrap_or_default())
+        .await
+        .map(UnsignedMessageJson)
+}
+
+/// Fills in the gas limit, premium and fee cap of a message which are not set. The gas limit is
+/// estimated on top of the sender's pending messages and padded by the mpool's gas limit
+/// overestimation, and the fee is capped by the max fee of the spec.
+pub(crate) async fn estimate_message_gas<DB, KS>(
+    data: &RpcState<DB, KS>,
+    mut msg: UnsignedMessage,
+    spec: &MessageSendSpec,
+) -> Result<UnsignedMessage, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    if msg.gas_limit() == 0 {
+        let gas_limit = estimate_gas_limit(data, msg.clone()).await?;
+        if gas_limit < 0 {
+            return Err("message execution failed during gas estimation".into());
+        }
+        let overestimation = data.mpool.get_config().gas_limit_overestimation();
+        msg.set_gas_limit((gas_limit as f64 * overestimation) as i64);
+    }
+    if msg.gas_premium().is_zero() {
+        msg.set_gas_premium(estimate_premium(data, DEFAULT_INCLUSION_BLOCKS).await?);
+    }
+    if msg.gas_fee_cap().is_zero() {
+        let fee_cap = estimate_fee_cap(data, &msg, DEFAULT_MAX_QUEUE_BLOCKS).await?;
+        // The fee cap must always cover the premium
+        let fee_cap = std::cmp::max(fee_cap, msg.gas_premium().clone());
+        msg.set_gas_fee_cap(fee_cap);
+    }
+    cap_gas_fee(&mut msg, &spec.max_fee);
+    Ok(msg)
+}
+
+/// Lowers the fee cap so the message costs at most `max_fee`, and keeps the premium within the
+/// fee cap.
+fn cap_gas_fee(msg: &mut UnsignedMessage, max_fee: &BigInt) {
+    if !max_fee.is_zero() && msg.gas_limit() > 0 {
+        let total_fee = msg.gas_fee_cap() * BigInt::from(msg.gas_limit());
+        if &total_fee > max_fee {
+            msg.set_gas_fee_cap(max_fee / BigInt::from(msg.gas_limit()));
+        }
+    }
+    if msg.gas_premium() > msg.gas_fee_cap() {
+        let fee_cap = msg.gas_fee_cap().clone();
+        msg.set_gas_premium(fee_cap);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use address::Address;
+
+    fn message(gas_limit: i64, fee_cap: u64, premium: u64) -> UnsignedMessage {
+        UnsignedMessage::builder()
+            .to(Address::new_id(1))
+            .from(Address::new_id(2))
+            .gas_limit(gas_limit)
+            .gas_fee_cap(fee_cap.into())
+            .gas_premium(premium.into())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn cap_gas_fee_limits_total_fee() {
+        let mut msg = message(1000, 100, 50);
+        cap_gas_fee(&mut msg, &BigInt::from(20_000));
+        assert_eq!(msg.gas_fee_cap(), &BigInt::from(20));
+        assert_eq!(msg.gas_premium(), &BigInt::from(20));
+
+        // A zero max fee leaves the fee uncapped
+        let mut msg = message(1000, 100, 50);
+        cap_gas_fee(&mut msg, &BigInt::zero());
+        assert_eq!(msg.gas_fee_cap(), &BigInt::from(100));
+        assert_eq!(msg.gas_premium(), &BigInt::from(50));
+    }
+
+    #[test]
+    fn prior_messages_by_sequence() {
+        let pending = |sequences: &[u64]| -> Vec<UnsignedMessage> {
+            sequences
+                .iter()
+                .map(|s| {
+                    let mut msg = message(1000, 100, 50);
+                    msg.set_sequence(*s);
+                    msg
+                })
+                .collect()
+        };
+        let sequences =
+            |msgs: Vec<UnsignedMessage>| msgs.iter().map(|m| m.sequence()).collect::<Vec<_>>();
+
+        // Replacing a pending message applies only the messages before it
+        assert_eq!(sequences(prior_messages(pending(&[7, 5, 6]), 6)), vec![5]);
+        assert!(prior_messages(pending(&[5, 6]), 5).is_empty());
+        // An unset or following sequence applies all pending messages
+        assert_eq!(sequences(prior_messages(pending(&[6, 5]), 0)), vec![5, 6]);
+        assert_eq!(sequences(prior_messages(pending(&[5, 6]), 7)), vec![5, 6]);
+    }
+}
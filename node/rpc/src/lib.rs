@@ -5,11 +5,18 @@ mod auth_api;
 mod chain_api;
 mod common_api;
 mod gas_api;
+mod log_api;
+mod miner_api;
 mod mpool_api;
+mod msig_api;
+mod node_api;
+mod paych_api;
 mod state_api;
 mod sync_api;
 mod wallet_api;
 
+pub use crate::node_api::{compute_node_status, NodeStatus, SYNC_TOLERANCE_EPOCHS};
+
 use crate::{common_api::version, state_api::*};
 use async_log::span;
 use async_std::net::{TcpListener, TcpStream};
@@ -19,9 +26,12 @@ use async_tungstenite::{
     tungstenite::handshake::server::Request, tungstenite::Message, WebSocketStream,
 };
 use auth::{has_perms, Error as AuthError, JWT_IDENTIFIER, WRITE_ACCESS};
+use beacon::Beacon;
 use blockstore::BlockStore;
 use chain::{headchange_json::HeadChangeJson, EventsPayload};
-use chain_sync::{BadBlockCache, SyncState};
+use chain_sync::{BadBlockCache, PeerManager, SyncState};
+use crypto::Signer;
+use fil_types::verifier::ProofVerifier;
 use flo_stream::{MessagePublisher, Publisher, Subscriber};
 use forest_libp2p::NetworkMessage;
 use futures::future;
@@ -34,15 +44,20 @@ use jsonrpc_v2::{
 };
 use log::{debug, error, info, warn};
 use message_pool::{MessagePool, MpoolRpcProvider};
+use paychmgr::PaychManager;
 use serde::Serialize;
 use state_manager::StateManager;
-use utils::get_home_dir;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use wallet::KeyStore;
-use wallet::PersistentKeyStore;
 
 type WsSink = SplitSink<WebSocketStream<TcpStream>, async_tungstenite::tungstenite::Message>;
 
 const CHAIN_NOTIFY_METHOD_NAME: &str = "Filecoin.ChainNotify";
+/// Error code of calls to methods which are not registered.
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+/// Method label of the metrics of calls to methods which are not registered.
+const UNKNOWN_METHOD_LABEL: &str = "unknown";
 #[derive(Serialize)]
 struct StreamingData<'a> {
     json_rpc: &'a str,
@@ -60,24 +75,45 @@ where
     pub keystore: Arc<RwLock<KS>>,
     pub events_pubsub: Arc<RwLock<Publisher<EventsPayload>>>,
     pub mpool: Arc<MessagePool<MpoolRpcProvider<DB>>>,
+    pub paych_manager: Arc<PaychManager<DB>>,
     pub bad_blocks: Arc<BadBlockCache>,
     pub sync_state: Arc<RwLock<Vec<Arc<RwLock<SyncState>>>>>,
     pub network_send: Sender<NetworkMessage>,
     pub network_name: String,
+    /// External signer used instead of the keystore keys, if configured.
+    pub signer: Option<Arc<dyn Signer + Send + Sync>>,
+    /// Beacon the entries included in mined blocks are fetched from.
+    pub beacon: Arc<dyn Beacon + Send + Sync>,
+    /// Peers blocks are synced from.
+    pub peer_manager: Arc<PeerManager>,
+    /// Seconds between epochs, used to know the epoch the chain is expected to be at.
+    pub block_delay: u64,
+    /// Set while the server is bound to its endpoint and accepts connections.
+    pub listening: Arc<AtomicBool>,
 }
 
-pub async fn start_rpc<DB, KS>(state: RpcState<DB, KS>, rpc_endpoint: &str)
+/// Serves the JSON RPC API on the endpoint. Blocks built through the API are computed and
+/// checked with the proof verifier `V` the node validates blocks with.
+pub async fn start_rpc<DB, KS, V>(state: RpcState<DB, KS>, rpc_endpoint: &str)
 where
     DB: BlockStore + Send + Sync + 'static,
     KS: KeyStore + Send + Sync + 'static,
+    V: ProofVerifier + Send + Sync + 'static,
 {
     use auth_api::*;
     use chain_api::*;
     use gas_api::*;
+    use log_api::*;
+    use miner_api::*;
     use mpool_api::*;
+    use msig_api::*;
+    use node_api::*;
+    use paych_api::*;
     use sync_api::*;
     use wallet_api::*;
     let events_pubsub = state.events_pubsub.clone();
+    let keystore = state.keystore.clone();
+    let listening = state.listening.clone();
     let rpc = Server::new()
         .with_data(Data::new(state))
         // Auth API
@@ -142,6 +178,74 @@ where
             mpool_push_message::<DB, KS>,
             false,
         )
+        // Log API
+        .with_method("Filecoin.LogList", log_list, false)
+        .with_method("Filecoin.LogSetLevel", log_set_level, false)
+        // Miner API
+        .with_method(
+            "Filecoin.MinerGetBaseInfo",
+            miner_get_base_info::<DB, KS, V>,
+            false,
+        )
+        .with_method(
+            "Filecoin.MinerCreateBlock",
+            miner_create_block::<DB, KS, V>,
+            false,
+        )
+        // Multisig API
+        .with_method("Filecoin.MsigCreate", msig_create::<DB, KS>, false)
+        .with_method("Filecoin.MsigPropose", msig_propose::<DB, KS>, false)
+        .with_method("Filecoin.MsigApprove", msig_approve::<DB, KS>, false)
+        .with_method("Filecoin.MsigCancel", msig_cancel::<DB, KS>, false)
+        .with_method("Filecoin.MsigAddSigner", msig_add_signer::<DB, KS>, false)
+        .with_method(
+            "Filecoin.MsigRemoveSigner",
+            msig_remove_signer::<DB, KS>,
+            false,
+        )
+        .with_method("Filecoin.MsigSwapSigner", msig_swap_signer::<DB, KS>, false)
+        .with_method(
+            "Filecoin.MsigChangeThreshold",
+            msig_change_threshold::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.MsigLockBalance",
+            msig_lock_balance::<DB, KS>,
+            false,
+        )
+        .with_method("Filecoin.MsigGetPending", msig_get_pending::<DB, KS>, false)
+        .with_method("Filecoin.MsigGetVested", msig_get_vested::<DB, KS>, false)
+        .with_method(
+            "Filecoin.MsigGetAvailableBalance",
+            msig_get_available_balance::<DB, KS>,
+            false,
+        )
+        // Payment Channel API
+        .with_method("Filecoin.PaychGet", paych_get::<DB, KS>, false)
+        .with_method("Filecoin.PaychList", paych_list::<DB, KS>, false)
+        .with_method(
+            "Filecoin.PaychVoucherCreate",
+            paych_voucher_create::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.PaychVoucherCheckValid",
+            paych_voucher_check_valid::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.PaychVoucherAdd",
+            paych_voucher_add::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.PaychVoucherList",
+            paych_voucher_list::<DB, KS>,
+            false,
+        )
+        .with_method("Filecoin.PaychSettle", paych_settle::<DB, KS>, false)
+        .with_method("Filecoin.PaychCollect", paych_collect::<DB, KS>, false)
         // Sync API
         .with_method("Filecoin.SyncCheckBad", sync_check_bad::<DB, KS>, false)
         .with_method("Filecoin.SyncMarkBad", sync_mark_bad::<DB, KS>, false)
@@ -219,6 +323,12 @@ where
             false,
         )
         .with_method("Filecoin.StateReplay", state_replay::<DB, KS>, false)
+        .with_method("Filecoin.StateCompute", state_compute::<DB, KS>, false)
+        .with_method(
+            "Filecoin.StateGasProfile",
+            state_gas_profile::<DB, KS>,
+            false,
+        )
         .with_method("Filecoin.StateGetActor", state_get_actor::<DB, KS>, false)
         .with_method(
             "Filecoin.StateAccountKey",
@@ -231,6 +341,41 @@ where
             state_market_balance::<DB, KS>,
             false,
         )
+        .with_method(
+            "Filecoin.StateMarketBalance",
+            state_market_balance::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.StateMarketParticipants",
+            state_market_participants::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.StateMarketDeals",
+            state_market_deals::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.StateMarketStorageDeal",
+            state_market_storage_deal::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.StateVerifiedClientStatus",
+            state_verified_client_status::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.StateVerifierStatus",
+            state_verifier_status::<DB, KS>,
+            false,
+        )
+        .with_method(
+            "Filecoin.StateDealProviderCollateralBounds",
+            state_deal_provider_collateral_bounds::<DB, KS>,
+            false,
+        )
         .with_method(
             "Filecoin.StateGetReceipt",
             state_get_receipt::<DB, KS>,
@@ -254,19 +399,33 @@ where
             gas_estimate_fee_cap::<DB, KS>,
             false,
         )
+        .with_method(
+            "Filecoin.GasEstimateMessageGas",
+            gas_estimate_message_gas::<DB, KS>,
+            false,
+        )
         // Common
         .with_method("Filecoin.Version", version, false)
+        // Node
+        .with_method("Filecoin.NodeStatus", node_status::<DB, KS>, false)
         .finish_unwrapped();
 
-    let try_socket = TcpListener::bind(rpc_endpoint).await;
-    let listener = try_socket.expect("Failed to bind to addr");
+    let listener = match TcpListener::bind(rpc_endpoint).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind RPC server to {}: {}", rpc_endpoint, e);
+            return;
+        }
+    };
     let rpc_state = Arc::new(rpc);
+    listening.store(true, Ordering::Relaxed);
 
     info!("waiting for web socket connections");
     while let Ok((stream, addr)) = listener.accept().await {
         let subscriber = events_pubsub.write().await.subscribe();
         task::spawn(handle_connection_and_log(
             rpc_state.clone(),
+            keystore.clone(),
             stream,
             addr,
             events_pubsub.clone(),
@@ -274,16 +433,20 @@ where
         ));
     }
 
+    listening.store(false, Ordering::Relaxed);
     info!("Stopped accepting websocket connections");
 }
 
-async fn handle_connection_and_log(
+async fn handle_connection_and_log<KS>(
     state: Arc<Server<MapRouter>>,
+    keystore: Arc<RwLock<KS>>,
     tcp_stream: TcpStream,
     addr: std::net::SocketAddr,
     events_out: Arc<RwLock<Publisher<EventsPayload>>>,
     events_in: Subscriber<EventsPayload>,
-) {
+) where
+    KS: KeyStore + Send + Sync + 'static,
+{
     span!("handle_connection_and_log", {
         let mut authorization_header: Option<String> = None;
         if let Ok(ws_stream) =
@@ -324,19 +487,20 @@ async fn handle_connection_and_log(
                                 } else {
                                     call
                                 };
-                                let response = handle_rpc(&state, call, &authorization_header)
-                                    .await
-                                    .unwrap_or_else(|e| {
-                                        ResponseObjects::One(ResponseObject::Error {
-                                            jsonrpc: V2,
-                                            error: Error::Full {
-                                                code: 1,
-                                                message: e.message(),
-                                                data: None,
-                                            },
-                                            id: Id::Null,
-                                        })
-                                    });
+                                let response =
+                                    handle_rpc(&state, &keystore, call, &authorization_header)
+                                        .await
+                                        .unwrap_or_else(|e| {
+                                            ResponseObjects::One(ResponseObject::Error {
+                                                jsonrpc: V2,
+                                                error: Error::Full {
+                                                    code: 1,
+                                                    message: e.message(),
+                                                    data: None,
+                                                },
+                                                id: Id::Null,
+                                            })
+                                        });
                                 let error_send = ws_sender.clone();
 
                                 // initiate response and streaming if applicable
@@ -436,15 +600,20 @@ async fn handle_connection_and_log(
     })
 }
 
-async fn handle_rpc(
+async fn handle_rpc<KS>(
     state: &Arc<Server<MapRouter>>,
+    keystore: &RwLock<KS>,
     call: RequestObject,
     authorization_header: &Option<String>,
-) -> Result<ResponseObjects, Error> {
+) -> Result<ResponseObjects, Error>
+where
+    KS: KeyStore + Send + Sync + 'static,
+{
     if WRITE_ACCESS.contains(&&*call.method) {
         if let Some(header) = authorization_header {
-            let keystore = PersistentKeyStore::new(get_home_dir() + "/.forest")?;
             let ki = keystore
+                .read()
+                .await
                 .get(JWT_IDENTIFIER)
                 .map_err(|_| AuthError::Other("No JWT private key found".to_owned()))?;
             let key = ki.private_key();
@@ -465,7 +634,41 @@ async fn handle_rpc(
         }
     };
 
-    Ok(state.handle(call).await)
+    let method = call.method.to_string();
+    let start = Instant::now();
+    let response = state.handle(call).await;
+    // Calls to unknown methods share a label, so clients can't add a series per method name
+    let label = if is_method_not_found(&response) {
+        UNKNOWN_METHOD_LABEL
+    } else {
+        method.as_str()
+    };
+    metrics::RPC_CALL_SECONDS
+        .with_label_values(&[label])
+        .observe(start.elapsed().as_secs_f64());
+    metrics::RPC_CALLS.with_label_values(&[label]).inc();
+
+    Ok(response)
+}
+
+/// Returns true if the response is the error of a call to a method which is not registered.
+fn is_method_not_found(response: &ResponseObjects) -> bool {
+    matches!(
+        response,
+        ResponseObjects::One(ResponseObject::Error {
+            error: Error::Provided {
+                code: METHOD_NOT_FOUND_CODE,
+                ..
+            },
+            ..
+        }) | ResponseObjects::One(ResponseObject::Error {
+            error: Error::Full {
+                code: METHOD_NOT_FOUND_CODE,
+                ..
+            },
+            ..
+        })
+    )
 }
 
 async fn send_error(code: i64, ws_sender: &RwLock<WsSink>, message: String) -> Result<(), Error> {
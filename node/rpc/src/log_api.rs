@@ -0,0 +1,36 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use jsonrpc_v2::{Error as JsonRpcError, Params};
+use log::LevelFilter;
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LogLevelJson {
+    pub module: String,
+    pub level: String,
+}
+
+/// Returns the level logged for each module which has logged or has a level set.
+pub(crate) async fn log_list() -> Result<Vec<LogLevelJson>, JsonRpcError> {
+    Ok(logger::levels()
+        .into_iter()
+        .map(|(module, level)| LogLevelJson {
+            module,
+            level: level.to_string().to_lowercase(),
+        })
+        .collect())
+}
+
+/// Sets the level logged for a module and its submodules, or for all modules if it is `*`.
+pub(crate) async fn log_set_level(
+    Params(params): Params<(String, String)>,
+) -> Result<(), JsonRpcError> {
+    let (module, level) = params;
+    let level: LevelFilter = level
+        .parse()
+        .map_err(|_| format!("Invalid log level: {}", level))?;
+    logger::set_level(&module, level);
+    Ok(())
+}
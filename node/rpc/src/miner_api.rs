@@ -0,0 +1,258 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::wallet_api::sign_bytes;
+use crate::RpcState;
+use address::{json::AddressJson, Address};
+use beacon::BeaconEntry;
+use block_producer::{
+    create_block, get_miner_worker, get_mining_base_info, BlockTemplate, MiningBaseInfo,
+};
+use blocks::{gossip_block::json::GossipBlockJson, ElectionProof, GossipBlock, Ticket, TipsetKeys};
+use blockstore::BlockStore;
+use cid::Cid;
+use clock::ChainEpoch;
+use encoding::Cbor;
+use fil_types::{verifier::ProofVerifier, PoStProof, SectorInfo, SectorNumber, StoragePower};
+use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
+use message::SignedMessage;
+use num_bigint::bigint_ser::json as bigint_json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use wallet::KeyStore;
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SectorInfoJson {
+    pub seal_proof: i64,
+    pub sector_number: SectorNumber,
+    #[serde(rename = "SealedCID", with = "cid::json")]
+    pub sealed_cid: Cid,
+}
+
+impl From<SectorInfo> for SectorInfoJson {
+    fn from(s: SectorInfo) -> Self {
+        Self {
+            seal_proof: s.proof.into(),
+            sector_number: s.sector_number,
+            sealed_cid: s.sealed_cid,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MiningBaseInfoJson {
+    #[serde(with = "bigint_json")]
+    pub miner_power: StoragePower,
+    #[serde(with = "bigint_json")]
+    pub network_power: StoragePower,
+    pub sectors: Vec<SectorInfoJson>,
+    #[serde(with = "address::json")]
+    pub worker_key: Address,
+    pub sector_size: u64,
+    #[serde(with = "beacon::json")]
+    pub prev_beacon_entry: BeaconEntry,
+    #[serde(with = "beacon::json::vec")]
+    pub beacon_entries: Vec<BeaconEntry>,
+    pub eligible_for_mining: bool,
+}
+
+impl From<MiningBaseInfo> for MiningBaseInfoJson {
+    fn from(info: MiningBaseInfo) -> Self {
+        Self {
+            miner_power: info.miner_power,
+            network_power: info.network_power,
+            sectors: info.sectors.into_iter().map(From::from).collect(),
+            worker_key: info.worker_key,
+            sector_size: info.sector_size as u64,
+            prev_beacon_entry: info.prev_beacon_entry,
+            beacon_entries: info.beacon_entries,
+            eligible_for_mining: info.eligible_for_mining,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BlockTemplateJson {
+    #[serde(with = "address::json")]
+    pub miner: Address,
+    #[serde(with = "blocks::tipset_keys_json")]
+    pub parents: TipsetKeys,
+    #[serde(with = "blocks::ticket::json")]
+    pub ticket: Ticket,
+    #[serde(rename = "Eproof", with = "blocks::election_proof::json")]
+    pub election_proof: ElectionProof,
+    #[serde(with = "beacon::json::vec")]
+    pub beacon_values: Vec<BeaconEntry>,
+    #[serde(with = "message::signed_message::json::vec")]
+    pub messages: Vec<SignedMessage>,
+    pub epoch: ChainEpoch,
+    pub timestamp: u64,
+    #[serde(
+        rename = "WinningPoStProof",
+        with = "fil_types::sector::post::json::vec"
+    )]
+    pub winning_post_proof: Vec<PoStProof>,
+}
+
+impl From<BlockTemplateJson> for BlockTemplate {
+    fn from(t: BlockTemplateJson) -> Self {
+        Self {
+            miner: t.miner,
+            parents: t.parents,
+            ticket: t.ticket,
+            election_proof: t.election_proof,
+            beacon_values: t.beacon_values,
+            messages: t.messages,
+            epoch: t.epoch,
+            timestamp: t.timestamp,
+            winning_post_proof: t.winning_post_proof,
+        }
+    }
+}
+
+/// Returns the information a miner needs to attempt to mine a block at the given epoch on top
+/// of the tipset. Returns null if the miner has no sectors to prove.
+pub(crate) async fn miner_get_base_info<DB, KS, V>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, ChainEpoch, TipsetKeys)>,
+) -> Result<Option<MiningBaseInfoJson>, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+    V: ProofVerifier + Send + Sync + 'static,
+{
+    let (AddressJson(miner), epoch, tsk) = params;
+    let base = Arc::new(data.state_manager.chain_store().tipset_from_keys(&tsk)?);
+    let info = get_mining_base_info::<_, _, V>(
+        &data.state_manager,
+        data.beacon.as_ref(),
+        &miner,
+        epoch,
+        &base,
+    )
+    .await?;
+    Ok(info.map(From::from))
+}
+
+/// Assembles a block from the template and signs it with the key of the worker of the miner.
+/// The block is returned to be submitted with `SyncSubmitBlock`.
+pub(crate) async fn miner_create_block<DB, KS, V>(
+    data: Data<RpcState<DB, KS>>,
+    Params((template,)): Params<(BlockTemplateJson,)>,
+) -> Result<GossipBlockJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+    V: ProofVerifier + Send + Sync + 'static,
+{
+    let template = BlockTemplate::from(template);
+    let base = Arc::new(
+        data.state_manager
+            .chain_store()
+            .tipset_from_keys(&template.parents)?,
+    );
+    let worker =
+        get_miner_worker::<_, V>(&data.state_manager, &template.miner, template.epoch, &base)
+            .await?;
+
+    let unsigned = create_block::<_, V>(&data.state_manager, template).await?;
+    let signing_bytes = unsigned.signing_bytes()?;
+    let signature = sign_bytes(&data, worker, signing_bytes).await?;
+    let block = unsigned.sign(signature)?;
+
+    Ok(GossipBlockJson(GossipBlock {
+        bls_messages: block
+            .bls_messages
+            .iter()
+            .map(Cbor::cid)
+            .collect::<Result<_, _>>()?,
+        secpk_messages: block
+            .secp_messages
+            .iter()
+            .map(Cbor::cid)
+            .collect::<Result<_, _>>()?,
+        header: block.header,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::sync::{channel, RwLock};
+    use block_producer::testing::{Devnet, DEVNET_BLOCK_DELAY};
+    use blocks::Block;
+    use chain_sync::validate_block;
+    use fil_types::verifier::MockVerifier;
+    use flo_stream::Publisher;
+    use message_pool::{MessagePool, MpoolRpcProvider};
+    use paychmgr::PaychManager;
+
+    #[async_std::test]
+    async fn create_block_on_devnet() {
+        let devnet = Devnet::new().await;
+        let sm = Arc::clone(&devnet.state_manager);
+
+        let provider = MpoolRpcProvider::new(sm.chain_store().subscribe().await, Arc::clone(&sm));
+        let mpool = Arc::new(
+            MessagePool::new(provider, "test".to_owned(), Default::default())
+                .await
+                .unwrap(),
+        );
+
+        // The producer elects the devnet miner and computes the ticket and proofs of the template
+        let (_, mined) = devnet.mine(&devnet.producer(Arc::clone(&mpool))).await;
+        let h = mined.header();
+        let template = BlockTemplateJson {
+            miner: *h.miner_address(),
+            parents: h.parents().clone(),
+            ticket: h.ticket().clone().unwrap(),
+            election_proof: h.election_proof().clone().unwrap(),
+            beacon_values: h.beacon_entries().to_vec(),
+            messages: Vec::new(),
+            epoch: h.epoch(),
+            timestamp: h.timestamp(),
+            winning_post_proof: h.winning_post_proof().to_vec(),
+        };
+
+        let (network_send, _) = channel(1);
+        let state = Arc::new(RpcState {
+            state_manager: Arc::clone(&sm),
+            keystore: Arc::clone(&devnet.keystore),
+            events_pubsub: Arc::new(RwLock::new(Publisher::new(1))),
+            mpool,
+            paych_manager: Arc::new(PaychManager::new(Arc::clone(&sm))),
+            bad_blocks: Default::default(),
+            sync_state: Default::default(),
+            network_send,
+            network_name: "test".to_owned(),
+            signer: None,
+            beacon: Arc::clone(&devnet.beacon),
+            peer_manager: Default::default(),
+            block_delay: DEVNET_BLOCK_DELAY,
+            listening: Default::default(),
+        });
+        let GossipBlockJson(created) =
+            miner_create_block::<_, _, MockVerifier>(Data(state), Params((template,)))
+                .await
+                .unwrap();
+
+        // BLS signatures are deterministic, so the block is the one the producer signed
+        assert_eq!(created.header, mined.header);
+        let block = Block {
+            header: created.header,
+            bls_messages: Vec::new(),
+            secp_messages: Vec::new(),
+        };
+        validate_block::<_, _, MockVerifier>(
+            sm,
+            devnet.beacon,
+            Arc::new(block),
+            DEVNET_BLOCK_DELAY,
+        )
+        .await
+        .unwrap();
+    }
+}
@@ -1,6 +1,8 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use crate::gas_api::{estimate_message_gas, MessageSendSpec};
+use crate::wallet_api::sign_bytes;
 use crate::RpcState;
 
 use address::Address;
@@ -11,8 +13,9 @@ use encoding::Cbor;
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use message::Message;
 use message::{
-    signed_message::json::SignedMessageJson, unsigned_message::json::UnsignedMessageJson,
-    SignedMessage,
+    signed_message::json::{vec::SignedMessageJsonVec, SignedMessageJson},
+    unsigned_message::json::UnsignedMessageJson,
+    SignedMessage, UnsignedMessage,
 };
 use std::collections::HashSet;
 use std::str::FromStr;
@@ -54,7 +57,7 @@ where
 pub(crate) async fn mpool_pending<DB, KS>(
     data: Data<RpcState<DB, KS>>,
     Params(params): Params<(CidJsonVec,)>,
-) -> Result<Vec<SignedMessage>, JsonRpcError>
+) -> Result<SignedMessageJsonVec, JsonRpcError>
 where
     DB: BlockStore + Send + Sync + 'static,
     KS: KeyStore + Send + Sync + 'static,
@@ -71,13 +74,13 @@ where
     }
 
     if mpts.epoch() > ts.epoch() {
-        return Ok(pending);
+        return Ok(SignedMessageJsonVec(pending));
     }
 
     loop {
         if mpts.epoch() == ts.epoch() {
             if mpts.as_ref() == &ts {
-                return Ok(pending);
+                return Ok(SignedMessageJsonVec(pending));
             }
 
             // mpts has different blocks than ts
@@ -100,7 +103,7 @@ where
         }
 
         if mpts.epoch() >= ts.epoch() {
-            return Ok(pending);
+            return Ok(SignedMessageJsonVec(pending));
         }
 
         ts = data
@@ -138,15 +141,9 @@ where
     // TODO handle defaults for sequence, gas limit and gas price
     let (UnsignedMessageJson(umsg),) = params;
 
-    let from = umsg.from();
+    let from = *umsg.from();
 
-    let keystore = data.keystore.as_ref().write().await;
-    let key = wallet::find_key(&from, &*keystore)?;
-    let sig = wallet::sign(
-        *key.key_info.key_type(),
-        key.key_info.private_key(),
-        umsg.to_signing_bytes().as_slice(),
-    )?;
+    let sig = sign_bytes(&data, from, umsg.to_signing_bytes()).await?;
 
     let smsg = SignedMessage::new_from_parts(umsg, sig)?;
 
@@ -154,3 +151,24 @@ where
 
     Ok(SignedMessageJson(smsg))
 }
+
+/// Assigns the next sequence of the sender to a message built by the node, estimates the gas
+/// values which are not set, then signs it and adds it to the mpool.
+pub(crate) async fn push_unsigned<DB, KS>(
+    data: &RpcState<DB, KS>,
+    mut umsg: UnsignedMessage,
+) -> Result<SignedMessage, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let from = *umsg.from();
+    umsg.set_sequence(data.mpool.get_sequence(&from).await?);
+    let umsg = estimate_message_gas(data, umsg, &MessageSendSpec::default()).await?;
+
+    let sig = sign_bytes(data, from, umsg.to_signing_bytes()).await?;
+    let smsg = SignedMessage::new_from_parts(umsg, sig)?;
+    data.mpool.as_ref().push(smsg.clone()).await?;
+
+    Ok(smsg)
+}
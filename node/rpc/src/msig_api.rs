@@ -0,0 +1,543 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::mpool_api::push_unsigned;
+use crate::RpcState;
+use actor::{
+    init::ExecParams,
+    make_map_with_root,
+    multisig::{
+        AddSignerParams, ChangeNumApprovalsThresholdParams, ConstructorParams, LockBalanceParams,
+        Method, ProposalHashData, ProposeParams, RemoveSignerParams, State, SwapSignerParams,
+        Transaction, TxnID, TxnIDParams,
+    },
+    Serialized, TokenAmount, INIT_ACTOR_ADDR, MULTISIG_ACTOR_CODE_ID,
+};
+use address::{json::AddressJson, Address};
+use blocks::{Tipset, TipsetKeys};
+use blockstore::BlockStore;
+use cid::json::CidJson;
+use clock::ChainEpoch;
+use encoding::{blake2b_256, to_vec, Cbor};
+use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
+use message::UnsignedMessage;
+use serde::Serialize;
+use wallet::KeyStore;
+
+/// Pending multisig transaction, as returned by `Filecoin.MsigGetPending`.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MsigTransaction {
+    #[serde(rename = "ID")]
+    pub id: i64,
+    pub to: AddressJson,
+    pub value: String,
+    pub method: u64,
+    /// Base64 encoded CBOR parameters.
+    pub params: String,
+    pub approved: Vec<AddressJson>,
+}
+
+/// Creates a multisig wallet through the init actor, returns the CID of the pushed message.
+pub(crate) async fn msig_create<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(u64, Vec<AddressJson>, ChainEpoch, String, AddressJson)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (threshold, signers, unlock_duration, value, AddressJson(src)) = params;
+    let signers: Vec<Address> = signers.into_iter().map(|a| a.0).collect();
+    if signers.is_empty() {
+        return Err("multisig must have at least one signer".into());
+    }
+    if threshold == 0 || threshold as usize > signers.len() {
+        return Err(format!(
+            "invalid approval threshold {} for {} signers",
+            threshold,
+            signers.len()
+        )
+        .into());
+    }
+
+    let constructor_params = Serialized::serialize(ConstructorParams {
+        signers,
+        num_approvals_threshold: threshold as usize,
+        unlock_duration,
+    })?;
+    let exec_params = Serialized::serialize(ExecParams {
+        code_cid: *MULTISIG_ACTOR_CODE_ID,
+        constructor_params,
+    })?;
+
+    let msg = UnsignedMessage::builder()
+        .to(*INIT_ACTOR_ADDR)
+        .from(src)
+        .value(value.parse()?)
+        .method_num(actor::init::Method::Exec as u64)
+        .params(exec_params)
+        .build()?;
+    push_msig_message(&data, msg).await
+}
+
+/// Proposes a transaction on a multisig, returns the CID of the pushed message.
+/// The method parameters are base64 encoded CBOR bytes.
+pub(crate) async fn msig_propose<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, AddressJson, String, AddressJson, u64, String)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), AddressJson(to), value, AddressJson(src), method, params) = params;
+    let params = Serialized::new(base64::decode(params)?);
+    propose(&data, msig, src, to, value.parse()?, method, params).await
+}
+
+/// Approves a pending multisig transaction, returns the CID of the pushed message.
+pub(crate) async fn msig_approve<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, i64, AddressJson)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), txn_id, AddressJson(src)) = params;
+    push_txn_id_message(&data, msig, src, TxnID(txn_id), Method::Approve).await
+}
+
+/// Cancels a pending multisig transaction, returns the CID of the pushed message.
+/// Only the proposer of the transaction can cancel it.
+pub(crate) async fn msig_cancel<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, i64, AddressJson)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), txn_id, AddressJson(src)) = params;
+    push_txn_id_message(&data, msig, src, TxnID(txn_id), Method::Cancel).await
+}
+
+/// Proposes adding a signer to a multisig, returns the CID of the pushed message.
+pub(crate) async fn msig_add_signer<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, AddressJson, AddressJson, bool)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), AddressJson(src), AddressJson(signer), increase) = params;
+    let params = Serialized::serialize(AddSignerParams { signer, increase })?;
+    propose_to_self(&data, msig, src, Method::AddSigner, params).await
+}
+
+/// Proposes removing a signer from a multisig, returns the CID of the pushed message.
+pub(crate) async fn msig_remove_signer<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, AddressJson, AddressJson, bool)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), AddressJson(src), AddressJson(signer), decrease) = params;
+    let params = Serialized::serialize(RemoveSignerParams { signer, decrease })?;
+    propose_to_self(&data, msig, src, Method::RemoveSigner, params).await
+}
+
+/// Proposes swapping a signer of a multisig, returns the CID of the pushed message.
+pub(crate) async fn msig_swap_signer<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, AddressJson, AddressJson, AddressJson)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), AddressJson(src), AddressJson(from), AddressJson(to)) = params;
+    let params = Serialized::serialize(SwapSignerParams { from, to })?;
+    propose_to_self(&data, msig, src, Method::SwapSigner, params).await
+}
+
+/// Proposes changing the approval threshold of a multisig, returns the CID of the pushed message.
+pub(crate) async fn msig_change_threshold<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, AddressJson, u64)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), AddressJson(src), new_threshold) = params;
+    let params = Serialized::serialize(ChangeNumApprovalsThresholdParams {
+        new_threshold: new_threshold as usize,
+    })?;
+    propose_to_self(
+        &data,
+        msig,
+        src,
+        Method::ChangeNumApprovalsThreshold,
+        params,
+    )
+    .await
+}
+
+/// Proposes locking part of the multisig balance with a linear vesting schedule,
+/// returns the CID of the pushed message.
+pub(crate) async fn msig_lock_balance<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, AddressJson, ChainEpoch, ChainEpoch, String)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), AddressJson(src), start_epoch, unlock_duration, amount) = params;
+    let params = Serialized::serialize(LockBalanceParams {
+        start_epoch,
+        unlock_duration,
+        amount: amount.parse()?,
+    })?;
+    propose_to_self(&data, msig, src, Method::LockBalance, params).await
+}
+
+/// Returns the pending transactions of a multisig at the given tipset.
+pub(crate) async fn msig_get_pending<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, TipsetKeys)>,
+) -> Result<Vec<MsigTransaction>, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), tsk) = params;
+    let ts = data.state_manager.chain_store().tipset_from_keys(&tsk)?;
+    let st: State = data
+        .state_manager
+        .load_actor_state(&msig, ts.parent_state())?;
+
+    let pending =
+        make_map_with_root::<_, Transaction>(&st.pending_txs, data.state_manager.blockstore())?;
+    let mut txns = Vec::new();
+    pending.for_each(|key, txn| {
+        let id = TxnID::from_key(key).ok_or("invalid pending transaction key")?;
+        txns.push(MsigTransaction {
+            id: id.0,
+            to: AddressJson(txn.to),
+            value: txn.value.to_string(),
+            method: txn.method,
+            params: base64::encode(txn.params.bytes()),
+            approved: txn.approved.iter().map(|a| AddressJson(*a)).collect(),
+        });
+        Ok(())
+    })?;
+    txns.sort_by_key(|txn| txn.id);
+    Ok(txns)
+}
+
+/// Returns the amount of FIL that vested in a multisig between two tipsets.
+pub(crate) async fn msig_get_vested<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, TipsetKeys, TipsetKeys)>,
+) -> Result<String, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), start_tsk, end_tsk) = params;
+    let chain_store = data.state_manager.chain_store();
+    let start_ts = chain_store.tipset_from_keys(&start_tsk)?;
+    let end_ts = chain_store.tipset_from_keys(&end_tsk)?;
+    if start_ts.epoch() > end_ts.epoch() {
+        return Err(format!(
+            "start tipset {} is after end tipset {}",
+            start_ts.epoch(),
+            end_ts.epoch()
+        )
+        .into());
+    }
+    if start_ts.epoch() == end_ts.epoch() {
+        return Ok("0".to_string());
+    }
+
+    let st: State = data
+        .state_manager
+        .load_actor_state(&msig, end_ts.parent_state())?;
+    if st.unlock_duration == 0 {
+        return Ok("0".to_string());
+    }
+
+    let start_locked = st.amount_locked(start_ts.epoch() - st.start_epoch);
+    let end_locked = st.amount_locked(end_ts.epoch() - st.start_epoch);
+    Ok((start_locked - end_locked).to_string())
+}
+
+/// Returns the portion of the multisig balance that is not locked at the given tipset.
+pub(crate) async fn msig_get_available_balance<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, TipsetKeys)>,
+) -> Result<String, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(msig), tsk) = params;
+    let ts = data.state_manager.chain_store().tipset_from_keys(&tsk)?;
+    available_balance(&data, &msig, &ts)
+        .map(|balance| balance.to_string())
+        .map_err(|e| e.into())
+}
+
+fn available_balance<DB, KS>(
+    data: &RpcState<DB, KS>,
+    msig: &Address,
+    ts: &Tipset,
+) -> Result<TokenAmount, String>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let actor = data
+        .state_manager
+        .get_actor(msig, ts.parent_state())
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("multisig actor {} not found", msig))?;
+    let st: State = data
+        .state_manager
+        .load_actor_state(msig, ts.parent_state())
+        .map_err(|e| e.to_string())?;
+    if st.unlock_duration == 0 {
+        return Ok(actor.balance);
+    }
+    let locked = st.amount_locked(ts.epoch() - st.start_epoch);
+    Ok(std::cmp::max(actor.balance - locked, TokenAmount::from(0)))
+}
+
+async fn propose<DB, KS>(
+    data: &RpcState<DB, KS>,
+    msig: Address,
+    src: Address,
+    to: Address,
+    value: TokenAmount,
+    method: u64,
+    params: Serialized,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let msg = propose_message(msig, src, to, value, method, params)?;
+    push_msig_message(data, msg).await
+}
+
+fn propose_message(
+    msig: Address,
+    src: Address,
+    to: Address,
+    value: TokenAmount,
+    method: u64,
+    params: Serialized,
+) -> Result<UnsignedMessage, JsonRpcError> {
+    let params = Serialized::serialize(ProposeParams {
+        to,
+        value,
+        method,
+        params,
+    })?;
+    Ok(UnsignedMessage::builder()
+        .to(msig)
+        .from(src)
+        .method_num(Method::Propose as u64)
+        .params(params)
+        .build()?)
+}
+
+/// Signer management goes through the multisig's own approval flow, so these methods are
+/// proposed as transactions sent from the multisig to itself.
+async fn propose_to_self<DB, KS>(
+    data: &RpcState<DB, KS>,
+    msig: Address,
+    src: Address,
+    method: Method,
+    params: Serialized,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    propose(
+        data,
+        msig,
+        src,
+        msig,
+        TokenAmount::from(0),
+        method as u64,
+        params,
+    )
+    .await
+}
+
+/// Builds an approve or cancel message for a pending transaction. The proposal hash is
+/// computed from the transaction currently in the multisig state.
+async fn push_txn_id_message<DB, KS>(
+    data: &RpcState<DB, KS>,
+    msig: Address,
+    src: Address,
+    id: TxnID,
+    method: Method,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let head = data
+        .state_manager
+        .chain_store()
+        .heaviest_tipset()
+        .await
+        .ok_or("No heaviest tipset")?;
+    let st: State = data
+        .state_manager
+        .load_actor_state(&msig, head.parent_state())?;
+    let pending =
+        make_map_with_root::<_, Transaction>(&st.pending_txs, data.state_manager.blockstore())?;
+    let txn = pending
+        .get(&id.key())?
+        .ok_or_else(|| format!("no pending transaction with id {} in {}", id.0, msig))?;
+
+    let msg = txn_id_message(msig, src, id, method, txn)?;
+    push_msig_message(data, msg).await
+}
+
+fn txn_id_message(
+    msig: Address,
+    src: Address,
+    id: TxnID,
+    method: Method,
+    txn: &Transaction,
+) -> Result<UnsignedMessage, JsonRpcError> {
+    let params = Serialized::serialize(TxnIDParams {
+        id,
+        proposal_hash: proposal_hash(txn)?.to_vec(),
+    })?;
+    Ok(UnsignedMessage::builder()
+        .to(msig)
+        .from(src)
+        .method_num(method as u64)
+        .params(params)
+        .build()?)
+}
+
+/// Hash the multisig actor checks approvals and cancellations against.
+fn proposal_hash(txn: &Transaction) -> Result<[u8; 32], JsonRpcError> {
+    let data = to_vec(&ProposalHashData {
+        requester: txn.approved.get(0),
+        to: &txn.to,
+        value: &txn.value,
+        method: &txn.method,
+        params: &txn.params,
+    })?;
+    Ok(blake2b_256(&data))
+}
+
+async fn push_msig_message<DB, KS>(
+    data: &RpcState<DB, KS>,
+    msg: UnsignedMessage,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let smsg = push_unsigned(data, msg).await?;
+    Ok(CidJson(smsg.cid()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message::Message;
+
+    #[test]
+    fn txn_id_key_round_trip() {
+        for id in &[0, 1, 127, 128, 1 << 40] {
+            let key = TxnID(*id).key();
+            assert_eq!(TxnID::from_key(&key).unwrap().0, *id);
+        }
+    }
+
+    #[test]
+    fn propose_message_params() {
+        let msig = Address::new_id(1000);
+        let src = Address::new_id(100);
+        let to = Address::new_id(101);
+        let inner = Serialized::serialize(AddSignerParams {
+            signer: to,
+            increase: true,
+        })
+        .unwrap();
+        let msg = propose_message(
+            msig,
+            src,
+            to,
+            TokenAmount::from(10),
+            Method::AddSigner as u64,
+            inner.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(msg.to(), &msig);
+        assert_eq!(msg.from(), &src);
+        assert_eq!(msg.method_num(), Method::Propose as u64);
+        assert_eq!(msg.value(), &TokenAmount::from(0));
+        let params: ProposeParams = msg.params().deserialize().unwrap();
+        assert_eq!(params.to, to);
+        assert_eq!(params.value, TokenAmount::from(10));
+        assert_eq!(params.method, Method::AddSigner as u64);
+        assert_eq!(params.params, inner);
+    }
+
+    #[test]
+    fn approve_message_params() {
+        let msig = Address::new_id(1000);
+        let proposer = Address::new_id(100);
+        let approver = Address::new_id(102);
+        let txn = Transaction {
+            to: Address::new_id(101),
+            value: TokenAmount::from(10),
+            method: 0,
+            params: Serialized::default(),
+            approved: vec![proposer],
+        };
+        let msg = txn_id_message(msig, approver, TxnID(3), Method::Approve, &txn).unwrap();
+
+        assert_eq!(msg.to(), &msig);
+        assert_eq!(msg.from(), &approver);
+        assert_eq!(msg.method_num(), Method::Approve as u64);
+        let params: TxnIDParams = msg.params().deserialize().unwrap();
+        assert_eq!(params.id.0, 3);
+        let expected = blake2b_256(
+            &to_vec(&ProposalHashData {
+                requester: Some(&proposer),
+                to: &txn.to,
+                value: &txn.value,
+                method: &txn.method,
+                params: &txn.params,
+            })
+            .unwrap(),
+        );
+        assert_eq!(params.proposal_hash, expected.to_vec());
+
+        // The hash covers the proposer, so a different requester must not match
+        let other = Transaction {
+            approved: vec![approver],
+            ..txn
+        };
+        assert_ne!(proposal_hash(&other).unwrap(), expected);
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::RpcState;
+use async_std::sync::RwLock;
+use blockstore::BlockStore;
+use chain::ChainStore;
+use chain_sync::{PeerManager, SyncState};
+use clock::ChainEpoch;
+use jsonrpc_v2::{Data, Error as JsonRpcError};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wallet::KeyStore;
+
+/// Epochs the head can be behind the expected epoch for the node to be considered synced.
+pub const SYNC_TOLERANCE_EPOCHS: ChainEpoch = 5;
+
+/// Status of the node, reported by the `Filecoin.NodeStatus` method and the health endpoints.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NodeStatus {
+    /// Stage of each sync worker.
+    pub sync_stages: Vec<String>,
+    /// Epoch of the heaviest tipset.
+    pub head_epoch: ChainEpoch,
+    /// Epoch expected at the current time, from the genesis timestamp and block delay.
+    pub expected_epoch: ChainEpoch,
+    /// Number of peers blocks can be synced from.
+    pub peers: usize,
+    /// Whether the RPC server is bound to its endpoint and accepts connections.
+    pub rpc_ready: bool,
+    /// Whether the head is within `SYNC_TOLERANCE_EPOCHS` of the expected epoch.
+    pub synced: bool,
+}
+
+impl NodeStatus {
+    /// Returns true if the node is synced and can serve RPC requests.
+    pub fn is_ready(&self) -> bool {
+        self.synced && self.rpc_ready
+    }
+}
+
+/// Returns the status of the node from its chain, sync workers and peers.
+pub async fn compute_node_status<DB>(
+    chain_store: &ChainStore<DB>,
+    sync_state: &RwLock<Vec<Arc<RwLock<SyncState>>>>,
+    peer_manager: &PeerManager,
+    block_delay: u64,
+    rpc_ready: bool,
+) -> Result<NodeStatus, chain::Error>
+where
+    DB: BlockStore,
+{
+    let head_epoch = chain_store
+        .heaviest_tipset()
+        .await
+        .map(|ts| ts.epoch())
+        .unwrap_or_default();
+    let genesis_timestamp = chain_store
+        .genesis()?
+        .map(|gen| gen.timestamp())
+        .unwrap_or_default();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let expected_epoch = expected_epoch(genesis_timestamp, now, block_delay);
+
+    let mut sync_stages = Vec::new();
+    for state in sync_state.read().await.iter() {
+        sync_stages.push(state.read().await.stage().to_string());
+    }
+
+    Ok(NodeStatus {
+        sync_stages,
+        head_epoch,
+        expected_epoch,
+        peers: peer_manager.len().await,
+        rpc_ready,
+        synced: expected_epoch - head_epoch <= SYNC_TOLERANCE_EPOCHS,
+    })
+}
+
+/// Returns the epoch of the time, counted in block delays since the genesis timestamp.
+fn expected_epoch(genesis_timestamp: u64, now: u64, block_delay: u64) -> ChainEpoch {
+    if block_delay == 0 {
+        return 0;
+    }
+    (now.saturating_sub(genesis_timestamp) / block_delay) as ChainEpoch
+}
+
+/// Returns the sync status, head epoch, peers and readiness of the node.
+pub(crate) async fn node_status<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+) -> Result<NodeStatus, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    Ok(compute_node_status(
+        data.state_manager.chain_store(),
+        &data.sync_state,
+        &data.peer_manager,
+        data.block_delay,
+        data.listening.load(Ordering::Relaxed),
+    )
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch_from_genesis() {
+        assert_eq!(expected_epoch(1000, 1000, 30), 0);
+        assert_eq!(expected_epoch(1000, 1095, 30), 3);
+        assert_eq!(expected_epoch(1000, 900, 30), 0);
+        assert_eq!(expected_epoch(1000, 1095, 0), 0);
+    }
+}
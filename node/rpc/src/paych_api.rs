@@ -0,0 +1,207 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::mpool_api::push_unsigned;
+use crate::wallet_api::sign_bytes;
+use crate::RpcState;
+use actor::paych::SignedVoucher;
+use address::json::AddressJson;
+use blockstore::BlockStore;
+use cid::json::CidJson;
+use encoding::{from_slice, to_vec, Cbor};
+use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
+use serde::Serialize;
+use wallet::KeyStore;
+
+/// Channel returned by `Filecoin.PaychGet`. `WaitSentinel` is the message creating or funding
+/// the channel, if one was sent.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ChannelResponse {
+    pub channel: Option<AddressJson>,
+    pub wait_sentinel: Option<CidJson>,
+}
+
+/// Returns the outbound channel from `from` to `to`, creating it or adding `amount` to it.
+/// Once the create message has executed, calling this again returns the channel address.
+pub(crate) async fn paych_get<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, AddressJson, String)>,
+) -> Result<ChannelResponse, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(from), AddressJson(to), amount) = params;
+    let amount = amount.parse()?;
+    let state: &RpcState<DB, KS> = &data;
+    let (channel, msg_cid) = data
+        .paych_manager
+        .get_paych(from, to, amount, |msg| async move {
+            let smsg = push_unsigned(state, msg).await?;
+            Ok::<_, JsonRpcError>(smsg.cid()?)
+        })
+        .await?;
+    Ok(ChannelResponse {
+        channel: channel.map(AddressJson),
+        wait_sentinel: msg_cid.map(CidJson),
+    })
+}
+
+/// Creates, signs and stores a voucher for `amount` on the given lane of an outbound channel.
+/// Returns the voucher as base64 encoded CBOR.
+pub(crate) async fn paych_voucher_create<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, String, u64)>,
+) -> Result<String, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(ch), amount, lane) = params;
+    let (signer, mut sv) = data
+        .paych_manager
+        .create_voucher(ch, amount.parse()?, Some(lane))
+        .await?;
+    sv.signature = Some(sign_bytes(&data, signer, sv.signing_bytes()?).await?);
+    data.paych_manager
+        .add_voucher(ch, sv.clone(), Vec::new(), 0.into())
+        .await?;
+    encode_voucher(&sv)
+}
+
+/// Checks a base64 encoded voucher against the channel state.
+pub(crate) async fn paych_voucher_check_valid<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, String)>,
+) -> Result<(), JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(ch), voucher) = params;
+    let sv = decode_voucher(&voucher)?;
+    data.paych_manager
+        .check_voucher_valid(&ch, &sv)
+        .await
+        .map_err(|e| e.into())
+}
+
+/// Validates and stores a base64 encoded voucher received on a channel. Fails if the voucher
+/// adds less than `min_delta` to its lane, otherwise returns the amount it adds.
+pub(crate) async fn paych_voucher_add<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, String, String, String)>,
+) -> Result<String, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(ch), voucher, proof, min_delta) = params;
+    let sv = decode_voucher(&voucher)?;
+    let proof = base64::decode(proof)?;
+    let delta = data
+        .paych_manager
+        .add_voucher(ch, sv, proof, min_delta.parse()?)
+        .await?;
+    Ok(delta.to_string())
+}
+
+/// Returns the stored vouchers of a channel, base64 encoded.
+pub(crate) async fn paych_voucher_list<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson,)>,
+) -> Result<Vec<String>, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(ch),) = params;
+    data.paych_manager
+        .list_vouchers(&ch)
+        .await?
+        .iter()
+        .map(|vi| encode_voucher(&vi.voucher))
+        .collect()
+}
+
+/// Returns the addresses of the channels tracked by the node.
+pub(crate) async fn paych_list<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+) -> Result<Vec<AddressJson>, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    Ok(data
+        .paych_manager
+        .list_channels()
+        .await?
+        .into_iter()
+        .filter_map(|info| info.channel.map(AddressJson))
+        .collect())
+}
+
+/// Starts settling a channel, returns the CID of the settle message.
+pub(crate) async fn paych_settle<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson,)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(ch),) = params;
+    let msg = data.paych_manager.settle(ch).await?;
+    let smsg = push_unsigned(&data, msg).await?;
+    Ok(CidJson(smsg.cid()?))
+}
+
+/// Collects the funds of a settled channel, returns the CID of the collect message.
+pub(crate) async fn paych_collect<DB, KS>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson,)>,
+) -> Result<CidJson, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    let (AddressJson(ch),) = params;
+    let msg = data.paych_manager.collect(ch).await?;
+    let smsg = push_unsigned(&data, msg).await?;
+    Ok(CidJson(smsg.cid()?))
+}
+
+fn encode_voucher(sv: &SignedVoucher) -> Result<String, JsonRpcError> {
+    Ok(base64::encode(to_vec(sv)?))
+}
+
+fn decode_voucher(voucher: &str) -> Result<SignedVoucher, JsonRpcError> {
+    Ok(from_slice(&base64::decode(voucher)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use address::Address;
+    use crypto::Signature;
+
+    #[test]
+    fn voucher_encoding_round_trip() {
+        let sv = SignedVoucher {
+            channel_addr: Address::new_id(1000),
+            time_lock_min: 0,
+            time_lock_max: 0,
+            secret_pre_image: Vec::new(),
+            extra: None,
+            lane: 2,
+            nonce: 3,
+            amount: 100.into(),
+            min_settle_height: 0,
+            merges: Vec::new(),
+            signature: Some(Signature::new_secp256k1(vec![1; 65])),
+        };
+        let encoded = encode_voucher(&sv).unwrap();
+        assert_eq!(decode_voucher(&encoded).unwrap(), sv);
+    }
+}
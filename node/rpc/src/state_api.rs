@@ -2,26 +2,30 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use crate::RpcState;
+use actor::market::DealProposal;
 use actor::miner::{
     ChainSectorInfo, Deadlines, Fault, MinerInfo, SectorOnChainInfo, SectorPreCommitOnChainInfo,
     State,
 };
+use actor::{DealID, TokenAmount};
 use address::{json::AddressJson, Address};
 use async_std::task;
 use bitfield::json::BitFieldJson;
 use blocks::{tipset_json::TipsetJson, Tipset, TipsetKeys};
 use blockstore::BlockStore;
 use cid::{json::CidJson, Cid};
-use clock::ChainEpoch;
-use fil_types::{deadlines::DeadlineInfo, verifier::FullVerifier, SectorNumber};
+use clock::{ChainEpoch, EPOCH_UNDEFINED};
+use fil_types::{deadlines::DeadlineInfo, verifier::FullVerifier, PaddedPieceSize, SectorNumber};
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use message::{
     message_receipt::json::MessageReceiptJson,
     unsigned_message::{json::UnsignedMessageJson, UnsignedMessage},
 };
+use num_bigint::bigint_ser::json as bigint_json;
 use serde::Serialize;
-use state_manager::{InvocResult, MarketBalance, StateManager};
+use state_manager::{GasProfile, InvocResult, MarketBalance, MarketDeal, StateManager};
 use state_tree::StateTree;
+use std::collections::HashMap;
 use std::sync::Arc;
 use wallet::KeyStore;
 
@@ -34,6 +38,99 @@ pub struct MessageLookup {
     pub tipset: TipsetJson,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ComputeStateOutput {
+    pub root: CidJson,
+    pub trace: Vec<InvocResult>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DealProposalJson {
+    #[serde(rename = "PieceCID", with = "cid::json")]
+    pub piece_cid: Cid,
+    pub piece_size: PaddedPieceSize,
+    pub verified_deal: bool,
+    #[serde(with = "address::json")]
+    pub client: Address,
+    #[serde(with = "address::json")]
+    pub provider: Address,
+    pub label: String,
+    pub start_epoch: ChainEpoch,
+    pub end_epoch: ChainEpoch,
+    #[serde(with = "bigint_json")]
+    pub storage_price_per_epoch: TokenAmount,
+    #[serde(with = "bigint_json")]
+    pub provider_collateral: TokenAmount,
+    #[serde(with = "bigint_json")]
+    pub client_collateral: TokenAmount,
+}
+
+impl From<DealProposal> for DealProposalJson {
+    fn from(p: DealProposal) -> Self {
+        Self {
+            piece_cid: p.piece_cid,
+            piece_size: p.piece_size,
+            verified_deal: p.verified_deal,
+            client: p.client,
+            provider: p.provider,
+            label: p.label,
+            start_epoch: p.start_epoch,
+            end_epoch: p.end_epoch,
+            storage_price_per_epoch: p.storage_price_per_epoch,
+            provider_collateral: p.provider_collateral,
+            client_collateral: p.client_collateral,
+        }
+    }
+}
+
+/// State of a deal, all epochs are -1 until the deal is activated.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DealStateJson {
+    pub sector_start_epoch: ChainEpoch,
+    pub last_updated_epoch: ChainEpoch,
+    pub slash_epoch: ChainEpoch,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MarketDealJson {
+    pub proposal: DealProposalJson,
+    pub state: DealStateJson,
+}
+
+impl From<MarketDeal> for MarketDealJson {
+    fn from(deal: MarketDeal) -> Self {
+        let state = match deal.state {
+            Some(state) => DealStateJson {
+                sector_start_epoch: state.sector_start_epoch,
+                last_updated_epoch: state.last_updated_epoch,
+                slash_epoch: state.slash_epoch,
+            },
+            None => DealStateJson {
+                sector_start_epoch: EPOCH_UNDEFINED,
+                last_updated_epoch: EPOCH_UNDEFINED,
+                slash_epoch: EPOCH_UNDEFINED,
+            },
+        };
+        Self {
+            proposal: deal.proposal.into(),
+            state,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DealCollateralBounds {
+    #[serde(with = "bigint_json")]
+    pub min: TokenAmount,
+    #[serde(with = "bigint_json")]
+    pub max: TokenAmount,
+}
+
 /// returns info about the given miner's sectors. If the filter bitfield is nil, all sectors are included.
 /// If the filterOut boolean is set to true, any sectors in the filter are excluded.
 /// If false, only those sectors in the filter are included.
@@ -250,6 +347,29 @@ pub(crate) async fn state_replay<
         msg,
         msg_rct: Some(ret.msg_receipt),
         error: ret.act_error.map(|e| e.to_string()),
+        execution_trace: ret.exec_trace,
+    })
+}
+
+/// applies the given messages on top of the given tipset at the given height, returns the
+/// resulting state root and the execution trace of every message applied.
+pub(crate) async fn state_compute<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(ChainEpoch, Vec<UnsignedMessageJson>, TipsetKeys)>,
+) -> Result<ComputeStateOutput, JsonRpcError> {
+    let (height, msgs, key) = params;
+    let msgs = msgs.into_iter().map(|m| m.into()).collect();
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    let (root, trace) = data
+        .state_manager
+        .compute_state::<FullVerifier>(height, msgs, &tipset)
+        .await?;
+    Ok(ComputeStateOutput {
+        root: CidJson(root),
+        trace,
     })
 }
 
@@ -336,6 +456,126 @@ pub(crate) async fn state_market_balance<
         .map_err(|e| e.into())
 }
 
+/// returns the Escrow and Locked balances of every participant in the Storage Market
+pub(crate) async fn state_market_participants<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(TipsetKeys,)>,
+) -> Result<HashMap<String, MarketBalance>, JsonRpcError> {
+    let (key,) = params;
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    Ok(data
+        .state_manager
+        .market_participants(&tipset)?
+        .into_iter()
+        .map(|(addr, balance)| (addr.to_string(), balance))
+        .collect())
+}
+
+/// returns all deals in the Storage Market, indexed by deal ID
+pub(crate) async fn state_market_deals<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(TipsetKeys,)>,
+) -> Result<HashMap<String, MarketDealJson>, JsonRpcError> {
+    let (key,) = params;
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    Ok(data
+        .state_manager
+        .market_deals(&tipset)?
+        .into_iter()
+        .map(|(deal_id, deal)| (deal_id.to_string(), deal.into()))
+        .collect())
+}
+
+/// returns information about the given Storage Market deal
+pub(crate) async fn state_market_storage_deal<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(DealID, TipsetKeys)>,
+) -> Result<MarketDealJson, JsonRpcError> {
+    let (deal_id, key) = params;
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    Ok(data
+        .state_manager
+        .market_storage_deal(deal_id, &tipset)?
+        .into())
+}
+
+/// returns the data cap of the given address if it is a verified client, or null otherwise
+pub(crate) async fn state_verified_client_status<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, TipsetKeys)>,
+) -> Result<Option<String>, JsonRpcError> {
+    let (address, key) = params;
+    let address = address.into();
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    Ok(data
+        .state_manager
+        .verified_client_status(&address, &tipset)?
+        .map(|dcap| dcap.to_string()))
+}
+
+/// returns the data cap the given address can allocate if it is a verifier, or null otherwise
+pub(crate) async fn state_verifier_status<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(AddressJson, TipsetKeys)>,
+) -> Result<Option<String>, JsonRpcError> {
+    let (address, key) = params;
+    let address = address.into();
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    Ok(data
+        .state_manager
+        .verifier_status(&address, &tipset)?
+        .map(|dcap| dcap.to_string()))
+}
+
+/// returns the minimum and maximum collateral a storage provider can put up for a deal
+pub(crate) async fn state_deal_provider_collateral_bounds<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(PaddedPieceSize, bool, TipsetKeys)>,
+) -> Result<DealCollateralBounds, JsonRpcError> {
+    let (size, verified, key) = params;
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    let (min, max) = data
+        .state_manager
+        .deal_provider_collateral_bounds(size, verified, &tipset)?;
+    Ok(DealCollateralBounds { min, max })
+}
+
+/// re-executes the tipsets from the given epoch up to the given tipset and returns the gas
+/// charged, broken down by charge name and by actor method. The range is capped at
+/// `MAX_GAS_PROFILE_EPOCHS`
+pub(crate) async fn state_gas_profile<
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+>(
+    data: Data<RpcState<DB, KS>>,
+    Params(params): Params<(ChainEpoch, TipsetKeys)>,
+) -> Result<GasProfile, JsonRpcError> {
+    let (start, key) = params;
+    let tipset = data.state_manager.chain_store().tipset_from_keys(&key)?;
+    data.state_manager
+        .gas_profile::<FullVerifier>(start, &tipset)
+        .await
+        .map_err(|e| e.into())
+}
+
 /// returns the message receipt for the given message
 pub(crate) async fn state_get_receipt<
     DB: BlockStore + Send + Sync + 'static,
@@ -96,17 +96,21 @@ mod tests {
     use super::*;
     use async_std::sync::{channel, Receiver, RwLock};
     use async_std::task;
+    use beacon::MockBeacon;
     use blocks::{BlockHeader, Tipset};
     use chain::ChainStore;
     use chain_sync::SyncStage;
     use db::{MemoryDB, Store};
+    use fil_types::BLOCK_DELAY_SECS;
     use flo_stream::Publisher;
     use forest_libp2p::NetworkMessage;
     use futures::StreamExt;
     use message_pool::{MessagePool, MpoolRpcProvider};
+    use paychmgr::PaychManager;
     use serde_json::from_str;
     use state_manager::StateManager;
     use std::sync::Arc;
+    use std::time::Duration;
     use wallet::MemKeyStore;
 
     const TEST_NET_NAME: &str = "test";
@@ -139,15 +143,22 @@ mod tests {
                 .unwrap()
         });
 
+        let paych_manager = Arc::new(PaychManager::new(state_manager.clone()));
         let state = Arc::new(RpcState {
             state_manager,
             keystore: Arc::new(RwLock::new(wallet::MemKeyStore::new())),
             mpool: Arc::new(pool),
+            paych_manager,
             bad_blocks: Default::default(),
             sync_state: Arc::new(RwLock::new(vec![Default::default()])),
             network_send,
             network_name: TEST_NET_NAME.to_owned(),
             events_pubsub: Arc::new(RwLock::new(Publisher::new(1000))),
+            signer: None,
+            beacon: Arc::new(MockBeacon::new(Duration::from_secs(1))),
+            peer_manager: Default::default(),
+            block_delay: BLOCK_DELAY_SECS,
+            listening: Default::default(),
         });
         (state, network_rx)
     }
@@ -4,8 +4,9 @@
 use crate::RpcState;
 
 use address::Address;
+use async_std::task;
 use blockstore::BlockStore;
-use crypto::{signature::json::SignatureJson, SignatureType};
+use crypto::{signature::json::SignatureJson, Signature, SignatureType, Signer};
 use encoding::Cbor;
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use message::{
@@ -16,7 +17,7 @@ use num_bigint::BigUint;
 use state_tree::StateTree;
 use std::convert::TryFrom;
 use std::str::FromStr;
-use wallet::{json::KeyInfoJson, Key, KeyStore};
+use wallet::{json::KeyInfoJson, Key, KeyStore, KeyStoreSigner};
 
 /// Return the balance from StateManager for a given Address
 pub(crate) async fn wallet_balance<DB, KS>(
@@ -195,15 +196,7 @@ where
     let address = Address::from_str(&addr_str)?;
     let msg = Vec::from(msg_string);
 
-    let keystore = data.keystore.write().await;
-
-    let key = wallet::find_key(&address, &*keystore)?;
-
-    let sig = wallet::sign(
-        *key.key_info.key_type(),
-        key.key_info.private_key(),
-        msg.as_slice(),
-    )?;
+    let sig = sign_bytes(&data, address, msg).await?;
 
     Ok(SignatureJson(sig))
 }
@@ -221,15 +214,7 @@ where
     let address = Address::from_str(&addr_str)?;
     let msg_cid = msg.cid()?;
 
-    let keystore = data.keystore.write().await;
-
-    let key = wallet::find_key(&address, &*keystore)?;
-
-    let sig = wallet::sign(
-        *key.key_info.key_type(),
-        key.key_info.private_key(),
-        msg_cid.to_bytes().as_slice(),
-    )?;
+    let sig = sign_bytes(&data, address, msg_cid.to_bytes()).await?;
 
     let smsg = SignedMessage::new_from_parts(msg, sig)?;
 
@@ -252,3 +237,35 @@ where
     let ret = sig.verify(&msg, &address).is_ok();
     Ok(ret)
 }
+
+/// Sign bytes with the key of the given address. Signing is routed through the node's external
+/// signer if one is configured, otherwise the key is loaded from the node keystore.
+pub(crate) async fn sign_bytes<DB, KS>(
+    data: &RpcState<DB, KS>,
+    address: Address,
+    bytes: Vec<u8>,
+) -> Result<Signature, JsonRpcError>
+where
+    DB: BlockStore + Send + Sync + 'static,
+    KS: KeyStore + Send + Sync + 'static,
+{
+    match &data.signer {
+        Some(signer) => {
+            let signer = signer.clone();
+            let sig = task::spawn_blocking(move || {
+                signer
+                    .sign_bytes(&bytes, &address)
+                    .map_err(|e| e.to_string())
+            })
+            .await?;
+            Ok(sig)
+        }
+        None => {
+            let keystore = data.keystore.read().await;
+            let sig = KeyStoreSigner::new(&*keystore)
+                .sign_bytes(&bytes, &address)
+                .map_err(|e| e.to_string())?;
+            Ok(sig)
+        }
+    }
+}
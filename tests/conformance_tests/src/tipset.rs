@@ -92,6 +92,7 @@ pub fn execute_tipset(
             applied_results.push(ret.clone());
             Ok(())
         }),
+        false,
     )?;
     Ok(ExecuteTipsetResult {
         receipts_root,
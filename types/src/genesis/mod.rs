@@ -1,9 +1,12 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use super::SectorSize;
+use super::{PaddedPieceSize, RegisteredSealProof, SectorNumber, SectorSize};
+use address::json::AddressJson;
 use address::{json as addr_json, Address};
-use chrono::Utc;
+use chrono::{DateTime, TimeZone, Utc};
+use cid::{json as cid_json, Cid};
+use clock::ChainEpoch;
 use num_bigint::bigint_ser::json as bigint_json;
 use serde::{Deserialize, Serialize};
 use vm::TokenAmount;
@@ -24,6 +27,21 @@ pub struct Actor {
 
     #[serde(with = "addr_json")]
     pub owner: Address,
+
+    /// Signers and vesting of a multisig actor, the owner is ignored for multisigs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigMeta>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultisigMeta {
+    pub signers: Vec<AddressJson>,
+    pub threshold: usize,
+    #[serde(default)]
+    pub vesting_duration: ChainEpoch,
+    #[serde(default)]
+    pub vesting_start: ChainEpoch,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -41,6 +59,48 @@ pub struct Miner {
     #[serde(with = "bigint_json")]
     pub power_balance: TokenAmount,
     pub sector_size: SectorSize,
+
+    /// Sectors sealed before genesis, which are active from the first epoch.
+    #[serde(default)]
+    pub sectors: Vec<PreSeal>,
+}
+
+/// Sector sealed before genesis, with the deal of the data stored in it.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreSeal {
+    #[serde(rename = "CommR", with = "cid_json")]
+    pub comm_r: Cid,
+    #[serde(rename = "CommD", with = "cid_json")]
+    pub comm_d: Cid,
+    #[serde(rename = "SectorID")]
+    pub sector_id: SectorNumber,
+    pub deal: PreSealDeal,
+    pub proof_type: RegisteredSealProof,
+}
+
+/// Storage deal of a pre-sealed sector, published in the genesis market state.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreSealDeal {
+    #[serde(rename = "PieceCID", with = "cid_json")]
+    pub piece_cid: Cid,
+    pub piece_size: PaddedPieceSize,
+    pub verified_deal: bool,
+    #[serde(with = "addr_json")]
+    pub client: Address,
+    #[serde(with = "addr_json")]
+    pub provider: Address,
+    #[serde(default)]
+    pub label: String,
+    pub start_epoch: ChainEpoch,
+    pub end_epoch: ChainEpoch,
+    #[serde(with = "bigint_json")]
+    pub storage_price_per_epoch: TokenAmount,
+    #[serde(with = "bigint_json")]
+    pub provider_collateral: TokenAmount,
+    #[serde(with = "bigint_json")]
+    pub client_collateral: TokenAmount,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -50,6 +110,10 @@ pub struct Template {
     pub miners: Vec<Miner>,
     pub network_name: String,
     timestamp: String,
+
+    /// Key of the verified registry root, which can add and remove verifiers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verifreg_root_key: Option<Actor>,
 }
 
 impl Template {
@@ -59,6 +123,19 @@ impl Template {
             miners: Vec::new(),
             network_name,
             timestamp: Utc::now().to_rfc3339(),
+            verifreg_root_key: None,
         }
     }
+
+    /// Returns the time of genesis, in seconds since the Unix epoch.
+    pub fn timestamp(&self) -> Result<u64, String> {
+        let time = DateTime::parse_from_rfc3339(&self.timestamp)
+            .map_err(|e| format!("invalid genesis timestamp {}: {}", self.timestamp, e))?;
+        Ok(time.timestamp() as u64)
+    }
+
+    /// Sets the time of genesis, in seconds since the Unix epoch.
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = Utc.timestamp(timestamp as i64, 0).to_rfc3339();
+    }
 }
@@ -0,0 +1,153 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::{RegisteredSealProof, FILECOIN_PRECISION, TOTAL_FILECOIN_BASE};
+use clock::ChainEpoch;
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Config trait which handles different network configurations.
+pub trait NetworkParams {
+    /// Total filecoin available to network.
+    const TOTAL_FILECOIN: i64;
+
+    /// Available rewards for mining.
+    const MINING_REWARD_TOTAL: i64;
+
+    /// Minimum power, in bytes, of an individual miner to meet the threshold for leader election.
+    const CONSENSUS_MINER_MIN_POWER: i64;
+
+    /// Seal proof types which can be used when creating miners and pre-committing sectors.
+    const SUPPORTED_PROOF_TYPES: &'static [RegisteredSealProof];
+
+    /// Number of epochs between publishing a pre-commit and drawing the interactive PoRep
+    /// challenge.
+    const PRE_COMMIT_CHALLENGE_DELAY: ChainEpoch;
+
+    /// Initial reward actor balance. This function is only called in genesis setting up state.
+    fn initial_reward_balance() -> BigInt {
+        BigInt::from(Self::MINING_REWARD_TOTAL) * FILECOIN_PRECISION
+    }
+
+    /// Convert integer value of tokens into BigInt based on the token precision.
+    fn from_fil(i: i64) -> BigInt {
+        BigInt::from(i) * FILECOIN_PRECISION
+    }
+}
+
+/// Mainnet parameters.
+pub struct MainnetParams;
+impl NetworkParams for MainnetParams {
+    const TOTAL_FILECOIN: i64 = TOTAL_FILECOIN_BASE;
+    const MINING_REWARD_TOTAL: i64 = 1_100_000_000;
+    const CONSENSUS_MINER_MIN_POWER: i64 = 10 << 40;
+    const SUPPORTED_PROOF_TYPES: &'static [RegisteredSealProof] = &[
+        RegisteredSealProof::StackedDRG32GiBV1,
+        RegisteredSealProof::StackedDRG64GiBV1,
+    ];
+    const PRE_COMMIT_CHALLENGE_DELAY: ChainEpoch = 150;
+}
+
+/// Testnet (calibration network) parameters, which allow smaller sectors and miners.
+pub struct TestnetParams;
+impl NetworkParams for TestnetParams {
+    const TOTAL_FILECOIN: i64 = TOTAL_FILECOIN_BASE;
+    const MINING_REWARD_TOTAL: i64 = 1_100_000_000;
+    const CONSENSUS_MINER_MIN_POWER: i64 = 32 << 30;
+    const SUPPORTED_PROOF_TYPES: &'static [RegisteredSealProof] = &[
+        RegisteredSealProof::StackedDRG512MiBV1,
+        RegisteredSealProof::StackedDRG32GiBV1,
+        RegisteredSealProof::StackedDRG64GiBV1,
+    ];
+    const PRE_COMMIT_CHALLENGE_DELAY: ChainEpoch = 150;
+}
+
+/// Devnet parameters, for local networks with tiny sectors and short delays.
+pub struct DevnetParams;
+impl NetworkParams for DevnetParams {
+    const TOTAL_FILECOIN: i64 = TOTAL_FILECOIN_BASE;
+    const MINING_REWARD_TOTAL: i64 = 1_400_000_000;
+    const CONSENSUS_MINER_MIN_POWER: i64 = 2048;
+    const SUPPORTED_PROOF_TYPES: &'static [RegisteredSealProof] = &[
+        RegisteredSealProof::StackedDRG2KiBV1,
+        RegisteredSealProof::StackedDRG8MiBV1,
+    ];
+    const PRE_COMMIT_CHALLENGE_DELAY: ChainEpoch = 10;
+}
+
+/// Network whose parameters are used when validating the chain, selected at startup. Each
+/// variant maps to the `NetworkParams` implementation of that network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkPolicy {
+    Mainnet,
+    Testnet,
+    Devnet,
+}
+
+impl Default for NetworkPolicy {
+    fn default() -> Self {
+        NetworkPolicy::Mainnet
+    }
+}
+
+impl fmt::Display for NetworkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NetworkPolicy::Mainnet => "mainnet",
+            NetworkPolicy::Testnet => "testnet",
+            NetworkPolicy::Devnet => "devnet",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for NetworkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(NetworkPolicy::Mainnet),
+            "testnet" | "calibnet" => Ok(NetworkPolicy::Testnet),
+            "devnet" => Ok(NetworkPolicy::Devnet),
+            _ => Err(format!(
+                "unknown network {}, expected one of mainnet, testnet or devnet",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_from_str_round_trip() {
+        for network in &[
+            NetworkPolicy::Mainnet,
+            NetworkPolicy::Testnet,
+            NetworkPolicy::Devnet,
+        ] {
+            assert_eq!(
+                network.to_string().parse::<NetworkPolicy>().unwrap(),
+                *network
+            );
+        }
+        assert_eq!(
+            "calibnet".parse::<NetworkPolicy>().unwrap(),
+            NetworkPolicy::Testnet
+        );
+        assert!("localnet".parse::<NetworkPolicy>().is_err());
+    }
+
+    #[test]
+    fn initial_reward_balance() {
+        assert_eq!(
+            MainnetParams::initial_reward_balance(),
+            BigInt::from(1_100_000_000) * FILECOIN_PRECISION
+        );
+    }
+}
@@ -133,7 +133,8 @@ enum ProofType {
     Window,
 }
 
-fn prover_id_from_u64(id: u64) -> ProverId {
+/// Converts the ID of a miner actor into the prover ID used by the proofs library.
+pub fn prover_id_from_u64(id: u64) -> ProverId {
     let mut prover_id = ProverId::default();
     let prover_bytes = Address::new_id(id).payload().to_raw_bytes();
     prover_id[..prover_bytes.len()].copy_from_slice(&prover_bytes);
@@ -21,13 +21,30 @@ pub const WRITE: [&str; 2] = ["read", "write"];
 /// Reading permissions
 pub const READ: [&str; 1] = ["read"];
 /// All methods that require write permission
-pub const WRITE_ACCESS: [&str; 6] = [
+pub const WRITE_ACCESS: [&str; 23] = [
     "Filecoin.MpoolPush",
     "Filecoin.WalletNew",
     "Filecoin.WalletHas",
     "Filecoin.WalletList",
     "Filecoin.WalletDefaultAddress",
     "Filecoin.WalletList",
+    "Filecoin.LogSetLevel",
+    "Filecoin.MsigCreate",
+    "Filecoin.MsigPropose",
+    "Filecoin.MsigApprove",
+    "Filecoin.MsigCancel",
+    "Filecoin.MsigAddSigner",
+    "Filecoin.MsigRemoveSigner",
+    "Filecoin.MsigSwapSigner",
+    "Filecoin.MsigChangeThreshold",
+    "Filecoin.MsigLockBalance",
+    "Filecoin.PaychGet",
+    "Filecoin.PaychSettle",
+    "Filecoin.PaychCollect",
+    "Filecoin.PaychVoucherCreate",
+    "Filecoin.PaychVoucherAdd",
+    "Filecoin.StateGasProfile",
+    "Filecoin.MinerCreateBlock",
 ];
 
 /// Error Enum for Authentification
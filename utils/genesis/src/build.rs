@@ -0,0 +1,785 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use actor::{
+    account, cron, init, make_map, make_map_with_root, market, miner, multisig, power, reward,
+    smooth::FilterEstimate, system, verifreg, BalanceTable, SetMultimap, ACCOUNT_ACTOR_CODE_ID,
+    BURNT_FUNDS_ACTOR_ADDR, CRON_ACTOR_ADDR, CRON_ACTOR_CODE_ID, INIT_ACTOR_ADDR,
+    INIT_ACTOR_CODE_ID, MARKET_ACTOR_CODE_ID, MULTISIG_ACTOR_CODE_ID, POWER_ACTOR_CODE_ID,
+    RESERVE_ADDRESS, REWARD_ACTOR_ADDR, REWARD_ACTOR_CODE_ID, STORAGE_MARKET_ACTOR_ADDR,
+    STORAGE_POWER_ACTOR_ADDR, SYSTEM_ACTOR_ADDR, SYSTEM_ACTOR_CODE_ID,
+    VERIFIED_REGISTRY_ACTOR_ADDR, VERIFREG_ACTOR_CODE_ID,
+};
+use address::Address;
+use beacon::BeaconEntry;
+use blocks::{BlockHeader, Ticket, TxMeta};
+use chain::INITIAL_BASE_FEE;
+use cid::{Cid, Code::Blake2b256};
+use clock::ChainEpoch;
+use crypto::{DomainSeparationTag, Signature, VRFProof};
+use encoding::Cbor;
+use fil_types::genesis::{Actor, ActorType, Miner, Template};
+use fil_types::{
+    get_network_version_default, verifier::ProofVerifier, NetworkParams, StoragePower, FIL_RESERVED,
+};
+use interpreter::{CircSupplyCalc, Rand, VM};
+use ipld_amt::Amt;
+use ipld_blockstore::BlockStore;
+use log::info;
+use message::UnsignedMessage;
+use num_bigint::BigInt;
+use num_traits::Zero;
+use serde::{de::DeserializeOwned, Serialize};
+use state_tree::StateTree;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use vm::{ActorState, MethodNum, Serialized, TokenAmount};
+
+/// ID of the first account created at genesis, after the singleton actors.
+const ACCOUNT_START: u64 = actor::FIRST_NON_SINGLETON_ADDR;
+
+/// ID of the first miner created at genesis. The miner at index `i` of the template gets the ID
+/// `MINER_START + i`, which preseal files are generated for.
+pub const MINER_START: u64 = 1000;
+
+/// ID of the actor which is the root key of the verified registry.
+const ROOT_VERIFIER_ID: u64 = 80;
+
+/// Builds the genesis state from a template into the store, and returns the genesis block
+/// header. The header is put in the store, so the genesis can be exported from its Cid.
+///
+/// The builtin actor states, accounts and multisigs are constructed directly. Miners are
+/// created by applying messages to this state, and their pre-sealed sectors are activated by
+/// confirming the proofs of their pre-commits, so they are set up the same way as on chain.
+pub fn build_genesis<V, P, BS>(
+    store: &BS,
+    template: &Template,
+) -> Result<BlockHeader, Box<dyn StdError>>
+where
+    V: ProofVerifier,
+    P: NetworkParams,
+    BS: BlockStore,
+{
+    let timestamp = template.timestamp()?;
+    let state_root = build_state::<V, P, BS>(store, template)?;
+
+    let empty_amt = Amt::<Cid, _>::new(store).flush()?;
+    let messages = store.put(
+        &TxMeta {
+            bls_message_root: empty_amt,
+            secp_message_root: empty_amt,
+        },
+        Blake2b256,
+    )?;
+
+    let header = BlockHeader::builder()
+        .miner_address(*SYSTEM_ACTOR_ADDR)
+        .epoch(0)
+        .weight(BigInt::zero())
+        .state_root(state_root)
+        .messages(messages)
+        .message_receipts(empty_amt)
+        .timestamp(timestamp)
+        .ticket(Some(Ticket::new(VRFProof::new(
+            b"vrf proof0000000vrf proof0000000".to_vec(),
+        ))))
+        // Beacon entry which the entries of the first blocks follow
+        .beacon_entries(vec![BeaconEntry::new(0, vec![0; 32])])
+        .bls_aggregate(Some(Signature::new_bls(Vec::new())))
+        .signature(Some(Signature::new_bls(Vec::new())))
+        .parent_base_fee(TokenAmount::from(INITIAL_BASE_FEE))
+        .build_and_validate()?;
+    let cid = store.put(&header, Blake2b256)?;
+    debug_assert_eq!(&cid, header.cid());
+
+    info!(
+        "Built genesis {} for network {}",
+        cid, template.network_name
+    );
+    Ok(header)
+}
+
+/// Builds the genesis state tree and returns its root.
+fn build_state<V, P, BS>(store: &BS, template: &Template) -> Result<Cid, Box<dyn StdError>>
+where
+    V: ProofVerifier,
+    P: NetworkParams,
+    BS: BlockStore,
+{
+    let mut tree = StateTree::new(store);
+    let empty_map = make_map::<_, ()>(store).flush()?;
+    let empty_array = Amt::<Cid, _>::new(store).flush()?;
+
+    put_actor(
+        &mut tree,
+        &SYSTEM_ACTOR_ADDR,
+        &SYSTEM_ACTOR_CODE_ID,
+        &system::State::default(),
+        TokenAmount::zero(),
+    )?;
+
+    // Accounts are allocated IDs and mapped from their keys in the init actor
+    let mut init_st = init::State::new(empty_map, template.network_name.clone());
+    let mut accounts = Accounts::default();
+    for a in template.accounts.iter() {
+        if let ActorType::Account = a.actor_type {
+            accounts.credit(&mut tree, &mut init_st, &a.owner, a.balance.clone())?;
+        }
+    }
+    for a in template.accounts.iter() {
+        if let ActorType::MultiSig = a.actor_type {
+            let id = Address::new_id(init_st.next_id);
+            init_st.next_id += 1;
+            put_multisig(&mut tree, &mut init_st, &mut accounts, &id, a, empty_map)?;
+        }
+    }
+    if let Some(root) = &template.verifreg_root_key {
+        let id = Address::new_id(ROOT_VERIFIER_ID);
+        match root.actor_type {
+            ActorType::Account => put_account(&mut tree, &id, &root.owner, root.balance.clone())?,
+            ActorType::MultiSig => {
+                put_multisig(&mut tree, &mut init_st, &mut accounts, &id, root, empty_map)?
+            }
+        }
+    }
+    if init_st.next_id > MINER_START {
+        return Err(format!(
+            "genesis has {} accounts, miners must start at ID {}",
+            init_st.next_id - ACCOUNT_START,
+            MINER_START
+        )
+        .into());
+    }
+    init_st.next_id = MINER_START;
+    put_actor(
+        &mut tree,
+        &INIT_ACTOR_ADDR,
+        &INIT_ACTOR_CODE_ID,
+        &init_st,
+        TokenAmount::zero(),
+    )?;
+
+    // The reward actor starts from the power of all pre-sealed sectors, which are activated at
+    // genesis and expire with their deal
+    let mut qa_power = StoragePower::zero();
+    for m in template.miners.iter() {
+        for s in m.sectors.iter() {
+            let deal_weight =
+                BigInt::from(s.deal.piece_size.0) * (s.deal.end_epoch - s.deal.start_epoch);
+            qa_power += miner::qa_power_for_weight(
+                m.sector_size,
+                s.deal.end_epoch,
+                &deal_weight,
+                &BigInt::zero(),
+            );
+        }
+    }
+    put_actor(
+        &mut tree,
+        &REWARD_ACTOR_ADDR,
+        &REWARD_ACTOR_CODE_ID,
+        &reward::State::new(qa_power.clone()),
+        P::initial_reward_balance(),
+    )?;
+
+    put_actor(
+        &mut tree,
+        &CRON_ACTOR_ADDR,
+        &CRON_ACTOR_CODE_ID,
+        &cron::State {
+            entries: vec![
+                cron::Entry {
+                    receiver: *STORAGE_POWER_ACTOR_ADDR,
+                    method_num: power::Method::OnEpochTickEnd as MethodNum,
+                },
+                cron::Entry {
+                    receiver: *STORAGE_MARKET_ACTOR_ADDR,
+                    method_num: market::Method::CronTick as MethodNum,
+                },
+            ],
+        },
+        TokenAmount::zero(),
+    )?;
+    put_actor(
+        &mut tree,
+        &STORAGE_POWER_ACTOR_ADDR,
+        &POWER_ACTOR_CODE_ID,
+        &power::State::new(empty_map, empty_map),
+        TokenAmount::zero(),
+    )?;
+    put_actor(
+        &mut tree,
+        &STORAGE_MARKET_ACTOR_ADDR,
+        &MARKET_ACTOR_CODE_ID,
+        &market::State::new(empty_array, empty_map, empty_map),
+        TokenAmount::zero(),
+    )?;
+    put_actor(
+        &mut tree,
+        &VERIFIED_REGISTRY_ACTOR_ADDR,
+        &VERIFREG_ACTOR_CODE_ID,
+        &verifreg::State::new(empty_map, Address::new_id(ROOT_VERIFIER_ID)),
+        TokenAmount::zero(),
+    )?;
+    put_account(
+        &mut tree,
+        &BURNT_FUNDS_ACTOR_ADDR,
+        &BURNT_FUNDS_ACTOR_ADDR,
+        TokenAmount::zero(),
+    )?;
+    put_account(
+        &mut tree,
+        &RESERVE_ADDRESS,
+        &RESERVE_ADDRESS,
+        FIL_RESERVED.clone(),
+    )?;
+
+    let mut root = tree.flush()?;
+    if template.miners.is_empty() {
+        return Ok(root);
+    }
+
+    // Pledges of pre-sealed sectors are computed against the power of all of them
+    mutate_state(
+        store,
+        &mut root,
+        &STORAGE_POWER_ACTOR_ADDR,
+        |st: &mut power::State| {
+            st.this_epoch_qa_power_smoothed = FilterEstimate::new(qa_power, BigInt::zero());
+            Ok(())
+        },
+    )?;
+    for (i, m) in template.miners.iter().enumerate() {
+        let maddr = Address::new_id(MINER_START + i as u64);
+        setup_miner::<V, P, BS>(store, &mut root, &accounts, &maddr, m)
+            .map_err(|e| format!("failed to set up miner {}: {}", maddr, e))?;
+    }
+    mutate_state(
+        store,
+        &mut root,
+        &STORAGE_POWER_ACTOR_ADDR,
+        |st: &mut power::State| {
+            let (raw, qa) = st.current_total_power();
+            st.this_epoch_raw_byte_power = raw;
+            st.this_epoch_quality_adj_power = qa;
+            st.this_epoch_pledge_collateral = st.total_pledge_collateral.clone();
+            Ok(())
+        },
+    )?;
+
+    Ok(root)
+}
+
+/// Creates a miner, funds its market escrow and activates its pre-sealed sectors.
+fn setup_miner<V, P, BS>(
+    store: &BS,
+    root: &mut Cid,
+    accounts: &Accounts,
+    maddr: &Address,
+    m: &Miner,
+) -> Result<(), Box<dyn StdError>>
+where
+    V: ProofVerifier,
+    P: NetworkParams,
+    BS: BlockStore,
+{
+    let owner = accounts.resolve(&m.owner)?;
+    let worker = accounts.resolve(&m.worker)?;
+    let peer = if m.peer_id.is_empty() {
+        Vec::new()
+    } else {
+        bs58::decode(&m.peer_id)
+            .into_vec()
+            .map_err(|e| format!("invalid peer id {}: {}", m.peer_id, e))?
+    };
+
+    let ret: power::CreateMinerReturn = exec::<V, P, BS>(
+        store,
+        root,
+        owner,
+        *STORAGE_POWER_ACTOR_ADDR,
+        power::Method::CreateMiner as MethodNum,
+        m.power_balance.clone(),
+        Serialized::serialize(power::CreateMinerParams {
+            owner,
+            worker,
+            seal_proof_type: m.sector_size.into(),
+            peer,
+            multiaddrs: Vec::new(),
+        })?,
+    )?
+    .deserialize()?;
+    if &ret.id_address != maddr {
+        return Err(format!("miner was created with ID address {}", ret.id_address).into());
+    }
+
+    // The owner funds the miner's escrow, which must cover the collateral of its deals
+    if !m.market_balance.is_zero() {
+        exec::<V, P, BS>(
+            store,
+            root,
+            owner,
+            *STORAGE_MARKET_ACTOR_ADDR,
+            market::Method::AddBalance as MethodNum,
+            m.market_balance.clone(),
+            Serialized::serialize(maddr)?,
+        )?;
+    }
+    if m.sectors.is_empty() {
+        return Ok(());
+    }
+
+    let mut client_funds: HashMap<Address, TokenAmount> = HashMap::new();
+    let mut proposals = Vec::with_capacity(m.sectors.len());
+    for s in m.sectors.iter() {
+        if s.deal.verified_deal {
+            return Err(format!("pre-sealed sector {} has a verified deal", s.sector_id).into());
+        }
+        if &s.deal.provider != maddr {
+            return Err(format!(
+                "pre-sealed deal of sector {} has provider {}",
+                s.sector_id, s.deal.provider
+            )
+            .into());
+        }
+        let proposal = market::DealProposal {
+            piece_cid: s.deal.piece_cid,
+            piece_size: s.deal.piece_size,
+            verified_deal: false,
+            client: accounts.resolve(&s.deal.client)?,
+            provider: *maddr,
+            label: s.deal.label.clone(),
+            start_epoch: s.deal.start_epoch,
+            end_epoch: s.deal.end_epoch,
+            storage_price_per_epoch: s.deal.storage_price_per_epoch.clone(),
+            provider_collateral: s.deal.provider_collateral.clone(),
+            client_collateral: s.deal.client_collateral.clone(),
+        };
+        *client_funds.entry(proposal.client).or_default() += proposal.client_balance_requirement();
+        proposals.push(proposal);
+    }
+    for (client, amount) in client_funds.into_iter() {
+        if amount.is_zero() {
+            continue;
+        }
+        exec::<V, P, BS>(
+            store,
+            root,
+            client,
+            *STORAGE_MARKET_ACTOR_ADDR,
+            market::Method::AddBalance as MethodNum,
+            amount,
+            Serialized::serialize(client)?,
+        )?;
+    }
+    let deal_ids = publish_deals(store, root, &proposals)?;
+
+    // Pre-commit the sectors, so their proofs can be confirmed
+    let market_st: market::State = load_state(store, root, &STORAGE_MARKET_ACTOR_ADDR)?;
+    let mut pre_commits = Vec::with_capacity(m.sectors.len());
+    for (s, deal_id) in m.sectors.iter().zip(deal_ids) {
+        let (deal_weight, verified_deal_weight) = market::validate_deals_for_activation(
+            &market_st,
+            store,
+            &[deal_id],
+            maddr,
+            s.deal.end_epoch,
+            0,
+        )?;
+        pre_commits.push(miner::SectorPreCommitOnChainInfo {
+            info: miner::SectorPreCommitInfo {
+                seal_proof: s.proof_type,
+                sector_number: s.sector_id,
+                sealed_cid: s.comm_r,
+                seal_rand_epoch: 0,
+                deal_ids: vec![deal_id],
+                expiration: s.deal.end_epoch,
+                replace_capacity: false,
+                replace_sector_deadline: 0,
+                replace_sector_partition: 0,
+                replace_sector_number: 0,
+            },
+            pre_commit_deposit: TokenAmount::zero(),
+            pre_commit_epoch: 0,
+            deal_weight,
+            verified_deal_weight,
+        });
+    }
+
+    let sectors: Vec<_> = pre_commits.iter().map(|p| p.info.sector_number).collect();
+    mutate_state(store, root, maddr, |st: &mut miner::State| {
+        for pre_commit in pre_commits {
+            st.allocate_sector_number(store, pre_commit.info.sector_number)
+                .map_err(|e| e.msg().to_owned())?;
+            st.put_precommitted_sector(store, pre_commit)?;
+        }
+        Ok(())
+    })?;
+
+    exec::<V, P, BS>(
+        store,
+        root,
+        *STORAGE_POWER_ACTOR_ADDR,
+        *maddr,
+        miner::Method::ConfirmSectorProofsValid as MethodNum,
+        TokenAmount::zero(),
+        Serialized::serialize(miner::ConfirmSectorProofsParams { sectors })?,
+    )?;
+    Ok(())
+}
+
+/// Publishes deals in the market state, locking the client and provider funds in escrow.
+/// Pre-sealed deals aren't signed by their client, so they can't be published with a message.
+/// Returns the IDs of the deals.
+fn publish_deals<BS: BlockStore>(
+    store: &BS,
+    root: &mut Cid,
+    proposals: &[market::DealProposal],
+) -> Result<Vec<u64>, Box<dyn StdError>> {
+    let mut ids = Vec::with_capacity(proposals.len());
+    mutate_state(
+        store,
+        root,
+        &STORAGE_MARKET_ACTOR_ADDR,
+        |st: &mut market::State| {
+            let mut deals = Amt::load(&st.proposals, store)?;
+            let mut pending = make_map_with_root(&st.pending_proposals, store)?;
+            let mut deals_by_epoch = SetMultimap::from_root(store, &st.deal_ops_by_epoch)?;
+            let escrow = BalanceTable::from_root(store, &st.escrow_table)?;
+            let mut locked = BalanceTable::from_root(store, &st.locked_table)?;
+
+            for proposal in proposals {
+                for (addr, amount) in &[
+                    (proposal.client, proposal.client_balance_requirement()),
+                    (proposal.provider, proposal.provider_collateral.clone()),
+                ] {
+                    if locked.get(addr)? + amount > escrow.get(addr)? {
+                        return Err(format!("not enough escrow to lock for {}", addr).into());
+                    }
+                    locked.add(addr, amount)?;
+                }
+                st.total_client_locked_colateral += &proposal.client_collateral;
+                st.total_client_storage_fee += proposal.total_storage_fee();
+                st.total_provider_locked_colateral += &proposal.provider_collateral;
+
+                let id = st.next_id;
+                st.next_id += 1;
+                pending.set(proposal.cid()?.to_bytes().into(), proposal.clone())?;
+                deals.set(id, proposal.clone())?;
+                deals_by_epoch.put(proposal.start_epoch, id)?;
+                ids.push(id);
+            }
+
+            st.proposals = deals.flush()?;
+            st.pending_proposals = pending.flush()?;
+            st.deal_ops_by_epoch = deals_by_epoch.root()?;
+            st.locked_table = locked.root()?;
+            Ok(())
+        },
+    )?;
+    Ok(ids)
+}
+
+/// ID addresses of the accounts created at genesis, by key address.
+#[derive(Default)]
+struct Accounts(HashMap<Address, Address>);
+
+impl Accounts {
+    /// Returns the ID address of an account, by its key or ID address.
+    fn resolve(&self, addr: &Address) -> Result<Address, String> {
+        if addr.id().is_ok() {
+            return Ok(*addr);
+        }
+        self.0
+            .get(addr)
+            .copied()
+            .ok_or_else(|| format!("{} is not an account of the genesis", addr))
+    }
+
+    /// Adds a balance to the account of a key, creating the account if it doesn't exist.
+    fn credit<BS: BlockStore>(
+        &mut self,
+        tree: &mut StateTree<BS>,
+        init_st: &mut init::State,
+        key: &Address,
+        balance: TokenAmount,
+    ) -> Result<Address, Box<dyn StdError>> {
+        if let Some(id) = self.0.get(key) {
+            let id = *id;
+            tree.mutate_actor(&id, |actor| {
+                actor.deposit_funds(&balance);
+                Ok(())
+            })?;
+            return Ok(id);
+        }
+        let id = init_st.map_address_to_new_id(tree.store(), key)?;
+        put_account(tree, &id, key, balance)?;
+        self.0.insert(*key, id);
+        Ok(id)
+    }
+}
+
+fn put_actor<BS, S>(
+    tree: &mut StateTree<BS>,
+    addr: &Address,
+    code: &Cid,
+    state: &S,
+    balance: TokenAmount,
+) -> Result<(), Box<dyn StdError>>
+where
+    BS: BlockStore,
+    S: Serialize,
+{
+    let head = tree.store().put(state, Blake2b256)?;
+    tree.set_actor(addr, ActorState::new(*code, head, balance, 0))
+}
+
+fn put_account<BS: BlockStore>(
+    tree: &mut StateTree<BS>,
+    addr: &Address,
+    key: &Address,
+    balance: TokenAmount,
+) -> Result<(), Box<dyn StdError>> {
+    put_actor(
+        tree,
+        addr,
+        &ACCOUNT_ACTOR_CODE_ID,
+        &account::State { address: *key },
+        balance,
+    )
+}
+
+/// Creates a multisig, with accounts for its signers which don't exist yet.
+fn put_multisig<BS: BlockStore>(
+    tree: &mut StateTree<BS>,
+    init_st: &mut init::State,
+    accounts: &mut Accounts,
+    addr: &Address,
+    actor: &Actor,
+    empty_map: Cid,
+) -> Result<(), Box<dyn StdError>> {
+    let meta = actor
+        .multisig
+        .as_ref()
+        .ok_or_else(|| format!("multisig owned by {} has no signers", actor.owner))?;
+    let mut signers = Vec::with_capacity(meta.signers.len());
+    for signer in meta.signers.iter() {
+        signers.push(match accounts.resolve(&signer.0) {
+            Ok(id) => id,
+            Err(_) => accounts.credit(tree, init_st, &signer.0, TokenAmount::zero())?,
+        });
+    }
+    if meta.threshold == 0 || meta.threshold > signers.len() {
+        return Err(format!(
+            "multisig threshold {} is invalid for {} signers",
+            meta.threshold,
+            signers.len()
+        )
+        .into());
+    }
+
+    let mut st = multisig::State {
+        signers,
+        num_approvals_threshold: meta.threshold,
+        next_tx_id: Default::default(),
+        initial_balance: TokenAmount::zero(),
+        start_epoch: 0,
+        unlock_duration: 0,
+        pending_txs: empty_map,
+    };
+    if meta.vesting_duration > 0 {
+        st.set_locked(
+            meta.vesting_start,
+            meta.vesting_duration,
+            actor.balance.clone(),
+        );
+    }
+    put_actor(
+        tree,
+        addr,
+        &MULTISIG_ACTOR_CODE_ID,
+        &st,
+        actor.balance.clone(),
+    )
+}
+
+fn load_state<BS, S>(store: &BS, root: &Cid, addr: &Address) -> Result<S, Box<dyn StdError>>
+where
+    BS: BlockStore,
+    S: DeserializeOwned,
+{
+    let tree = StateTree::new_from_root(store, root)?;
+    let actor = tree
+        .get_actor(addr)?
+        .ok_or_else(|| format!("actor {} not found in genesis state", addr))?;
+    Ok(store
+        .get(&actor.state)?
+        .ok_or_else(|| format!("state of actor {} not found", addr))?)
+}
+
+/// Applies a change to the state of an actor, updating the state root.
+fn mutate_state<BS, S, F>(
+    store: &BS,
+    root: &mut Cid,
+    addr: &Address,
+    f: F,
+) -> Result<(), Box<dyn StdError>>
+where
+    BS: BlockStore,
+    S: Serialize + DeserializeOwned,
+    F: FnOnce(&mut S) -> Result<(), Box<dyn StdError>>,
+{
+    let mut tree = StateTree::new_from_root(store, root)?;
+    let mut actor = tree
+        .get_actor(addr)?
+        .ok_or_else(|| format!("actor {} not found in genesis state", addr))?;
+    let mut state: S = store
+        .get(&actor.state)?
+        .ok_or_else(|| format!("state of actor {} not found", addr))?;
+    f(&mut state)?;
+    actor.state = store.put(&state, Blake2b256)?;
+    tree.set_actor(addr, actor)?;
+    *root = tree.flush()?;
+    Ok(())
+}
+
+/// Applies a message at genesis, updating the state root. Returns the return value of the
+/// message, or an error if it failed.
+fn exec<V, P, BS>(
+    store: &BS,
+    root: &mut Cid,
+    from: Address,
+    to: Address,
+    method_num: MethodNum,
+    value: TokenAmount,
+    params: Serialized,
+) -> Result<Serialized, Box<dyn StdError>>
+where
+    V: ProofVerifier,
+    P: NetworkParams,
+    BS: BlockStore,
+{
+    let mut vm = VM::<_, _, _, _, V, P>::new(
+        root,
+        store,
+        0,
+        &GenesisRand,
+        TokenAmount::zero(),
+        get_network_version_default,
+        &GenesisSupply,
+    )?;
+    let msg = UnsignedMessage {
+        from,
+        to,
+        sequence: 0,
+        value,
+        method_num,
+        params,
+        gas_limit: fil_types::BLOCK_GAS_LIMIT * 10000,
+        version: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let ret = vm.apply_implicit_message(&msg);
+    if let Some(err) = ret.act_error {
+        return Err(format!("message to {} method {} failed: {}", to, method_num, err).into());
+    }
+    *root = vm.flush()?;
+    Ok(ret.msg_receipt.return_data)
+}
+
+/// Randomness at genesis, before there are any tickets or beacon entries.
+struct GenesisRand;
+
+impl Rand for GenesisRand {
+    fn get_chain_randomness(
+        &self,
+        _: DomainSeparationTag,
+        _: ChainEpoch,
+        _: &[u8],
+    ) -> Result<[u8; 32], Box<dyn StdError>> {
+        Ok([0; 32])
+    }
+
+    fn get_beacon_randomness(
+        &self,
+        _: DomainSeparationTag,
+        _: ChainEpoch,
+        _: &[u8],
+    ) -> Result<[u8; 32], Box<dyn StdError>> {
+        Ok([0; 32])
+    }
+}
+
+/// Circulating supply at genesis, where no tokens have been released yet.
+struct GenesisSupply;
+
+impl CircSupplyCalc for GenesisSupply {
+    fn get_supply<DB: BlockStore>(
+        &self,
+        _: ChainEpoch,
+        _: &StateTree<DB>,
+    ) -> Result<TokenAmount, Box<dyn StdError>> {
+        Ok(TokenAmount::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devnet_template;
+    use actor::MINER_ACTOR_CODE_ID;
+    use db::MemoryDB;
+    use fil_types::{verifier::MockVerifier, DevnetParams, FILECOIN_PRECISION};
+
+    #[test]
+    fn build_devnet_genesis() {
+        let store = MemoryDB::default();
+        let key = Address::new_secp256k1(&[1; 65]).unwrap();
+        let template = devnet_template("test".to_owned(), key, 2);
+        let header = build_genesis::<MockVerifier, DevnetParams, _>(&store, &template).unwrap();
+
+        let root = header.state_root();
+        let tree = StateTree::new_from_root(&store, root).unwrap();
+        let init_st: init::State = load_state(&store, root, &INIT_ACTOR_ADDR).unwrap();
+        assert_eq!(init_st.network_name, "test");
+
+        // The account paid the power actor balance of the miner it created
+        let account = init_st.resolve_address(&store, &key).unwrap().unwrap();
+        assert_eq!(account, Address::new_id(ACCOUNT_START));
+        let account_balance = tree.get_actor(&account).unwrap().unwrap().balance;
+        assert_eq!(
+            account_balance,
+            BigInt::from(50_000_000 - 1_000) * FILECOIN_PRECISION
+        );
+
+        let maddr = Address::new_id(MINER_START);
+        let miner_actor = tree.get_actor(&maddr).unwrap().unwrap();
+        assert_eq!(miner_actor.code, *MINER_ACTOR_CODE_ID);
+        assert_eq!(
+            miner_actor.balance,
+            BigInt::from(1_000) * FILECOIN_PRECISION
+        );
+        let miner_st: miner::State = load_state(&store, root, &maddr).unwrap();
+        let info = miner_st.get_info(&store).unwrap();
+        assert_eq!(info.owner, account);
+        assert_eq!(info.worker, account);
+        assert_eq!(
+            Amt::<miner::SectorOnChainInfo, _>::load(&miner_st.sectors, &store)
+                .unwrap()
+                .count(),
+            2
+        );
+
+        // Sectors full of unverified deals have as much quality adjusted power as raw power
+        let power_st: power::State = load_state(&store, root, &STORAGE_POWER_ACTOR_ADDR).unwrap();
+        let claims = make_map_with_root(&power_st.claims, &store).unwrap();
+        let claim = power::get_claim(&claims, &maddr).unwrap().unwrap();
+        let sector_power = StoragePower::from(2 * 2048);
+        assert_eq!(claim.raw_byte_power, sector_power);
+        assert_eq!(claim.quality_adj_power, sector_power);
+        assert_eq!(power_st.miner_count, 1);
+        assert_eq!(power_st.this_epoch_raw_byte_power, sector_power);
+        assert_eq!(power_st.this_epoch_quality_adj_power, sector_power);
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::MINER_START;
+use address::Address;
+use clock::ChainEpoch;
+use commcid::{data_commitment_v1_to_cid, replica_commitment_v1_to_cid};
+use fil_types::genesis::{Actor, ActorType, Miner, PreSeal, PreSealDeal, Template};
+use fil_types::{PaddedPieceSize, RegisteredSealProof, SectorSize, FILECOIN_PRECISION};
+use num_bigint::BigInt;
+use vm::TokenAmount;
+
+/// Epochs the deals of devnet pre-sealed sectors last, which is also when the sectors expire.
+const DEVNET_DEAL_DURATION: ChainEpoch = 1_000_000;
+
+/// Returns the genesis template of a local devnet. It has one miner, owned and worked by the
+/// given key, with pre-sealed 2KiB sectors which have mock commitments. These sectors can only be
+/// proven to nodes which use mock proofs.
+pub fn devnet_template(network_name: String, worker: Address, sectors: u64) -> Template {
+    let maddr = Address::new_id(MINER_START);
+    let sector_size = SectorSize::_2KiB;
+
+    let sectors = (0..sectors)
+        .map(|i| {
+            // Commitments only have to be unique, they are never checked against sealed data
+            let mut commitment = [0; 32];
+            commitment[..8].copy_from_slice(&i.to_be_bytes());
+            let comm_d = data_commitment_v1_to_cid(&commitment).unwrap();
+            PreSeal {
+                comm_r: replica_commitment_v1_to_cid(&commitment).unwrap(),
+                comm_d,
+                sector_id: i,
+                deal: PreSealDeal {
+                    piece_cid: comm_d,
+                    piece_size: PaddedPieceSize(sector_size as u64),
+                    verified_deal: false,
+                    client: worker,
+                    provider: maddr,
+                    label: String::new(),
+                    start_epoch: 0,
+                    end_epoch: DEVNET_DEAL_DURATION,
+                    storage_price_per_epoch: TokenAmount::default(),
+                    provider_collateral: TokenAmount::default(),
+                    client_collateral: TokenAmount::default(),
+                },
+                proof_type: RegisteredSealProof::from(sector_size),
+            }
+        })
+        .collect();
+
+    let mut template = Template::new(network_name);
+    template.accounts.push(Actor {
+        actor_type: ActorType::Account,
+        balance: BigInt::from(50_000_000) * FILECOIN_PRECISION,
+        owner: worker,
+        multisig: None,
+    });
+    template.miners.push(Miner {
+        owner: worker,
+        worker,
+        peer_id: String::new(),
+        market_balance: TokenAmount::default(),
+        power_balance: BigInt::from(1_000) * FILECOIN_PRECISION,
+        sector_size,
+        sectors,
+    });
+    template
+}
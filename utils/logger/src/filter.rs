@@ -0,0 +1,139 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use log::LevelFilter;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Modules which are too noisy at the info level, logged at the warn level by default.
+const QUIET_MODULES: [&str; 5] = [
+    "libp2p_gossipsub",
+    "filecoin_proofs",
+    "storage_proofs_core",
+    "surf::middleware",
+    "tide",
+];
+
+/// Level filters of the logger, set for all modules by default and per module.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Filters {
+    default: LevelFilter,
+    modules: BTreeMap<String, LevelFilter>,
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Self {
+            default: LevelFilter::Info,
+            modules: QUIET_MODULES
+                .iter()
+                .map(|m| (m.to_string(), LevelFilter::Warn))
+                .collect(),
+        }
+    }
+}
+
+impl Filters {
+    /// Parses filters in the `RUST_LOG` format, e.g. `info,chain_sync=debug`. Directives which
+    /// fail to parse are ignored.
+    pub fn parse(spec: &str) -> Self {
+        let mut filters = Self {
+            default: LevelFilter::Error,
+            modules: BTreeMap::new(),
+        };
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let mut parts = directive.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(level), None) => match LevelFilter::from_str(level) {
+                    Ok(level) => filters.default = level,
+                    // A module without a level is logged at all levels
+                    Err(_) => filters.set(level, LevelFilter::Trace),
+                },
+                (Some(module), Some(level)) => {
+                    if let Ok(level) = LevelFilter::from_str(level) {
+                        filters.set(module, level);
+                    }
+                }
+                _ => {}
+            }
+        }
+        filters
+    }
+
+    /// Sets the level of the module, or the default level if the module is empty or `*`.
+    pub fn set(&mut self, module: &str, level: LevelFilter) {
+        if module.is_empty() || module == "*" {
+            self.default = level;
+        } else {
+            self.modules.insert(module.to_owned(), level);
+        }
+    }
+
+    /// Returns the level of the target, from the filter of the closest parent module.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .rev()
+            .find(|(module, _)| {
+                target.starts_with(module.as_str())
+                    && (target.len() == module.len() || target[module.len()..].starts_with("::"))
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// Returns the most verbose level of any module, above which nothing is logged.
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .values()
+            .copied()
+            .fold(self.default, LevelFilter::max)
+    }
+
+    /// Returns the modules with a level set.
+    pub fn modules(&self) -> impl Iterator<Item = (&String, &LevelFilter)> {
+        self.modules.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_filters() {
+        let filters = Filters::parse("debug,chain_sync=trace,libp2p=warn,forest_libp2p");
+        assert_eq!(filters.level("rpc"), LevelFilter::Debug);
+        assert_eq!(filters.level("chain_sync::sync_worker"), LevelFilter::Trace);
+        assert_eq!(filters.level("libp2p_gossipsub"), LevelFilter::Debug);
+        assert_eq!(filters.level("libp2p::swarm"), LevelFilter::Warn);
+        assert_eq!(filters.level("forest_libp2p"), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn closest_module_level() {
+        let mut filters = Filters::default();
+        filters.set("chain_sync", LevelFilter::Debug);
+        filters.set("chain_sync::network_context", LevelFilter::Error);
+        filters.set("*", LevelFilter::Warn);
+
+        assert_eq!(filters.level("chain_sync::sync"), LevelFilter::Debug);
+        assert_eq!(
+            filters.level("chain_sync::network_context"),
+            LevelFilter::Error
+        );
+        assert_eq!(filters.level("surf::middleware::logger"), LevelFilter::Warn);
+        assert_eq!(filters.level("forest"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn max_level_of_modules() {
+        let mut filters = Filters::default();
+        assert_eq!(filters.max_level(), LevelFilter::Info);
+        filters.set("chain_sync", LevelFilter::Trace);
+        assert_eq!(filters.max_level(), LevelFilter::Trace);
+        filters.set("chain_sync", LevelFilter::Error);
+        filters.set("*", LevelFilter::Debug);
+        assert_eq!(filters.max_level(), LevelFilter::Debug);
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod filter;
+mod rotate;
+
+use self::filter::Filters;
+use self::rotate::RotatingFile;
+use chrono::{SecondsFormat, Utc};
+use lazy_static::lazy_static;
+use log::{kv, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error as StdError;
+use std::io::{self, Write};
+use std::sync::{Mutex, RwLock};
+
+lazy_static! {
+    /// Filters of the global logger, shared so levels can be changed at runtime.
+    static ref FILTERS: RwLock<Filters> = RwLock::new(Filters::default());
+    /// Root modules which have logged, or attempted to log, since startup.
+    static ref MODULES: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+}
+
+/// Logger configuration.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LoggerConfig {
+    /// If this is true, logs are written as JSON objects, one per line, with the timestamp,
+    /// level, module and message of the record, and its structured key-value fields.
+    pub json: bool,
+    /// File logs are written to, in addition to stderr.
+    pub file: Option<String>,
+    /// Size in bytes at which the log file is rotated, or 0 to never rotate it.
+    pub max_file_size: u64,
+    /// Number of rotated log files kept.
+    pub max_files: usize,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            json: false,
+            file: None,
+            max_file_size: 100 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+struct Logger {
+    json: bool,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let target = metadata.target();
+        let root = target.split("::").next().unwrap_or(target);
+        if !MODULES.read().unwrap().contains(root) {
+            MODULES.write().unwrap().insert(root.to_owned());
+        }
+        metadata.level() <= FILTERS.read().unwrap().level(target)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut line = if self.json {
+            format_json(record)
+        } else {
+            format_text(record)
+        };
+        // Lines are written at once, so lines of concurrent records don't interleave
+        line.push('\n');
+
+        let _ = io::stderr().write_all(line.as_bytes());
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.write_all(line.as_bytes());
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+fn timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn format_text(record: &Record) -> String {
+    let mut line = format!(
+        "{} {:<5} {} > {}",
+        timestamp(),
+        record.level(),
+        record.target(),
+        record.args()
+    );
+    let mut fields = Fields(Map::new());
+    let _ = record.key_values().visit(&mut fields);
+    for (key, value) in fields.0 {
+        if let Value::String(value) = value {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    line
+}
+
+fn format_json(record: &Record) -> String {
+    let mut entry = json!({
+        "timestamp": timestamp(),
+        "level": record.level().to_string(),
+        "module": record.target(),
+        "message": record.args().to_string(),
+    });
+    let mut fields = Fields(Map::new());
+    // Collecting the fields can't fail
+    let _ = record.key_values().visit(&mut fields);
+    if !fields.0.is_empty() {
+        entry["fields"] = Value::Object(fields.0);
+    }
+    entry.to_string()
+}
+
+/// Structured key-value fields of a record, such as the epoch or peer it is about.
+struct Fields(Map<String, Value>);
+
+impl<'kvs> kv::Visitor<'kvs> for Fields {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0
+            .insert(key.to_string(), Value::String(value.to_string()));
+        Ok(())
+    }
+}
+
+/// Sets up the global logger. Filters are read from the `RUST_LOG` environment variable, or
+/// default to the info level with noisy modules at the warn level.
+pub fn setup_logger(config: &LoggerConfig) -> Result<(), Box<dyn StdError>> {
+    if let Ok(s) = std::env::var("RUST_LOG") {
+        *FILTERS.write().unwrap() = Filters::parse(&s);
+    }
+
+    let file = match &config.file {
+        Some(path) => Some(Mutex::new(RotatingFile::open(
+            path,
+            config.max_file_size,
+            config.max_files,
+        )?)),
+        None => None,
+    };
+    let logger = Logger {
+        json: config.json,
+        file,
+    };
+
+    // Wrap Logger in async_log. Records above the most verbose level of the filters are
+    // skipped before they are formatted, this level is raised when filters change.
+    let max_level = FILTERS.read().unwrap().max_level();
+    async_log::Logger::wrap(logger, || 0).start(max_level)?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Sets the level logged for the module and its submodules, or the default level of all
+/// modules if the module is `*`.
+pub fn set_level(module: &str, level: LevelFilter) {
+    let mut filters = FILTERS.write().unwrap();
+    filters.set(module, level);
+    log::set_max_level(filters.max_level());
+}
+
+/// Returns the level logged for each module which has logged since startup or has a level set.
+pub fn levels() -> BTreeMap<String, LevelFilter> {
+    let filters = FILTERS.read().unwrap();
+    let mut levels: BTreeMap<_, _> = MODULES
+        .read()
+        .unwrap()
+        .iter()
+        .map(|m| (m.clone(), filters.level(m)))
+        .collect();
+    levels.extend(filters.modules().map(|(m, l)| (m.clone(), *l)));
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use log::{info, Level};
+
+    /// Logger keeping the JSON lines of the records it receives.
+    struct Capture(Mutex<Vec<String>>);
+
+    impl Log for Capture {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push(format_json(record));
+        }
+        fn flush(&self) {}
+    }
+
+    lazy_static! {
+        static ref CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+    }
+
+    #[test]
+    fn json_record_fields() {
+        let line = format_json(
+            &Record::builder()
+                .args(format_args!("Received block"))
+                .level(Level::Info)
+                .target("chain_sync")
+                .key_values(&("epoch", 10))
+                .build(),
+        );
+        let entry: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(entry["level"], "INFO");
+        assert_eq!(entry["module"], "chain_sync");
+        assert_eq!(entry["message"], "Received block");
+        assert_eq!(entry["fields"], json!({ "epoch": "10" }));
+
+        // Messages are not parsed for fields
+        let line = format_json(
+            &Record::builder()
+                .args(format_args!("Received block epoch=10"))
+                .level(Level::Info)
+                .target("chain_sync")
+                .build(),
+        );
+        let entry: Value = serde_json::from_str(&line).unwrap();
+        assert!(entry.get("fields").is_none());
+    }
+
+    #[test]
+    fn json_macro_fields() {
+        log::set_logger(&*CAPTURE).unwrap();
+        log::set_max_level(LevelFilter::Info);
+
+        let cid = "bafy2bzacea";
+        info!(cid = cid, epoch = 12; "Produced block");
+
+        let lines = CAPTURE.0.lock().unwrap();
+        let entry: Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+        assert_eq!(entry["message"], "Produced block");
+        assert_eq!(
+            entry["fields"],
+            json!({ "cid": "bafy2bzacea", "epoch": "12" })
+        );
+    }
+
+    #[test]
+    fn text_record_fields() {
+        let line = format_text(
+            &Record::builder()
+                .args(format_args!("Rolled back head"))
+                .level(Level::Info)
+                .target("forest")
+                .key_values(&("epoch", 10))
+                .build(),
+        );
+        assert!(line.ends_with("forest > Rolled back head epoch=10"));
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod server;
+
+pub use self::server::{serve_http, start_metrics_server, HttpResponse};
+
+use lazy_static::lazy_static;
+use prometheus::{
+    exponential_buckets, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge, register_int_gauge_vec, Histogram, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
+
+lazy_static! {
+    /// Epoch of the heaviest tipset.
+    pub static ref CHAIN_HEAD_EPOCH: IntGauge = register_int_gauge!(
+        "forest_chain_head_epoch",
+        "Epoch of the heaviest tipset"
+    )
+    .unwrap();
+    /// Number of sync workers in each stage, labeled by `stage`.
+    pub static ref SYNC_WORKERS: IntGaugeVec = register_int_gauge_vec!(
+        "forest_sync_workers",
+        "Number of sync workers in each stage",
+        &["stage"]
+    )
+    .unwrap();
+    /// Epochs between the heaviest tipset and the highest tipset being synced to.
+    pub static ref SYNC_LAG_EPOCHS: IntGauge = register_int_gauge!(
+        "forest_sync_lag_epochs",
+        "Epochs between the heaviest tipset and the highest sync target"
+    )
+    .unwrap();
+    /// Time taken to validate the blocks of a tipset.
+    pub static ref TIPSET_VALIDATION_SECONDS: Histogram = register_histogram!(
+        "forest_tipset_validation_seconds",
+        "Time taken to validate the blocks of a tipset",
+        exponential_buckets(0.01, 2.0, 12).unwrap()
+    )
+    .unwrap();
+    /// Number of messages pending in the message pool.
+    pub static ref MPOOL_MESSAGES: IntGauge = register_int_gauge!(
+        "forest_mpool_pending_messages",
+        "Number of messages pending in the message pool"
+    )
+    .unwrap();
+    /// Number of peers connected.
+    pub static ref PEERS_CONNECTED: IntGauge = register_int_gauge!(
+        "forest_peers_connected",
+        "Number of peers connected"
+    )
+    .unwrap();
+    /// Latency of blocksync requests to peers, labeled by `result` (`success` or `failure`).
+    pub static ref BLOCKSYNC_REQUEST_SECONDS: HistogramVec = register_histogram_vec!(
+        "forest_blocksync_request_seconds",
+        "Latency of blocksync requests to peers",
+        &["result"],
+        exponential_buckets(0.05, 2.0, 10).unwrap()
+    )
+    .unwrap();
+    /// Gossip messages received, labeled by `kind` (`block` or `message`) and `result`
+    /// (`accepted` or `rejected`).
+    pub static ref GOSSIP_MESSAGES: IntCounterVec = register_int_counter_vec!(
+        "forest_gossip_messages_total",
+        "Gossip messages received",
+        &["kind", "result"]
+    )
+    .unwrap();
+    /// Size of the database files.
+    pub static ref DB_SIZE_BYTES: IntGauge = register_int_gauge!(
+        "forest_db_size_bytes",
+        "Size of the database files"
+    )
+    .unwrap();
+    /// Reads served from the block cache of the database.
+    pub static ref DB_CACHE_HITS: IntCounter = register_int_counter!(
+        "forest_db_cache_hits_total",
+        "Reads served from the block cache of the database"
+    )
+    .unwrap();
+    /// Reads which missed the block cache of the database.
+    pub static ref DB_CACHE_MISSES: IntCounter = register_int_counter!(
+        "forest_db_cache_misses_total",
+        "Reads which missed the block cache of the database"
+    )
+    .unwrap();
+    /// RPC calls handled, labeled by `method`.
+    pub static ref RPC_CALLS: IntCounterVec = register_int_counter_vec!(
+        "forest_rpc_calls_total",
+        "RPC calls handled",
+        &["method"]
+    )
+    .unwrap();
+    /// Time taken to handle RPC calls, labeled by `method`.
+    pub static ref RPC_CALL_SECONDS: HistogramVec = register_histogram_vec!(
+        "forest_rpc_call_seconds",
+        "Time taken to handle RPC calls",
+        &["method"]
+    )
+    .unwrap();
+}
+
+/// Label of gossip blocks in `GOSSIP_MESSAGES`.
+pub const GOSSIP_BLOCK: &str = "block";
+/// Label of gossip messages in `GOSSIP_MESSAGES`.
+pub const GOSSIP_MESSAGE: &str = "message";
+
+/// Counts a gossip message of the kind as accepted or rejected.
+pub fn record_gossip(kind: &str, accepted: bool) {
+    let result = if accepted { "accepted" } else { "rejected" };
+    GOSSIP_MESSAGES.with_label_values(&[kind, result]).inc();
+}
+
+/// Sets a counter to a total read from an external source, such as database statistics.
+pub fn set_counter(counter: &IntCounter, total: u64) {
+    counter.inc_by(total.saturating_sub(counter.get()));
+}
@@ -0,0 +1,162 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use async_std::io::{self, prelude::*, BufReader};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task;
+use futures::future::Future;
+use futures::stream::StreamExt;
+use log::{debug, info, warn};
+use prometheus::{Encoder, TextEncoder};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time a client has to send its request before the connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum size in bytes of the request line and headers of a request.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// Response to a request of the HTTP server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    /// Status line of the response, e.g. `200 OK`.
+    pub status: &'static str,
+    /// Media type of the body.
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Returns a `200 OK` response with the body.
+    pub fn ok(content_type: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: content_type.into(),
+            body: body.into(),
+        }
+    }
+
+    /// Returns a `404 Not Found` response.
+    pub fn not_found() -> Self {
+        Self {
+            status: "404 Not Found",
+            content_type: "text/plain".to_owned(),
+            body: b"Not Found\n".to_vec(),
+        }
+    }
+}
+
+/// Serves the metrics in the Prometheus text format at `/metrics` on the address. The refresh
+/// function is awaited before each scrape, to update the metrics sampled from the node state.
+pub async fn start_metrics_server<F, Fut>(addr: &str, refresh: F) -> io::Result<()>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    info!("Metrics endpoint at {}", addr);
+    serve_http(addr, move |path| {
+        let scrape = if path == "/metrics" {
+            Some(refresh())
+        } else {
+            None
+        };
+        async move {
+            match scrape {
+                Some(refresh) => {
+                    refresh.await;
+                    encode_metrics()
+                }
+                None => HttpResponse::not_found(),
+            }
+        }
+    })
+    .await
+}
+
+fn encode_metrics() -> HttpResponse {
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    match encoder.encode(&prometheus::gather(), &mut body) {
+        Ok(()) => HttpResponse::ok(encoder.format_type(), body),
+        Err(e) => HttpResponse {
+            status: "500 Internal Server Error",
+            content_type: "text/plain".to_owned(),
+            body: format!("{}\n", e).into_bytes(),
+        },
+    }
+}
+
+/// Serves HTTP `GET` requests on the address, responding with the response the handler returns
+/// for the path of the request. Each connection is served on its own task, so a slow client
+/// doesn't hold up others.
+pub async fn serve_http<F, Fut>(addr: &str, handler: F) -> io::Result<()>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    let listener = TcpListener::bind(addr).await?;
+    let handler = Arc::new(handler);
+
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        match stream {
+            Ok(stream) => {
+                let handler = Arc::clone(&handler);
+                task::spawn(async move {
+                    if let Err(e) = handle_request(stream, handler.as_ref()).await {
+                        debug!("Failed to serve HTTP request: {}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("Failed to accept HTTP connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+async fn handle_request<F, Fut>(mut stream: TcpStream, handler: &F) -> io::Result<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = HttpResponse>,
+{
+    let path = io::timeout(REQUEST_TIMEOUT, read_request_path(&stream)).await?;
+    let response = handler(path).await;
+
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await
+}
+
+/// Reads the request line and headers, returning the path of the request without its query.
+/// Requests are read up to `MAX_REQUEST_SIZE` bytes, and rejected if they are longer.
+async fn read_request_path(stream: &TcpStream) -> io::Result<String> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+
+    // Headers are not used, but are read so the client does not see the connection reset
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            if reader.get_ref().limit() == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "request is too large",
+                ));
+            }
+            break;
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    Ok(target.split('?').next().unwrap_or(target).to_owned())
+}
@@ -2,8 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use async_std::{
-    fs::{self, File},
-    io::{copy, BufRead, BufWriter},
+    fs::{self, File, OpenOptions},
+    io::{copy, BufRead, BufReader, BufWriter},
     sync::{channel, Arc},
     task,
 };
@@ -11,6 +11,7 @@ use blake2b_simd::{Hash, State as Blake2b};
 use core::time::Duration;
 use fil_types::SectorSize;
 use futures::prelude::*;
+use futures::stream;
 use log::{info, warn};
 use pbr::{MultiBar, ProgressBar, Units};
 use pin_project_lite::pin_project;
@@ -22,14 +23,24 @@ use std::io::{self, copy as sync_copy, BufReader as SyncBufReader, ErrorKind, St
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use surf::Client;
+use surf::{Client, StatusCode};
 
 const GATEWAY: &str = "https://proofs.filecoin.io/ipfs/";
 const PARAM_DIR: &str = "/var/tmp/filecoin-proof-parameters";
 const DIR_ENV: &str = "FIL_PROOFS_PARAMETER_CACHE";
 const GATEWAY_ENV: &str = "IPFS_GATEWAY";
 const TRUST_PARAMS_ENV: &str = "TRUST_PARAMS";
-const DEFAULT_PARAMETERS: &str = include_str!("parameters.json");
+const CONCURRENCY_ENV: &str = "PARAM_FETCH_CONCURRENCY";
+/// Default number of parameter files fetched at the same time.
+const DEFAULT_CONCURRENCY: usize = 4;
+/// Number of times a download is attempted, each one resuming from where the last one stopped.
+const FETCH_ATTEMPTS: usize = 3;
+/// Delay before the second download attempt, which doubles before each following one.
+const FETCH_BACKOFF: Duration = Duration::from_secs(2);
+/// Directory in the parameter cache where files which fail verification are moved to.
+const QUARANTINE_DIR: &str = "quarantine";
+/// Manifest of the parameter files used by the supported proofs.
+pub const DEFAULT_PARAMETERS: &str = include_str!("parameters.json");
 
 /// Sector size options for fetching.
 pub enum SectorSizeOpt {
@@ -41,6 +52,36 @@ pub enum SectorSizeOpt {
     Size(SectorSize),
 }
 
+/// Source the parameter files are fetched from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamSource {
+    /// IPFS gateway the files are requested from by CID.
+    Gateway(String),
+    /// Local directory holding a copy of the files, named either by file name or by CID.
+    Mirror(PathBuf),
+}
+
+impl ParamSource {
+    /// Parses a gateway URL, or the `file://` URL or path of a mirror directory.
+    pub fn parse(s: &str) -> Self {
+        if let Some(path) = s.strip_prefix("file://") {
+            ParamSource::Mirror(path.into())
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            ParamSource::Gateway(s.to_owned())
+        } else {
+            ParamSource::Mirror(s.into())
+        }
+    }
+
+    /// Returns the source set with the `IPFS_GATEWAY` environment variable, or the default
+    /// gateway if it is not set.
+    pub fn from_env() -> Self {
+        std::env::var(GATEWAY_ENV)
+            .map(|s| Self::parse(&s))
+            .unwrap_or_else(|_| ParamSource::Gateway(GATEWAY.to_owned()))
+    }
+}
+
 type ParameterMap = HashMap<String, ParameterData>;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -55,6 +96,15 @@ fn param_dir() -> String {
     std::env::var(DIR_ENV).unwrap_or_else(|_| PARAM_DIR.to_owned())
 }
 
+#[inline]
+fn concurrency() -> usize {
+    std::env::var(CONCURRENCY_ENV)
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .filter(|c| *c > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+}
+
 /// Get proofs parameters and all verification keys for a given sector size given
 /// a param JSON manifest.
 pub async fn get_params(
@@ -62,10 +112,28 @@ pub async fn get_params(
     storage_size: SectorSizeOpt,
     is_verbose: bool,
 ) -> Result<(), Box<dyn StdError>> {
-    fs::create_dir_all(param_dir()).await?;
+    get_params_from(
+        param_json,
+        storage_size,
+        is_verbose,
+        ParamSource::from_env(),
+    )
+    .await
+}
+
+/// Get proofs parameters and all verification keys for a given sector size given
+/// a param JSON manifest, from the given source.
+pub async fn get_params_from(
+    param_json: &str,
+    storage_size: SectorSizeOpt,
+    is_verbose: bool,
+    source: ParamSource,
+) -> Result<(), Box<dyn StdError>> {
+    let dir = Arc::new(PathBuf::from(param_dir()));
+    fs::create_dir_all(dir.as_ref()).await?;
 
     let params: ParameterMap = serde_json::from_str(param_json)?;
-    let mut tasks = Vec::with_capacity(params.len());
+    let source = Arc::new(source);
 
     let mb = if is_verbose {
         Some(Arc::new(MultiBar::new()))
@@ -73,7 +141,7 @@ pub async fn get_params(
         None
     };
 
-    params
+    let params: Vec<_> = params
         .into_iter()
         .filter(|(name, info)| match storage_size {
             SectorSizeOpt::Keys => !name.ends_with("params"),
@@ -82,16 +150,31 @@ pub async fn get_params(
             }
             SectorSizeOpt::All => true,
         })
-        .for_each(|(name, info)| {
-            let cmb = mb.clone();
-            tasks.push(task::spawn(async move {
-                if let Err(e) = fetch_verify_params(&name, Arc::new(info), cmb).await {
-                    warn!("Error in validating params {}", e);
-                }
-            }))
-        });
-
-    if let Some(multi_bar) = mb {
+        .collect();
+
+    // Tasks are only spawned as running ones complete, to bound the number of downloads
+    let cmb = mb.clone();
+    let fetches = task::spawn(
+        stream::iter(params)
+            .map(move |(name, info)| {
+                let cmb = cmb.clone();
+                let source = source.clone();
+                let dir = dir.clone();
+                task::spawn(async move {
+                    match fetch_verify_params(&dir, &source, &name, Arc::new(info), cmb).await {
+                        Ok(()) => true,
+                        Err(e) => {
+                            warn!("Error in validating params {}: {}", name, e);
+                            false
+                        }
+                    }
+                })
+            })
+            .buffer_unordered(concurrency())
+            .collect::<Vec<bool>>(),
+    );
+
+    let results = if let Some(multi_bar) = mb {
         let cmb = multi_bar.clone();
         let (mb_send, mb_rx) = channel(1);
         let mb = task::spawn(async move {
@@ -100,17 +183,18 @@ pub async fn get_params(
                 task::sleep(Duration::from_millis(1000)).await;
             }
         });
-        for t in tasks {
-            t.await;
-        }
+        let results = fetches.await;
         mb_send.send(()).await;
         mb.await;
+        results
     } else {
-        for t in tasks {
-            t.await;
-        }
-    }
+        fetches.await
+    };
 
+    let failed = results.into_iter().filter(|ok| !ok).count();
+    if failed > 0 {
+        return Err(format!("failed to fetch {} parameter files", failed).into());
+    }
     Ok(())
 }
 
@@ -123,30 +207,76 @@ pub async fn get_params_default(
     get_params(DEFAULT_PARAMETERS, storage_size, is_verbose).await
 }
 
+/// Fetches the parameter file into the parameter cache directory, unless a valid one is already
+/// there, and verifies it.
 async fn fetch_verify_params(
+    dir: &Path,
+    source: &ParamSource,
     name: &str,
     info: Arc<ParameterData>,
     mb: Option<Arc<MultiBar<Stdout>>>,
-) -> Result<(), Box<dyn StdError>> {
-    let mut path: PathBuf = param_dir().into();
-    path.push(name);
-    let path = Arc::new(path);
+) -> Result<(), String> {
+    let path = Arc::new(dir.join(name));
 
     match check_file(path.clone(), info.clone()).await {
         Ok(()) => return Ok(()),
         Err(e) => {
             if e.kind() != ErrorKind::NotFound {
-                warn!("{}", e)
+                warn!("{}", e);
+                quarantine(dir, &path).await.map_err(|e| e.to_string())?;
             }
         }
     }
 
-    fetch_params(&path, &info, mb).await?;
+    let mut attempt = 1;
+    loop {
+        let res = match source {
+            ParamSource::Gateway(gw) => fetch_params(gw, &path, &info, mb.clone()).await,
+            ParamSource::Mirror(dir) => copy_params(dir, &path, &info, mb.clone()).await,
+        };
+        match res {
+            Ok(()) => break,
+            Err(e) if attempt < FETCH_ATTEMPTS => {
+                let backoff = fetch_backoff(attempt);
+                warn!(
+                    "Fetching {:?} failed, resuming in {:?}: {}",
+                    path, backoff, e
+                );
+                task::sleep(backoff).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
 
-    check_file(path, info).await.map_err(|e| {
-        // TODO remove invalid file
-        e.into()
-    })
+    if let Err(e) = check_file(path.clone(), info).await {
+        quarantine(dir, &path).await.map_err(|e| e.to_string())?;
+        return Err(e.to_string());
+    }
+    Ok(())
+}
+
+/// Returns the delay before the download attempt following the given failed one.
+fn fetch_backoff(attempt: usize) -> Duration {
+    FETCH_BACKOFF * 2u32.pow(attempt as u32 - 1)
+}
+
+/// Moves a parameter file which failed verification out of the cache directory, so it is
+/// downloaded again from scratch rather than resumed.
+async fn quarantine(cache_dir: &Path, path: &Path) -> Result<(), io::Error> {
+    let dir = cache_dir.join(QUARANTINE_DIR);
+    fs::create_dir_all(&dir).await?;
+    let name = path.file_name().unwrap_or_default();
+    let dest = dir.join(name);
+    warn!("Moving invalid parameter file {:?} to {:?}", path, dest);
+    fs::rename(path, dest).await
+}
+
+/// Returns the path of the partially downloaded file.
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".part");
+    path.with_file_name(name)
 }
 
 pin_project! {
@@ -188,51 +318,114 @@ impl<R: BufRead + Unpin> BufRead for FetchProgress<R> {
     }
 }
 
-async fn fetch_params(
-    path: &Path,
-    info: &ParameterData,
+/// Copies the source into the writer, reporting progress on a new bar of the multi bar if given.
+/// The bar starts at the offset, for resumed downloads.
+async fn write_progress<R, W>(
+    source: R,
+    writer: &mut W,
+    offset: u64,
+    total_size: u64,
     multi_bar: Option<Arc<MultiBar<Stdout>>>,
-) -> Result<(), Box<dyn StdError>> {
-    let gw = std::env::var(GATEWAY_ENV).unwrap_or_else(|_| GATEWAY.to_owned());
-    info!("Fetching {:?} from {}", path, gw);
-
-    let file = File::create(path).await?;
-    let mut writer = BufWriter::new(file);
-
-    let url = format!("{}{}", gw, info.cid);
-
-    let client = Client::new();
-
-    let req = client.get(url.as_str());
-
+) -> Result<(), io::Error>
+where
+    R: AsyncRead + BufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
     if let Some(mb) = multi_bar {
-        let total_size: u64 = {
-            // TODO head requests are broken in Surf right now, revisit when fixed
-            // let res = client.head(&url).await?;
-            // if res.status().is_success() {
-            //     res.header("Content-Length")
-            //         .and_then(|len| len.as_str().parse().ok())
-            //         .unwrap_or_default()
-            // } else {
-            //     return Err(format!("failed to download file: {}", url).into());
-            // }
-            100
-        };
         let mut pb = mb.create_bar(total_size);
         pb.set_units(Units::Bytes);
+        pb.set(offset);
 
         let mut source = FetchProgress {
-            inner: req.await?,
+            inner: source,
             progress_bar: pb,
         };
-        copy(&mut source, &mut writer).await?;
+        copy(&mut source, writer).await?;
         source.finish();
     } else {
-        let mut source = req.await?;
-        copy(&mut source, &mut writer).await?;
+        let mut source = source;
+        copy(&mut source, writer).await?;
+    }
+    writer.flush().await
+}
+
+/// Downloads the file from the gateway. Data is written to a partial file first, which is
+/// resumed with a range request if a previous download was interrupted.
+async fn fetch_params(
+    gw: &str,
+    path: &Path,
+    info: &ParameterData,
+    multi_bar: Option<Arc<MultiBar<Stdout>>>,
+) -> Result<(), String> {
+    let part = part_path(path);
+    let offset = match fs::metadata(&part).await {
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
     };
+    info!("Fetching {:?} from {} (offset {})", path, gw, offset);
 
-    Ok(())
+    let url = format!("{}{}", gw, info.cid);
+    let client = Client::new();
+    let mut req = client.get(url.as_str());
+    if offset > 0 {
+        req = req.header("Range", format!("bytes={}-", offset));
+    }
+    let res = req.await.map_err(|e| e.to_string())?;
+
+    let (file, offset) = match res.status() {
+        // Partial file is already complete
+        StatusCode::RequestedRangeNotSatisfiable if offset > 0 => {
+            return fs::rename(&part, path).await.map_err(|e| e.to_string());
+        }
+        StatusCode::PartialContent => (
+            OpenOptions::new()
+                .append(true)
+                .open(&part)
+                .await
+                .map_err(|e| e.to_string())?,
+            offset,
+        ),
+        // The range was ignored, so the download starts over
+        s if s.is_success() => (File::create(&part).await.map_err(|e| e.to_string())?, 0),
+        s => return Err(format!("failed to download file {}: {}", url, s)),
+    };
+    let total_size = offset + res.len().unwrap_or_default() as u64;
+
+    let mut writer = BufWriter::new(file);
+    write_progress(res, &mut writer, offset, total_size, multi_bar)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    fs::rename(&part, path).await.map_err(|e| e.to_string())
+}
+
+/// Copies the file from a local mirror directory, where it is named either by its file name or
+/// by its CID.
+async fn copy_params(
+    dir: &Path,
+    path: &Path,
+    info: &ParameterData,
+    multi_bar: Option<Arc<MultiBar<Stdout>>>,
+) -> Result<(), String> {
+    let name = path.file_name().unwrap_or_default();
+    let mut src = dir.join(name);
+    if !src.exists() {
+        src = dir.join(&info.cid);
+    }
+    info!("Copying {:?} from {:?}", path, src);
+
+    let file = File::open(&src)
+        .await
+        .map_err(|e| format!("failed to open {:?}: {}", src, e))?;
+    let total_size = file.metadata().await.map_err(|e| e.to_string())?.len();
+
+    let part = part_path(path);
+    let mut writer = BufWriter::new(File::create(&part).await.map_err(|e| e.to_string())?);
+    write_progress(BufReader::new(file), &mut writer, 0, total_size, multi_bar)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    fs::rename(&part, path).await.map_err(|e| e.to_string())
 }
 
 async fn check_file(path: Arc<PathBuf>, info: Arc<ParameterData>) -> Result<(), io::Error> {
@@ -266,3 +459,166 @@ async fn check_file(path: Arc<PathBuf>, info: Arc<ParameterData>) -> Result<(),
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::net::TcpListener;
+    use async_std::task::JoinHandle;
+
+    const PARAMS: &[u8] = b"parameter file contents";
+
+    /// Returns an empty directory in the system temp directory, unique to the test.
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("forest_paramfetch_{}_{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Returns the manifest entry of a parameter file with the given contents.
+    fn param_info(data: &[u8]) -> Arc<ParameterData> {
+        let digest = blake2b_simd::blake2b(data).to_hex();
+        Arc::new(ParameterData {
+            cid: "QmParams".to_owned(),
+            digest: digest[..32].to_owned(),
+            sector_size: 2048,
+        })
+    }
+
+    /// Serves one request for the data, honouring its range header, and returns the offset the
+    /// data was requested from.
+    fn serve_once(listener: TcpListener, data: &'static [u8]) -> JoinHandle<u64> {
+        task::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream.clone());
+            let mut offset = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(range) = line.to_lowercase().strip_prefix("range: bytes=") {
+                    offset = range.trim_end_matches('-').parse().unwrap();
+                }
+            }
+            let status = if offset > 0 {
+                "206 Partial Content"
+            } else {
+                "200 OK"
+            };
+            let body = &data[offset as usize..];
+            let head = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let mut stream = stream;
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(body).await.unwrap();
+            offset
+        })
+    }
+
+    #[test]
+    fn parse_param_source() {
+        assert_eq!(
+            ParamSource::parse("https://proofs.filecoin.io/ipfs/"),
+            ParamSource::Gateway("https://proofs.filecoin.io/ipfs/".to_owned())
+        );
+        assert_eq!(
+            ParamSource::parse("file:///mnt/params"),
+            ParamSource::Mirror("/mnt/params".into())
+        );
+        assert_eq!(
+            ParamSource::parse("/mnt/params"),
+            ParamSource::Mirror("/mnt/params".into())
+        );
+    }
+
+    #[test]
+    fn partial_file_path() {
+        assert_eq!(
+            part_path(Path::new("/tmp/v28-stacked-proof.vk")),
+            PathBuf::from("/tmp/v28-stacked-proof.vk.part")
+        );
+    }
+
+    #[test]
+    fn fetch_backoff_doubles() {
+        assert_eq!(fetch_backoff(1), FETCH_BACKOFF);
+        assert_eq!(fetch_backoff(2), FETCH_BACKOFF * 2);
+    }
+
+    #[async_std::test]
+    async fn copy_from_mirror() {
+        let cache = temp_dir("copy_cache");
+        let mirror = temp_dir("copy_mirror");
+        let info = param_info(PARAMS);
+        // Mirrors may name files by CID
+        std::fs::write(mirror.join(&info.cid), PARAMS).unwrap();
+
+        let source = ParamSource::Mirror(mirror.clone());
+        fetch_verify_params(&cache, &source, "test.params", info, None)
+            .await
+            .unwrap();
+        let path = cache.join("test.params");
+        assert_eq!(std::fs::read(&path).unwrap(), PARAMS);
+        assert!(!part_path(&path).exists());
+
+        std::fs::remove_dir_all(cache).unwrap();
+        std::fs::remove_dir_all(mirror).unwrap();
+    }
+
+    #[async_std::test]
+    async fn quarantine_invalid_files() {
+        let cache = temp_dir("quarantine_cache");
+        let mirror = temp_dir("quarantine_mirror");
+        std::fs::write(mirror.join("test.params"), PARAMS).unwrap();
+        let source = ParamSource::Mirror(mirror.clone());
+        let path = cache.join("test.params");
+        let quarantined = cache.join(QUARANTINE_DIR).join("test.params");
+
+        // A corrupt file in the cache is moved aside and fetched again
+        std::fs::write(&path, b"corrupt").unwrap();
+        fetch_verify_params(&cache, &source, "test.params", param_info(PARAMS), None)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), PARAMS);
+        assert_eq!(std::fs::read(&quarantined).unwrap(), b"corrupt");
+
+        // A fetched file which doesn't match the manifest is not left in the cache
+        std::fs::remove_file(&path).unwrap();
+        let res =
+            fetch_verify_params(&cache, &source, "test.params", param_info(b"other"), None).await;
+        assert!(res.is_err());
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(&quarantined).unwrap(), PARAMS);
+
+        std::fs::remove_dir_all(cache).unwrap();
+        std::fs::remove_dir_all(mirror).unwrap();
+    }
+
+    #[async_std::test]
+    async fn resume_partial_download() {
+        let cache = temp_dir("resume_cache");
+        let path = cache.join("test.params");
+        std::fs::write(part_path(&path), &PARAMS[..10]).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gateway = format!("http://{}/", listener.local_addr().unwrap());
+        let server = serve_once(listener, PARAMS);
+
+        fetch_params(&gateway, &path, &param_info(PARAMS), None)
+            .await
+            .unwrap();
+        assert_eq!(server.await, 10);
+        assert_eq!(std::fs::read(&path).unwrap(), PARAMS);
+        assert!(!part_path(&path).exists());
+
+        std::fs::remove_dir_all(cache).unwrap();
+    }
+}
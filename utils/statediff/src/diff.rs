@@ -0,0 +1,352 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::walk::{amt_changes, hamt_changes, Change};
+use actor::{miner, parse_uint_key, MINER_ACTOR_CODE_ID};
+use address::Address;
+use blockstore::BlockStore;
+use cid::{json::CidJson, Cid};
+use ipld::json::IpldJson;
+use ipld::{from_ipld, Ipld};
+use num_bigint::{bigint_ser::json as bigint_json, BigInt};
+use serde::Serialize;
+use std::error::Error as StdError;
+use vm::ActorState;
+
+/// Differences between two state trees.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StateDiff {
+    pub added: Vec<ActorEntry>,
+    pub removed: Vec<ActorEntry>,
+    pub changed: Vec<ActorDiff>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An actor which only exists in one of the state trees.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ActorEntry {
+    #[serde(with = "address::json")]
+    pub address: Address,
+    #[serde(with = "cid::json")]
+    pub code: Cid,
+    #[serde(with = "cid::json")]
+    pub head: Cid,
+    #[serde(with = "bigint_json")]
+    pub balance: BigInt,
+    pub sequence: u64,
+}
+
+impl ActorEntry {
+    fn new(address: Address, actor: ActorState) -> Self {
+        Self {
+            address,
+            code: actor.code,
+            head: actor.state,
+            balance: actor.balance,
+            sequence: actor.sequence,
+        }
+    }
+}
+
+/// Changes to an actor which exists in both state trees.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ActorDiff {
+    #[serde(with = "address::json")]
+    pub address: Address,
+    #[serde(with = "cid::json")]
+    pub code: Cid,
+    /// New code of the actor, if it changed.
+    pub new_code: Option<CidJson>,
+    #[serde(with = "bigint_json")]
+    pub balance_delta: BigInt,
+    pub sequence_delta: i64,
+    /// Changes to the actor's state, if its head changed.
+    pub state: Option<ActorStateDiff>,
+}
+
+/// Field level changes to the state of an actor.
+#[derive(Serialize)]
+#[serde(tag = "Type")]
+pub enum ActorStateDiff {
+    /// Changes to a miner's sectors, deadlines and balances.
+    Miner(Box<MinerStateDiff>),
+    /// Top level fields of the state which differ, by field index.
+    Fields {
+        #[serde(rename = "Changes")]
+        changes: Vec<FieldChange>,
+    },
+}
+
+/// A top level field of an actor state which differs.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FieldChange {
+    pub index: usize,
+    pub before: Option<IpldJson>,
+    pub after: Option<IpldJson>,
+}
+
+/// Keys added, removed or modified in a collection.
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct KeyChanges {
+    pub added: Vec<u64>,
+    pub removed: Vec<u64>,
+    pub modified: Vec<u64>,
+}
+
+impl KeyChanges {
+    fn from_changes<K, V, F>(changes: Vec<Change<K, V>>, key: F) -> Result<Self, Box<dyn StdError>>
+    where
+        F: Fn(&K) -> Result<u64, Box<dyn StdError>>,
+    {
+        let mut keys = Self::default();
+        for change in changes {
+            let k = key(change.key())?;
+            match change {
+                Change::Added(..) => keys.added.push(k),
+                Change::Removed(..) => keys.removed.push(k),
+                Change::Modified(..) => keys.modified.push(k),
+            }
+        }
+        keys.added.sort_unstable();
+        keys.removed.sort_unstable();
+        keys.modified.sort_unstable();
+        Ok(keys)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Changes to the state of a miner actor.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MinerStateDiff {
+    pub info_changed: bool,
+    #[serde(with = "bigint_json")]
+    pub pre_commit_deposits_delta: BigInt,
+    #[serde(with = "bigint_json")]
+    pub locked_funds_delta: BigInt,
+    #[serde(with = "bigint_json")]
+    pub initial_pledge_delta: BigInt,
+    /// Pre-committed sectors, by sector number.
+    pub pre_committed_sectors: KeyChanges,
+    /// Proven sectors, by sector number.
+    pub sectors: KeyChanges,
+    /// Indexes of the deadlines whose state changed.
+    pub deadlines: Vec<u64>,
+    pub proving_period_start: Option<(i64, i64)>,
+    pub current_deadline: Option<(u64, u64)>,
+}
+
+/// Returns the differences between the actors of two state trees. Subtrees of the actors HAMT,
+/// and of the structures of known actors, are only loaded if they differ.
+pub fn diff_state_roots<BS>(
+    bs: &BS,
+    before: &Cid,
+    after: &Cid,
+) -> Result<StateDiff, Box<dyn StdError>>
+where
+    BS: BlockStore,
+{
+    let mut diff = StateDiff::default();
+    for change in hamt_changes(bs, before, after)? {
+        match change {
+            Change::Added(k, a) => diff
+                .added
+                .push(ActorEntry::new(Address::from_bytes(&k)?, from_ipld(&a)?)),
+            Change::Removed(k, b) => diff
+                .removed
+                .push(ActorEntry::new(Address::from_bytes(&k)?, from_ipld(&b)?)),
+            Change::Modified(k, b, a) => {
+                let address = Address::from_bytes(&k)?;
+                diff.changed
+                    .push(diff_actors(bs, address, from_ipld(&b)?, from_ipld(&a)?)?);
+            }
+        }
+    }
+    diff.added.sort_by_key(|a| a.address.to_string());
+    diff.removed.sort_by_key(|a| a.address.to_string());
+    diff.changed.sort_by_key(|a| a.address.to_string());
+    Ok(diff)
+}
+
+fn diff_actors<BS: BlockStore>(
+    bs: &BS,
+    address: Address,
+    before: ActorState,
+    after: ActorState,
+) -> Result<ActorDiff, Box<dyn StdError>> {
+    let state = if before.state == after.state {
+        None
+    } else if before.code == *MINER_ACTOR_CODE_ID && after.code == *MINER_ACTOR_CODE_ID {
+        Some(ActorStateDiff::Miner(Box::new(diff_miner_states(
+            bs,
+            &before.state,
+            &after.state,
+        )?)))
+    } else {
+        Some(ActorStateDiff::Fields {
+            changes: diff_fields(bs, &before.state, &after.state)?,
+        })
+    };
+    Ok(ActorDiff {
+        address,
+        code: before.code,
+        new_code: if before.code != after.code {
+            Some(CidJson(after.code))
+        } else {
+            None
+        },
+        balance_delta: after.balance - before.balance,
+        sequence_delta: after.sequence as i64 - before.sequence as i64,
+        state,
+    })
+}
+
+/// Compares the top level fields of two actor states. States which are not tuples are
+/// compared as a whole.
+fn diff_fields<BS: BlockStore>(
+    bs: &BS,
+    before: &Cid,
+    after: &Cid,
+) -> Result<Vec<FieldChange>, Box<dyn StdError>> {
+    let load = |cid: &Cid| -> Result<Ipld, Box<dyn StdError>> {
+        bs.get(cid)?
+            .ok_or_else(|| format!("actor state {} not found in store", cid).into())
+    };
+    Ok(match (load(before)?, load(after)?) {
+        (Ipld::List(b), Ipld::List(a)) => {
+            let len = std::cmp::max(b.len(), a.len());
+            (0..len)
+                .filter(|i| b.get(*i) != a.get(*i))
+                .map(|i| FieldChange {
+                    index: i,
+                    before: b.get(i).cloned().map(IpldJson),
+                    after: a.get(i).cloned().map(IpldJson),
+                })
+                .collect()
+        }
+        (b, a) => vec![FieldChange {
+            index: 0,
+            before: Some(IpldJson(b)),
+            after: Some(IpldJson(a)),
+        }],
+    })
+}
+
+fn diff_miner_states<BS: BlockStore>(
+    bs: &BS,
+    before: &Cid,
+    after: &Cid,
+) -> Result<MinerStateDiff, Box<dyn StdError>> {
+    let load = |cid: &Cid| -> Result<miner::State, Box<dyn StdError>> {
+        bs.get(cid)?
+            .ok_or_else(|| format!("miner state {} not found in store", cid).into())
+    };
+    let b = load(before)?;
+    let a = load(after)?;
+
+    let pre_committed_sectors = KeyChanges::from_changes(
+        hamt_changes(bs, &b.pre_committed_sectors, &a.pre_committed_sectors)?,
+        |k: &Vec<u8>| Ok(parse_uint_key(k)?),
+    )?;
+    let sectors = KeyChanges::from_changes(amt_changes(bs, &b.sectors, &a.sectors)?, |k| Ok(*k))?;
+
+    let mut deadlines = Vec::new();
+    if b.deadlines != a.deadlines {
+        let b_deadlines = b.load_deadlines(bs)?;
+        let a_deadlines = a.load_deadlines(bs)?;
+        for (i, (b_dl, a_dl)) in b_deadlines
+            .due
+            .iter()
+            .zip(a_deadlines.due.iter())
+            .enumerate()
+        {
+            if b_dl != a_dl {
+                deadlines.push(i as u64);
+            }
+        }
+    }
+
+    Ok(MinerStateDiff {
+        info_changed: b.info != a.info,
+        pre_commit_deposits_delta: &a.pre_commit_deposits - &b.pre_commit_deposits,
+        locked_funds_delta: &a.locked_funds - &b.locked_funds,
+        initial_pledge_delta: &a.initial_pledge_requirement - &b.initial_pledge_requirement,
+        pre_committed_sectors,
+        sectors,
+        deadlines,
+        proving_period_start: if b.proving_period_start != a.proving_period_start {
+            Some((b.proving_period_start, a.proving_period_start))
+        } else {
+            None
+        },
+        current_deadline: if b.current_deadline != a.current_deadline {
+            Some((b.current_deadline, a.current_deadline))
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actor::ACCOUNT_ACTOR_CODE_ID;
+    use db::MemoryDB;
+    use fil_types::HAMT_BIT_WIDTH;
+    use ipld_hamt::Hamt;
+
+    fn actor(store: &MemoryDB, balance: u64, sequence: u64) -> ActorState {
+        let head = store
+            .put(&Address::new_id(0), cid::Code::Blake2b256)
+            .unwrap();
+        ActorState::new(*ACCOUNT_ACTOR_CODE_ID, head, balance.into(), sequence)
+    }
+
+    #[test]
+    fn diff_actor_balances() {
+        let store = MemoryDB::default();
+        let mut actors: Hamt<_, ActorState> = Hamt::new_with_bit_width(&store, HAMT_BIT_WIDTH);
+        for id in 100..110 {
+            actors
+                .set(Address::new_id(id).to_bytes().into(), actor(&store, 10, 0))
+                .unwrap();
+        }
+        let before = actors.flush().unwrap();
+
+        actors
+            .set(Address::new_id(100).to_bytes().into(), actor(&store, 4, 1))
+            .unwrap();
+        actors.delete(&Address::new_id(101).to_bytes()).unwrap();
+        actors
+            .set(Address::new_id(200).to_bytes().into(), actor(&store, 6, 0))
+            .unwrap();
+        let after = actors.flush().unwrap();
+
+        let diff = diff_state_roots(&store, &before, &after).unwrap();
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].address, Address::new_id(200));
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].address, Address::new_id(101));
+        assert_eq!(diff.changed.len(), 1);
+        let changed = &diff.changed[0];
+        assert_eq!(changed.address, Address::new_id(100));
+        assert_eq!(changed.balance_delta, BigInt::from(-6));
+        assert_eq!(changed.sequence_delta, 1);
+        assert!(changed.state.is_none());
+
+        assert!(diff_state_roots(&store, &after, &after).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Collects the changes between two HAMTs or AMTs of raw IPLD values. Only the subtrees whose
+//! links differ are loaded, which avoids loading the entries shared by both structures.
+
+use blockstore::BlockStore;
+use cid::Cid;
+use fil_types::HAMT_BIT_WIDTH;
+use ipld::Ipld;
+use ipld_amt::Amt;
+use ipld_hamt::{BytesKey, Hamt};
+use std::error::Error as StdError;
+
+pub use ipld_hamt::Change;
+
+/// Returns the entries which differ between two HAMTs, keyed by the raw key bytes.
+pub fn hamt_changes<BS: BlockStore>(
+    bs: &BS,
+    before: &Cid,
+    after: &Cid,
+) -> Result<Vec<Change<Vec<u8>, Ipld>>, Box<dyn StdError>> {
+    let before: Hamt<_, Ipld, BytesKey> = Hamt::load_with_bit_width(before, bs, HAMT_BIT_WIDTH)?;
+    let after: Hamt<_, Ipld, BytesKey> = Hamt::load_with_bit_width(after, bs, HAMT_BIT_WIDTH)?;
+    let mut changes = Vec::new();
+    for change in before.diff(&after)? {
+        changes.push(match change? {
+            Change::Added(k, a) => Change::Added(k.0, a),
+            Change::Removed(k, b) => Change::Removed(k.0, b),
+            Change::Modified(k, b, a) => Change::Modified(k.0, b, a),
+        });
+    }
+    Ok(changes)
+}
+
+/// Returns the entries which differ between two AMTs, keyed by index.
+pub fn amt_changes<BS: BlockStore>(
+    bs: &BS,
+    before: &Cid,
+    after: &Cid,
+) -> Result<Vec<Change<u64, Ipld>>, Box<dyn StdError>> {
+    let before: Amt<Ipld, _> = Amt::load(before, bs)?;
+    let after: Amt<Ipld, _> = Amt::load(after, bs)?;
+    let mut changes = Vec::new();
+    for change in before.diff(&after)? {
+        changes.push(match change? {
+            ipld_amt::Change::Added(i, a) => Change::Added(i, a),
+            ipld_amt::Change::Removed(i, b) => Change::Removed(i, b),
+            ipld_amt::Change::Modified(i, b, a) => Change::Modified(i, b, a),
+        });
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::MemoryDB;
+    use ipld_hamt::BytesKey;
+
+    #[test]
+    fn hamt_changes_between_roots() {
+        let store = MemoryDB::default();
+        let mut hamt: Hamt<_, u64> = Hamt::new_with_bit_width(&store, 5);
+        for i in 0..200u64 {
+            hamt.set(BytesKey(i.to_be_bytes().to_vec()), i).unwrap();
+        }
+        let before = hamt.flush().unwrap();
+
+        hamt.set(BytesKey(7u64.to_be_bytes().to_vec()), 1000)
+            .unwrap();
+        hamt.delete(&BytesKey(8u64.to_be_bytes().to_vec())).unwrap();
+        hamt.set(BytesKey(500u64.to_be_bytes().to_vec()), 500)
+            .unwrap();
+        let after = hamt.flush().unwrap();
+
+        let mut changes = hamt_changes(&store, &before, &after).unwrap();
+        changes.sort_by(|a, b| a.key().cmp(b.key()));
+        assert_eq!(
+            changes,
+            vec![
+                Change::Modified(
+                    7u64.to_be_bytes().to_vec(),
+                    Ipld::Integer(7),
+                    Ipld::Integer(1000)
+                ),
+                Change::Removed(8u64.to_be_bytes().to_vec(), Ipld::Integer(8)),
+                Change::Added(500u64.to_be_bytes().to_vec(), Ipld::Integer(500)),
+            ]
+        );
+        assert!(hamt_changes(&store, &after, &after).unwrap().is_empty());
+    }
+
+    #[test]
+    fn amt_changes_between_roots() {
+        let store = MemoryDB::default();
+        let mut amt = Amt::new(&store);
+        for i in 0..100u64 {
+            amt.set(i, i).unwrap();
+        }
+        let before = amt.flush().unwrap();
+
+        amt.set(3, 300).unwrap();
+        amt.delete(50).unwrap();
+        // Grows the tree, so the roots have different heights
+        amt.set(1000, 1000).unwrap();
+        let after = amt.flush().unwrap();
+
+        let changes = amt_changes(&store, &before, &after).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Modified(3, Ipld::Integer(3), Ipld::Integer(300)),
+                Change::Removed(50, Ipld::Integer(50)),
+                Change::Added(1000, Ipld::Integer(1000)),
+            ]
+        );
+
+        amt.set(4, 400).unwrap();
+        let last = amt.flush().unwrap();
+        assert_eq!(
+            amt_changes(&store, &after, &last).unwrap(),
+            vec![Change::Modified(4, Ipld::Integer(4), Ipld::Integer(400))]
+        );
+    }
+}
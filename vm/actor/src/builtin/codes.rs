@@ -62,3 +62,25 @@ pub fn is_account_actor(code: &Cid) -> bool {
 pub fn is_principal(code: &Cid) -> bool {
     CALLER_TYPES_SIGNABLE.iter().any(|c| c == code)
 }
+
+/// Returns the name of a builtin actor from its code `Cid`, such as `storageminer`.
+pub fn builtin_actor_name(code: &Cid) -> Option<&'static str> {
+    let names: [(&Cid, &'static str); 12] = [
+        (&*SYSTEM_ACTOR_CODE_ID, "system"),
+        (&*INIT_ACTOR_CODE_ID, "init"),
+        (&*CRON_ACTOR_CODE_ID, "cron"),
+        (&*ACCOUNT_ACTOR_CODE_ID, "account"),
+        (&*POWER_ACTOR_CODE_ID, "storagepower"),
+        (&*MINER_ACTOR_CODE_ID, "storageminer"),
+        (&*MARKET_ACTOR_CODE_ID, "storagemarket"),
+        (&*PAYCH_ACTOR_CODE_ID, "paymentchannel"),
+        (&*MULTISIG_ACTOR_CODE_ID, "multisig"),
+        (&*REWARD_ACTOR_CODE_ID, "reward"),
+        (&*VERIFREG_ACTOR_CODE_ID, "verifiedregistry"),
+        (&*CHAOS_ACTOR_CODE_ID, "chaos"),
+    ];
+    names
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| *name)
+}
@@ -7,6 +7,7 @@ mod state;
 mod types;
 
 pub use self::deal::*;
+pub use self::policy::deal_provider_collateral_bounds;
 use self::policy::*;
 pub use self::state::*;
 pub use self::types::*;
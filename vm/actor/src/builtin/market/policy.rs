@@ -38,7 +38,7 @@ pub(super) fn deal_price_per_epoch_bounds(
     (0.into(), &TOTAL_FILECOIN)
 }
 
-pub(super) fn deal_provider_collateral_bounds(
+pub fn deal_provider_collateral_bounds(
     size: PaddedPieceSize,
     verified: bool,
     network_raw_power: &StoragePower,
@@ -121,7 +121,7 @@ impl Actor {
     {
         rt.validate_immediate_caller_is(&[*INIT_ACTOR_ADDR])?;
 
-        if !check_supported_proof_types(params.seal_proof_type) {
+        if !check_supported_proof_types::<RT::Params>(params.seal_proof_type) {
             return Err(actor_error!(
                 ErrIllegalArgument,
                 "proof type {:?} not allowed for new miner actors",
@@ -654,7 +654,7 @@ impl Actor {
         BS: BlockStore,
         RT: Runtime<BS>,
     {
-        if !check_supported_proof_types(params.seal_proof) {
+        if !check_supported_proof_types::<RT::Params>(params.seal_proof) {
             return Err(actor_error!(
                 ErrIllegalArgument,
                 "unsupported seal proof type: {:?}",
@@ -974,7 +974,8 @@ impl Actor {
             rt,
             SealVerifyParams {
                 sealed_cid: precommit.info.sealed_cid,
-                interactive_epoch: precommit.pre_commit_epoch + PRE_COMMIT_CHALLENGE_DELAY,
+                interactive_epoch: precommit.pre_commit_epoch
+                    + pre_commit_challenge_delay::<RT::Params>(),
                 seal_rand_epoch: precommit.info.seal_rand_epoch,
                 proof: params.proof,
                 deal_ids: precommit.info.deal_ids.clone(),
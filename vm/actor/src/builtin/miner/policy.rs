@@ -4,7 +4,7 @@
 use super::types::SectorOnChainInfo;
 use crate::{network::*, DealWeight};
 use clock::ChainEpoch;
-use fil_types::{RegisteredSealProof, SectorQuality, SectorSize, StoragePower};
+use fil_types::{NetworkParams, RegisteredSealProof, SectorQuality, SectorSize, StoragePower};
 use num_bigint::BigUint;
 use num_bigint::{BigInt, Integer};
 use num_traits::Pow;
@@ -56,9 +56,8 @@ pub const SEALED_CID_PREFIX: cid::Prefix = cid::Prefix {
 };
 
 /// List of proof types which can be used when creating new miner actors
-pub fn check_supported_proof_types(proof: RegisteredSealProof) -> bool {
-    use RegisteredSealProof::*;
-    matches!(proof, StackedDRG32GiBV1 | StackedDRG64GiBV1)
+pub fn check_supported_proof_types<P: NetworkParams>(proof: RegisteredSealProof) -> bool {
+    P::SUPPORTED_PROOF_TYPES.contains(&proof)
 }
 /// Maximum duration to allow for the sealing process for seal algorithms.
 /// Dependent on algorithm and sector size
@@ -72,7 +71,9 @@ pub fn max_seal_duration(proof: RegisteredSealProof) -> Option<ChainEpoch> {
 }
 /// Number of epochs between publishing the precommit and when the challenge for interactive PoRep is drawn
 /// used to ensure it is not predictable by miner.
-pub const PRE_COMMIT_CHALLENGE_DELAY: ChainEpoch = 150;
+pub fn pre_commit_challenge_delay<P: NetworkParams>() -> ChainEpoch {
+    P::PRE_COMMIT_CHALLENGE_DELAY
+}
 
 /// Lookback from the current epoch for state view for leader elections.
 pub const ELECTION_LOOKBACK: ChainEpoch = 1; // PARAM_FINISH
@@ -19,6 +19,11 @@ impl TxnID {
     pub fn key(self) -> BytesKey {
         self.0.encode_var_vec().into()
     }
+
+    /// Decodes a transaction ID from its pending transactions map key.
+    pub fn from_key(key: &[u8]) -> Option<Self> {
+        i64::decode_var(key).map(|(id, _)| Self(id))
+    }
 }
 
 /// Transaction type used in multisig actor
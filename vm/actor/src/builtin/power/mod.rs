@@ -158,7 +158,7 @@ impl Actor {
                 e.downcast_default(ExitCode::ErrIllegalState, "failed to load claims")
             })?;
 
-            st.add_to_claim(
+            st.add_to_claim::<RT::Params, _>(
                 &mut claims,
                 &miner_addr,
                 &params.raw_byte_delta,
@@ -302,7 +302,7 @@ impl Actor {
             assert_ne!(raw_byte_power.sign(), Sign::Minus);
             assert_ne!(quality_adj_power.sign(), Sign::Minus);
 
-            st.add_to_claim(
+            st.add_to_claim::<RT::Params, _>(
                 &mut claims,
                 &miner_addr,
                 &raw_byte_power.neg(),
@@ -606,7 +606,12 @@ impl Actor {
                 };
 
                 // zero out miner power
-                let res = st.add_to_claim(&mut claims, &miner_addr, &rbp.neg(), &qap.neg());
+                let res = st.add_to_claim::<RT::Params, _>(
+                    &mut claims,
+                    &miner_addr,
+                    &rbp.neg(),
+                    &qap.neg(),
+                );
                 if let Err(e) = res {
                     log::warn!(
                         "failed to remove power for miner {} after to failed cron: {}",
@@ -1,7 +1,7 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use super::{CONSENSUS_MINER_MIN_MINERS, CONSENSUS_MINER_MIN_POWER};
+use super::{consensus_miner_min_power, CONSENSUS_MINER_MIN_MINERS};
 use crate::{
     make_map_with_root,
     smooth::{AlphaBetaFilter, FilterEstimate, DEFAULT_ALPHA, DEFAULT_BETA},
@@ -11,7 +11,7 @@ use address::Address;
 use cid::Cid;
 use clock::{ChainEpoch, EPOCH_UNDEFINED};
 use encoding::{tuple::*, Cbor};
-use fil_types::StoragePower;
+use fil_types::{NetworkParams, StoragePower};
 use integer_encoding::VarInt;
 use ipld_blockstore::BlockStore;
 use num_bigint::{bigint_ser, BigInt, Sign};
@@ -86,7 +86,7 @@ impl State {
     }
 
     /// Checks power actor state for if miner meets minimum consensus power.
-    pub fn miner_nominal_power_meets_consensus_minimum<BS: BlockStore>(
+    pub fn miner_nominal_power_meets_consensus_minimum<P: NetworkParams, BS: BlockStore>(
         &self,
         s: &BS,
         miner: &Address,
@@ -98,7 +98,7 @@ impl State {
 
         let miner_nominal_power = &claim.quality_adj_power;
 
-        if miner_nominal_power >= &CONSENSUS_MINER_MIN_POWER {
+        if *miner_nominal_power >= consensus_miner_min_power::<P>() {
             // If miner is larger than min power requirement, valid
             Ok(true)
         } else if self.miner_above_min_power_count >= CONSENSUS_MINER_MIN_MINERS {
@@ -110,7 +110,7 @@ impl State {
         }
     }
 
-    pub(super) fn add_to_claim<BS: BlockStore>(
+    pub(super) fn add_to_claim<P: NetworkParams, BS: BlockStore>(
         &mut self,
         claims: &mut Map<BS, Claim>,
         miner: &Address,
@@ -128,9 +128,9 @@ impl State {
             quality_adj_power: old_claim.quality_adj_power.clone() + qa_power,
         };
 
-        let min_power_ref: &StoragePower = &*CONSENSUS_MINER_MIN_POWER;
-        let prev_below: bool = &old_claim.quality_adj_power < min_power_ref;
-        let still_below: bool = &new_claim.quality_adj_power < min_power_ref;
+        let min_power = consensus_miner_min_power::<P>();
+        let prev_below: bool = old_claim.quality_adj_power < min_power;
+        let still_below: bool = new_claim.quality_adj_power < min_power;
 
         if prev_below && !still_below {
             // Just passed min miner size
@@ -196,7 +196,8 @@ impl State {
         Ok(())
     }
 
-    pub(super) fn current_total_power(&self) -> (StoragePower, StoragePower) {
+    /// Returns the raw byte and quality adjusted power of the network.
+    pub fn current_total_power(&self) -> (StoragePower, StoragePower) {
         if self.miner_above_min_power_count < CONSENSUS_MINER_MIN_MINERS {
             (
                 self.total_bytes_committed.clone(),
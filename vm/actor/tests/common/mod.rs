@@ -8,7 +8,7 @@ use crypto::{DomainSeparationTag, Signature};
 use db::MemoryDB;
 use encoding::{blake2b_256, de::DeserializeOwned, Cbor};
 use fil_types::{
-    NetworkVersion, PieceInfo, Randomness, RegisteredSealProof, SealVerifyInfo,
+    MainnetParams, NetworkVersion, PieceInfo, Randomness, RegisteredSealProof, SealVerifyInfo,
     WindowPoStVerifyInfo,
 };
 use ipld_blockstore::BlockStore;
@@ -375,6 +375,8 @@ impl MessageInfo for MockRuntime {
 }
 
 impl Runtime<MemoryDB> for MockRuntime {
+    type Params = MainnetParams;
+
     fn network_version(&self) -> NetworkVersion {
         self.network_version
     }
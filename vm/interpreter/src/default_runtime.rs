@@ -3,6 +3,7 @@
 
 use super::gas_block_store::GasBlockStore;
 use super::gas_tracker::{price_list_by_epoch, GasCharge, GasTracker, PriceList};
+use super::trace::{CallTrace, ExecutionTrace};
 use super::{CircSupplyCalc, Rand};
 use actor::*;
 use address::{Address, Protocol};
@@ -11,12 +12,14 @@ use byteorder::{BigEndian, WriteBytesExt};
 use cid::{Cid, Code::Blake2b256};
 use clock::ChainEpoch;
 use crypto::{DomainSeparationTag, Signature};
-use fil_types::{verifier::ProofVerifier, DevnetParams, NetworkParams, NetworkVersion, Randomness};
+use fil_types::{
+    verifier::ProofVerifier, MainnetParams, NetworkParams, NetworkVersion, Randomness,
+};
 use fil_types::{PieceInfo, RegisteredSealProof, SealVerifyInfo, WindowPoStVerifyInfo};
 use forest_encoding::{blake2b_256, to_vec, Cbor};
 use ipld_blockstore::BlockStore;
 use log::warn;
-use message::{Message, UnsignedMessage};
+use message::{Message, MessageReceipt, UnsignedMessage};
 use num_bigint::BigInt;
 use num_traits::Zero;
 use rayon::prelude::*;
@@ -62,7 +65,7 @@ impl MessageInfo for VMMsg {
 }
 
 /// Implementation of the Runtime trait.
-pub struct DefaultRuntime<'db, 'vm, BS, R, C, V, P = DevnetParams> {
+pub struct DefaultRuntime<'db, 'vm, BS, R, C, V, P = MainnetParams> {
     version: NetworkVersion,
     state: &'vm mut StateTree<'db, BS>,
     store: GasBlockStore<'db, BS>,
@@ -78,6 +81,10 @@ pub struct DefaultRuntime<'db, 'vm, BS, R, C, V, P = DevnetParams> {
     allow_internal: bool,
     registered_actors: &'vm HashSet<Cid>,
     circ_supply_calc: &'vm C,
+    /// Whether gas charges and internal sends are recorded for execution traces.
+    trace: bool,
+    /// Traces of the internal sends made by the call currently executing.
+    subcalls: Vec<ExecutionTrace>,
     verifier: PhantomData<V>,
     params: PhantomData<P>,
 }
@@ -141,6 +148,8 @@ where
             rand,
             registered_actors,
             circ_supply_calc,
+            trace: false,
+            subcalls: Vec::new(),
             allow_internal: true,
             caller_validated: false,
             params: PhantomData,
@@ -164,6 +173,26 @@ where
         self.gas_tracker.borrow().gas_available()
     }
 
+    /// Records gas charges and internal sends, which are returned by `take_trace`.
+    pub(crate) fn enable_tracing(&mut self) {
+        self.trace = true;
+        self.gas_tracker.borrow_mut().enable_tracing();
+    }
+
+    /// Takes the gas charges and subcalls recorded for the current call.
+    pub(crate) fn take_trace(&mut self) -> CallTrace {
+        CallTrace {
+            gas_charges: self.gas_tracker.borrow_mut().take_charges(),
+            subcalls: std::mem::take(&mut self.subcalls),
+        }
+    }
+
+    /// Restores the trace of a call which was taken to record a subcall.
+    fn set_trace(&mut self, trace: CallTrace) {
+        self.gas_tracker.borrow_mut().set_charges(trace.gas_charges);
+        self.subcalls = trace.subcalls;
+    }
+
     /// Returns the price list for gas charges within the runtime
     pub fn price_list(&self) -> &PriceList {
         &self.price_list
@@ -275,7 +304,29 @@ where
         };
         self.caller_validated = false;
 
-        let send_res = vm_send::<BS, R, C, V, P>(self, &msg, None);
+        let send_res = if self.trace {
+            // Record the subcall separately from the trace of the current call.
+            let prev_trace = self.take_trace();
+            let gas_before = self.gas_used();
+
+            let send_res = vm_send::<BS, R, C, V, P>(self, &msg, None);
+
+            let msg_rct = MessageReceipt {
+                return_data: send_res.as_ref().cloned().unwrap_or_default(),
+                exit_code: match &send_res {
+                    Ok(_) => ExitCode::Ok,
+                    Err(e) => e.exit_code(),
+                },
+                gas_used: self.gas_used() - gas_before,
+            };
+            let error = send_res.as_ref().err().map(|e| e.to_string());
+            let subcall = self.take_trace().finish(msg, msg_rct, error);
+            self.set_trace(prev_trace);
+            self.subcalls.push(subcall);
+            send_res
+        } else {
+            vm_send::<BS, R, C, V, P>(self, &msg, None)
+        };
 
         // Reset values back to their values before the call
         self.vm_msg = prev_msg;
@@ -362,6 +413,8 @@ where
     R: Rand,
     CS: CircSupplyCalc,
 {
+    type Params = P;
+
     fn network_version(&self) -> NetworkVersion {
         self.version
     }
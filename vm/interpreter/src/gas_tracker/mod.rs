@@ -11,6 +11,10 @@ use vm::{actor_error, ActorError, ExitCode};
 pub(crate) struct GasTracker {
     gas_available: i64,
     gas_used: i64,
+    /// Whether charges are recorded for execution traces.
+    trace: bool,
+    /// Charges made since the trace was last taken.
+    charges: Vec<GasCharge>,
 }
 
 impl GasTracker {
@@ -18,6 +22,19 @@ impl GasTracker {
         Self {
             gas_available,
             gas_used,
+            trace: false,
+            charges: Vec::new(),
+        }
+    }
+
+    /// Records the charges made from now on, to attach them to execution traces.
+    pub fn enable_tracing(&mut self) {
+        self.trace = true;
+    }
+
+    fn record(&mut self, charge: GasCharge) {
+        if self.trace {
+            self.charges.push(charge);
         }
     }
 
@@ -27,12 +44,14 @@ impl GasTracker {
         let used = self.gas_used + to_use;
         if used > self.gas_available {
             self.gas_used = self.gas_available;
+            self.record(charge);
             Err(actor_error!(SysErrOutOfGas;
                     "not enough gas (used={}) (available={})",
                used, self.gas_available
             ))
         } else {
             self.gas_used += to_use;
+            self.record(charge);
             Ok(())
         }
     }
@@ -46,6 +65,16 @@ impl GasTracker {
     pub fn gas_used(&self) -> i64 {
         self.gas_used
     }
+
+    /// Takes the charges made since the last call, to attach them to an execution trace.
+    pub fn take_charges(&mut self) -> Vec<GasCharge> {
+        std::mem::take(&mut self.charges)
+    }
+
+    /// Replaces the recorded charges, used to resume tracing a call after a subcall returns.
+    pub fn set_charges(&mut self, charges: Vec<GasCharge>) {
+        self.charges = charges;
+    }
 }
 
 #[cfg(test)]
@@ -61,4 +90,22 @@ mod tests {
         assert_eq!(t.gas_used(), 20);
         assert!(t.charge_gas(GasCharge::new("", 1, 0)).is_err())
     }
+
+    #[test]
+    fn gas_tracker_records_charges() {
+        let mut t = GasTracker::new(20, 0);
+        t.charge_gas(GasCharge::new("untraced", 1, 0)).unwrap();
+        assert!(t.take_charges().is_empty());
+
+        t.enable_tracing();
+        t.charge_gas(GasCharge::new("first", 5, 0)).unwrap();
+        t.charge_gas(GasCharge::new("second", 3, 0)).unwrap();
+        let charges = t.take_charges();
+        assert_eq!(
+            charges.iter().map(|c| c.name).collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+        assert_eq!(charges[0].total(), 5);
+        assert!(t.take_charges().is_empty());
+    }
 }
@@ -0,0 +1,66 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::gas_tracker::GasCharge;
+use message::{message_receipt, unsigned_message, MessageReceipt, UnsignedMessage};
+use serde::{Deserialize, Serialize};
+
+/// Gas charged during the execution of a message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GasTrace {
+    pub name: String,
+    pub total_gas: i64,
+    pub compute_gas: i64,
+    pub storage_gas: i64,
+}
+
+impl From<&GasCharge> for GasTrace {
+    fn from(charge: &GasCharge) -> Self {
+        Self {
+            name: charge.name.to_owned(),
+            total_gas: charge.total(),
+            compute_gas: charge.compute_gas,
+            storage_gas: charge.storage_gas,
+        }
+    }
+}
+
+/// Trace of the execution of a message, including the gas charged and the internal sends made
+/// by the invoked actor, in the order they were executed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ExecutionTrace {
+    #[serde(with = "unsigned_message::json")]
+    pub msg: UnsignedMessage,
+    #[serde(with = "message_receipt::json")]
+    pub msg_rct: MessageReceipt,
+    pub error: Option<String>,
+    pub gas_charges: Vec<GasTrace>,
+    pub subcalls: Vec<ExecutionTrace>,
+}
+
+/// Gas charges and subcalls recorded for a call while it is executing.
+#[derive(Default)]
+pub(crate) struct CallTrace {
+    pub gas_charges: Vec<GasCharge>,
+    pub subcalls: Vec<ExecutionTrace>,
+}
+
+impl CallTrace {
+    /// Completes the trace of a call once its receipt is known.
+    pub fn finish(
+        self,
+        msg: UnsignedMessage,
+        msg_rct: MessageReceipt,
+        error: Option<String>,
+    ) -> ExecutionTrace {
+        ExecutionTrace {
+            msg,
+            msg_rct,
+            error,
+            gas_charges: self.gas_charges.iter().map(GasTrace::from).collect(),
+            subcalls: self.subcalls,
+        }
+    }
+}
@@ -3,6 +3,7 @@
 
 use super::{
     gas_tracker::{price_list_by_epoch, GasCharge},
+    trace::ExecutionTrace,
     vm_send, DefaultRuntime, Rand,
 };
 use actor::{
@@ -15,7 +16,7 @@ use clock::ChainEpoch;
 use fil_types::BLOCK_GAS_LIMIT;
 use fil_types::{
     verifier::{FullVerifier, ProofVerifier},
-    DevnetParams, NetworkParams, NetworkVersion,
+    MainnetParams, NetworkParams, NetworkVersion,
 };
 use forest_encoding::Cbor;
 use ipld_blockstore::BlockStore;
@@ -49,7 +50,7 @@ pub trait CircSupplyCalc {
 
 /// Interpreter which handles execution of state transitioning messages and returns receipts
 /// from the vm execution.
-pub struct VM<'db, 'r, DB, R, N, C, V = FullVerifier, P = DevnetParams> {
+pub struct VM<'db, 'r, DB, R, N, C, V = FullVerifier, P = MainnetParams> {
     state: StateTree<'db, DB>,
     store: &'db DB,
     epoch: ChainEpoch,
@@ -58,6 +59,8 @@ pub struct VM<'db, 'r, DB, R, N, C, V = FullVerifier, P = DevnetParams> {
     registered_actors: HashSet<Cid>,
     network_version_getter: N,
     circ_supply_calc: &'r C,
+    /// Whether execution traces are recorded, which is off unless enabled with `enable_tracing`.
+    trace: bool,
     verifier: PhantomData<V>,
     params: PhantomData<P>,
 }
@@ -92,11 +95,19 @@ where
             base_fee,
             registered_actors,
             circ_supply_calc,
+            trace: false,
             verifier: PhantomData,
             params: PhantomData,
         })
     }
 
+    /// Records the gas charges and internal sends of the messages applied, returned in
+    /// `ApplyRet::exec_trace`. Only enabled for replaying messages, as it adds overhead to every
+    /// gas charge.
+    pub fn enable_tracing(&mut self) {
+        self.trace = true;
+    }
+
     /// Registers an actor that is not part of the set of default builtin actors by providing the code cid
     pub fn register_actor(&mut self, code_cid: Cid) -> bool {
         self.registered_actors.insert(code_cid)
@@ -250,21 +261,32 @@ where
     }
 
     pub fn apply_implicit_message(&mut self, msg: &UnsignedMessage) -> ApplyRet {
-        let (return_data, _, act_err) = self.send(msg, None);
+        let trace = self.trace;
+        let (return_data, rt, act_err) = self.send(msg, None);
 
-        ApplyRet {
-            msg_receipt: MessageReceipt {
-                return_data,
-                exit_code: if let Some(err) = &act_err {
-                    err.exit_code()
-                } else {
-                    ExitCode::Ok
-                },
-                gas_used: 0,
+        let msg_receipt = MessageReceipt {
+            return_data,
+            exit_code: if let Some(err) = &act_err {
+                err.exit_code()
+            } else {
+                ExitCode::Ok
             },
+            gas_used: 0,
+        };
+        let exec_trace = rt.filter(|_| trace).map(|mut rt| {
+            rt.take_trace().finish(
+                msg.clone(),
+                msg_receipt.clone(),
+                act_err.as_ref().map(|e| e.to_string()),
+            )
+        });
+
+        ApplyRet {
+            msg_receipt,
             act_error: act_err,
             penalty: BigInt::zero(),
             miner_tip: BigInt::zero(),
+            exec_trace,
         }
     }
 
@@ -289,6 +311,7 @@ where
                     "Out of gas ({} > {})", cost_total, msg.gas_limit())),
                 penalty: &self.base_fee * cost_total,
                 miner_tip: BigInt::zero(),
+                exec_trace: None,
             });
         }
 
@@ -305,6 +328,7 @@ where
                     penalty: miner_penalty_amount,
                     act_error: Some(actor_error!(SysErrSenderInvalid; "Sender invalid")),
                     miner_tip: 0.into(),
+                    exec_trace: None,
                 });
             }
         };
@@ -319,6 +343,7 @@ where
                 penalty: miner_penalty_amount,
                 act_error: Some(actor_error!(SysErrSenderInvalid; "send not from account actor")),
                 miner_tip: 0.into(),
+                exec_trace: None,
             });
         };
 
@@ -333,6 +358,7 @@ where
                 act_error: Some(actor_error!(SysErrSenderStateInvalid;
                     "actor sequence invalid: {} != {}", msg.sequence(), from_act.sequence)),
                 miner_tip: 0.into(),
+                exec_trace: None,
             });
         };
 
@@ -348,6 +374,7 @@ where
                 act_error: Some(actor_error!(SysErrSenderStateInvalid;
                     "actor balance less than needed: {} < {}", from_act.balance, gas_cost)),
                 miner_tip: 0.into(),
+                exec_trace: None,
             });
         };
 
@@ -391,7 +418,7 @@ where
             }
         }
 
-        let gas_used = if let Some(mut rt) = rt {
+        let (gas_used, call_trace) = if let Some(mut rt) = rt {
             if !ret_data.is_empty() {
                 if let Err(e) = rt.charge_gas(rt.price_list().on_chain_return_value(ret_data.len()))
                 {
@@ -399,11 +426,8 @@ where
                     ret_data = Serialized::default();
                 }
             }
-            if rt.gas_used() < 0 {
-                0
-            } else {
-                rt.gas_used()
-            }
+            let gas_used = if rt.gas_used() < 0 { 0 } else { rt.gas_used() };
+            (gas_used, rt.take_trace())
         } else {
             return Err(format!("send returned None runtime: {:?}", act_err));
         };
@@ -464,15 +488,27 @@ where
         }
         self.state.clear_snapshot()?;
 
+        let msg_receipt = MessageReceipt {
+            return_data: ret_data,
+            exit_code: err_code,
+            gas_used,
+        };
+        let exec_trace = if self.trace {
+            Some(call_trace.finish(
+                msg.message().clone(),
+                msg_receipt.clone(),
+                act_err.as_ref().map(|e| e.to_string()),
+            ))
+        } else {
+            None
+        };
+
         Ok(ApplyRet {
-            msg_receipt: MessageReceipt {
-                return_data: ret_data,
-                exit_code: err_code,
-                gas_used,
-            },
+            msg_receipt,
             penalty: miner_penalty,
             act_error: act_err,
             miner_tip,
+            exec_trace,
         })
     }
 
@@ -503,10 +539,15 @@ where
         );
 
         match res {
-            Ok(mut rt) => match vm_send(&mut rt, msg, gas_cost) {
-                Ok(ser) => (ser, Some(rt), None),
-                Err(actor_err) => (Serialized::default(), Some(rt), Some(actor_err)),
-            },
+            Ok(mut rt) => {
+                if self.trace {
+                    rt.enable_tracing();
+                }
+                match vm_send(&mut rt, msg, gas_cost) {
+                    Ok(ser) => (ser, Some(rt), None),
+                    Err(actor_err) => (Serialized::default(), Some(rt), Some(actor_err)),
+                }
+            }
             Err(e) => (Serialized::default(), None, Some(e)),
         }
     }
@@ -590,6 +631,9 @@ pub struct ApplyRet {
     pub act_error: Option<ActorError>,
     pub penalty: BigInt,
     pub miner_tip: BigInt,
+    /// Trace of the execution, unset if tracing is not enabled or the message failed validation
+    /// before being executed.
+    pub exec_trace: Option<ExecutionTrace>,
 }
 
 /// Does some basic checks on the Message to see if the fields are valid.
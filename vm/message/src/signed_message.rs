@@ -186,6 +186,11 @@ pub mod json {
         use forest_json_utils::GoVecVisitor;
         use serde::ser::SerializeSeq;
 
+        /// Wrapper for serializing and deserializing a SignedMessage vector from JSON.
+        #[derive(Deserialize, Serialize)]
+        #[serde(transparent)]
+        pub struct SignedMessageJsonVec(#[serde(with = "self")] pub Vec<SignedMessage>);
+
         pub fn serialize<S>(m: &[SignedMessage], serializer: S) -> Result<S::Ok, S::Error>
         where
             S: Serializer,
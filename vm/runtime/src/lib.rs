@@ -11,7 +11,7 @@ use clock::ChainEpoch;
 use commcid::data_commitment_v1_to_cid;
 use crypto::{DomainSeparationTag, Signature};
 use fil_types::{
-    zero_piece_commitment, NetworkVersion, PaddedPieceSize, PieceInfo, Randomness,
+    zero_piece_commitment, NetworkParams, NetworkVersion, PaddedPieceSize, PieceInfo, Randomness,
     RegisteredSealProof, SealVerifyInfo, WindowPoStVerifyInfo,
 };
 use filecoin_proofs_api::seal::compute_comm_d;
@@ -26,6 +26,9 @@ use vm::{ActorError, MethodNum, Serialized, TokenAmount};
 /// Runtime is the VM's internal runtime object.
 /// this is everything that is accessible to actors, beyond parameters.
 pub trait Runtime<BS: BlockStore> {
+    /// Parameters of the network the messages are applied for.
+    type Params: NetworkParams;
+
     /// The network protocol version number at the current epoch.
     fn network_version(&self) -> NetworkVersion;
 
@@ -0,0 +1,63 @@
+// Copyright 2020 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use actor::{
+    INIT_ACTOR_ADDR, REWARD_ACTOR_ADDR, STORAGE_MARKET_ACTOR_ADDR, STORAGE_POWER_ACTOR_ADDR,
+};
+use address::Address;
+use cid::Cid;
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use vm::ActorState;
+
+/// Number of hot actor states kept, enough for the hot actors of a few recent state roots.
+const HOT_ACTORS_CACHE_SIZE: usize = 64;
+
+lazy_static! {
+    /// States of the actors which are loaded by almost every message, keyed by state root.
+    /// Shared by all state trees so that the state computed for one tipset is reused by the next.
+    static ref HOT_ACTORS: Mutex<LruCache<(Cid, Address), ActorState>> =
+        Mutex::new(LruCache::new(HOT_ACTORS_CACHE_SIZE));
+}
+
+/// Returns true if the actor at the ID address is cached across state trees.
+pub(crate) fn is_hot(addr: &Address) -> bool {
+    addr == &*INIT_ACTOR_ADDR
+        || addr == &*STORAGE_POWER_ACTOR_ADDR
+        || addr == &*REWARD_ACTOR_ADDR
+        || addr == &*STORAGE_MARKET_ACTOR_ADDR
+}
+
+/// Returns the cached state of a hot actor in the state tree with the given root.
+pub(crate) fn get(root: &Cid, addr: &Address) -> Option<ActorState> {
+    HOT_ACTORS.lock().get(&(*root, *addr)).cloned()
+}
+
+/// Caches the state of a hot actor in the state tree with the given root.
+pub(crate) fn insert(root: Cid, addr: Address, actor: ActorState) {
+    HOT_ACTORS.lock().put((root, addr), actor);
+}
+
+/// Caches the hot actors of a state tree with root `old` which was flushed to root `new`,
+/// with the given actor changes.
+pub(crate) fn update(old: Option<&Cid>, new: &Cid, changes: &HashMap<Address, Option<ActorState>>) {
+    let mut cache = HOT_ACTORS.lock();
+    for addr in [
+        *INIT_ACTOR_ADDR,
+        *STORAGE_POWER_ACTOR_ADDR,
+        *REWARD_ACTOR_ADDR,
+        *STORAGE_MARKET_ACTOR_ADDR,
+    ]
+    .iter()
+    {
+        let actor = match changes.get(addr) {
+            Some(actor) => actor.clone(),
+            // Actors which weren't changed have the same state in the new tree
+            None => old.and_then(|old| cache.peek(&(*old, *addr)).cloned()),
+        };
+        if let Some(actor) = actor {
+            cache.put((*new, *addr), actor);
+        }
+    }
+}
@@ -1,6 +1,11 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+#[macro_use]
+extern crate lazy_static;
+
+mod cache;
+
 use actor::{init, INIT_ACTOR_ADDR};
 use address::{Address, Protocol};
 use cid::{Cid, Code::Blake2b256};
@@ -15,6 +20,8 @@ use vm::ActorState;
 /// State tree implementation using hamt
 pub struct StateTree<'db, S> {
     hamt: Hamt<'db, S, ActorState>,
+    /// Root of the actors Hamt, if it was loaded or flushed.
+    root: Option<Cid>,
 
     /// State cache
     snaps: StateSnapshots,
@@ -178,6 +185,7 @@ where
         let hamt = Hamt::new_with_bit_width(store, HAMT_BIT_WIDTH);
         Self {
             hamt,
+            root: None,
             snaps: StateSnapshots::new(),
         }
     }
@@ -187,6 +195,7 @@ where
         let hamt = Hamt::load_with_bit_width(root, store, HAMT_BIT_WIDTH)?;
         Ok(Self {
             hamt,
+            root: Some(*root),
             snaps: StateSnapshots::new(),
         })
     }
@@ -208,8 +217,18 @@ where
             return Ok(Some(actor_state));
         }
 
+        // Check the actors shared between state trees, if the tree is unchanged since loaded
+        let hot_root = self.root.filter(|_| cache::is_hot(&addr));
+        if let Some(actor_state) = hot_root.and_then(|root| cache::get(&root, &addr)) {
+            self.snaps.set_actor(addr, actor_state.clone())?;
+            return Ok(Some(actor_state));
+        }
+
         // if state doesn't exist, find using hamt
         let act = self.hamt.get(&addr.to_bytes())?.cloned();
+        if let (Some(root), Some(act_s)) = (hot_root, &act) {
+            cache::insert(root, addr, act_s.clone());
+        }
 
         // Update cache if state was found
         if let Some(act_s) = &act {
@@ -345,7 +364,8 @@ where
             .into());
         }
 
-        for (addr, sto) in self.snaps.layers[0].actors.read().iter() {
+        let actors = self.snaps.layers[0].actors.read();
+        for (addr, sto) in actors.iter() {
             match sto {
                 None => {
                     self.hamt.delete(&addr.to_bytes())?;
@@ -356,6 +376,10 @@ where
             }
         }
 
-        Ok(self.hamt.flush()?)
+        let root = self.hamt.flush()?;
+        cache::update(self.root.as_ref(), &root, &actors);
+        drop(actors);
+        self.root = Some(root);
+        Ok(root)
     }
 }
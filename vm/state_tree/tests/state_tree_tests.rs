@@ -1,13 +1,13 @@
 // Copyright 2020 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use actor::{init, ActorState, ACCOUNT_ACTOR_CODE_ID, INIT_ACTOR_ADDR};
+use actor::{init, ActorState, ACCOUNT_ACTOR_CODE_ID, INIT_ACTOR_ADDR, STORAGE_POWER_ACTOR_ADDR};
 use address::{Address, SECP_PUB_LEN};
 use cid::{
     Cid,
     Code::{Blake2b256, Identity},
 };
-use ipld_blockstore::BlockStore;
+use ipld_blockstore::{BlockStore, TrackingBlockStore};
 use ipld_hamt::Hamt;
 use state_tree::*;
 
@@ -198,3 +198,34 @@ fn revert_snapshot() {
 
     assert_eq!(tree.get_actor(&addr).unwrap(), None);
 }
+
+#[test]
+fn hot_actors_cached_across_trees() {
+    let mem = db::MemoryDB::default();
+    let store = TrackingBlockStore::new(&mem);
+    let init_act = ActorState::new(empty_cid(), empty_cid(), Default::default(), 7);
+    let power_act = ActorState::new(empty_cid(), empty_cid(), Default::default(), 8);
+
+    let mut tree = StateTree::new(&store);
+    tree.set_actor(&INIT_ACTOR_ADDR, init_act.clone()).unwrap();
+    tree.set_actor(&STORAGE_POWER_ACTOR_ADDR, power_act.clone())
+        .unwrap();
+    let root = tree.flush().unwrap();
+
+    // Only the power actor changes, the init actor is still cached for the next root
+    let mut tree = StateTree::new_from_root(&store, &root).unwrap();
+    let mut power_act = tree.get_actor(&STORAGE_POWER_ACTOR_ADDR).unwrap().unwrap();
+    power_act.sequence += 1;
+    tree.set_actor(&STORAGE_POWER_ACTOR_ADDR, power_act.clone())
+        .unwrap();
+    let root = tree.flush().unwrap();
+
+    let tree = StateTree::new_from_root(&store, &root).unwrap();
+    let reads = store.stats.borrow().r;
+    assert_eq!(tree.get_actor(&INIT_ACTOR_ADDR).unwrap(), Some(init_act));
+    assert_eq!(
+        tree.get_actor(&STORAGE_POWER_ACTOR_ADDR).unwrap(),
+        Some(power_act)
+    );
+    assert_eq!(store.stats.borrow().r, reads);
+}